@@ -1,7 +1,20 @@
+use serde::{Deserialize, Serialize};
+
+use crate::types::{TextureDimension, TextureType};
 use crate::{DataType, ScalarType};
 
 #[derive(
-    Clone, Copy, Debug, PartialEq, Eq, Hash, strum::AsRefStr, strum::EnumIter, strum::EnumString,
+    Clone,
+    Copy,
+    Debug,
+    PartialEq,
+    Eq,
+    Hash,
+    strum::AsRefStr,
+    strum::EnumIter,
+    strum::EnumString,
+    Serialize,
+    Deserialize,
 )]
 #[strum(serialize_all = "camelCase")]
 pub enum BuiltinFn {
@@ -101,6 +114,23 @@ pub enum BuiltinFn {
     SubgroupShuffleXor,
     SubgroupXor,
 
+    // Texture
+    TextureDimensions,
+    TextureGather,
+    TextureGatherCompare,
+    TextureLoad,
+    TextureNumLayers,
+    TextureNumLevels,
+    TextureNumSamples,
+    TextureSample,
+    TextureSampleBaseClampToEdge,
+    TextureSampleBias,
+    TextureSampleCompare,
+    TextureSampleCompareLevel,
+    TextureSampleGrad,
+    TextureSampleLevel,
+    TextureStore,
+
     // Synchronization
     StorageBarrier,
     TextureBarrier,
@@ -133,6 +163,9 @@ impl BuiltinFn {
                 | BuiltinFn::SubgroupShuffleUp
                 | BuiltinFn::SubgroupShuffleXor
                 | BuiltinFn::SubgroupXor
+                | BuiltinFn::TextureSample
+                | BuiltinFn::TextureSampleBias
+                | BuiltinFn::TextureSampleCompare
                 | BuiltinFn::WorkgroupUniformLoad
                 | BuiltinFn::StorageBarrier
                 | BuiltinFn::TextureBarrier
@@ -251,6 +284,43 @@ impl BuiltinFn {
             SubgroupAll | SubgroupAny | SubgroupElect => Bool.into(),
             StorageBarrier | TextureBarrier | WorkgroupBarrier => return None,
             Tan => first_param()?,
+            TextureDimensions => match first_param()? {
+                DataType::StorageTexture(dim, _, _) => match dim {
+                    TextureDimension::D1 => U32.into(),
+                    TextureDimension::D2 => DataType::Vector(2, U32),
+                    TextureDimension::D3 => DataType::Vector(3, U32),
+                },
+                DataType::Texture(texture) => texture.size_type(),
+                _ => return None,
+            },
+            // The component index comes before the texture when gathering from a sampled texture
+            TextureGather => match first_param()? {
+                DataType::Texture(TextureType::Depth(_)) => DataType::Vector(4, F32),
+                DataType::Scalar(I32 | U32) => match first_param()? {
+                    DataType::Texture(TextureType::Sampled(_, t)) => DataType::Vector(4, t),
+                    _ => return None,
+                },
+                _ => return None,
+            },
+            TextureGatherCompare => DataType::Vector(4, F32),
+            TextureLoad => match first_param()? {
+                DataType::StorageTexture(_, format, _) => {
+                    DataType::Vector(4, format.channel_type())
+                }
+                DataType::Texture(texture) => texture.texel_type(),
+                _ => return None,
+            },
+            TextureNumLayers | TextureNumLevels | TextureNumSamples => U32.into(),
+            TextureSample
+            | TextureSampleBaseClampToEdge
+            | TextureSampleBias
+            | TextureSampleGrad
+            | TextureSampleLevel => match first_param()? {
+                DataType::Texture(texture) => texture.texel_type(),
+                _ => return None,
+            },
+            TextureSampleCompare | TextureSampleCompareLevel => F32.into(),
+            TextureStore => return None,
             Tanh => first_param()?,
             Trunc => first_param()?,
             WorkgroupUniformLoad => {
@@ -271,7 +341,9 @@ impl BuiltinFn {
     }
 }
 
-#[derive(Clone, Debug, PartialEq, Eq, Hash, strum::Display, strum::EnumString)]
+#[derive(
+    Clone, Debug, PartialEq, Eq, Hash, strum::Display, strum::EnumString, Serialize, Deserialize,
+)]
 #[strum(serialize_all = "snake_case")]
 pub enum BuiltinValue {
     VertexIndex,
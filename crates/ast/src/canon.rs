@@ -0,0 +1,401 @@
+//! Canonical forms of modules.
+//!
+//! Two programs that only differ in the names of their local variables, the order of independent
+//! module scope declarations or the spelling of their literals have the same canonical form, and
+//! therefore the same [`structural_hash`]. This is what lets the fuzzer recognise that a failing
+//! program is really one it has already saved.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write;
+
+use crate::visit::{self, Visitor, VisitorMut};
+use crate::writer::{Options, Writer};
+use crate::{
+    ContinuingBlock, DataType, Expr, ExprNode, FnCallStatement, FnDecl, ForLoopStatement,
+    GlobalDecl, LetDeclStatement, LhsExpr, LhsExprNode, Lit, LoopStatement, Module, ScalarType,
+    Statement, TypeConsExpr, UnOp, UnOpExpr, VarDeclStatement,
+};
+
+/// Rewrites a module into its canonical form.
+///
+/// Function parameters and local variables are renamed to `arg_N` and `var_N` in the order in
+/// which they are declared, negated literals and scalar constructors of literals are folded into
+/// plain literals, and the declarations are grouped by kind. The declarations of each kind are
+/// sorted so that every declaration still comes after the declarations it depends on, with ties
+/// broken by their printed form. Comments are dropped.
+pub fn canonicalize(mut module: Module) -> Module {
+    let globals = module.decls.iter().map(|it| it.name().to_owned()).collect();
+
+    module.comments.clear();
+
+    LitNormalizer.visit_module_mut(&mut module);
+    Renamer::new(&globals).visit_module_mut(&mut module);
+
+    let writer = Writer::default();
+
+    module
+        .extensions
+        .sort_by_key(|extension| extension.to_string());
+    module.extensions.dedup();
+
+    module.requires.sort_by_key(|feature| feature.to_string());
+    module.requires.dedup();
+
+    let mut structs = vec![];
+    let mut consts = vec![];
+    let mut vars = vec![];
+    let mut functions = vec![];
+
+    for decl in module.decls.drain(..) {
+        match decl {
+            GlobalDecl::Struct(decl) => structs.push(decl),
+            GlobalDecl::Const(decl) => consts.push(decl),
+            GlobalDecl::Var(decl) => vars.push(decl),
+            GlobalDecl::Fn(decl) => functions.push(decl),
+        }
+    }
+
+    let structs = sort_decls(
+        structs,
+        |decl| &decl.name,
+        |decl| {
+            let mut deps = HashSet::new();
+            for member in &decl.members {
+                collect_struct_deps(&member.data_type, &mut deps);
+            }
+            deps
+        },
+        |f, decl| writer.write_struct(f, decl),
+    );
+
+    let consts = sort_decls(
+        consts,
+        |decl| &decl.name,
+        |decl| {
+            let mut deps = DepCollector::default();
+            deps.visit_global_const(decl);
+            deps.0
+        },
+        |f, decl| writer.write_global_const(f, decl),
+    );
+
+    let vars = sort_decls(
+        vars,
+        |decl| &decl.name,
+        |_| HashSet::new(),
+        |f, decl| writer.write_global_var(f, decl),
+    );
+
+    let functions = sort_decls(
+        functions,
+        |decl| &decl.name,
+        |decl| {
+            let mut deps = DepCollector::default();
+            deps.visit_fn(decl);
+            deps.0
+        },
+        |f, decl| writer.write_func(f, decl),
+    );
+
+    module.decls = structs
+        .into_iter()
+        .map(GlobalDecl::from)
+        .chain(consts.into_iter().map(GlobalDecl::from))
+        .chain(vars.into_iter().map(GlobalDecl::from))
+        .chain(functions.into_iter().map(GlobalDecl::from))
+        .collect();
+
+    module
+}
+
+/// Computes a hash of the structure of a module.
+///
+/// The hash is computed from the module as printed by the default [`Writer`] without comments, so
+/// it ignores comments, spans and node ids, and it is stable across runs and platforms. Hash the
+/// result of [`canonicalize`] to also ignore naming and declaration order.
+pub fn structural_hash(module: &Module) -> u64 {
+    let options = Options {
+        strip_comments: true,
+        ..Options::default()
+    };
+
+    let mut hasher = Fnv1a::default();
+    Writer::new(options)
+        .write_module(&mut hasher, module)
+        .expect("writing to a hasher cannot fail");
+    hasher.0
+}
+
+/// 64-bit FNV-1a, which unlike the std hasher is guaranteed to be the same in every process.
+struct Fnv1a(u64);
+
+impl Default for Fnv1a {
+    fn default() -> Self {
+        Fnv1a(0xcbf29ce484222325)
+    }
+}
+
+impl Write for Fnv1a {
+    fn write_str(&mut self, s: &str) -> std::fmt::Result {
+        for byte in s.bytes() {
+            self.0 ^= byte as u64;
+            self.0 = self.0.wrapping_mul(0x100000001b3);
+        }
+
+        Ok(())
+    }
+}
+
+/// Sorts declarations topologically, picking the declaration with the smallest printed form
+/// whenever there is a choice.
+fn sort_decls<T>(
+    decls: Vec<T>,
+    name: impl Fn(&T) -> &str,
+    deps: impl Fn(&T) -> HashSet<String>,
+    print: impl Fn(&mut String, &T) -> std::fmt::Result,
+) -> Vec<T> {
+    let names: HashSet<String> = decls.iter().map(|it| name(it).to_owned()).collect();
+
+    let mut pending: Vec<(String, HashSet<String>, String, T)> = decls
+        .into_iter()
+        .map(|decl| {
+            let mut key = String::new();
+            print(&mut key, &decl).unwrap();
+            let own_name = name(&decl).to_owned();
+            let mut deps = deps(&decl);
+            deps.retain(|dep| dep != &own_name && names.contains(dep));
+            (own_name, deps, key, decl)
+        })
+        .collect();
+
+    let mut sorted = Vec::with_capacity(pending.len());
+    let mut emitted = HashSet::new();
+
+    while !pending.is_empty() {
+        // A cycle can only come from an invalid program, in which case we fall back to picking
+        // the smallest declaration regardless of its dependencies
+        let next = pending
+            .iter()
+            .enumerate()
+            .filter(|(_, (_, deps, _, _))| deps.is_subset(&emitted))
+            .min_by(|(_, a), (_, b)| a.2.cmp(&b.2))
+            .or_else(|| {
+                pending
+                    .iter()
+                    .enumerate()
+                    .min_by(|(_, a), (_, b)| a.2.cmp(&b.2))
+            })
+            .map(|(i, _)| i)
+            .unwrap();
+
+        let (name, _, _, decl) = pending.remove(next);
+        emitted.insert(name);
+        sorted.push(decl);
+    }
+
+    sorted
+}
+
+fn collect_struct_deps(ty: &DataType, deps: &mut HashSet<String>) {
+    match ty {
+        DataType::Struct(decl) => {
+            deps.insert(decl.name.clone());
+        }
+        DataType::Array(inner, _) => collect_struct_deps(inner, deps),
+        _ => {}
+    }
+}
+
+/// Collects the names of the module scope constants and functions that are referred to.
+#[derive(Default)]
+struct DepCollector(HashSet<String>);
+
+impl<'a> Visitor<'a> for DepCollector {
+    fn visit_fn_call_stmt(&mut self, stmt: &'a FnCallStatement) {
+        self.0.insert(stmt.ident.clone());
+        visit::walk_fn_call_stmt(self, stmt);
+    }
+
+    fn visit_expr(&mut self, node: &'a ExprNode) {
+        match &node.expr {
+            Expr::Var(expr) => {
+                self.0.insert(expr.ident.clone());
+            }
+            Expr::FnCall(expr) => {
+                self.0.insert(expr.ident.clone());
+            }
+            _ => {}
+        }
+
+        visit::walk_expr(self, node);
+    }
+}
+
+/// Folds negated literals and scalar constructors applied to literals into plain literals, so
+/// that e.g. `-(1i)`, `i32(-1)` and `-1i` are all the same.
+struct LitNormalizer;
+
+impl VisitorMut for LitNormalizer {
+    fn visit_expr_mut(&mut self, node: &mut ExprNode) {
+        visit::walk_expr_mut(self, node);
+
+        let lit = match &node.expr {
+            Expr::UnOp(UnOpExpr {
+                op: UnOp::Neg,
+                inner,
+            }) => match inner.expr {
+                Expr::Lit(Lit::I32(v)) if v != i32::MIN => Some(Lit::I32(-v)),
+                Expr::Lit(Lit::F32(v)) => Some(Lit::F32(-v)),
+                Expr::Lit(Lit::F16(v)) => Some(Lit::F16(-v)),
+                Expr::Lit(Lit::AbstractInt(v)) => v.checked_neg().map(Lit::AbstractInt),
+                Expr::Lit(Lit::AbstractFloat(v)) => Some(Lit::AbstractFloat(-v)),
+                _ => None,
+            },
+            Expr::TypeCons(TypeConsExpr {
+                data_type: DataType::Scalar(ty),
+                args,
+            }) if args.len() == 1 => match (ty, &args[0].expr) {
+                (_, Expr::Lit(lit @ (Lit::AbstractInt(_) | Lit::AbstractFloat(_)))) => {
+                    lit.convert_abstract(*ty)
+                }
+                (_, Expr::Lit(lit)) if args[0].data_type == node.data_type => Some(*lit),
+                // Conversions which don't change the value, like `u32(1i)` and `f16(1.0f)`
+                (ScalarType::U32, Expr::Lit(Lit::I32(v))) if *v >= 0 => Some(Lit::U32(*v as u32)),
+                (ScalarType::F16, Expr::Lit(Lit::F32(v)))
+                    if half::f16::from_f32(*v).to_f32() == *v =>
+                {
+                    Some(Lit::F16(half::f16::from_f32(*v)))
+                }
+                _ => None,
+            },
+            _ => None,
+        };
+
+        if let Some(lit) = lit {
+            node.expr = Expr::Lit(lit);
+        }
+    }
+}
+
+/// Renames function parameters and local variables in declaration order, keeping track of
+/// shadowing so that every use still refers to the same declaration.
+struct Renamer<'a> {
+    globals: &'a HashSet<String>,
+    scopes: Vec<HashMap<String, String>>,
+    next_arg: usize,
+    next_var: usize,
+}
+
+impl<'a> Renamer<'a> {
+    fn new(globals: &'a HashSet<String>) -> Self {
+        Renamer {
+            globals,
+            scopes: vec![],
+            next_arg: 0,
+            next_var: 0,
+        }
+    }
+
+    fn declare(&mut self, ident: &mut String) {
+        let name = fresh_name(self.globals, "var", &mut self.next_var);
+        self.scopes
+            .last_mut()
+            .unwrap()
+            .insert(std::mem::replace(ident, name.clone()), name);
+    }
+
+    fn resolve(&self, ident: &mut String) {
+        if let Some(name) = self.scopes.iter().rev().find_map(|scope| scope.get(ident)) {
+            *ident = name.clone();
+        }
+    }
+}
+
+/// Picks the next numbered name that doesn't clash with a module scope declaration.
+fn fresh_name(globals: &HashSet<String>, prefix: &str, next: &mut usize) -> String {
+    loop {
+        let name = format!("{prefix}_{next}");
+        *next += 1;
+        if !globals.contains(&name) {
+            return name;
+        }
+    }
+}
+
+impl VisitorMut for Renamer<'_> {
+    fn visit_fn_mut(&mut self, decl: &mut FnDecl) {
+        self.next_arg = 0;
+        self.next_var = 0;
+
+        let mut params = HashMap::new();
+        for param in &mut decl.inputs {
+            let name = fresh_name(self.globals, "arg", &mut self.next_arg);
+            params.insert(std::mem::replace(&mut param.name, name.clone()), name);
+        }
+
+        self.scopes.push(params);
+        visit::walk_fn_mut(self, decl);
+        self.scopes.pop();
+    }
+
+    fn visit_block_mut(&mut self, block: &mut Vec<Statement>) {
+        self.scopes.push(HashMap::new());
+        visit::walk_block_mut(self, block);
+        self.scopes.pop();
+    }
+
+    fn visit_loop_mut(&mut self, stmt: &mut LoopStatement) {
+        // The continuing block is nested in the scope of the loop body
+        self.scopes.push(HashMap::new());
+        visit::walk_block_mut(self, &mut stmt.body);
+
+        if let Some(continuing) = &mut stmt.continuing {
+            self.visit_continuing_mut(continuing);
+        }
+
+        self.scopes.pop();
+    }
+
+    fn visit_continuing_mut(&mut self, continuing: &mut ContinuingBlock) {
+        // Likewise, the break-if condition can see the declarations in the continuing block
+        self.scopes.push(HashMap::new());
+        visit::walk_block_mut(self, &mut continuing.stmts);
+
+        if let Some(break_if) = &mut continuing.break_if {
+            self.visit_expr_mut(break_if);
+        }
+
+        self.scopes.pop();
+    }
+
+    fn visit_for_loop_mut(&mut self, stmt: &mut ForLoopStatement) {
+        // The loop variable is only visible in the header and the body
+        self.scopes.push(HashMap::new());
+        visit::walk_for_loop_mut(self, stmt);
+        self.scopes.pop();
+    }
+
+    fn visit_let_decl_mut(&mut self, stmt: &mut LetDeclStatement) {
+        visit::walk_let_decl_mut(self, stmt);
+        self.declare(&mut stmt.ident);
+    }
+
+    fn visit_var_decl_mut(&mut self, stmt: &mut VarDeclStatement) {
+        visit::walk_var_decl_mut(self, stmt);
+        self.declare(&mut stmt.ident);
+    }
+
+    fn visit_lhs_expr_mut(&mut self, node: &mut LhsExprNode) {
+        match &mut node.expr {
+            LhsExpr::Ident(ident) => self.resolve(ident),
+            _ => visit::walk_lhs_expr_mut(self, node),
+        }
+    }
+
+    fn visit_expr_mut(&mut self, node: &mut ExprNode) {
+        match &mut node.expr {
+            Expr::Var(expr) => self.resolve(&mut expr.ident),
+            _ => visit::walk_expr_mut(self, node),
+        }
+    }
+}
@@ -0,0 +1,43 @@
+use serde::{Deserialize, Serialize};
+
+/// A comment from the source of a module, along with the node that it was written next to.
+///
+/// Comments are kept in a side table on the [`Module`](crate::Module) rather than in the nodes
+/// themselves, so that they are carried along by transformations which leave the anchor intact,
+/// and are simply dropped for nodes that are removed.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Comment {
+    /// The text of the comment, including its `//` or `/* */` delimiters.
+    ///
+    /// Continuation lines of block comments have the indentation of the first line removed, so
+    /// that the comment can be re-indented to wherever it is written.
+    pub text: String,
+    pub anchor: CommentAnchor,
+    pub position: CommentPosition,
+}
+
+/// The node that a comment is attached to.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum CommentAnchor {
+    /// The module itself, for comments in a module that has no declarations.
+    Module,
+    /// A module scope declaration, by name.
+    Decl(String),
+    /// A struct member, by the name of the struct and the member.
+    Member(String, String),
+    /// A statement, by the start of its span.
+    Stmt(usize),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum CommentPosition {
+    /// On the lines before the node.
+    Before,
+    /// At the end of the node's last line.
+    Inline,
+    /// On the lines after the node, which is the last one in its block.
+    After,
+    /// At the end of the `n`th block of the node, counting in the order that the writer emits
+    /// them. This is used for comments in an empty block.
+    Inside(usize),
+}
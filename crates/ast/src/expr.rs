@@ -1,52 +1,90 @@
 use std::fmt::Display;
 
 use derive_more::{Display, From};
+use serde::{Deserialize, Serialize};
 
 use crate::types::{DataType, ScalarType};
+use crate::writer::Writer;
+use crate::{NodeMeta, Span};
 
-#[derive(Clone, Copy, Debug, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
 pub enum Lit {
     Bool(bool),
     I32(i32),
     U32(u32),
     F32(f32),
     F16(half::f16),
+    /// An integer literal without a suffix.
+    AbstractInt(i64),
+    /// A float literal without a suffix.
+    AbstractFloat(f64),
 }
 
 impl Lit {
+    /// Returns the type of the literal.
+    ///
+    /// Abstract literals have the type they are concretized to when nothing else constrains them,
+    /// which is `i32` or `f32`.
     pub fn data_type(&self) -> DataType {
         match self {
             Lit::Bool(_) => ScalarType::Bool.into(),
-            Lit::I32(_) => ScalarType::I32.into(),
+            Lit::I32(_) | Lit::AbstractInt(_) => ScalarType::I32.into(),
             Lit::U32(_) => ScalarType::U32.into(),
-            Lit::F32(_) => ScalarType::F32.into(),
+            Lit::F32(_) | Lit::AbstractFloat(_) => ScalarType::F32.into(),
             Lit::F16(_) => ScalarType::F16.into(),
         }
     }
-}
 
-impl Display for Lit {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    /// Converts an abstract literal to a concrete literal of its [`Lit::data_type`], or returns
+    /// `None` if its value is out of range for that type. Concrete literals are returned as is.
+    pub fn concretize(self) -> Option<Lit> {
         match self {
-            Lit::Bool(v) => v.fmt(f),
-            Lit::I32(v) => {
-                if *v == i32::MIN {
-                    write!(f, "i32({v})")?;
-                } else {
-                    write!(f, "{v}i")?;
-                }
+            Lit::AbstractInt(_) => self.convert_abstract(ScalarType::I32),
+            Lit::AbstractFloat(_) => self.convert_abstract(ScalarType::F32),
+            lit => Some(lit),
+        }
+    }
 
-                Ok(())
+    /// Converts an abstract literal to a concrete literal of type `t`, or returns `None` if it
+    /// can't be represented by that type.
+    pub fn convert_abstract(self, t: ScalarType) -> Option<Lit> {
+        match (self, t) {
+            (Lit::AbstractInt(v), ScalarType::I32) => i32::try_from(v).ok().map(Lit::I32),
+            (Lit::AbstractInt(v), ScalarType::U32) => u32::try_from(v).ok().map(Lit::U32),
+            (Lit::AbstractInt(v), ScalarType::F32 | ScalarType::F16) => {
+                Lit::AbstractFloat(v as f64).convert_abstract(t)
+            }
+            (Lit::AbstractFloat(v), ScalarType::F32) => {
+                let v = v as f32;
+                v.is_finite().then_some(Lit::F32(v))
+            }
+            (Lit::AbstractFloat(v), ScalarType::F16) => {
+                let v = half::f16::from_f64(v);
+                v.is_finite().then_some(Lit::F16(v))
             }
-            Lit::U32(v) => write!(f, "{v}u"),
-            Lit::F32(v) => write!(f, "{v}f"),
-            Lit::F16(v) => write!(f, "{v}h"),
+            _ => None,
         }
     }
 }
 
-#[derive(Clone, Debug, Display, PartialEq)]
-#[display("{data_type}({})", crate::FmtArgs(args))]
+/// Returns the type that an abstract expression of type `ty` has when it is converted to the
+/// scalar type `t`, or `None` if it can't be. An abstract integer has type `i32` and converts to any
+/// numeric type, and an abstract float has type `f32` and converts to a float type.
+pub fn convert_abstract_type(ty: &DataType, t: ScalarType) -> Option<DataType> {
+    match (ty.dereference(), t) {
+        (DataType::Scalar(ScalarType::I32), t) if t != ScalarType::Bool => Some(t.into()),
+        (DataType::Scalar(ScalarType::F32), ScalarType::F32 | ScalarType::F16) => Some(t.into()),
+        _ => None,
+    }
+}
+
+impl Display for Lit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        Writer::default().write_lit(f, self)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct TypeConsExpr {
     pub data_type: DataType,
     pub args: Vec<ExprNode>,
@@ -58,7 +96,7 @@ impl TypeConsExpr {
     }
 }
 
-#[derive(Clone, Copy, Debug, Display, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, Display, PartialEq, Eq, Serialize, Deserialize)]
 pub enum UnOp {
     #[display("-")]
     Neg,
@@ -75,29 +113,32 @@ pub enum UnOp {
 impl UnOp {
     /// Determines the return type of a unary operator given its operand type.
     pub fn type_eval(&self, ty: &DataType) -> DataType {
+        self.try_type_eval(ty).unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    /// Like [`UnOp::type_eval`], but returns an error if the operator can't be applied to `ty`.
+    pub fn try_type_eval(&self, ty: &DataType) -> Result<DataType, String> {
         match self {
             UnOp::Neg | UnOp::Not | UnOp::BitNot => {
                 if let DataType::Ref(view) = ty {
-                    self.type_eval(&view.inner)
+                    self.try_type_eval(&view.inner)
                 } else {
-                    ty.clone()
+                    Ok(ty.clone())
                 }
             }
-            UnOp::AddressOf => DataType::Ptr(
-                ty.as_memory_view()
-                    .expect("target of address-of must be a reference")
-                    .clone(),
-            ),
-            UnOp::Deref => DataType::Ref(
-                ty.as_memory_view()
-                    .expect("expression being dereferenced must be a pointer")
-                    .clone(),
-            ),
+            UnOp::AddressOf => match ty.as_memory_view() {
+                Some(view) => Ok(DataType::Ptr(view.clone())),
+                None => Err(format!("cannot take the address of a value of type `{ty}`")),
+            },
+            UnOp::Deref => match ty.as_memory_view() {
+                Some(view) => Ok(DataType::Ref(view.clone())),
+                None => Err(format!("cannot dereference a value of type `{ty}`")),
+            },
         }
     }
 }
 
-#[derive(Clone, Copy, Debug, Display, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, Display, PartialEq, Eq, Serialize, Deserialize)]
 pub enum BinOp {
     #[display("+")]
     Plus,
@@ -138,6 +179,41 @@ pub enum BinOp {
 }
 
 impl BinOp {
+    /// Returns the types of the operands of `left op right` after abstract operands are converted,
+    /// given the types `left_type` and `right_type` of the operands.
+    ///
+    /// An abstract operand takes the scalar type of the other operand if that is concrete or an
+    /// abstract float, and the amount of a shift is a `u32`.
+    pub fn operand_types(
+        &self,
+        left: &ExprNode,
+        left_type: &DataType,
+        right: &ExprNode,
+        right_type: &DataType,
+    ) -> (DataType, DataType) {
+        let convert = |node: &ExprNode, ty: &DataType, t: Option<ScalarType>| {
+            t.filter(|_| node.is_abstract())
+                .and_then(|t| convert_abstract_type(ty, t))
+                .unwrap_or_else(|| ty.clone())
+        };
+
+        let target = |node: &ExprNode, ty: &DataType| {
+            let t = ty.dereference().as_scalar()?;
+            (!node.is_abstract() || t == ScalarType::F32).then_some(t)
+        };
+
+        match self {
+            BinOp::LShift | BinOp::RShift => (
+                left_type.clone(),
+                convert(right, right_type, Some(ScalarType::U32)),
+            ),
+            _ => (
+                convert(left, left_type, target(right, right_type)),
+                convert(right, right_type, target(left, left_type)),
+            ),
+        }
+    }
+
     /// Determines the return type of a binary operator given its operand types.
     pub fn type_eval(&self, left: &DataType, right: &DataType) -> DataType {
         let left = if let DataType::Ref(view) = left {
@@ -211,11 +287,9 @@ impl BinOp {
     }
 }
 
-#[derive(Clone, Debug, Display, PartialEq)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub enum Postfix {
-    #[display("[{_0}]")]
     Index(Box<ExprNode>),
-    #[display(".{_0}")]
     Member(String),
 }
 
@@ -229,67 +303,75 @@ impl Postfix {
     }
 
     pub fn type_eval(&self, ty: &DataType) -> DataType {
+        self.try_type_eval(ty).unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    /// Like [`Postfix::type_eval`], but returns an error if the postfix can't be applied to `ty`.
+    pub fn try_type_eval(&self, ty: &DataType) -> Result<DataType, String> {
         if let DataType::Ref(view) = ty {
-            return DataType::Ref(view.clone_with_type(self.type_eval(&view.inner)));
+            return Ok(DataType::Ref(
+                view.clone_with_type(self.try_type_eval(&view.inner)?),
+            ));
         }
 
+        let index_error = || format!("index operator cannot be applied to type `{ty}`");
+        let member_error = |ident| format!("type `{ty}` has no member `{ident}`");
+
         match self {
             Postfix::Index(_) => match ty {
-                DataType::Vector(_, t) => DataType::Scalar(*t),
-                DataType::Matrix(_, r, t) => DataType::Vector(*r, *t),
-                DataType::Array(t, _) => (**t).clone(),
+                DataType::Vector(_, t) => Ok(DataType::Scalar(*t)),
+                DataType::Matrix(_, r, t) => Ok(DataType::Vector(*r, *t)),
+                DataType::Array(t, _) => Ok((**t).clone()),
                 DataType::Ptr(view) => match view.inner.as_ref() {
                     DataType::Vector(_, t) => {
-                        DataType::Ref(view.clone_with_type(DataType::Scalar(*t)))
+                        Ok(DataType::Ref(view.clone_with_type(DataType::Scalar(*t))))
                     }
-                    DataType::Matrix(_, r, t) => {
-                        DataType::Ref(view.clone_with_type(DataType::Vector(*r, *t)))
-                    }
-                    DataType::Array(t, _) => DataType::Ref(view.clone_with_type((**t).clone())),
-                    _ => panic!("index operator cannot be applied to type `{ty}`"),
+                    DataType::Matrix(_, r, t) => Ok(DataType::Ref(
+                        view.clone_with_type(DataType::Vector(*r, *t)),
+                    )),
+                    DataType::Array(t, _) => Ok(DataType::Ref(view.clone_with_type((**t).clone()))),
+                    _ => Err(index_error()),
                 },
-                ty => panic!("index operator cannot be applied to type `{ty}`"),
+                _ => Err(index_error()),
             },
             Postfix::Member(ident) => match ty {
-                DataType::Struct(decl) => decl.member_type(ident).unwrap().clone(),
-                DataType::Vector(_, t) => {
-                    if ident.len() == 1 {
-                        DataType::Scalar(*t)
-                    } else {
-                        DataType::Vector(ident.len() as u8, *t)
-                    }
-                }
-                DataType::AtomicCompareExchangeResult(t) => {
-                    if ident == "old_value" {
-                        DataType::Scalar(*t)
-                    } else if ident == "exchanged" {
-                        DataType::Scalar(ScalarType::Bool)
-                    } else {
-                        panic!("invalid member access on __atomic_compare_exchange_result")
-                    }
-                }
+                DataType::Struct(decl) => decl
+                    .member_type(ident)
+                    .cloned()
+                    .ok_or_else(|| member_error(ident)),
+                DataType::Vector(_, t) => Ok(swizzle_type(ident, *t)),
+                DataType::AtomicCompareExchangeResult(t) => match ident.as_str() {
+                    "old_value" => Ok(DataType::Scalar(*t)),
+                    "exchanged" => Ok(DataType::Scalar(ScalarType::Bool)),
+                    _ => Err(member_error(ident)),
+                },
                 DataType::Ptr(view) => match view.inner.as_ref() {
-                    DataType::Struct(decl) => DataType::Ref(
-                        view.clone_with_type(decl.member_type(ident).unwrap().clone()),
-                    ),
+                    DataType::Struct(decl) => match decl.member_type(ident) {
+                        Some(member_type) => {
+                            Ok(DataType::Ref(view.clone_with_type(member_type.clone())))
+                        }
+                        None => Err(member_error(ident)),
+                    },
                     DataType::Vector(_, t) => {
-                        let inner_t = if ident.len() == 1 {
-                            DataType::Scalar(*t)
-                        } else {
-                            DataType::Vector(ident.len() as u8, *t)
-                        };
-                        DataType::Ref(view.clone_with_type(inner_t))
+                        Ok(DataType::Ref(view.clone_with_type(swizzle_type(ident, *t))))
                     }
-                    _ => panic!("member access operator cannot be applied to type `{ty}`"),
+                    _ => Err(member_error(ident)),
                 },
-                ty => panic!("member access operator cannot be applied to type `{ty}`"),
+                _ => Err(member_error(ident)),
             },
         }
     }
 }
 
-#[derive(Clone, Debug, Display, PartialEq)]
-#[display("{ident}")]
+fn swizzle_type(swizzle: &str, t: ScalarType) -> DataType {
+    if swizzle.len() == 1 {
+        DataType::Scalar(t)
+    } else {
+        DataType::Vector(swizzle.len() as u8, t)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct VarExpr {
     pub ident: String,
 }
@@ -305,11 +387,12 @@ impl VarExpr {
         ExprNode {
             data_type,
             expr: self.into(),
+            meta: NodeMeta::default(),
         }
     }
 }
 
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct PostfixExpr {
     pub inner: Box<ExprNode>,
     pub postfix: Postfix,
@@ -324,18 +407,7 @@ impl PostfixExpr {
     }
 }
 
-impl Display for PostfixExpr {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let PostfixExpr { inner, postfix } = self;
-        if matches!(inner.expr, Expr::UnOp(_) | Expr::BinOp(_)) {
-            write!(f, "({inner}){postfix}")
-        } else {
-            write!(f, "{inner}{postfix}")
-        }
-    }
-}
-
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct UnOpExpr {
     pub op: UnOp,
     pub inner: Box<ExprNode>,
@@ -350,25 +422,7 @@ impl UnOpExpr {
     }
 }
 
-impl Display for UnOpExpr {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let UnOpExpr { op, inner } = self;
-        if matches!(op, UnOp::Neg) && matches!(inner.expr, Expr::Lit(Lit::I32(0))) {
-            return write!(f, "{inner}");
-        }
-        if matches!(inner.expr, Expr::UnOp(_) | Expr::BinOp(_))
-            || matches!(inner.expr, Expr::Lit(Lit::I32(v)) if v < 0)
-            || matches!(inner.expr, Expr::Lit(Lit::F32(v)) if v < 0.0)
-            || matches!(inner.expr, Expr::Lit(Lit::F16(v)) if v < half::f16::ZERO)
-        {
-            write!(f, "{op}({inner})")
-        } else {
-            write!(f, "{op}{inner}")
-        }
-    }
-}
-
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct BinOpExpr {
     pub op: BinOp,
     pub left: Box<ExprNode>,
@@ -385,43 +439,7 @@ impl BinOpExpr {
     }
 }
 
-impl Display for BinOpExpr {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let BinOpExpr { op, left, right } = self;
-
-        // We specifically wrap '<' in parentheses to avoid
-        // the tint parser confusing '<' and '>' with template lists.
-        // For example, the expression vec2<bool>(x < y, y > x) is not parsed correctly.
-        // Note that vec2<bool>(x <= y, y > x) is parsed correctly.
-        let needs_parentheses = matches!(op, BinOp::Less);
-
-        if needs_parentheses {
-            write!(f, "(")?;
-        }
-
-        if matches!(left.expr, Expr::BinOp(_)) {
-            write!(f, "({left})")?;
-        } else {
-            write!(f, "{left}")?;
-        }
-        write!(f, " {op} ")?;
-
-        if matches!(right.expr, Expr::BinOp(_)) {
-            write!(f, "({right})")?
-        } else {
-            write!(f, "{right}")?
-        }
-
-        if needs_parentheses {
-            write!(f, ")")?
-        }
-
-        Ok(())
-    }
-}
-
-#[derive(Clone, Debug, Display, PartialEq)]
-#[display("{ident}({})", crate::FmtArgs(args))]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct FnCallExpr {
     pub ident: String,
     pub args: Vec<ExprNode>,
@@ -439,11 +457,12 @@ impl FnCallExpr {
         ExprNode {
             data_type: return_type.into(),
             expr: self.into(),
+            meta: NodeMeta::default(),
         }
     }
 }
 
-#[derive(Clone, Debug, Display, PartialEq, From)]
+#[derive(Clone, Debug, PartialEq, From, Serialize, Deserialize)]
 pub enum Expr {
     Lit(Lit),
     TypeCons(TypeConsExpr),
@@ -454,11 +473,54 @@ pub enum Expr {
     FnCall(FnCallExpr),
 }
 
-#[derive(Clone, Debug, Display, PartialEq)]
-#[display("{expr}")]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct ExprNode {
     pub data_type: DataType,
     pub expr: Expr,
+    pub meta: NodeMeta,
+}
+
+impl Display for ExprNode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        Writer::default().write_expr(f, self)
+    }
+}
+
+impl ExprNode {
+    pub fn with_span(mut self, span: Span) -> Self {
+        self.meta.span = Some(span);
+        self
+    }
+
+    /// Returns whether this is an abstract expression, which is an unsuffixed literal or an
+    /// arithmetic or bitwise operation on abstract operands. Its node has the type it is
+    /// concretized to when nothing else constrains it, and its value is converted to the type it
+    /// is used as.
+    pub fn is_abstract(&self) -> bool {
+        match &self.expr {
+            Expr::Lit(lit) => matches!(lit, Lit::AbstractInt(_) | Lit::AbstractFloat(_)),
+            Expr::UnOp(expr) => {
+                matches!(expr.op, UnOp::Neg | UnOp::BitNot) && expr.inner.is_abstract()
+            }
+            Expr::BinOp(expr) => match expr.op {
+                // An abstract integer can be shifted by any constant amount
+                BinOp::LShift | BinOp::RShift => {
+                    expr.left.is_abstract()
+                        && (expr.right.is_abstract() || matches!(expr.right.expr, Expr::Lit(_)))
+                }
+                BinOp::Plus
+                | BinOp::Minus
+                | BinOp::Times
+                | BinOp::Divide
+                | BinOp::Mod
+                | BinOp::BitAnd
+                | BinOp::BitOr
+                | BinOp::BitXOr => expr.left.is_abstract() && expr.right.is_abstract(),
+                _ => false,
+            },
+            _ => false,
+        }
+    }
 }
 
 impl From<Lit> for ExprNode {
@@ -466,6 +528,7 @@ impl From<Lit> for ExprNode {
         ExprNode {
             data_type: lit.data_type(),
             expr: lit.into(),
+            meta: NodeMeta::default(),
         }
     }
 }
@@ -475,6 +538,7 @@ impl From<TypeConsExpr> for ExprNode {
         ExprNode {
             data_type: expr.data_type.clone(),
             expr: expr.into(),
+            meta: NodeMeta::default(),
         }
     }
 }
@@ -484,6 +548,7 @@ impl From<PostfixExpr> for ExprNode {
         ExprNode {
             data_type: expr.postfix.type_eval(&expr.inner.data_type),
             expr: expr.into(),
+            meta: NodeMeta::default(),
         }
     }
 }
@@ -493,17 +558,24 @@ impl From<UnOpExpr> for ExprNode {
         ExprNode {
             data_type: expr.op.type_eval(&expr.inner.data_type),
             expr: expr.into(),
+            meta: NodeMeta::default(),
         }
     }
 }
 
 impl From<BinOpExpr> for ExprNode {
     fn from(expr: BinOpExpr) -> Self {
+        let (left, right) = expr.op.operand_types(
+            &expr.left,
+            &expr.left.data_type,
+            &expr.right,
+            &expr.right.data_type,
+        );
+
         ExprNode {
-            data_type: expr
-                .op
-                .type_eval(&expr.left.data_type, &expr.right.data_type),
+            data_type: expr.op.type_eval(&left, &right),
             expr: expr.into(),
+            meta: NodeMeta::default(),
         }
     }
 }
@@ -1,13 +1,14 @@
 use std::fmt::Display;
 
 use derive_more::Display;
+use serde::{Deserialize, Serialize};
 
 use crate::builtins::BuiltinValue;
 use crate::stmt::Statement;
 use crate::types::DataType;
 use crate::{InterpolationSampling, InterpolationType};
 
-#[derive(Debug, Display, PartialEq, Eq)]
+#[derive(Debug, Display, PartialEq, Eq, Serialize, Deserialize)]
 pub enum ShaderStage {
     #[display("compute")]
     Compute,
@@ -17,7 +18,7 @@ pub enum ShaderStage {
     Fragment,
 }
 
-#[derive(Debug, Display, PartialEq)]
+#[derive(Debug, Display, PartialEq, Serialize, Deserialize)]
 pub enum FnAttr {
     #[display("stage({_0})")]
     Stage(ShaderStage),
@@ -25,9 +26,11 @@ pub enum FnAttr {
     WorkgroupSize(Vec<crate::ExprNode>),
     #[display("must_use")]
     MustUse,
+    #[display("diagnostic{_0}")]
+    Diagnostic(crate::DiagnosticControl),
 }
 
-#[derive(Debug, Display, PartialEq, Eq)]
+#[derive(Debug, Display, PartialEq, Eq, Serialize, Deserialize)]
 pub enum FnIOAttr {
     #[display("builtin({_0})")]
     Builtin(BuiltinValue),
@@ -39,7 +42,7 @@ pub enum FnIOAttr {
     Interpolate(InterpolationType, Option<InterpolationSampling>),
 }
 
-#[derive(Debug, Display, PartialEq, Eq)]
+#[derive(Debug, Display, PartialEq, Eq, Serialize, Deserialize)]
 #[display("{}{name}: {data_type}", InlineAttrs(attrs))]
 pub struct FnInput {
     pub attrs: Vec<FnIOAttr>,
@@ -57,7 +60,7 @@ impl FnInput {
     }
 }
 
-#[derive(Debug, Display, PartialEq, Eq)]
+#[derive(Debug, Display, PartialEq, Eq, Serialize, Deserialize)]
 #[display("{}{data_type}", InlineAttrs(attrs))]
 pub struct FnOutput {
     pub attrs: Vec<FnIOAttr>,
@@ -73,7 +76,7 @@ impl FnOutput {
     }
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
 pub struct FnDecl {
     pub attrs: Vec<FnAttr>,
     pub name: String,
@@ -1,9 +1,10 @@
 use derive_more::Display;
+use serde::{Deserialize, Serialize};
 
 use crate::types::DataType;
 use crate::ExprNode;
 
-#[derive(Debug, Display, PartialEq, Eq)]
+#[derive(Debug, Display, PartialEq, Eq, Serialize, Deserialize)]
 pub enum GlobalVarAttr {
     #[display("binding({_0})")]
     Binding(i32),
@@ -11,7 +12,7 @@ pub enum GlobalVarAttr {
     Group(i32),
 }
 
-#[derive(Clone, Copy, Debug, Display, Hash, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, Display, Hash, PartialEq, Eq, Serialize, Deserialize)]
 pub enum StorageClass {
     #[display("function")]
     Function,
@@ -37,7 +38,7 @@ impl StorageClass {
     }
 }
 
-#[derive(Clone, Copy, Debug, Display, Hash, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, Display, Hash, PartialEq, Eq, Serialize, Deserialize)]
 pub enum AccessMode {
     #[display("read")]
     Read,
@@ -47,13 +48,13 @@ pub enum AccessMode {
     ReadWrite,
 }
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct VarQualifier {
     pub storage_class: StorageClass,
     pub access_mode: Option<AccessMode>,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
 pub struct GlobalVarDecl {
     pub attrs: Vec<GlobalVarAttr>,
     pub qualifier: Option<VarQualifier>,
@@ -84,16 +85,67 @@ impl GlobalVarDecl {
     }
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
 pub struct GlobalConstDecl {
     pub name: String,
     pub data_type: DataType,
     pub initializer: ExprNode,
 }
 
-#[derive(Debug, PartialEq, strum::Display, strum::EnumString)]
+#[derive(Debug, PartialEq, strum::Display, strum::EnumString, Serialize, Deserialize)]
 #[strum(serialize_all = "snake_case")]
 pub enum Extension {
     F16,
     Subgroups,
+    ClipDistances,
+    DualSourceBlending,
+}
+
+/// A language feature that a module requires with a `requires` directive.
+#[derive(Debug, PartialEq, strum::Display, strum::EnumString, Serialize, Deserialize)]
+#[strum(serialize_all = "snake_case")]
+pub enum LanguageFeature {
+    ReadonlyAndReadwriteStorageTextures,
+    #[strum(serialize = "packed_4x8_integer_dot_product")]
+    Packed4x8IntegerDotProduct,
+    UnrestrictedPointerParameters,
+    PointerCompositeAccess,
+    UniformBufferStandardLayout,
+    SubgroupId,
+    TextureAndSamplerLet,
+    SubgroupUniformity,
+    #[strum(serialize = "texture_formats_tier1")]
+    TextureFormatsTier1,
+}
+
+#[derive(
+    Clone, Copy, Debug, PartialEq, Eq, strum::Display, strum::EnumString, Serialize, Deserialize,
+)]
+#[strum(serialize_all = "snake_case")]
+pub enum Severity {
+    Error,
+    Warning,
+    Info,
+    Off,
+}
+
+/// Sets the severity of a diagnostic rule, either for the whole module with a `diagnostic`
+/// directive or for a function with a `@diagnostic` attribute.
+///
+/// Rules from outside the WGSL spec are qualified with a namespace, like
+/// `chromium.unreachable_code`.
+#[derive(Clone, Debug, Display, PartialEq, Eq, Serialize, Deserialize)]
+#[display("({severity}, {rule})")]
+pub struct DiagnosticControl {
+    pub severity: Severity,
+    pub rule: String,
+}
+
+impl DiagnosticControl {
+    pub fn new(severity: Severity, rule: impl Into<String>) -> Self {
+        Self {
+            severity,
+            rule: rule.into(),
+        }
+    }
 }
@@ -1,31 +1,184 @@
+pub mod canon;
+pub mod metrics;
 pub mod types;
+pub mod visit;
 pub mod writer;
 
 mod builtins;
+mod comments;
 mod expr;
 mod func;
 mod globals;
+mod meta;
 mod stmt;
 mod structs;
 
 pub use builtins::*;
+pub use comments::*;
 pub use expr::*;
 pub use func::*;
 pub use globals::*;
+pub use meta::*;
 pub use stmt::*;
 pub use structs::*;
 
 use std::rc::Rc;
 
+use serde::{Deserialize, Serialize};
+
 pub use types::{DataType, ScalarType};
 
 #[derive(Debug, PartialEq)]
 pub struct Module {
     pub extensions: Vec<Extension>,
-    pub structs: Vec<Rc<StructDecl>>,
-    pub consts: Vec<GlobalConstDecl>,
-    pub vars: Vec<GlobalVarDecl>,
-    pub functions: Vec<FnDecl>,
+    pub requires: Vec<LanguageFeature>,
+    /// Diagnostic filters from `diagnostic` directives, which apply to the whole module.
+    pub diagnostics: Vec<DiagnosticControl>,
+    /// Module scope declarations, in the order in which they are declared.
+    pub decls: Vec<GlobalDecl>,
+    pub comments: Vec<Comment>,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub enum GlobalDecl {
+    Struct(#[serde(with = "structs::by_name")] Rc<StructDecl>),
+    Const(GlobalConstDecl),
+    Var(GlobalVarDecl),
+    Fn(FnDecl),
+}
+
+impl GlobalDecl {
+    pub fn name(&self) -> &str {
+        match self {
+            GlobalDecl::Struct(decl) => &decl.name,
+            GlobalDecl::Const(decl) => &decl.name,
+            GlobalDecl::Var(decl) => &decl.name,
+            GlobalDecl::Fn(decl) => &decl.name,
+        }
+    }
+}
+
+impl From<Rc<StructDecl>> for GlobalDecl {
+    fn from(decl: Rc<StructDecl>) -> Self {
+        GlobalDecl::Struct(decl)
+    }
+}
+
+impl From<GlobalConstDecl> for GlobalDecl {
+    fn from(decl: GlobalConstDecl) -> Self {
+        GlobalDecl::Const(decl)
+    }
+}
+
+impl From<GlobalVarDecl> for GlobalDecl {
+    fn from(decl: GlobalVarDecl) -> Self {
+        GlobalDecl::Var(decl)
+    }
+}
+
+impl From<FnDecl> for GlobalDecl {
+    fn from(decl: FnDecl) -> Self {
+        GlobalDecl::Fn(decl)
+    }
+}
+
+macro_rules! decl_accessors {
+    ($variant:ident, $ty:ty, $name:ident, $name_mut:ident) => {
+        pub fn $name(&self) -> impl DoubleEndedIterator<Item = &$ty> {
+            self.decls.iter().filter_map(|decl| match decl {
+                GlobalDecl::$variant(decl) => Some(decl),
+                _ => None,
+            })
+        }
+
+        pub fn $name_mut(&mut self) -> impl DoubleEndedIterator<Item = &mut $ty> {
+            self.decls.iter_mut().filter_map(|decl| match decl {
+                GlobalDecl::$variant(decl) => Some(decl),
+                _ => None,
+            })
+        }
+    };
+}
+
+impl Module {
+    decl_accessors!(Struct, Rc<StructDecl>, structs, structs_mut);
+    decl_accessors!(Const, GlobalConstDecl, consts, consts_mut);
+    decl_accessors!(Var, GlobalVarDecl, vars, vars_mut);
+    decl_accessors!(Fn, FnDecl, functions, functions_mut);
+
+    /// Inserts declarations before the first function, which keeps them ahead of any use when the
+    /// module is written in its declaration order.
+    pub fn insert_before_functions<T: Into<GlobalDecl>>(
+        &mut self,
+        decls: impl IntoIterator<Item = T>,
+    ) {
+        let index = self
+            .decls
+            .iter()
+            .position(|it| matches!(it, GlobalDecl::Fn(_)))
+            .unwrap_or(self.decls.len());
+
+        self.decls
+            .splice(index..index, decls.into_iter().map(Into::into));
+    }
+}
+
+// Struct types are serialized by name, and a declaration may refer to a struct that is declared
+// after it. The struct declarations are therefore written ahead of the other declarations, in an
+// order where each one comes after the structs that it refers to.
+
+impl Serialize for Module {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        #[derive(Serialize)]
+        #[serde(rename = "Module")]
+        struct Repr<'a> {
+            extensions: &'a [Extension],
+            requires: &'a [LanguageFeature],
+            diagnostics: &'a [DiagnosticControl],
+            structs: Vec<&'a StructDecl>,
+            decls: &'a [GlobalDecl],
+            comments: &'a [Comment],
+        }
+
+        Repr {
+            extensions: &self.extensions,
+            requires: &self.requires,
+            diagnostics: &self.diagnostics,
+            structs: structs::dependency_order(self.structs()),
+            decls: &self.decls,
+            comments: &self.comments,
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Module {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(rename = "Module")]
+        struct Repr {
+            extensions: Vec<Extension>,
+            requires: Vec<LanguageFeature>,
+            diagnostics: Vec<DiagnosticControl>,
+            #[serde(rename = "structs", deserialize_with = "structs::deserialize_decls")]
+            _structs: Vec<Rc<StructDecl>>,
+            decls: Vec<GlobalDecl>,
+            comments: Vec<Comment>,
+        }
+
+        // The struct declarations are registered as they are read, and references to them in
+        // the declarations that follow are resolved against the registry
+        let _scope = structs::registry::enter();
+        let module = Repr::deserialize(deserializer)?;
+
+        Ok(Module {
+            extensions: module.extensions,
+            requires: module.requires,
+            diagnostics: module.diagnostics,
+            decls: module.decls,
+            comments: module.comments,
+        })
+    }
 }
 
 struct FmtArgs<'a>(&'a [ExprNode]);
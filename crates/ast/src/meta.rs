@@ -0,0 +1,184 @@
+use std::fmt::{Debug, Display};
+
+use serde::{Deserialize, Serialize};
+
+use crate::visit::{self, VisitorMut};
+use crate::{
+    AssignmentStatement, DecrementStatement, ExprNode, FnCallStatement, ForLoopStatement,
+    IfStatement, IncrementStatement, LetDeclStatement, LhsExprNode, LoopStatement, Module,
+    Statement, SwitchStatement, VarDeclStatement, WhileStatement,
+};
+
+/// A region of source text that a node was parsed from.
+///
+/// `start` and `end` are byte offsets into the source, while `line` and `column` give the
+/// (1-based) position of `start` for use in diagnostics.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Span {
+    /// Returns a span covering both `self` and `other`, which must come after `self`.
+    pub fn to(self, other: Span) -> Span {
+        Span {
+            end: other.end,
+            ..self
+        }
+    }
+}
+
+impl Display for Span {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct NodeId(pub u32);
+
+impl Display for NodeId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "#{}", self.0)
+    }
+}
+
+/// Information about where a node came from, which is carried along as the node is transformed.
+///
+/// Metadata does not take part in comparisons, so two nodes with the same structure are equal
+/// regardless of where they were parsed from.
+#[derive(Clone, Copy, Default, Serialize, Deserialize)]
+pub struct NodeMeta {
+    pub id: Option<NodeId>,
+    pub span: Option<Span>,
+}
+
+impl NodeMeta {
+    pub fn with_span(span: Span) -> NodeMeta {
+        NodeMeta {
+            id: None,
+            span: Some(span),
+        }
+    }
+}
+
+impl PartialEq for NodeMeta {
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
+}
+
+impl Eq for NodeMeta {}
+
+impl Debug for NodeMeta {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("NodeMeta(")?;
+
+        match self.id {
+            Some(id) => write!(f, "{id}")?,
+            None => f.write_str("-")?,
+        }
+
+        match self.span {
+            Some(span) => write!(f, " @ {span} [{}..{}])", span.start, span.end),
+            None => f.write_str(")"),
+        }
+    }
+}
+
+/// Numbers every expression and statement in the module in pre-order, starting from zero.
+///
+/// Ids only depend on the structure of the module, so parsing the same source twice will produce
+/// the same ids.
+pub fn assign_node_ids(module: &mut Module) {
+    Numbering { next_id: 0 }.visit_module_mut(module);
+}
+
+struct Numbering {
+    next_id: u32,
+}
+
+impl Numbering {
+    fn assign(&mut self, meta: &mut NodeMeta) {
+        meta.id = Some(NodeId(self.next_id));
+        self.next_id += 1;
+    }
+}
+
+impl VisitorMut for Numbering {
+    fn visit_stmt_mut(&mut self, stmt: &mut Statement) {
+        if let Statement::Return(stmt) = stmt {
+            self.assign(&mut stmt.meta);
+        }
+
+        visit::walk_stmt_mut(self, stmt);
+    }
+
+    fn visit_let_decl_mut(&mut self, stmt: &mut LetDeclStatement) {
+        self.assign(&mut stmt.meta);
+        visit::walk_let_decl_mut(self, stmt);
+    }
+
+    fn visit_var_decl_mut(&mut self, stmt: &mut VarDeclStatement) {
+        self.assign(&mut stmt.meta);
+        visit::walk_var_decl_mut(self, stmt);
+    }
+
+    fn visit_assignment_mut(&mut self, stmt: &mut AssignmentStatement) {
+        self.assign(&mut stmt.meta);
+        visit::walk_assignment_mut(self, stmt);
+    }
+
+    fn visit_increment_mut(&mut self, stmt: &mut IncrementStatement) {
+        self.assign(&mut stmt.meta);
+        visit::walk_increment_mut(self, stmt);
+    }
+
+    fn visit_decrement_mut(&mut self, stmt: &mut DecrementStatement) {
+        self.assign(&mut stmt.meta);
+        visit::walk_decrement_mut(self, stmt);
+    }
+
+    fn visit_if_mut(&mut self, stmt: &mut IfStatement) {
+        self.assign(&mut stmt.meta);
+        visit::walk_if_mut(self, stmt);
+    }
+
+    fn visit_loop_mut(&mut self, stmt: &mut LoopStatement) {
+        self.assign(&mut stmt.meta);
+        visit::walk_loop_mut(self, stmt);
+    }
+
+    fn visit_while_mut(&mut self, stmt: &mut WhileStatement) {
+        self.assign(&mut stmt.meta);
+        visit::walk_while_mut(self, stmt);
+    }
+
+    fn visit_switch_mut(&mut self, stmt: &mut SwitchStatement) {
+        self.assign(&mut stmt.meta);
+        visit::walk_switch_mut(self, stmt);
+    }
+
+    fn visit_for_loop_mut(&mut self, stmt: &mut ForLoopStatement) {
+        self.assign(&mut stmt.meta);
+        visit::walk_for_loop_mut(self, stmt);
+    }
+
+    fn visit_fn_call_stmt_mut(&mut self, stmt: &mut FnCallStatement) {
+        self.assign(&mut stmt.meta);
+        visit::walk_fn_call_stmt_mut(self, stmt);
+    }
+
+    fn visit_lhs_expr_mut(&mut self, node: &mut LhsExprNode) {
+        self.assign(&mut node.meta);
+        visit::walk_lhs_expr_mut(self, node);
+    }
+
+    fn visit_expr_mut(&mut self, node: &mut ExprNode) {
+        self.assign(&mut node.meta);
+        visit::walk_expr_mut(self, node);
+    }
+}
@@ -0,0 +1,286 @@
+//! Size and shape metrics of programs.
+//!
+//! These are used to characterize the programs produced by a fuzzing campaign and to compare the
+//! results of different reducers.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+
+use serde::Serialize;
+
+use crate::visit::{self, Visitor};
+use crate::{
+    DataType, Expr, ExprNode, FnCallStatement, FnDecl, ForLoopStatement, LhsExpr, LhsExprNode,
+    LoopStatement, Module, Statement, UnOp, VarDeclStatement, WhileStatement,
+};
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct Metrics {
+    /// Number of module scope declarations of each kind.
+    pub declarations: BTreeMap<&'static str, usize>,
+    /// Number of statements of each kind.
+    pub statements: BTreeMap<&'static str, usize>,
+    /// Number of expressions of each kind.
+    pub expressions: BTreeMap<&'static str, usize>,
+    /// Height of the tallest expression tree, where a leaf expression has a depth of 1.
+    pub max_expr_depth: usize,
+    /// Deepest nesting of blocks, where a function body has a depth of 1.
+    pub max_block_depth: usize,
+    /// Deepest nesting of `loop`, `while` and `for` statements.
+    pub max_loop_depth: usize,
+    /// Number of functions on the longest chain of calls between user-defined functions.
+    pub call_graph_depth: usize,
+    /// Names of the functions that are called but not declared in the module.
+    pub builtins: BTreeSet<String>,
+    /// Every type that a declaration or expression has.
+    pub types: BTreeSet<String>,
+    pub pointers: PointerMetrics,
+}
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct PointerMetrics {
+    /// Number of function parameters with a pointer type.
+    pub params: usize,
+    /// Number of `&` expressions, including on the left of assignments.
+    pub address_of: usize,
+    /// Number of `*` expressions, including on the left of assignments.
+    pub deref: usize,
+}
+
+impl Metrics {
+    /// Total number of statements.
+    pub fn total_statements(&self) -> usize {
+        self.statements.values().sum()
+    }
+
+    /// Total number of expressions.
+    pub fn total_expressions(&self) -> usize {
+        self.expressions.values().sum()
+    }
+}
+
+/// Computes metrics for a module.
+pub fn compute(module: &Module) -> Metrics {
+    let mut collector = Collector {
+        functions: module.functions().map(|it| it.name.as_str()).collect(),
+        structs: module.structs().map(|it| it.name.as_str()).collect(),
+        ..Default::default()
+    };
+
+    collector.metrics.declarations = BTreeMap::from([
+        ("struct", module.structs().count()),
+        ("const", module.consts().count()),
+        ("var", module.vars().count()),
+        ("fn", module.functions().count()),
+    ]);
+
+    for decl in module.structs() {
+        for member in &decl.members {
+            collector.add_type(&member.data_type);
+        }
+    }
+
+    for decl in module.consts() {
+        collector.add_type(&decl.data_type);
+    }
+
+    for decl in module.vars() {
+        collector.add_type(&decl.data_type);
+    }
+
+    collector.visit_module(module);
+
+    let mut metrics = collector.metrics;
+    let mut depths = HashMap::new();
+
+    metrics.call_graph_depth = module
+        .functions()
+        .map(|it| call_depth(&it.name, &collector.calls, &mut depths, &mut HashSet::new()))
+        .max()
+        .unwrap_or(0);
+
+    metrics
+}
+
+fn call_depth<'a>(
+    func: &'a str,
+    calls: &HashMap<&'a str, HashSet<&'a str>>,
+    depths: &mut HashMap<&'a str, usize>,
+    visiting: &mut HashSet<&'a str>,
+) -> usize {
+    if let Some(depth) = depths.get(func) {
+        return *depth;
+    }
+
+    // Recursion isn't allowed in WGSL, but we don't want to overflow the stack if we're given an
+    // invalid program
+    if !visiting.insert(func) {
+        return 0;
+    }
+
+    let depth = 1 + calls
+        .get(func)
+        .into_iter()
+        .flatten()
+        .map(|callee| call_depth(callee, calls, depths, visiting))
+        .max()
+        .unwrap_or(0);
+
+    visiting.remove(func);
+    depths.insert(func, depth);
+
+    depth
+}
+
+#[derive(Default)]
+struct Collector<'a> {
+    metrics: Metrics,
+    functions: HashSet<&'a str>,
+    structs: HashSet<&'a str>,
+    calls: HashMap<&'a str, HashSet<&'a str>>,
+    current_fn: Option<&'a str>,
+    expr_depth: usize,
+    block_depth: usize,
+    loop_depth: usize,
+}
+
+impl<'a> Collector<'a> {
+    fn add_type(&mut self, ty: &DataType) {
+        self.metrics.types.insert(ty.to_string());
+    }
+
+    fn add_call(&mut self, ident: &'a str) {
+        if self.functions.contains(ident) {
+            if let Some(caller) = self.current_fn {
+                self.calls.entry(caller).or_default().insert(ident);
+            }
+        } else if !self.structs.contains(ident) {
+            // Struct constructors are parsed as calls, but they're not builtins
+            self.metrics.builtins.insert(ident.to_owned());
+        }
+    }
+
+    fn in_loop(&mut self, f: impl FnOnce(&mut Self)) {
+        self.loop_depth += 1;
+        self.metrics.max_loop_depth = self.metrics.max_loop_depth.max(self.loop_depth);
+        f(self);
+        self.loop_depth -= 1;
+    }
+}
+
+impl<'a> Visitor<'a> for Collector<'a> {
+    fn visit_fn(&mut self, decl: &'a FnDecl) {
+        self.current_fn = Some(&decl.name);
+
+        for param in &decl.inputs {
+            self.add_type(&param.data_type);
+            if let DataType::Ptr(_) = param.data_type {
+                self.metrics.pointers.params += 1;
+            }
+        }
+
+        if let Some(output) = &decl.output {
+            self.add_type(&output.data_type);
+        }
+
+        visit::walk_fn(self, decl);
+        self.current_fn = None;
+    }
+
+    fn visit_block(&mut self, block: &'a [Statement]) {
+        self.block_depth += 1;
+        self.metrics.max_block_depth = self.metrics.max_block_depth.max(self.block_depth);
+        visit::walk_block(self, block);
+        self.block_depth -= 1;
+    }
+
+    fn visit_stmt(&mut self, stmt: &'a Statement) {
+        let kind = match stmt {
+            Statement::LetDecl(_) => "let",
+            Statement::VarDecl(_) => "var",
+            Statement::Assignment(_) => "assignment",
+            Statement::Increment(_) => "increment",
+            Statement::Decrement(_) => "decrement",
+            Statement::Compound(_) => "compound",
+            Statement::If(_) => "if",
+            Statement::Return(_) => "return",
+            Statement::Loop(_) => "loop",
+            Statement::While(_) => "while",
+            Statement::Break => "break",
+            Statement::Continue => "continue",
+            Statement::Fallthrough => "fallthrough",
+            Statement::Switch(_) => "switch",
+            Statement::ForLoop(_) => "for",
+            Statement::FnCall(_) => "call",
+        };
+
+        *self.metrics.statements.entry(kind).or_default() += 1;
+
+        visit::walk_stmt(self, stmt);
+    }
+
+    fn visit_var_decl(&mut self, stmt: &'a VarDeclStatement) {
+        if let Some(data_type) = &stmt.data_type {
+            self.add_type(data_type);
+        }
+
+        visit::walk_var_decl(self, stmt);
+    }
+
+    fn visit_loop(&mut self, stmt: &'a LoopStatement) {
+        self.in_loop(|this| visit::walk_loop(this, stmt));
+    }
+
+    fn visit_while(&mut self, stmt: &'a WhileStatement) {
+        self.in_loop(|this| visit::walk_while(this, stmt));
+    }
+
+    fn visit_for_loop(&mut self, stmt: &'a ForLoopStatement) {
+        self.in_loop(|this| visit::walk_for_loop(this, stmt));
+    }
+
+    fn visit_fn_call_stmt(&mut self, stmt: &'a FnCallStatement) {
+        self.add_call(&stmt.ident);
+        visit::walk_fn_call_stmt(self, stmt);
+    }
+
+    fn visit_lhs_expr(&mut self, node: &'a LhsExprNode) {
+        match node.expr {
+            LhsExpr::Deref(_) => self.metrics.pointers.deref += 1,
+            LhsExpr::AddressOf(_) => self.metrics.pointers.address_of += 1,
+            _ => {}
+        }
+
+        visit::walk_lhs_expr(self, node);
+    }
+
+    fn visit_expr(&mut self, node: &'a ExprNode) {
+        let kind = match &node.expr {
+            Expr::Lit(_) => "literal",
+            Expr::TypeCons(_) => "constructor",
+            Expr::Var(_) => "ident",
+            Expr::Postfix(_) => "postfix",
+            Expr::UnOp(expr) => {
+                match expr.op {
+                    UnOp::AddressOf => self.metrics.pointers.address_of += 1,
+                    UnOp::Deref => self.metrics.pointers.deref += 1,
+                    _ => {}
+                }
+
+                "unop"
+            }
+            Expr::BinOp(_) => "binop",
+            Expr::FnCall(expr) => {
+                self.add_call(&expr.ident);
+                "call"
+            }
+        };
+
+        *self.metrics.expressions.entry(kind).or_default() += 1;
+        self.add_type(&node.data_type);
+
+        self.expr_depth += 1;
+        self.metrics.max_expr_depth = self.metrics.max_expr_depth.max(self.expr_depth);
+        visit::walk_expr(self, node);
+        self.expr_depth -= 1;
+    }
+}
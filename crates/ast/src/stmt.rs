@@ -1,27 +1,41 @@
-use std::fmt::{Display, Write};
+use std::fmt::Display;
 
 use derive_more::{Display, From};
-use indenter::indented;
+use serde::{Deserialize, Serialize};
 
 use crate::types::DataType;
-use crate::{ExprNode, Postfix};
+use crate::writer::Writer;
+use crate::{ExprNode, NodeMeta, Postfix, Span};
 
-#[derive(Debug, Display, PartialEq)]
-#[display("let {ident} = {initializer}")]
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
 pub struct LetDeclStatement {
     pub ident: String,
+    pub data_type: Option<DataType>,
     pub initializer: ExprNode,
+    pub meta: NodeMeta,
 }
 
 impl LetDeclStatement {
     pub fn new(ident: impl Into<String>, initializer: impl Into<ExprNode>) -> Self {
         Self {
             ident: ident.into(),
+            data_type: None,
             initializer: initializer.into(),
+            meta: NodeMeta::default(),
         }
     }
 
+    /// Declares the type of the variable explicitly, as in `let x: u32 = 1;`.
+    pub fn with_type(mut self, data_type: DataType) -> Self {
+        self.data_type = Some(data_type);
+        self
+    }
+
     pub fn inferred_type(&self) -> &DataType {
+        if let Some(data_type) = &self.data_type {
+            return data_type;
+        }
+
         // If the type of the initializer expression is a reference, then we infer the declaration
         // type to be the target type of the reference. Otherwise it is simply the type of the initializer.
         if let DataType::Ref(view) = &self.initializer.data_type {
@@ -32,11 +46,12 @@ impl LetDeclStatement {
     }
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
 pub struct VarDeclStatement {
     pub ident: String,
     pub data_type: Option<DataType>,
     pub initializer: Option<ExprNode>,
+    pub meta: NodeMeta,
 }
 
 impl VarDeclStatement {
@@ -49,6 +64,7 @@ impl VarDeclStatement {
             ident: ident.into(),
             data_type,
             initializer,
+            meta: NodeMeta::default(),
         }
     }
 
@@ -64,31 +80,8 @@ impl VarDeclStatement {
     }
 }
 
-impl Display for VarDeclStatement {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let VarDeclStatement {
-            ident,
-            data_type,
-            initializer,
-        } = self;
-
-        write!(f, "var {ident}")?;
-
-        if let Some(data_type) = data_type {
-            write!(f, ": {data_type}")?;
-        }
-
-        if let Some(initializer) = initializer {
-            write!(f, " = {initializer}")?;
-        }
-
-        Ok(())
-    }
-}
-
-#[derive(Debug, Display, PartialEq)]
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
 pub enum AssignmentLhs {
-    #[display("_")]
     Phony,
     Expr(LhsExprNode),
 }
@@ -113,35 +106,53 @@ impl AssignmentLhs {
     }
 }
 
-#[derive(Debug, Display, PartialEq)]
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
 pub enum LhsExpr {
     Ident(String),
-    #[display("({_0}){_1}")]
     Postfix(Box<LhsExprNode>, Postfix),
-    #[display("*(_0)")]
     Deref(Box<LhsExprNode>),
-    #[display("&(_0)")]
     AddressOf(Box<LhsExprNode>),
 }
 
+impl Display for AssignmentLhs {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AssignmentLhs::Phony => write!(f, "_"),
+            AssignmentLhs::Expr(node) => node.fmt(f),
+        }
+    }
+}
+
 impl From<LhsExprNode> for AssignmentLhs {
     fn from(node: LhsExprNode) -> Self {
         AssignmentLhs::Expr(node)
     }
 }
 
-#[derive(Debug, Display, PartialEq)]
-#[display("{expr}")]
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
 pub struct LhsExprNode {
     pub data_type: DataType,
     pub expr: LhsExpr,
+    pub meta: NodeMeta,
+}
+
+impl Display for LhsExprNode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        Writer::default().write_lhs_expr(f, self)
+    }
 }
 
 impl LhsExprNode {
+    pub fn with_span(mut self, span: Span) -> Self {
+        self.meta.span = Some(span);
+        self
+    }
+
     pub fn name(name: String, data_type: DataType) -> LhsExprNode {
         LhsExprNode {
             data_type,
             expr: LhsExpr::Ident(name),
+            meta: NodeMeta::default(),
         }
     }
 
@@ -168,6 +179,7 @@ impl LhsExprNode {
         LhsExprNode {
             data_type: element_type,
             expr: LhsExpr::Postfix(Box::new(self), Postfix::Index(Box::new(index))),
+            meta: NodeMeta::default(),
         }
     }
 
@@ -193,11 +205,12 @@ impl LhsExprNode {
         LhsExprNode {
             data_type: resulting_type,
             expr: LhsExpr::Postfix(Box::new(self), Postfix::Member(member)),
+            meta: NodeMeta::default(),
         }
     }
 }
 
-#[derive(Debug, Display, PartialEq, Eq)]
+#[derive(Debug, Display, PartialEq, Eq, Serialize, Deserialize)]
 pub enum AssignmentOp {
     #[display("=")]
     Simple,
@@ -219,12 +232,12 @@ pub enum AssignmentOp {
     Xor,
 }
 
-#[derive(Debug, Display, PartialEq)]
-#[display("{lhs} {op} {rhs}")]
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
 pub struct AssignmentStatement {
     pub lhs: AssignmentLhs,
     pub op: AssignmentOp,
     pub rhs: ExprNode,
+    pub meta: NodeMeta,
 }
 
 impl AssignmentStatement {
@@ -233,74 +246,90 @@ impl AssignmentStatement {
             lhs,
             op,
             rhs: rhs.into(),
+            meta: NodeMeta::default(),
         }
     }
 }
 
-#[derive(Debug, Display, PartialEq)]
-#[display("{lhs}++")]
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
 pub struct IncrementStatement {
     pub lhs: AssignmentLhs,
+    pub meta: NodeMeta,
 }
 
 impl IncrementStatement {
     pub fn new(lhs: AssignmentLhs) -> Self {
-        Self { lhs }
+        Self {
+            lhs,
+            meta: NodeMeta::default(),
+        }
     }
 }
 
-#[derive(Debug, Display, PartialEq)]
-#[display("{lhs}--")]
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
 pub struct DecrementStatement {
     pub lhs: AssignmentLhs,
+    pub meta: NodeMeta,
 }
 
 impl DecrementStatement {
     pub fn new(lhs: AssignmentLhs) -> Self {
-        Self { lhs }
+        Self {
+            lhs,
+            meta: NodeMeta::default(),
+        }
     }
 }
 
-#[derive(Debug, PartialEq)]
+// An else is always boxed by the if statement that owns it
+#[allow(clippy::large_enum_variant)]
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
 pub enum Else {
     If(IfStatement),
     Else(Vec<Statement>),
 }
 
-impl Display for Else {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "else ")?;
-
-        match self {
-            Else::If(stmt) => stmt.fmt(f)?,
-            Else::Else(stmts) => {
-                writeln!(f, "{{")?;
+/// An attribute of a compound, if, switch, loop, while or for statement.
+#[derive(Clone, Debug, Display, PartialEq, Eq, Serialize, Deserialize)]
+pub enum StmtAttr {
+    #[display("diagnostic{_0}")]
+    Diagnostic(crate::DiagnosticControl),
+}
 
-                for stmt in stmts {
-                    writeln!(indented(f), "{}", stmt)?;
-                }
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct CompoundStatement {
+    pub attrs: Vec<StmtAttr>,
+    pub body: Vec<Statement>,
+    pub meta: NodeMeta,
+}
 
-                write!(f, "}}")?;
-            }
+impl CompoundStatement {
+    pub fn new(body: Vec<Statement>) -> Self {
+        Self {
+            attrs: vec![],
+            body,
+            meta: NodeMeta::default(),
         }
-
-        Ok(())
     }
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
 pub struct IfStatement {
+    pub attrs: Vec<StmtAttr>,
     pub condition: ExprNode,
     pub body: Vec<Statement>,
     pub else_: Option<Box<Else>>,
+    pub meta: NodeMeta,
 }
 
 impl IfStatement {
     pub fn new(condition: impl Into<ExprNode>, body: Vec<Statement>) -> Self {
         Self {
+            attrs: vec![],
             condition: condition.into(),
             body,
             else_: None,
+            meta: NodeMeta::default(),
         }
     }
 
@@ -310,94 +339,58 @@ impl IfStatement {
     }
 }
 
-impl Display for IfStatement {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let IfStatement {
-            condition,
-            body,
-            else_,
-        } = self;
-
-        writeln!(f, "if ({condition}) {{")?;
-
-        for stmt in body {
-            writeln!(indented(f), "{}", stmt)?;
-        }
-
-        write!(f, "}}")?;
-
-        if let Some(else_) = else_ {
-            write!(f, " {else_}")?;
-        }
-
-        Ok(())
-    }
-}
-
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
 pub struct ReturnStatement {
     pub value: Option<ExprNode>,
+    pub meta: NodeMeta,
 }
 
 impl ReturnStatement {
     pub fn new(value: impl Into<ExprNode>) -> Self {
         Self {
             value: Some(value.into()),
+            meta: NodeMeta::default(),
         }
     }
 
     pub fn optional(value: Option<impl Into<ExprNode>>) -> Self {
         Self {
             value: value.map(|it| it.into()),
+            meta: NodeMeta::default(),
         }
     }
 
     pub fn none() -> Self {
-        Self { value: None }
-    }
-}
-
-impl Display for ReturnStatement {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "return")?;
-
-        if let Some(value) = &self.value {
-            write!(f, " {}", value)?;
+        Self {
+            value: None,
+            meta: NodeMeta::default(),
         }
-
-        Ok(())
     }
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
 pub struct LoopStatement {
+    pub attrs: Vec<StmtAttr>,
+    /// Attributes of the body of the loop, which are written after the `loop` keyword.
+    pub body_attrs: Vec<StmtAttr>,
     pub body: Vec<Statement>,
     pub continuing: Option<ContinuingBlock>,
+    pub meta: NodeMeta,
 }
 
 impl LoopStatement {
     pub fn new(body: Vec<Statement>, continuing: Option<ContinuingBlock>) -> Self {
-        Self { body, continuing }
-    }
-}
-
-impl Display for LoopStatement {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        writeln!(f, "loop {{")?;
-
-        for stmt in &self.body {
-            writeln!(indented(f), "{}", stmt)?;
-        }
-
-        if let Some(continuing) = &self.continuing {
-            writeln!(indented(f), "{}", continuing)?;
+        Self {
+            attrs: vec![],
+            body_attrs: vec![],
+            body,
+            continuing,
+            meta: NodeMeta::default(),
         }
-
-        write!(f, "}}")
     }
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
 pub struct ContinuingBlock {
     pub stmts: Vec<Statement>,
     pub break_if: Option<ExprNode>,
@@ -412,104 +405,56 @@ impl ContinuingBlock {
     }
 }
 
-impl Display for ContinuingBlock {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        writeln!(f, "continuing {{")?;
-
-        for stmt in &self.stmts {
-            writeln!(indented(f), "{}", stmt)?;
-        }
-
-        if let Some(break_if) = &self.break_if {
-            writeln!(indented(f), "break if {};", break_if)?;
-        }
-
-        write!(f, "}}")
-    }
-}
-
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
 pub struct WhileStatement {
+    pub attrs: Vec<StmtAttr>,
     pub condition: ExprNode,
     pub body: Vec<Statement>,
+    pub meta: NodeMeta,
 }
 
 impl WhileStatement {
     pub fn new(condition: ExprNode, body: Vec<Statement>) -> Self {
-        Self { condition, body }
-    }
-}
-
-impl Display for WhileStatement {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let WhileStatement { condition, body } = self;
-
-        writeln!(f, "while ({condition}) {{")?;
-
-        for stmt in body {
-            writeln!(indented(f), "{}", stmt)?;
+        Self {
+            attrs: vec![],
+            condition,
+            body,
+            meta: NodeMeta::default(),
         }
-
-        write!(f, "}}")
     }
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
 pub struct SwitchStatement {
+    pub attrs: Vec<StmtAttr>,
     pub selector: ExprNode,
+    /// Attributes of the body of the switch statement, which are written after the selector.
+    pub body_attrs: Vec<StmtAttr>,
     pub cases: Vec<SwitchCase>,
     pub default: Vec<Statement>,
+    pub meta: NodeMeta,
 }
 
 impl SwitchStatement {
     pub fn new(selector: ExprNode, cases: Vec<SwitchCase>, default: Vec<Statement>) -> Self {
         Self {
+            attrs: vec![],
             selector,
+            body_attrs: vec![],
             cases,
             default,
+            meta: NodeMeta::default(),
         }
     }
 }
 
-impl Display for SwitchStatement {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let SwitchStatement {
-            selector,
-            cases,
-            default,
-        } = self;
-
-        writeln!(f, "switch ({selector}) {{")?;
-
-        for SwitchCase { selector, body } in cases {
-            writeln!(indented(f), "case {selector}: {{")?;
-
-            for stmt in body {
-                writeln!(indented(&mut indented(f)), "{}", stmt)?;
-            }
-
-            writeln!(indented(f), "}}")?;
-        }
-
-        writeln!(indented(f), "default: {{")?;
-
-        for stmt in default {
-            writeln!(indented(&mut indented(f)), "{}", stmt)?;
-        }
-
-        writeln!(indented(f), "}}")?;
-
-        write!(f, "}}")
-    }
-}
-
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
 pub struct SwitchCase {
     pub selector: ExprNode,
     pub body: Vec<Statement>,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
 pub enum ForLoopInit {
     VarDecl(VarDeclStatement),
     LetDecl(LetDeclStatement),
@@ -519,7 +464,7 @@ pub enum ForLoopInit {
     Call(FnCallStatement),
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
 pub enum ForLoopUpdate {
     Assignment(AssignmentStatement),
     Increment(IncrementStatement),
@@ -527,107 +472,57 @@ pub enum ForLoopUpdate {
     Call(FnCallStatement),
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
 pub struct ForLoopHeader {
     pub init: Option<ForLoopInit>,
     pub condition: Option<ExprNode>,
     pub update: Option<ForLoopUpdate>,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
 pub struct ForLoopStatement {
+    pub attrs: Vec<StmtAttr>,
     pub header: Box<ForLoopHeader>,
     pub body: Vec<Statement>,
+    pub meta: NodeMeta,
 }
 
 impl ForLoopStatement {
     pub fn new(header: ForLoopHeader, body: Vec<Statement>) -> Self {
         Self {
+            attrs: vec![],
             header: Box::new(header),
             body,
+            meta: NodeMeta::default(),
         }
     }
 }
 
-impl Display for ForLoopStatement {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let ForLoopStatement { header, body } = self;
-
-        write!(f, "for (")?;
-
-        if let Some(init) = &header.init {
-            match init {
-                ForLoopInit::VarDecl(stmt) => stmt.fmt(f)?,
-                ForLoopInit::LetDecl(stmt) => stmt.fmt(f)?,
-                ForLoopInit::Assignment(stmt) => stmt.fmt(f)?,
-                ForLoopInit::Increment(stmt) => stmt.fmt(f)?,
-                ForLoopInit::Decrement(stmt) => stmt.fmt(f)?,
-                ForLoopInit::Call(stmt) => stmt.fmt(f)?,
-            }
-        }
-
-        write!(f, "; ")?;
-
-        if let Some(condition) = &header.condition {
-            write!(f, "{condition}")?;
-        }
-
-        write!(f, "; ")?;
-
-        if let Some(update) = &header.update {
-            match update {
-                ForLoopUpdate::Assignment(stmt) => stmt.fmt(f)?,
-                ForLoopUpdate::Increment(stmt) => stmt.fmt(f)?,
-                ForLoopUpdate::Decrement(stmt) => stmt.fmt(f)?,
-                ForLoopUpdate::Call(stmt) => stmt.fmt(f)?,
-            }
-        }
-
-        writeln!(f, ") {{")?;
-
-        for stmt in body {
-            writeln!(indented(f), "{}", stmt)?;
-        }
-
-        write!(f, "}}")
-    }
-}
-
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
 pub struct FnCallStatement {
     pub ident: String,
     pub args: Vec<ExprNode>,
+    pub meta: NodeMeta,
 }
 
 impl FnCallStatement {
     pub fn new(ident: String, args: Vec<ExprNode>) -> Self {
-        Self { ident, args }
-    }
-}
-
-impl Display for FnCallStatement {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}(", self.ident)?;
-
-        for (i, arg) in self.args.iter().enumerate() {
-            write!(f, "{arg}")?;
-            if i != self.args.len() - 1 {
-                write!(f, ", ")?;
-            }
+        Self {
+            ident,
+            args,
+            meta: NodeMeta::default(),
         }
-
-        write!(f, ")")
     }
 }
 
-#[derive(Debug, PartialEq, From)]
+#[derive(Debug, PartialEq, From, Serialize, Deserialize)]
 pub enum Statement {
     LetDecl(LetDeclStatement),
     VarDecl(VarDeclStatement),
     Assignment(AssignmentStatement),
     Increment(IncrementStatement),
     Decrement(DecrementStatement),
-    Compound(Vec<Statement>),
+    Compound(CompoundStatement),
     If(IfStatement),
     Return(ReturnStatement),
     Loop(LoopStatement),
@@ -646,39 +541,68 @@ impl Statement {
     /// This will panic if `self` is not a `Statement::CompoundStatement`.
     pub fn into_compound_statement(self) -> Vec<Statement> {
         match self {
-            Statement::Compound(stmts) => stmts,
+            Statement::Compound(stmt) => stmt.body,
             _ => unreachable!(),
         }
     }
+
+    /// Returns the metadata attached to this statement.
+    ///
+    /// `break`, `continue` and `fallthrough` statements do not carry any metadata.
+    pub fn meta(&self) -> Option<&NodeMeta> {
+        match self {
+            Statement::LetDecl(stmt) => Some(&stmt.meta),
+            Statement::VarDecl(stmt) => Some(&stmt.meta),
+            Statement::Assignment(stmt) => Some(&stmt.meta),
+            Statement::Increment(stmt) => Some(&stmt.meta),
+            Statement::Decrement(stmt) => Some(&stmt.meta),
+            Statement::If(stmt) => Some(&stmt.meta),
+            Statement::Return(stmt) => Some(&stmt.meta),
+            Statement::Loop(stmt) => Some(&stmt.meta),
+            Statement::While(stmt) => Some(&stmt.meta),
+            Statement::Switch(stmt) => Some(&stmt.meta),
+            Statement::ForLoop(stmt) => Some(&stmt.meta),
+            Statement::FnCall(stmt) => Some(&stmt.meta),
+            Statement::Compound(stmt) => Some(&stmt.meta),
+            Statement::Break | Statement::Continue | Statement::Fallthrough => None,
+        }
+    }
+
+    pub fn meta_mut(&mut self) -> Option<&mut NodeMeta> {
+        match self {
+            Statement::LetDecl(stmt) => Some(&mut stmt.meta),
+            Statement::VarDecl(stmt) => Some(&mut stmt.meta),
+            Statement::Assignment(stmt) => Some(&mut stmt.meta),
+            Statement::Increment(stmt) => Some(&mut stmt.meta),
+            Statement::Decrement(stmt) => Some(&mut stmt.meta),
+            Statement::If(stmt) => Some(&mut stmt.meta),
+            Statement::Return(stmt) => Some(&mut stmt.meta),
+            Statement::Loop(stmt) => Some(&mut stmt.meta),
+            Statement::While(stmt) => Some(&mut stmt.meta),
+            Statement::Switch(stmt) => Some(&mut stmt.meta),
+            Statement::ForLoop(stmt) => Some(&mut stmt.meta),
+            Statement::FnCall(stmt) => Some(&mut stmt.meta),
+            Statement::Compound(stmt) => Some(&mut stmt.meta),
+            Statement::Break | Statement::Continue | Statement::Fallthrough => None,
+        }
+    }
+
+    /// Returns the attributes of this statement, if it is a kind of statement that can have them.
+    pub fn attrs_mut(&mut self) -> Option<&mut Vec<StmtAttr>> {
+        match self {
+            Statement::Compound(stmt) => Some(&mut stmt.attrs),
+            Statement::If(stmt) => Some(&mut stmt.attrs),
+            Statement::Loop(stmt) => Some(&mut stmt.attrs),
+            Statement::While(stmt) => Some(&mut stmt.attrs),
+            Statement::Switch(stmt) => Some(&mut stmt.attrs),
+            Statement::ForLoop(stmt) => Some(&mut stmt.attrs),
+            _ => None,
+        }
+    }
 }
 
 impl Display for Statement {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            Statement::LetDecl(stmt) => write!(f, "{stmt};"),
-            Statement::VarDecl(stmt) => write!(f, "{stmt};"),
-            Statement::Assignment(stmt) => write!(f, "{stmt};"),
-            Statement::Increment(stmt) => write!(f, "{stmt};"),
-            Statement::Decrement(stmt) => write!(f, "{stmt};"),
-            Statement::Compound(stmts) => {
-                writeln!(f, "{{")?;
-
-                for stmt in stmts {
-                    writeln!(indented(f), "{}", stmt)?;
-                }
-
-                write!(f, "}}")
-            }
-            Statement::If(stmt) => stmt.fmt(f),
-            Statement::Return(stmt) => write!(f, "{stmt};"),
-            Statement::Loop(stmt) => stmt.fmt(f),
-            Statement::While(stmt) => stmt.fmt(f),
-            Statement::Break => write!(f, "break;"),
-            Statement::Continue => write!(f, "continue;"),
-            Statement::Fallthrough => write!(f, "fallthrough;"),
-            Statement::Switch(stmt) => stmt.fmt(f),
-            Statement::ForLoop(stmt) => stmt.fmt(f),
-            Statement::FnCall(stmt) => write!(f, "{stmt};"),
-        }
+        Writer::default().write_stmt(f, self)
     }
 }
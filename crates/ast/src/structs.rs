@@ -1,13 +1,17 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::fmt::Debug;
 use std::hash::Hash;
 use std::rc::Rc;
 
 use derive_more::Display;
+use serde::{Deserialize, Serialize};
 
 use crate::builtins::BuiltinValue;
 use crate::types::DataType;
 
-#[derive(Clone, Debug, PartialEq, Eq, Hash, strum::Display, strum::EnumString)]
+#[derive(
+    Clone, Debug, PartialEq, Eq, Hash, strum::Display, strum::EnumString, Serialize, Deserialize,
+)]
 #[strum(serialize_all = "snake_case")]
 pub enum InterpolationType {
     Perspective,
@@ -15,7 +19,9 @@ pub enum InterpolationType {
     Flat,
 }
 
-#[derive(Clone, Debug, PartialEq, Eq, Hash, strum::Display, strum::EnumString)]
+#[derive(
+    Clone, Debug, PartialEq, Eq, Hash, strum::Display, strum::EnumString, Serialize, Deserialize,
+)]
 #[strum(serialize_all = "snake_case")]
 pub enum InterpolationSampling {
     Center,
@@ -25,7 +31,7 @@ pub enum InterpolationSampling {
     Either,
 }
 
-#[derive(Clone, Debug, Display, Hash, PartialEq, Eq)]
+#[derive(Clone, Debug, Display, Hash, PartialEq, Eq, Serialize, Deserialize)]
 pub enum StructMemberAttr {
     #[display("align({_0})")]
     Align(u32),
@@ -43,7 +49,7 @@ pub enum StructMemberAttr {
     Size(u32),
 }
 
-#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+#[derive(Clone, Debug, Hash, PartialEq, Eq, Serialize, Deserialize)]
 pub struct StructMember {
     pub attrs: Vec<StructMemberAttr>,
     pub name: String,
@@ -64,13 +70,24 @@ impl StructMember {
     }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone)]
 pub struct StructDecl {
     pub name: String,
     pub members: Vec<Rc<StructMember>>,
     accessors: HashMap<DataType, Vec<Rc<StructMember>>>,
 }
 
+// The accessors are derived from the members, and the order in which they're printed would change
+// from run to run
+impl Debug for StructDecl {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("StructDecl")
+            .field("name", &self.name)
+            .field("members", &self.members)
+            .finish_non_exhaustive()
+    }
+}
+
 impl Hash for StructDecl {
     fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
         self.name.hash(state);
@@ -167,8 +184,185 @@ fn collect_struct_accessors(
             DataType::Ptr(_) => unreachable!("pointers are not storable"),
             DataType::Ref(_) => unreachable!("references are not storable"),
             DataType::Atomic(_) | DataType::AtomicCompareExchangeResult(_) => {}
+            DataType::StorageTexture(..) | DataType::Texture(_) => {
+                unreachable!("textures are not storable")
+            }
+            DataType::Sampler(_) => unreachable!("samplers are not storable"),
         }
     }
 
     accessors
 }
+
+impl Serialize for StructDecl {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+
+        let mut s = serializer.serialize_struct("StructDecl", 2)?;
+        s.serialize_field("name", &self.name)?;
+        s.serialize_field("members", &self.members)?;
+        s.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for StructDecl {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(rename = "StructDecl")]
+        struct Repr {
+            name: String,
+            members: Vec<Rc<StructMember>>,
+        }
+
+        let Repr { name, members } = Repr::deserialize(deserializer)?;
+        let accessors = collect_struct_accessors(&members);
+
+        Ok(StructDecl {
+            name,
+            members,
+            accessors,
+        })
+    }
+}
+
+/// Tracks the structs that have been deserialized so far, so that references to a struct type can
+/// be resolved to the same shared declaration.
+pub(crate) mod registry {
+    use std::cell::RefCell;
+    use std::collections::HashMap;
+    use std::rc::Rc;
+
+    use super::StructDecl;
+
+    thread_local! {
+        static STRUCTS: RefCell<Option<HashMap<String, Rc<StructDecl>>>> =
+            const { RefCell::new(None) };
+    }
+
+    /// Starts a new scope for struct declarations, which lasts until the returned guard is dropped.
+    pub fn enter() -> Scope {
+        Scope(STRUCTS.with(|it| it.replace(Some(HashMap::new()))))
+    }
+
+    pub fn register(decl: Rc<StructDecl>) {
+        STRUCTS.with(|it| {
+            if let Some(structs) = it.borrow_mut().as_mut() {
+                structs.insert(decl.name.clone(), decl);
+            }
+        })
+    }
+
+    pub fn lookup(name: &str) -> Option<Rc<StructDecl>> {
+        STRUCTS.with(|it| it.borrow().as_ref()?.get(name).cloned())
+    }
+
+    pub struct Scope(Option<HashMap<String, Rc<StructDecl>>>);
+
+    impl Drop for Scope {
+        fn drop(&mut self) {
+            STRUCTS.with(|it| *it.borrow_mut() = self.0.take());
+        }
+    }
+}
+
+/// Serializes a struct type as the name of its declaration.
+pub(crate) mod by_name {
+    use std::rc::Rc;
+
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    use super::{registry, StructDecl};
+
+    pub fn serialize<S: Serializer>(
+        decl: &Rc<StructDecl>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&decl.name)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Rc<StructDecl>, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        registry::lookup(&name)
+            .ok_or_else(|| D::Error::custom(format!("reference to undeclared struct `{name}`")))
+    }
+}
+
+/// Orders struct declarations so that each one comes after the structs that its members refer to.
+pub(crate) fn dependency_order<'a>(
+    decls: impl Iterator<Item = &'a Rc<StructDecl>>,
+) -> Vec<&'a StructDecl> {
+    fn visit<'a>(
+        decl: &'a StructDecl,
+        decls: &HashMap<&str, &'a StructDecl>,
+        visited: &mut HashSet<&'a str>,
+        order: &mut Vec<&'a StructDecl>,
+    ) {
+        if !visited.insert(&decl.name) {
+            return;
+        }
+
+        for member in &decl.members {
+            let mut data_type = &member.data_type;
+            while let DataType::Array(inner, _) = data_type {
+                data_type = inner;
+            }
+
+            if let DataType::Struct(dep) = data_type {
+                if let Some(dep) = decls.get(dep.name.as_str()) {
+                    visit(dep, decls, visited, order);
+                }
+            }
+        }
+
+        order.push(decl);
+    }
+
+    let decls: Vec<&StructDecl> = decls.map(|decl| &**decl).collect();
+    let by_name = decls
+        .iter()
+        .map(|decl| (decl.name.as_str(), *decl))
+        .collect();
+
+    let mut visited = HashSet::new();
+    let mut order = vec![];
+
+    for decl in decls {
+        visit(decl, &by_name, &mut visited, &mut order);
+    }
+
+    order
+}
+
+/// Deserializes a list of struct declarations, registering each one as it is read so that later
+/// declarations can refer to earlier ones.
+pub(crate) fn deserialize_decls<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Vec<Rc<StructDecl>>, D::Error> {
+    struct DeclsVisitor;
+
+    impl<'de> serde::de::Visitor<'de> for DeclsVisitor {
+        type Value = Vec<Rc<StructDecl>>;
+
+        fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+            f.write_str("a list of struct declarations")
+        }
+
+        fn visit_seq<A: serde::de::SeqAccess<'de>>(
+            self,
+            mut seq: A,
+        ) -> Result<Self::Value, A::Error> {
+            let mut decls = Vec::new();
+            while let Some(decl) = seq.next_element::<StructDecl>()? {
+                let decl = Rc::new(decl);
+                registry::register(decl.clone());
+                decls.push(decl);
+            }
+            Ok(decls)
+        }
+    }
+
+    deserializer.deserialize_seq(DeclsVisitor)
+}
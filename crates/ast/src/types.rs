@@ -2,10 +2,11 @@ use std::fmt::{self, Display};
 use std::rc::Rc;
 
 use derive_more::Display;
+use serde::{Deserialize, Serialize};
 
 use crate::{AccessMode, StorageClass, StructDecl};
 
-#[derive(Clone, Copy, Debug, Display, Hash, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, Display, Hash, PartialEq, Eq, Serialize, Deserialize)]
 pub enum ScalarType {
     #[display("bool")]
     Bool,
@@ -19,7 +20,160 @@ pub enum ScalarType {
     F16,
 }
 
-#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, Display, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TextureDimension {
+    #[display("1d")]
+    D1,
+    #[display("2d")]
+    D2,
+    #[display("3d")]
+    D3,
+}
+
+impl TextureDimension {
+    /// Returns the number of components in a texel coordinate for this dimension.
+    pub fn coord_size(&self) -> u8 {
+        match self {
+            TextureDimension::D1 => 1,
+            TextureDimension::D2 => 2,
+            TextureDimension::D3 => 3,
+        }
+    }
+}
+
+/// The dimension of a sampled or depth texture, which unlike a storage texture may also be a cube
+/// or an array of layers.
+#[derive(Clone, Copy, Debug, Display, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TextureViewDimension {
+    #[display("1d")]
+    D1,
+    #[display("2d")]
+    D2,
+    #[display("2d_array")]
+    D2Array,
+    #[display("3d")]
+    D3,
+    #[display("cube")]
+    Cube,
+    #[display("cube_array")]
+    CubeArray,
+}
+
+/// A texture that is read through `textureLoad` or sampled, as opposed to a storage texture.
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TextureType {
+    Sampled(TextureViewDimension, ScalarType),
+    Multisampled(ScalarType),
+    Depth(TextureViewDimension),
+    DepthMultisampled,
+    External,
+}
+
+impl TextureType {
+    /// Returns the type of a texel that is loaded or sampled from the texture.
+    pub fn texel_type(&self) -> DataType {
+        match self {
+            TextureType::Sampled(_, t) | TextureType::Multisampled(t) => DataType::Vector(4, *t),
+            TextureType::Depth(_) | TextureType::DepthMultisampled => ScalarType::F32.into(),
+            TextureType::External => DataType::Vector(4, ScalarType::F32),
+        }
+    }
+
+    /// Returns the type of the texture's size, as returned by `textureDimensions`.
+    pub fn size_type(&self) -> DataType {
+        match self {
+            TextureType::Sampled(TextureViewDimension::D1, _) => ScalarType::U32.into(),
+            TextureType::Sampled(TextureViewDimension::D3, _) => {
+                DataType::Vector(3, ScalarType::U32)
+            }
+            _ => DataType::Vector(2, ScalarType::U32),
+        }
+    }
+}
+
+impl Display for TextureType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TextureType::Sampled(dim, t) => write!(f, "texture_{dim}<{t}>"),
+            TextureType::Multisampled(t) => write!(f, "texture_multisampled_2d<{t}>"),
+            TextureType::Depth(dim) => write!(f, "texture_depth_{dim}"),
+            TextureType::DepthMultisampled => write!(f, "texture_depth_multisampled_2d"),
+            TextureType::External => write!(f, "texture_external"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Display, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SamplerKind {
+    #[display("sampler")]
+    Sampler,
+    #[display("sampler_comparison")]
+    Comparison,
+}
+
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Hash,
+    PartialEq,
+    Eq,
+    strum::Display,
+    strum::EnumIter,
+    strum::EnumString,
+    Serialize,
+    Deserialize,
+)]
+#[strum(serialize_all = "lowercase")]
+pub enum TexelFormat {
+    Rgba8unorm,
+    Rgba8uint,
+    Rgba8sint,
+    Rgba32uint,
+    Rgba32sint,
+    Rgba32float,
+    R32uint,
+    R32sint,
+    R32float,
+}
+
+impl TexelFormat {
+    /// Returns the scalar type of the texel values that are loaded from or stored to a texture
+    /// with this format.
+    pub fn channel_type(&self) -> ScalarType {
+        match self {
+            TexelFormat::Rgba8unorm | TexelFormat::Rgba32float | TexelFormat::R32float => {
+                ScalarType::F32
+            }
+            TexelFormat::Rgba8uint | TexelFormat::Rgba32uint | TexelFormat::R32uint => {
+                ScalarType::U32
+            }
+            TexelFormat::Rgba8sint | TexelFormat::Rgba32sint | TexelFormat::R32sint => {
+                ScalarType::I32
+            }
+        }
+    }
+
+    /// Returns the size in bytes of a single texel.
+    pub fn texel_size(&self) -> u32 {
+        match self {
+            TexelFormat::Rgba8unorm | TexelFormat::Rgba8uint | TexelFormat::Rgba8sint => 4,
+            TexelFormat::Rgba32uint | TexelFormat::Rgba32sint | TexelFormat::Rgba32float => 16,
+            TexelFormat::R32uint | TexelFormat::R32sint | TexelFormat::R32float => 4,
+        }
+    }
+
+    /// Returns `true` if storage textures with this format may be declared with `read` or
+    /// `read_write` access without requiring any additional features.
+    pub fn is_readable(&self) -> bool {
+        matches!(
+            self,
+            TexelFormat::R32uint | TexelFormat::R32sint | TexelFormat::R32float
+        )
+    }
+}
+
+#[derive(Clone, Debug, Hash, PartialEq, Eq, Serialize, Deserialize)]
 pub struct MemoryViewType {
     pub inner: Rc<DataType>,
     pub storage_class: StorageClass,
@@ -53,17 +207,22 @@ impl Display for MemoryViewType {
     }
 }
 
-#[derive(Clone, Hash, PartialEq, Eq)]
+#[derive(Clone, Hash, PartialEq, Eq, Serialize, Deserialize)]
 pub enum DataType {
     Scalar(ScalarType),
     Vector(u8, ScalarType),
     Matrix(u8, u8, ScalarType),
     Array(Rc<DataType>, Option<u32>),
-    Struct(Rc<StructDecl>),
+    /// Struct types are serialized by name, and resolved against the structs declared earlier in
+    /// the same module when deserializing.
+    Struct(#[serde(with = "crate::structs::by_name")] Rc<StructDecl>),
     Ptr(MemoryViewType),
     Ref(MemoryViewType),
     Atomic(ScalarType),
     AtomicCompareExchangeResult(ScalarType),
+    StorageTexture(TextureDimension, TexelFormat, AccessMode),
+    Texture(TextureType),
+    Sampler(SamplerKind),
 }
 
 impl DataType {
@@ -130,6 +289,18 @@ impl DataType {
         matches!(self, Self::Matrix(..))
     }
 
+    #[must_use]
+    pub fn is_texture(&self) -> bool {
+        matches!(self, Self::StorageTexture(..) | Self::Texture(..))
+    }
+
+    /// Returns `true` if the data type is a texture or sampler, which are opaque handles to
+    /// resources.
+    #[must_use]
+    pub fn is_handle(&self) -> bool {
+        self.is_texture() || matches!(self, Self::Sampler(..))
+    }
+
     /// Returns `true` if the data type is a scalar or vector of integers.
     pub fn is_integer(&self) -> bool {
         matches!(self.as_scalar(), Some(ScalarType::I32 | ScalarType::U32))
@@ -151,6 +322,14 @@ impl fmt::Debug for DataType {
                 .debug_tuple("AtomicCompareExchangeResult")
                 .field(arg0)
                 .finish(),
+            Self::StorageTexture(dim, format, access) => f
+                .debug_tuple("StorageTexture")
+                .field(dim)
+                .field(format)
+                .field(access)
+                .finish(),
+            Self::Texture(arg0) => f.debug_tuple("Texture").field(arg0).finish(),
+            Self::Sampler(arg0) => f.debug_tuple("Sampler").field(arg0).finish(),
         }
     }
 }
@@ -175,6 +354,11 @@ impl Display for DataType {
             DataType::AtomicCompareExchangeResult(t) => {
                 write!(f, "__atomic_compare_exchange_result<{}>", t)
             }
+            DataType::StorageTexture(dim, format, access) => {
+                write!(f, "texture_storage_{dim}<{format}, {access}>")
+            }
+            DataType::Texture(texture) => write!(f, "{texture}"),
+            DataType::Sampler(kind) => write!(f, "{kind}"),
         }
     }
 }
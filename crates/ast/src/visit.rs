@@ -0,0 +1,367 @@
+//! Generic traversals over the AST.
+//!
+//! [`Visitor`] walks the tree by shared reference, [`VisitorMut`] walks it by mutable reference,
+//! and [`Fold`] consumes the tree and rebuilds it. Every method has a default implementation which
+//! recurses into the children of the node through the matching `walk_*` (or `fold_*`) function, so
+//! an implementation only needs to override the methods for the nodes it cares about. Overrides
+//! that still want to visit the children of a node should call the corresponding `walk_*`
+//! function themselves.
+
+pub mod fold;
+mod visit_mut;
+
+pub use fold::Fold;
+pub use visit_mut::*;
+
+use crate::{
+    AssignmentLhs, AssignmentStatement, ContinuingBlock, DecrementStatement, Else, Expr, ExprNode,
+    FnAttr, FnCallStatement, FnDecl, ForLoopHeader, ForLoopInit, ForLoopStatement, ForLoopUpdate,
+    GlobalConstDecl, GlobalDecl, GlobalVarDecl, IfStatement, IncrementStatement, LetDeclStatement,
+    LhsExpr, LhsExprNode, LoopStatement, Module, Postfix, Statement, SwitchCase, SwitchStatement,
+    VarDeclStatement, WhileStatement,
+};
+
+pub trait Visitor<'a> {
+    fn visit_module(&mut self, module: &'a Module) {
+        walk_module(self, module)
+    }
+
+    fn visit_global_const(&mut self, decl: &'a GlobalConstDecl) {
+        walk_global_const(self, decl)
+    }
+
+    fn visit_global_var(&mut self, decl: &'a GlobalVarDecl) {
+        walk_global_var(self, decl)
+    }
+
+    fn visit_fn(&mut self, decl: &'a FnDecl) {
+        walk_fn(self, decl)
+    }
+
+    /// Visits a sequence of statements which forms its own scope.
+    fn visit_block(&mut self, block: &'a [Statement]) {
+        walk_block(self, block)
+    }
+
+    fn visit_stmt(&mut self, stmt: &'a Statement) {
+        walk_stmt(self, stmt)
+    }
+
+    fn visit_let_decl(&mut self, stmt: &'a LetDeclStatement) {
+        walk_let_decl(self, stmt)
+    }
+
+    fn visit_var_decl(&mut self, stmt: &'a VarDeclStatement) {
+        walk_var_decl(self, stmt)
+    }
+
+    fn visit_assignment(&mut self, stmt: &'a AssignmentStatement) {
+        walk_assignment(self, stmt)
+    }
+
+    fn visit_increment(&mut self, stmt: &'a IncrementStatement) {
+        walk_increment(self, stmt)
+    }
+
+    fn visit_decrement(&mut self, stmt: &'a DecrementStatement) {
+        walk_decrement(self, stmt)
+    }
+
+    fn visit_if(&mut self, stmt: &'a IfStatement) {
+        walk_if(self, stmt)
+    }
+
+    fn visit_else(&mut self, else_: &'a Else) {
+        walk_else(self, else_)
+    }
+
+    fn visit_loop(&mut self, stmt: &'a LoopStatement) {
+        walk_loop(self, stmt)
+    }
+
+    fn visit_continuing(&mut self, continuing: &'a ContinuingBlock) {
+        walk_continuing(self, continuing)
+    }
+
+    fn visit_while(&mut self, stmt: &'a WhileStatement) {
+        walk_while(self, stmt)
+    }
+
+    fn visit_switch(&mut self, stmt: &'a SwitchStatement) {
+        walk_switch(self, stmt)
+    }
+
+    fn visit_switch_case(&mut self, case: &'a SwitchCase) {
+        walk_switch_case(self, case)
+    }
+
+    fn visit_for_loop(&mut self, stmt: &'a ForLoopStatement) {
+        walk_for_loop(self, stmt)
+    }
+
+    fn visit_for_header(&mut self, header: &'a ForLoopHeader) {
+        walk_for_header(self, header)
+    }
+
+    fn visit_for_init(&mut self, init: &'a ForLoopInit) {
+        walk_for_init(self, init)
+    }
+
+    fn visit_for_update(&mut self, update: &'a ForLoopUpdate) {
+        walk_for_update(self, update)
+    }
+
+    fn visit_fn_call_stmt(&mut self, stmt: &'a FnCallStatement) {
+        walk_fn_call_stmt(self, stmt)
+    }
+
+    fn visit_assignment_lhs(&mut self, lhs: &'a AssignmentLhs) {
+        walk_assignment_lhs(self, lhs)
+    }
+
+    fn visit_lhs_expr(&mut self, node: &'a LhsExprNode) {
+        walk_lhs_expr(self, node)
+    }
+
+    fn visit_expr(&mut self, node: &'a ExprNode) {
+        walk_expr(self, node)
+    }
+
+    fn visit_postfix(&mut self, postfix: &'a Postfix) {
+        walk_postfix(self, postfix)
+    }
+}
+
+pub fn walk_module<'a, V: Visitor<'a> + ?Sized>(v: &mut V, module: &'a Module) {
+    for decl in &module.decls {
+        match decl {
+            GlobalDecl::Struct(_) => {}
+            GlobalDecl::Const(decl) => v.visit_global_const(decl),
+            GlobalDecl::Var(decl) => v.visit_global_var(decl),
+            GlobalDecl::Fn(decl) => v.visit_fn(decl),
+        }
+    }
+}
+
+pub fn walk_global_const<'a, V: Visitor<'a> + ?Sized>(v: &mut V, decl: &'a GlobalConstDecl) {
+    v.visit_expr(&decl.initializer);
+}
+
+pub fn walk_global_var<'a, V: Visitor<'a> + ?Sized>(v: &mut V, decl: &'a GlobalVarDecl) {
+    if let Some(initializer) = &decl.initializer {
+        v.visit_expr(initializer);
+    }
+}
+
+pub fn walk_fn<'a, V: Visitor<'a> + ?Sized>(v: &mut V, decl: &'a FnDecl) {
+    for attr in &decl.attrs {
+        if let FnAttr::WorkgroupSize(args) = attr {
+            for arg in args {
+                v.visit_expr(arg);
+            }
+        }
+    }
+
+    v.visit_block(&decl.body);
+}
+
+pub fn walk_block<'a, V: Visitor<'a> + ?Sized>(v: &mut V, block: &'a [Statement]) {
+    for stmt in block {
+        v.visit_stmt(stmt);
+    }
+}
+
+pub fn walk_stmt<'a, V: Visitor<'a> + ?Sized>(v: &mut V, stmt: &'a Statement) {
+    match stmt {
+        Statement::LetDecl(stmt) => v.visit_let_decl(stmt),
+        Statement::VarDecl(stmt) => v.visit_var_decl(stmt),
+        Statement::Assignment(stmt) => v.visit_assignment(stmt),
+        Statement::Increment(stmt) => v.visit_increment(stmt),
+        Statement::Decrement(stmt) => v.visit_decrement(stmt),
+        Statement::Compound(stmt) => v.visit_block(&stmt.body),
+        Statement::If(stmt) => v.visit_if(stmt),
+        Statement::Return(stmt) => {
+            if let Some(value) = &stmt.value {
+                v.visit_expr(value);
+            }
+        }
+        Statement::Loop(stmt) => v.visit_loop(stmt),
+        Statement::While(stmt) => v.visit_while(stmt),
+        Statement::Switch(stmt) => v.visit_switch(stmt),
+        Statement::ForLoop(stmt) => v.visit_for_loop(stmt),
+        Statement::FnCall(stmt) => v.visit_fn_call_stmt(stmt),
+        Statement::Break | Statement::Continue | Statement::Fallthrough => {}
+    }
+}
+
+pub fn walk_let_decl<'a, V: Visitor<'a> + ?Sized>(v: &mut V, stmt: &'a LetDeclStatement) {
+    v.visit_expr(&stmt.initializer);
+}
+
+pub fn walk_var_decl<'a, V: Visitor<'a> + ?Sized>(v: &mut V, stmt: &'a VarDeclStatement) {
+    if let Some(initializer) = &stmt.initializer {
+        v.visit_expr(initializer);
+    }
+}
+
+pub fn walk_assignment<'a, V: Visitor<'a> + ?Sized>(v: &mut V, stmt: &'a AssignmentStatement) {
+    v.visit_assignment_lhs(&stmt.lhs);
+    v.visit_expr(&stmt.rhs);
+}
+
+pub fn walk_increment<'a, V: Visitor<'a> + ?Sized>(v: &mut V, stmt: &'a IncrementStatement) {
+    v.visit_assignment_lhs(&stmt.lhs);
+}
+
+pub fn walk_decrement<'a, V: Visitor<'a> + ?Sized>(v: &mut V, stmt: &'a DecrementStatement) {
+    v.visit_assignment_lhs(&stmt.lhs);
+}
+
+pub fn walk_if<'a, V: Visitor<'a> + ?Sized>(v: &mut V, stmt: &'a IfStatement) {
+    v.visit_expr(&stmt.condition);
+    v.visit_block(&stmt.body);
+
+    if let Some(else_) = &stmt.else_ {
+        v.visit_else(else_);
+    }
+}
+
+pub fn walk_else<'a, V: Visitor<'a> + ?Sized>(v: &mut V, else_: &'a Else) {
+    match else_ {
+        Else::If(stmt) => v.visit_if(stmt),
+        Else::Else(block) => v.visit_block(block),
+    }
+}
+
+pub fn walk_loop<'a, V: Visitor<'a> + ?Sized>(v: &mut V, stmt: &'a LoopStatement) {
+    v.visit_block(&stmt.body);
+
+    if let Some(continuing) = &stmt.continuing {
+        v.visit_continuing(continuing);
+    }
+}
+
+pub fn walk_continuing<'a, V: Visitor<'a> + ?Sized>(v: &mut V, continuing: &'a ContinuingBlock) {
+    v.visit_block(&continuing.stmts);
+
+    if let Some(break_if) = &continuing.break_if {
+        v.visit_expr(break_if);
+    }
+}
+
+pub fn walk_while<'a, V: Visitor<'a> + ?Sized>(v: &mut V, stmt: &'a WhileStatement) {
+    v.visit_expr(&stmt.condition);
+    v.visit_block(&stmt.body);
+}
+
+pub fn walk_switch<'a, V: Visitor<'a> + ?Sized>(v: &mut V, stmt: &'a SwitchStatement) {
+    v.visit_expr(&stmt.selector);
+
+    for case in &stmt.cases {
+        v.visit_switch_case(case);
+    }
+
+    v.visit_block(&stmt.default);
+}
+
+pub fn walk_switch_case<'a, V: Visitor<'a> + ?Sized>(v: &mut V, case: &'a SwitchCase) {
+    v.visit_expr(&case.selector);
+    v.visit_block(&case.body);
+}
+
+pub fn walk_for_loop<'a, V: Visitor<'a> + ?Sized>(v: &mut V, stmt: &'a ForLoopStatement) {
+    v.visit_for_header(&stmt.header);
+    v.visit_block(&stmt.body);
+}
+
+pub fn walk_for_header<'a, V: Visitor<'a> + ?Sized>(v: &mut V, header: &'a ForLoopHeader) {
+    if let Some(init) = &header.init {
+        v.visit_for_init(init);
+    }
+
+    if let Some(condition) = &header.condition {
+        v.visit_expr(condition);
+    }
+
+    if let Some(update) = &header.update {
+        v.visit_for_update(update);
+    }
+}
+
+pub fn walk_for_init<'a, V: Visitor<'a> + ?Sized>(v: &mut V, init: &'a ForLoopInit) {
+    match init {
+        ForLoopInit::VarDecl(stmt) => v.visit_var_decl(stmt),
+        ForLoopInit::LetDecl(stmt) => v.visit_let_decl(stmt),
+        ForLoopInit::Assignment(stmt) => v.visit_assignment(stmt),
+        ForLoopInit::Increment(stmt) => v.visit_increment(stmt),
+        ForLoopInit::Decrement(stmt) => v.visit_decrement(stmt),
+        ForLoopInit::Call(stmt) => v.visit_fn_call_stmt(stmt),
+    }
+}
+
+pub fn walk_for_update<'a, V: Visitor<'a> + ?Sized>(v: &mut V, update: &'a ForLoopUpdate) {
+    match update {
+        ForLoopUpdate::Assignment(stmt) => v.visit_assignment(stmt),
+        ForLoopUpdate::Increment(stmt) => v.visit_increment(stmt),
+        ForLoopUpdate::Decrement(stmt) => v.visit_decrement(stmt),
+        ForLoopUpdate::Call(stmt) => v.visit_fn_call_stmt(stmt),
+    }
+}
+
+pub fn walk_fn_call_stmt<'a, V: Visitor<'a> + ?Sized>(v: &mut V, stmt: &'a FnCallStatement) {
+    for arg in &stmt.args {
+        v.visit_expr(arg);
+    }
+}
+
+pub fn walk_assignment_lhs<'a, V: Visitor<'a> + ?Sized>(v: &mut V, lhs: &'a AssignmentLhs) {
+    match lhs {
+        AssignmentLhs::Phony => {}
+        AssignmentLhs::Expr(node) => v.visit_lhs_expr(node),
+    }
+}
+
+pub fn walk_lhs_expr<'a, V: Visitor<'a> + ?Sized>(v: &mut V, node: &'a LhsExprNode) {
+    match &node.expr {
+        LhsExpr::Ident(_) => {}
+        LhsExpr::Postfix(inner, postfix) => {
+            v.visit_lhs_expr(inner);
+            v.visit_postfix(postfix);
+        }
+        LhsExpr::Deref(inner) => v.visit_lhs_expr(inner),
+        LhsExpr::AddressOf(inner) => v.visit_lhs_expr(inner),
+    }
+}
+
+pub fn walk_expr<'a, V: Visitor<'a> + ?Sized>(v: &mut V, node: &'a ExprNode) {
+    match &node.expr {
+        Expr::Lit(_) => {}
+        Expr::TypeCons(expr) => {
+            for arg in &expr.args {
+                v.visit_expr(arg);
+            }
+        }
+        Expr::Var(_) => {}
+        Expr::Postfix(expr) => {
+            v.visit_expr(&expr.inner);
+            v.visit_postfix(&expr.postfix);
+        }
+        Expr::UnOp(expr) => v.visit_expr(&expr.inner),
+        Expr::BinOp(expr) => {
+            v.visit_expr(&expr.left);
+            v.visit_expr(&expr.right);
+        }
+        Expr::FnCall(expr) => {
+            for arg in &expr.args {
+                v.visit_expr(arg);
+            }
+        }
+    }
+}
+
+pub fn walk_postfix<'a, V: Visitor<'a> + ?Sized>(v: &mut V, postfix: &'a Postfix) {
+    match postfix {
+        Postfix::Index(index) => v.visit_expr(index),
+        Postfix::Member(_) => {}
+    }
+}
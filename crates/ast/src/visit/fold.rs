@@ -0,0 +1,409 @@
+//! A rewriting traversal which takes ownership of each node and returns its replacement.
+
+use crate::{
+    AssignmentLhs, AssignmentStatement, BinOpExpr, CompoundStatement, ContinuingBlock,
+    DecrementStatement, Else, Expr, ExprNode, FnAttr, FnCallExpr, FnCallStatement, FnDecl,
+    ForLoopHeader, ForLoopInit, ForLoopStatement, ForLoopUpdate, GlobalConstDecl, GlobalDecl,
+    GlobalVarDecl, IfStatement, IncrementStatement, LetDeclStatement, LhsExpr, LhsExprNode,
+    LoopStatement, Module, Postfix, PostfixExpr, ReturnStatement, Statement, SwitchCase,
+    SwitchStatement, TypeConsExpr, UnOpExpr, VarDeclStatement, WhileStatement,
+};
+
+pub trait Fold {
+    fn fold_module(&mut self, module: Module) -> Module {
+        fold_module(self, module)
+    }
+
+    fn fold_global_const(&mut self, decl: GlobalConstDecl) -> GlobalConstDecl {
+        fold_global_const(self, decl)
+    }
+
+    fn fold_global_var(&mut self, decl: GlobalVarDecl) -> GlobalVarDecl {
+        fold_global_var(self, decl)
+    }
+
+    fn fold_fn(&mut self, decl: FnDecl) -> FnDecl {
+        fold_fn(self, decl)
+    }
+
+    /// Folds a sequence of statements which forms its own scope.
+    fn fold_block(&mut self, block: Vec<Statement>) -> Vec<Statement> {
+        fold_block(self, block)
+    }
+
+    fn fold_stmt(&mut self, stmt: Statement) -> Statement {
+        fold_stmt(self, stmt)
+    }
+
+    fn fold_let_decl(&mut self, stmt: LetDeclStatement) -> LetDeclStatement {
+        fold_let_decl(self, stmt)
+    }
+
+    fn fold_var_decl(&mut self, stmt: VarDeclStatement) -> VarDeclStatement {
+        fold_var_decl(self, stmt)
+    }
+
+    fn fold_assignment(&mut self, stmt: AssignmentStatement) -> AssignmentStatement {
+        fold_assignment(self, stmt)
+    }
+
+    fn fold_increment(&mut self, stmt: IncrementStatement) -> IncrementStatement {
+        fold_increment(self, stmt)
+    }
+
+    fn fold_decrement(&mut self, stmt: DecrementStatement) -> DecrementStatement {
+        fold_decrement(self, stmt)
+    }
+
+    fn fold_if(&mut self, stmt: IfStatement) -> IfStatement {
+        fold_if(self, stmt)
+    }
+
+    fn fold_else(&mut self, else_: Else) -> Else {
+        fold_else(self, else_)
+    }
+
+    fn fold_loop(&mut self, stmt: LoopStatement) -> LoopStatement {
+        fold_loop(self, stmt)
+    }
+
+    fn fold_continuing(&mut self, continuing: ContinuingBlock) -> ContinuingBlock {
+        fold_continuing(self, continuing)
+    }
+
+    fn fold_while(&mut self, stmt: WhileStatement) -> WhileStatement {
+        fold_while(self, stmt)
+    }
+
+    fn fold_switch(&mut self, stmt: SwitchStatement) -> SwitchStatement {
+        fold_switch(self, stmt)
+    }
+
+    fn fold_switch_case(&mut self, case: SwitchCase) -> SwitchCase {
+        fold_switch_case(self, case)
+    }
+
+    fn fold_for_loop(&mut self, stmt: ForLoopStatement) -> ForLoopStatement {
+        fold_for_loop(self, stmt)
+    }
+
+    fn fold_for_header(&mut self, header: ForLoopHeader) -> ForLoopHeader {
+        fold_for_header(self, header)
+    }
+
+    fn fold_for_init(&mut self, init: ForLoopInit) -> ForLoopInit {
+        fold_for_init(self, init)
+    }
+
+    fn fold_for_update(&mut self, update: ForLoopUpdate) -> ForLoopUpdate {
+        fold_for_update(self, update)
+    }
+
+    fn fold_fn_call_stmt(&mut self, stmt: FnCallStatement) -> FnCallStatement {
+        fold_fn_call_stmt(self, stmt)
+    }
+
+    fn fold_assignment_lhs(&mut self, lhs: AssignmentLhs) -> AssignmentLhs {
+        fold_assignment_lhs(self, lhs)
+    }
+
+    fn fold_lhs_expr(&mut self, node: LhsExprNode) -> LhsExprNode {
+        fold_lhs_expr(self, node)
+    }
+
+    fn fold_expr(&mut self, node: ExprNode) -> ExprNode {
+        fold_expr(self, node)
+    }
+
+    fn fold_postfix(&mut self, postfix: Postfix) -> Postfix {
+        fold_postfix(self, postfix)
+    }
+}
+
+pub fn fold_module<F: Fold + ?Sized>(f: &mut F, module: Module) -> Module {
+    Module {
+        decls: module
+            .decls
+            .into_iter()
+            .map(|decl| match decl {
+                GlobalDecl::Struct(decl) => GlobalDecl::Struct(decl),
+                GlobalDecl::Const(decl) => GlobalDecl::Const(f.fold_global_const(decl)),
+                GlobalDecl::Var(decl) => GlobalDecl::Var(f.fold_global_var(decl)),
+                GlobalDecl::Fn(decl) => GlobalDecl::Fn(f.fold_fn(decl)),
+            })
+            .collect(),
+        ..module
+    }
+}
+
+pub fn fold_global_const<F: Fold + ?Sized>(f: &mut F, decl: GlobalConstDecl) -> GlobalConstDecl {
+    GlobalConstDecl {
+        initializer: f.fold_expr(decl.initializer),
+        ..decl
+    }
+}
+
+pub fn fold_global_var<F: Fold + ?Sized>(f: &mut F, decl: GlobalVarDecl) -> GlobalVarDecl {
+    GlobalVarDecl {
+        initializer: decl.initializer.map(|e| f.fold_expr(e)),
+        ..decl
+    }
+}
+
+pub fn fold_fn<F: Fold + ?Sized>(f: &mut F, decl: FnDecl) -> FnDecl {
+    let attrs = decl
+        .attrs
+        .into_iter()
+        .map(|attr| match attr {
+            FnAttr::WorkgroupSize(args) => {
+                FnAttr::WorkgroupSize(args.into_iter().map(|e| f.fold_expr(e)).collect())
+            }
+            attr => attr,
+        })
+        .collect();
+
+    FnDecl {
+        attrs,
+        body: f.fold_block(decl.body),
+        ..decl
+    }
+}
+
+pub fn fold_block<F: Fold + ?Sized>(f: &mut F, block: Vec<Statement>) -> Vec<Statement> {
+    block.into_iter().map(|s| f.fold_stmt(s)).collect()
+}
+
+pub fn fold_stmt<F: Fold + ?Sized>(f: &mut F, stmt: Statement) -> Statement {
+    match stmt {
+        Statement::LetDecl(stmt) => f.fold_let_decl(stmt).into(),
+        Statement::VarDecl(stmt) => f.fold_var_decl(stmt).into(),
+        Statement::Assignment(stmt) => f.fold_assignment(stmt).into(),
+        Statement::Increment(stmt) => f.fold_increment(stmt).into(),
+        Statement::Decrement(stmt) => f.fold_decrement(stmt).into(),
+        Statement::Compound(stmt) => CompoundStatement {
+            body: f.fold_block(stmt.body),
+            ..stmt
+        }
+        .into(),
+        Statement::If(stmt) => f.fold_if(stmt).into(),
+        Statement::Return(ReturnStatement { value, meta }) => ReturnStatement {
+            value: value.map(|e| f.fold_expr(e)),
+            meta,
+        }
+        .into(),
+        Statement::Loop(stmt) => f.fold_loop(stmt).into(),
+        Statement::While(stmt) => f.fold_while(stmt).into(),
+        Statement::Switch(stmt) => f.fold_switch(stmt).into(),
+        Statement::ForLoop(stmt) => f.fold_for_loop(stmt).into(),
+        Statement::FnCall(stmt) => f.fold_fn_call_stmt(stmt).into(),
+        stmt @ (Statement::Break | Statement::Continue | Statement::Fallthrough) => stmt,
+    }
+}
+
+pub fn fold_let_decl<F: Fold + ?Sized>(f: &mut F, stmt: LetDeclStatement) -> LetDeclStatement {
+    LetDeclStatement {
+        initializer: f.fold_expr(stmt.initializer),
+        ..stmt
+    }
+}
+
+pub fn fold_var_decl<F: Fold + ?Sized>(f: &mut F, stmt: VarDeclStatement) -> VarDeclStatement {
+    VarDeclStatement {
+        initializer: stmt.initializer.map(|e| f.fold_expr(e)),
+        ..stmt
+    }
+}
+
+pub fn fold_assignment<F: Fold + ?Sized>(
+    f: &mut F,
+    stmt: AssignmentStatement,
+) -> AssignmentStatement {
+    let lhs = f.fold_assignment_lhs(stmt.lhs);
+    let rhs = f.fold_expr(stmt.rhs);
+    AssignmentStatement { lhs, rhs, ..stmt }
+}
+
+pub fn fold_increment<F: Fold + ?Sized>(f: &mut F, stmt: IncrementStatement) -> IncrementStatement {
+    IncrementStatement {
+        lhs: f.fold_assignment_lhs(stmt.lhs),
+        ..stmt
+    }
+}
+
+pub fn fold_decrement<F: Fold + ?Sized>(f: &mut F, stmt: DecrementStatement) -> DecrementStatement {
+    DecrementStatement {
+        lhs: f.fold_assignment_lhs(stmt.lhs),
+        ..stmt
+    }
+}
+
+pub fn fold_if<F: Fold + ?Sized>(f: &mut F, stmt: IfStatement) -> IfStatement {
+    let condition = f.fold_expr(stmt.condition);
+    let body = f.fold_block(stmt.body);
+    let else_ = stmt.else_.map(|else_| Box::new(f.fold_else(*else_)));
+    IfStatement {
+        condition,
+        body,
+        else_,
+        ..stmt
+    }
+}
+
+pub fn fold_else<F: Fold + ?Sized>(f: &mut F, else_: Else) -> Else {
+    match else_ {
+        Else::If(stmt) => Else::If(f.fold_if(stmt)),
+        Else::Else(block) => Else::Else(f.fold_block(block)),
+    }
+}
+
+pub fn fold_loop<F: Fold + ?Sized>(f: &mut F, stmt: LoopStatement) -> LoopStatement {
+    let body = f.fold_block(stmt.body);
+    let continuing = stmt.continuing.map(|c| f.fold_continuing(c));
+    LoopStatement {
+        body,
+        continuing,
+        ..stmt
+    }
+}
+
+pub fn fold_continuing<F: Fold + ?Sized>(
+    f: &mut F,
+    continuing: ContinuingBlock,
+) -> ContinuingBlock {
+    let stmts = f.fold_block(continuing.stmts);
+    let break_if = continuing.break_if.map(|e| f.fold_expr(e));
+    ContinuingBlock { stmts, break_if }
+}
+
+pub fn fold_while<F: Fold + ?Sized>(f: &mut F, stmt: WhileStatement) -> WhileStatement {
+    let condition = f.fold_expr(stmt.condition);
+    let body = f.fold_block(stmt.body);
+    WhileStatement {
+        condition,
+        body,
+        ..stmt
+    }
+}
+
+pub fn fold_switch<F: Fold + ?Sized>(f: &mut F, stmt: SwitchStatement) -> SwitchStatement {
+    let selector = f.fold_expr(stmt.selector);
+    let cases = stmt
+        .cases
+        .into_iter()
+        .map(|case| f.fold_switch_case(case))
+        .collect();
+    let default = f.fold_block(stmt.default);
+    SwitchStatement {
+        selector,
+        cases,
+        default,
+        ..stmt
+    }
+}
+
+pub fn fold_switch_case<F: Fold + ?Sized>(f: &mut F, case: SwitchCase) -> SwitchCase {
+    let selector = f.fold_expr(case.selector);
+    let body = f.fold_block(case.body);
+    SwitchCase { selector, body }
+}
+
+pub fn fold_for_loop<F: Fold + ?Sized>(f: &mut F, stmt: ForLoopStatement) -> ForLoopStatement {
+    let header = f.fold_for_header(*stmt.header);
+    let body = f.fold_block(stmt.body);
+    ForLoopStatement {
+        header: Box::new(header),
+        body,
+        ..stmt
+    }
+}
+
+pub fn fold_for_header<F: Fold + ?Sized>(f: &mut F, header: ForLoopHeader) -> ForLoopHeader {
+    let init = header.init.map(|init| f.fold_for_init(init));
+    let condition = header.condition.map(|e| f.fold_expr(e));
+    let update = header.update.map(|update| f.fold_for_update(update));
+    ForLoopHeader {
+        init,
+        condition,
+        update,
+    }
+}
+
+pub fn fold_for_init<F: Fold + ?Sized>(f: &mut F, init: ForLoopInit) -> ForLoopInit {
+    match init {
+        ForLoopInit::VarDecl(stmt) => ForLoopInit::VarDecl(f.fold_var_decl(stmt)),
+        ForLoopInit::LetDecl(stmt) => ForLoopInit::LetDecl(f.fold_let_decl(stmt)),
+        ForLoopInit::Assignment(stmt) => ForLoopInit::Assignment(f.fold_assignment(stmt)),
+        ForLoopInit::Increment(stmt) => ForLoopInit::Increment(f.fold_increment(stmt)),
+        ForLoopInit::Decrement(stmt) => ForLoopInit::Decrement(f.fold_decrement(stmt)),
+        ForLoopInit::Call(stmt) => ForLoopInit::Call(f.fold_fn_call_stmt(stmt)),
+    }
+}
+
+pub fn fold_for_update<F: Fold + ?Sized>(f: &mut F, update: ForLoopUpdate) -> ForLoopUpdate {
+    match update {
+        ForLoopUpdate::Assignment(stmt) => ForLoopUpdate::Assignment(f.fold_assignment(stmt)),
+        ForLoopUpdate::Increment(stmt) => ForLoopUpdate::Increment(f.fold_increment(stmt)),
+        ForLoopUpdate::Decrement(stmt) => ForLoopUpdate::Decrement(f.fold_decrement(stmt)),
+        ForLoopUpdate::Call(stmt) => ForLoopUpdate::Call(f.fold_fn_call_stmt(stmt)),
+    }
+}
+
+pub fn fold_fn_call_stmt<F: Fold + ?Sized>(f: &mut F, stmt: FnCallStatement) -> FnCallStatement {
+    let args = stmt.args.into_iter().map(|e| f.fold_expr(e)).collect();
+    FnCallStatement { args, ..stmt }
+}
+
+pub fn fold_assignment_lhs<F: Fold + ?Sized>(f: &mut F, lhs: AssignmentLhs) -> AssignmentLhs {
+    match lhs {
+        AssignmentLhs::Phony => AssignmentLhs::Phony,
+        AssignmentLhs::Expr(node) => AssignmentLhs::Expr(f.fold_lhs_expr(node)),
+    }
+}
+
+pub fn fold_lhs_expr<F: Fold + ?Sized>(f: &mut F, node: LhsExprNode) -> LhsExprNode {
+    let expr = match node.expr {
+        LhsExpr::Ident(ident) => LhsExpr::Ident(ident),
+        LhsExpr::Postfix(inner, postfix) => {
+            let inner = f.fold_lhs_expr(*inner);
+            LhsExpr::Postfix(Box::new(inner), f.fold_postfix(postfix))
+        }
+        LhsExpr::Deref(inner) => LhsExpr::Deref(Box::new(f.fold_lhs_expr(*inner))),
+        LhsExpr::AddressOf(inner) => LhsExpr::AddressOf(Box::new(f.fold_lhs_expr(*inner))),
+    };
+
+    LhsExprNode { expr, ..node }
+}
+
+pub fn fold_expr<F: Fold + ?Sized>(f: &mut F, node: ExprNode) -> ExprNode {
+    let expr = match node.expr {
+        Expr::Lit(lit) => Expr::Lit(lit),
+        Expr::TypeCons(expr) => Expr::TypeCons(TypeConsExpr::new(
+            expr.data_type,
+            expr.args.into_iter().map(|e| f.fold_expr(e)).collect(),
+        )),
+        Expr::Var(expr) => Expr::Var(expr),
+        Expr::Postfix(expr) => {
+            let inner = f.fold_expr(*expr.inner);
+            Expr::Postfix(PostfixExpr::new(inner, f.fold_postfix(expr.postfix)))
+        }
+        Expr::UnOp(expr) => Expr::UnOp(UnOpExpr::new(expr.op, f.fold_expr(*expr.inner))),
+        Expr::BinOp(expr) => {
+            let left = f.fold_expr(*expr.left);
+            let right = f.fold_expr(*expr.right);
+            Expr::BinOp(BinOpExpr::new(expr.op, left, right))
+        }
+        Expr::FnCall(expr) => Expr::FnCall(FnCallExpr::new(
+            expr.ident,
+            expr.args.into_iter().map(|e| f.fold_expr(e)).collect(),
+        )),
+    };
+
+    ExprNode { expr, ..node }
+}
+
+pub fn fold_postfix<F: Fold + ?Sized>(f: &mut F, postfix: Postfix) -> Postfix {
+    match postfix {
+        Postfix::Index(index) => Postfix::index(f.fold_expr(*index)),
+        Postfix::Member(member) => Postfix::Member(member),
+    }
+}
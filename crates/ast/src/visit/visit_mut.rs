@@ -0,0 +1,352 @@
+use crate::{
+    AssignmentLhs, AssignmentStatement, ContinuingBlock, DecrementStatement, Else, Expr, ExprNode,
+    FnAttr, FnCallStatement, FnDecl, ForLoopHeader, ForLoopInit, ForLoopStatement, ForLoopUpdate,
+    GlobalConstDecl, GlobalDecl, GlobalVarDecl, IfStatement, IncrementStatement, LetDeclStatement,
+    LhsExpr, LhsExprNode, LoopStatement, Module, Postfix, Statement, SwitchCase, SwitchStatement,
+    VarDeclStatement, WhileStatement,
+};
+
+pub trait VisitorMut {
+    fn visit_module_mut(&mut self, module: &mut Module) {
+        walk_module_mut(self, module)
+    }
+
+    fn visit_global_const_mut(&mut self, decl: &mut GlobalConstDecl) {
+        walk_global_const_mut(self, decl)
+    }
+
+    fn visit_global_var_mut(&mut self, decl: &mut GlobalVarDecl) {
+        walk_global_var_mut(self, decl)
+    }
+
+    fn visit_fn_mut(&mut self, decl: &mut FnDecl) {
+        walk_fn_mut(self, decl)
+    }
+
+    /// Visits a sequence of statements which forms its own scope.
+    fn visit_block_mut(&mut self, block: &mut Vec<Statement>) {
+        walk_block_mut(self, block)
+    }
+
+    fn visit_stmt_mut(&mut self, stmt: &mut Statement) {
+        walk_stmt_mut(self, stmt)
+    }
+
+    fn visit_let_decl_mut(&mut self, stmt: &mut LetDeclStatement) {
+        walk_let_decl_mut(self, stmt)
+    }
+
+    fn visit_var_decl_mut(&mut self, stmt: &mut VarDeclStatement) {
+        walk_var_decl_mut(self, stmt)
+    }
+
+    fn visit_assignment_mut(&mut self, stmt: &mut AssignmentStatement) {
+        walk_assignment_mut(self, stmt)
+    }
+
+    fn visit_increment_mut(&mut self, stmt: &mut IncrementStatement) {
+        walk_increment_mut(self, stmt)
+    }
+
+    fn visit_decrement_mut(&mut self, stmt: &mut DecrementStatement) {
+        walk_decrement_mut(self, stmt)
+    }
+
+    fn visit_if_mut(&mut self, stmt: &mut IfStatement) {
+        walk_if_mut(self, stmt)
+    }
+
+    fn visit_else_mut(&mut self, else_: &mut Else) {
+        walk_else_mut(self, else_)
+    }
+
+    fn visit_loop_mut(&mut self, stmt: &mut LoopStatement) {
+        walk_loop_mut(self, stmt)
+    }
+
+    fn visit_continuing_mut(&mut self, continuing: &mut ContinuingBlock) {
+        walk_continuing_mut(self, continuing)
+    }
+
+    fn visit_while_mut(&mut self, stmt: &mut WhileStatement) {
+        walk_while_mut(self, stmt)
+    }
+
+    fn visit_switch_mut(&mut self, stmt: &mut SwitchStatement) {
+        walk_switch_mut(self, stmt)
+    }
+
+    fn visit_switch_case_mut(&mut self, case: &mut SwitchCase) {
+        walk_switch_case_mut(self, case)
+    }
+
+    fn visit_for_loop_mut(&mut self, stmt: &mut ForLoopStatement) {
+        walk_for_loop_mut(self, stmt)
+    }
+
+    fn visit_for_header_mut(&mut self, header: &mut ForLoopHeader) {
+        walk_for_header_mut(self, header)
+    }
+
+    fn visit_for_init_mut(&mut self, init: &mut ForLoopInit) {
+        walk_for_init_mut(self, init)
+    }
+
+    fn visit_for_update_mut(&mut self, update: &mut ForLoopUpdate) {
+        walk_for_update_mut(self, update)
+    }
+
+    fn visit_fn_call_stmt_mut(&mut self, stmt: &mut FnCallStatement) {
+        walk_fn_call_stmt_mut(self, stmt)
+    }
+
+    fn visit_assignment_lhs_mut(&mut self, lhs: &mut AssignmentLhs) {
+        walk_assignment_lhs_mut(self, lhs)
+    }
+
+    fn visit_lhs_expr_mut(&mut self, node: &mut LhsExprNode) {
+        walk_lhs_expr_mut(self, node)
+    }
+
+    fn visit_expr_mut(&mut self, node: &mut ExprNode) {
+        walk_expr_mut(self, node)
+    }
+
+    fn visit_postfix_mut(&mut self, postfix: &mut Postfix) {
+        walk_postfix_mut(self, postfix)
+    }
+}
+
+pub fn walk_module_mut<V: VisitorMut + ?Sized>(v: &mut V, module: &mut Module) {
+    for decl in &mut module.decls {
+        match decl {
+            GlobalDecl::Struct(_) => {}
+            GlobalDecl::Const(decl) => v.visit_global_const_mut(decl),
+            GlobalDecl::Var(decl) => v.visit_global_var_mut(decl),
+            GlobalDecl::Fn(decl) => v.visit_fn_mut(decl),
+        }
+    }
+}
+
+pub fn walk_global_const_mut<V: VisitorMut + ?Sized>(v: &mut V, decl: &mut GlobalConstDecl) {
+    v.visit_expr_mut(&mut decl.initializer);
+}
+
+pub fn walk_global_var_mut<V: VisitorMut + ?Sized>(v: &mut V, decl: &mut GlobalVarDecl) {
+    if let Some(initializer) = &mut decl.initializer {
+        v.visit_expr_mut(initializer);
+    }
+}
+
+pub fn walk_fn_mut<V: VisitorMut + ?Sized>(v: &mut V, decl: &mut FnDecl) {
+    for attr in &mut decl.attrs {
+        if let FnAttr::WorkgroupSize(args) = attr {
+            for arg in args {
+                v.visit_expr_mut(arg);
+            }
+        }
+    }
+
+    v.visit_block_mut(&mut decl.body);
+}
+
+pub fn walk_block_mut<V: VisitorMut + ?Sized>(v: &mut V, block: &mut Vec<Statement>) {
+    for stmt in block {
+        v.visit_stmt_mut(stmt);
+    }
+}
+
+pub fn walk_stmt_mut<V: VisitorMut + ?Sized>(v: &mut V, stmt: &mut Statement) {
+    match stmt {
+        Statement::LetDecl(stmt) => v.visit_let_decl_mut(stmt),
+        Statement::VarDecl(stmt) => v.visit_var_decl_mut(stmt),
+        Statement::Assignment(stmt) => v.visit_assignment_mut(stmt),
+        Statement::Increment(stmt) => v.visit_increment_mut(stmt),
+        Statement::Decrement(stmt) => v.visit_decrement_mut(stmt),
+        Statement::Compound(stmt) => v.visit_block_mut(&mut stmt.body),
+        Statement::If(stmt) => v.visit_if_mut(stmt),
+        Statement::Return(stmt) => {
+            if let Some(value) = &mut stmt.value {
+                v.visit_expr_mut(value);
+            }
+        }
+        Statement::Loop(stmt) => v.visit_loop_mut(stmt),
+        Statement::While(stmt) => v.visit_while_mut(stmt),
+        Statement::Switch(stmt) => v.visit_switch_mut(stmt),
+        Statement::ForLoop(stmt) => v.visit_for_loop_mut(stmt),
+        Statement::FnCall(stmt) => v.visit_fn_call_stmt_mut(stmt),
+        Statement::Break | Statement::Continue | Statement::Fallthrough => {}
+    }
+}
+
+pub fn walk_let_decl_mut<V: VisitorMut + ?Sized>(v: &mut V, stmt: &mut LetDeclStatement) {
+    v.visit_expr_mut(&mut stmt.initializer);
+}
+
+pub fn walk_var_decl_mut<V: VisitorMut + ?Sized>(v: &mut V, stmt: &mut VarDeclStatement) {
+    if let Some(initializer) = &mut stmt.initializer {
+        v.visit_expr_mut(initializer);
+    }
+}
+
+pub fn walk_assignment_mut<V: VisitorMut + ?Sized>(v: &mut V, stmt: &mut AssignmentStatement) {
+    v.visit_assignment_lhs_mut(&mut stmt.lhs);
+    v.visit_expr_mut(&mut stmt.rhs);
+}
+
+pub fn walk_increment_mut<V: VisitorMut + ?Sized>(v: &mut V, stmt: &mut IncrementStatement) {
+    v.visit_assignment_lhs_mut(&mut stmt.lhs);
+}
+
+pub fn walk_decrement_mut<V: VisitorMut + ?Sized>(v: &mut V, stmt: &mut DecrementStatement) {
+    v.visit_assignment_lhs_mut(&mut stmt.lhs);
+}
+
+pub fn walk_if_mut<V: VisitorMut + ?Sized>(v: &mut V, stmt: &mut IfStatement) {
+    v.visit_expr_mut(&mut stmt.condition);
+    v.visit_block_mut(&mut stmt.body);
+
+    if let Some(else_) = &mut stmt.else_ {
+        v.visit_else_mut(else_);
+    }
+}
+
+pub fn walk_else_mut<V: VisitorMut + ?Sized>(v: &mut V, else_: &mut Else) {
+    match else_ {
+        Else::If(stmt) => v.visit_if_mut(stmt),
+        Else::Else(block) => v.visit_block_mut(block),
+    }
+}
+
+pub fn walk_loop_mut<V: VisitorMut + ?Sized>(v: &mut V, stmt: &mut LoopStatement) {
+    v.visit_block_mut(&mut stmt.body);
+
+    if let Some(continuing) = &mut stmt.continuing {
+        v.visit_continuing_mut(continuing);
+    }
+}
+
+pub fn walk_continuing_mut<V: VisitorMut + ?Sized>(v: &mut V, continuing: &mut ContinuingBlock) {
+    v.visit_block_mut(&mut continuing.stmts);
+
+    if let Some(break_if) = &mut continuing.break_if {
+        v.visit_expr_mut(break_if);
+    }
+}
+
+pub fn walk_while_mut<V: VisitorMut + ?Sized>(v: &mut V, stmt: &mut WhileStatement) {
+    v.visit_expr_mut(&mut stmt.condition);
+    v.visit_block_mut(&mut stmt.body);
+}
+
+pub fn walk_switch_mut<V: VisitorMut + ?Sized>(v: &mut V, stmt: &mut SwitchStatement) {
+    v.visit_expr_mut(&mut stmt.selector);
+
+    for case in &mut stmt.cases {
+        v.visit_switch_case_mut(case);
+    }
+
+    v.visit_block_mut(&mut stmt.default);
+}
+
+pub fn walk_switch_case_mut<V: VisitorMut + ?Sized>(v: &mut V, case: &mut SwitchCase) {
+    v.visit_expr_mut(&mut case.selector);
+    v.visit_block_mut(&mut case.body);
+}
+
+pub fn walk_for_loop_mut<V: VisitorMut + ?Sized>(v: &mut V, stmt: &mut ForLoopStatement) {
+    v.visit_for_header_mut(&mut stmt.header);
+    v.visit_block_mut(&mut stmt.body);
+}
+
+pub fn walk_for_header_mut<V: VisitorMut + ?Sized>(v: &mut V, header: &mut ForLoopHeader) {
+    if let Some(init) = &mut header.init {
+        v.visit_for_init_mut(init);
+    }
+
+    if let Some(condition) = &mut header.condition {
+        v.visit_expr_mut(condition);
+    }
+
+    if let Some(update) = &mut header.update {
+        v.visit_for_update_mut(update);
+    }
+}
+
+pub fn walk_for_init_mut<V: VisitorMut + ?Sized>(v: &mut V, init: &mut ForLoopInit) {
+    match init {
+        ForLoopInit::VarDecl(stmt) => v.visit_var_decl_mut(stmt),
+        ForLoopInit::LetDecl(stmt) => v.visit_let_decl_mut(stmt),
+        ForLoopInit::Assignment(stmt) => v.visit_assignment_mut(stmt),
+        ForLoopInit::Increment(stmt) => v.visit_increment_mut(stmt),
+        ForLoopInit::Decrement(stmt) => v.visit_decrement_mut(stmt),
+        ForLoopInit::Call(stmt) => v.visit_fn_call_stmt_mut(stmt),
+    }
+}
+
+pub fn walk_for_update_mut<V: VisitorMut + ?Sized>(v: &mut V, update: &mut ForLoopUpdate) {
+    match update {
+        ForLoopUpdate::Assignment(stmt) => v.visit_assignment_mut(stmt),
+        ForLoopUpdate::Increment(stmt) => v.visit_increment_mut(stmt),
+        ForLoopUpdate::Decrement(stmt) => v.visit_decrement_mut(stmt),
+        ForLoopUpdate::Call(stmt) => v.visit_fn_call_stmt_mut(stmt),
+    }
+}
+
+pub fn walk_fn_call_stmt_mut<V: VisitorMut + ?Sized>(v: &mut V, stmt: &mut FnCallStatement) {
+    for arg in &mut stmt.args {
+        v.visit_expr_mut(arg);
+    }
+}
+
+pub fn walk_assignment_lhs_mut<V: VisitorMut + ?Sized>(v: &mut V, lhs: &mut AssignmentLhs) {
+    match lhs {
+        AssignmentLhs::Phony => {}
+        AssignmentLhs::Expr(node) => v.visit_lhs_expr_mut(node),
+    }
+}
+
+pub fn walk_lhs_expr_mut<V: VisitorMut + ?Sized>(v: &mut V, node: &mut LhsExprNode) {
+    match &mut node.expr {
+        LhsExpr::Ident(_) => {}
+        LhsExpr::Postfix(inner, postfix) => {
+            v.visit_lhs_expr_mut(inner);
+            v.visit_postfix_mut(postfix);
+        }
+        LhsExpr::Deref(inner) => v.visit_lhs_expr_mut(inner),
+        LhsExpr::AddressOf(inner) => v.visit_lhs_expr_mut(inner),
+    }
+}
+
+pub fn walk_expr_mut<V: VisitorMut + ?Sized>(v: &mut V, node: &mut ExprNode) {
+    match &mut node.expr {
+        Expr::Lit(_) => {}
+        Expr::TypeCons(expr) => {
+            for arg in &mut expr.args {
+                v.visit_expr_mut(arg);
+            }
+        }
+        Expr::Var(_) => {}
+        Expr::Postfix(expr) => {
+            v.visit_expr_mut(&mut expr.inner);
+            v.visit_postfix_mut(&mut expr.postfix);
+        }
+        Expr::UnOp(expr) => v.visit_expr_mut(&mut expr.inner),
+        Expr::BinOp(expr) => {
+            v.visit_expr_mut(&mut expr.left);
+            v.visit_expr_mut(&mut expr.right);
+        }
+        Expr::FnCall(expr) => {
+            for arg in &mut expr.args {
+                v.visit_expr_mut(arg);
+            }
+        }
+    }
+}
+
+pub fn walk_postfix_mut<V: VisitorMut + ?Sized>(v: &mut V, postfix: &mut Postfix) {
+    match postfix {
+        Postfix::Index(index) => v.visit_expr_mut(index),
+        Postfix::Member(_) => {}
+    }
+}
@@ -1,59 +1,226 @@
+//! Conversion of the AST back into WGSL source.
+//!
+//! [`Writer`] is the only place that knows how each node is spelled. The `Display` impls for
+//! statements and expressions go through a writer with the default [`Options`], which produce the
+//! same output that wgslsmith has always emitted. The other options pick between equivalent
+//! spellings of the same program, since different spellings exercise different paths in the
+//! compiler frontends under test.
+
+mod expr;
+mod stmt;
+
 use std::fmt::{Display, Result, Write};
 
-use indenter::indented;
+use crate::types::{DataType, ScalarType};
+use crate::{
+    Comment, CommentAnchor, CommentPosition, FnAttr, FnDecl, GlobalConstDecl, GlobalDecl,
+    GlobalVarDecl, Module, Statement, StructDecl,
+};
 
-use crate::{FnAttr, FnDecl, GlobalConstDecl, GlobalVarDecl, Module, StructDecl};
+/// Whitespace that indentation strings are sliced from, which also bounds the indentation width.
+const SPACES: &str = "                ";
 
-#[derive(Default)]
-pub struct Writer {
-    #[allow(unused)]
-    options: Options,
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "clap", derive(clap::Args))]
+pub struct Options {
+    /// Declare module scope constants with `const` instead of `let`.
+    #[cfg_attr(feature = "clap", clap(long, action))]
+    pub module_scope_constants: bool,
+
+    /// Number of spaces per indentation level (at most 16).
+    #[cfg_attr(feature = "clap", clap(long, action, default_value = "4"))]
+    pub indent: usize,
+
+    /// Spell vector and matrix types with their predeclared aliases where possible (e.g. `vec3f`
+    /// instead of `vec3<f32>`).
+    #[cfg_attr(feature = "clap", clap(long, action))]
+    pub type_aliases: bool,
+
+    /// How to parenthesize nested operator expressions.
+    #[cfg_attr(
+        feature = "clap",
+        clap(long, action, value_enum, default_value = "conservative")
+    )]
+    pub parens: ParenStyle,
+
+    /// How to spell concrete numeric literals.
+    #[cfg_attr(
+        feature = "clap",
+        clap(long, action, value_enum, default_value = "suffix")
+    )]
+    pub literals: LiteralStyle,
+
+    /// Which base and notation to spell numeric literals in.
+    #[cfg_attr(
+        feature = "clap",
+        clap(long, action, value_enum, default_value = "decimal")
+    )]
+    pub numerals: NumeralStyle,
+
+    /// Order in which module scope declarations are emitted.
+    #[cfg_attr(
+        feature = "clap",
+        clap(long, action, value_enum, default_value = "original")
+    )]
+    pub decl_order: DeclOrder,
+
+    /// Leave out the comments that were parsed from the source.
+    #[cfg_attr(feature = "clap", clap(long, action))]
+    pub strip_comments: bool,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            module_scope_constants: false,
+            indent: 4,
+            type_aliases: false,
+            parens: ParenStyle::Conservative,
+            literals: LiteralStyle::Suffix,
+            numerals: NumeralStyle::Decimal,
+            decl_order: DeclOrder::Original,
+            strip_comments: false,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+pub enum ParenStyle {
+    /// Parenthesize every operator expression that is an operand of another operator.
+    Conservative,
+    /// Only insert the parentheses that are required by the WGSL grammar.
+    Minimal,
+    /// Parenthesize every operand of a unary or binary operator, even if it is a primary
+    /// expression.
+    Redundant,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+pub enum LiteralStyle {
+    /// Use type suffixes, e.g. `1i`, `2u` and `1.5f`.
+    Suffix,
+    /// Use value constructors applied to abstract literals, e.g. `i32(1)`, `u32(2)` and
+    /// `f32(1.5)`.
+    Constructor,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+pub enum NumeralStyle {
+    /// Decimal integers and floats, e.g. `255u` and `1.5f`.
+    Decimal,
+    /// Hexadecimal integers and floats, e.g. `0xffu` and `0x1.8p0f`.
+    Hex,
+    /// Decimal integers and floats with an exponent, e.g. `255u` and `1.5e0f`.
+    Exponent,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+pub enum DeclOrder {
+    /// The order in which the declarations appear in the module.
+    Original,
+    /// Structs, then constants, then variables, then functions, each in declaration order.
+    Grouped,
+    /// The reverse of `original`, so that declarations are used before they are declared.
+    Reversed,
 }
 
 #[derive(Default)]
-pub struct Options {
-    pub module_scope_constants: bool,
+pub struct Writer<'a> {
+    options: Options,
+    /// Comments of the module that is being written, which are emitted next to the nodes that
+    /// they are attached to.
+    comments: &'a [Comment],
 }
 
-impl Writer {
-    pub fn new(options: Options) -> Writer {
-        Writer { options }
+impl Writer<'_> {
+    pub fn new(options: Options) -> Self {
+        Writer {
+            options,
+            comments: &[],
+        }
     }
 
     pub fn write_module(&self, f: &mut dyn Write, module: &Module) -> Result {
+        let comments = if self.options.strip_comments {
+            &[]
+        } else {
+            module.comments.as_slice()
+        };
+
+        Writer {
+            options: self.options.clone(),
+            comments,
+        }
+        .write_module_with_comments(f, module)
+    }
+
+    fn write_module_with_comments(&self, f: &mut dyn Write, module: &Module) -> Result {
+        self.write_comments(f, &CommentAnchor::Module, CommentPosition::Before)?;
+
+        // Directives must always come before any declarations
         for enable in &module.extensions {
             writeln!(f, "enable {enable};")?;
         }
 
-        for decl in &module.structs {
-            self.write_struct(f, decl)?;
-            writeln!(f)?;
+        for feature in &module.requires {
+            writeln!(f, "requires {feature};")?;
         }
 
-        for decl in &module.consts {
-            self.write_global_const(f, decl)?;
-            writeln!(f)?;
+        for control in &module.diagnostics {
+            writeln!(f, "diagnostic{control};")?;
         }
 
-        for decl in &module.vars {
-            self.write_global_var(f, decl)?;
-            writeln!(f)?;
+        let mut decls: Vec<&GlobalDecl> = module.decls.iter().collect();
+
+        match self.options.decl_order {
+            DeclOrder::Original => {}
+            DeclOrder::Grouped => decls.sort_by_key(|decl| match decl {
+                GlobalDecl::Struct(_) => 0,
+                GlobalDecl::Const(_) => 1,
+                GlobalDecl::Var(_) => 2,
+                GlobalDecl::Fn(_) => 3,
+            }),
+            DeclOrder::Reversed => decls.reverse(),
         }
 
-        for decl in &module.functions {
-            self.write_func(f, decl)?;
+        for decl in decls {
+            let anchor = CommentAnchor::Decl(decl.name().to_owned());
+            self.write_comments(f, &anchor, CommentPosition::Before)?;
+            self.write_line_with_comments(f, &anchor, |f| self.write_decl(f, decl))?;
+            self.write_comments(f, &anchor, CommentPosition::After)?;
             writeln!(f)?;
         }
 
-        Ok(())
+        self.write_comments(f, &CommentAnchor::Module, CommentPosition::After)
+    }
+
+    pub fn write_decl(&self, f: &mut dyn Write, decl: &GlobalDecl) -> Result {
+        match decl {
+            GlobalDecl::Struct(decl) => self.write_struct(f, decl),
+            GlobalDecl::Const(decl) => self.write_global_const(f, decl),
+            GlobalDecl::Var(decl) => self.write_global_var(f, decl),
+            GlobalDecl::Fn(decl) => self.write_func(f, decl),
+        }
     }
 
     pub fn write_struct(&self, f: &mut dyn Write, decl: &StructDecl) -> Result {
         writeln!(f, "struct {} {{", decl.name)?;
 
         for member in &decl.members {
-            self.write_attrs(&mut indented(f), member.attrs.iter())?;
-            writeln!(indented(f), "{}: {},", member.name, member.data_type)?;
+            let mut f = self.indented(f);
+            let anchor = CommentAnchor::Member(decl.name.clone(), member.name.clone());
+            self.write_comments(&mut f, &anchor, CommentPosition::Before)?;
+            self.write_attrs(&mut f, member.attrs.iter())?;
+            write!(f, "{}: ", member.name)?;
+            self.write_type(&mut f, &member.data_type)?;
+            write!(f, ",")?;
+            self.write_inline_comments(&mut f, &anchor)?;
+            writeln!(f)?;
+            self.write_comments(&mut f, &anchor, CommentPosition::After)?;
         }
 
         writeln!(f, "}}")?;
@@ -68,11 +235,11 @@ impl Writer {
             write!(f, "let")?;
         }
 
-        writeln!(
-            f,
-            " {}: {} = {};",
-            decl.name, decl.data_type, decl.initializer
-        )
+        write!(f, " {}: ", decl.name)?;
+        self.write_type(f, &decl.data_type)?;
+        write!(f, " = ")?;
+        self.write_expr(f, &decl.initializer)?;
+        writeln!(f, ";")
     }
 
     pub fn write_global_var(&self, f: &mut dyn Write, decl: &GlobalVarDecl) -> Result {
@@ -88,10 +255,12 @@ impl Writer {
             write!(f, ">")?;
         }
 
-        write!(f, " {}: {}", decl.name, decl.data_type)?;
+        write!(f, " {}: ", decl.name)?;
+        self.write_type(f, &decl.data_type)?;
 
         if let Some(initializer) = &decl.initializer {
-            write!(f, " = {initializer}")?;
+            write!(f, " = ")?;
+            self.write_expr(f, initializer)?;
         }
 
         writeln!(f, ";")
@@ -103,6 +272,11 @@ impl Writer {
                 FnAttr::Stage(stage) => {
                     writeln!(f, "@{stage}")?;
                 }
+                FnAttr::WorkgroupSize(args) => {
+                    write!(f, "@workgroup_size")?;
+                    self.write_args(f, args)?;
+                    writeln!(f)?;
+                }
                 _ => self.write_attr(f, attr)?,
             }
         }
@@ -110,7 +284,9 @@ impl Writer {
         write!(f, "fn {}(", func.name)?;
 
         for (i, param) in func.inputs.iter().enumerate() {
-            write!(f, "{param}")?;
+            self.write_inline_attrs(f, &param.attrs)?;
+            write!(f, "{}: ", param.name)?;
+            self.write_type(f, &param.data_type)?;
             if i != func.inputs.len() - 1 {
                 f.write_str(", ")?;
             }
@@ -119,16 +295,65 @@ impl Writer {
         write!(f, ") ")?;
 
         if let Some(output) = &func.output {
-            write!(f, "-> {output} ")?;
+            write!(f, "-> ")?;
+            self.write_inline_attrs(f, &output.attrs)?;
+            self.write_type(f, &output.data_type)?;
+            write!(f, " ")?;
         }
 
         writeln!(f, "{{")?;
+        let anchor = CommentAnchor::Decl(func.name.clone());
+        self.write_body(f, &func.body, Some(&anchor), 0)?;
+        writeln!(f, "}}")?;
+
+        Ok(())
+    }
 
-        for stmt in &func.body {
-            writeln!(indented(f), "{}", stmt)?;
+    /// Writes the spelling of a type, as it would appear in a declaration or constructor.
+    pub fn write_type(&self, f: &mut dyn Write, ty: &DataType) -> Result {
+        if !self.options.type_aliases {
+            return write!(f, "{ty}");
         }
 
-        writeln!(f, "}}")?;
+        match ty {
+            DataType::Vector(n, t) if *t != ScalarType::Bool => {
+                write!(f, "vec{n}{}", alias_suffix(*t))
+            }
+            DataType::Matrix(c, r, t @ (ScalarType::F32 | ScalarType::F16)) => {
+                write!(f, "mat{c}x{r}{}", alias_suffix(*t))
+            }
+            DataType::Array(inner, n) => {
+                write!(f, "array<")?;
+                self.write_type(f, inner)?;
+                if let Some(n) = n {
+                    write!(f, ", {n}")?;
+                }
+                write!(f, ">")
+            }
+            DataType::Ptr(view) => {
+                write!(f, "ptr<{}, ", view.storage_class)?;
+                self.write_type(f, &view.inner)?;
+                if view.access_mode != view.storage_class.default_access_mode() {
+                    write!(f, ", {}", view.access_mode)?;
+                }
+                write!(f, ">")
+            }
+            _ => write!(f, "{ty}"),
+        }
+    }
+
+    fn indented<'a>(&self, f: &'a mut dyn Write) -> Indented<'a> {
+        Indented {
+            inner: f,
+            indent: &SPACES[..self.options.indent.min(SPACES.len())],
+            needs_indent: true,
+        }
+    }
+
+    fn write_inline_attrs<T: Display>(&self, f: &mut dyn Write, attrs: &[T]) -> Result {
+        for attr in attrs {
+            write!(f, "@{attr} ")?;
+        }
 
         Ok(())
     }
@@ -148,4 +373,107 @@ impl Writer {
     fn write_attr<T: Display>(&self, f: &mut dyn Write, attr: &T) -> Result {
         writeln!(f, "@{attr}")
     }
+
+    fn comments_at<'c>(
+        &'c self,
+        anchor: &'c CommentAnchor,
+        position: CommentPosition,
+    ) -> impl Iterator<Item = &'c str> {
+        self.comments
+            .iter()
+            .filter(move |it| it.position == position && &it.anchor == anchor)
+            .map(|it| it.text.as_str())
+    }
+
+    /// Writes the comments that go on their own lines before or after a node.
+    fn write_comments(
+        &self,
+        f: &mut dyn Write,
+        anchor: &CommentAnchor,
+        position: CommentPosition,
+    ) -> Result {
+        for comment in self.comments_at(anchor, position) {
+            writeln!(f, "{comment}")?;
+        }
+
+        Ok(())
+    }
+
+    fn write_inline_comments(&self, f: &mut dyn Write, anchor: &CommentAnchor) -> Result {
+        for comment in self.comments_at(anchor, CommentPosition::Inline) {
+            write!(f, " {comment}")?;
+        }
+
+        Ok(())
+    }
+
+    /// Writes a node whose output ends with a newline, inserting its inline comments before the
+    /// newline.
+    fn write_line_with_comments(
+        &self,
+        f: &mut dyn Write,
+        anchor: &CommentAnchor,
+        write: impl FnOnce(&mut dyn Write) -> Result,
+    ) -> Result {
+        if self
+            .comments_at(anchor, CommentPosition::Inline)
+            .next()
+            .is_none()
+        {
+            return write(f);
+        }
+
+        let mut line = String::new();
+        write(&mut line)?;
+        f.write_str(line.trim_end_matches('\n'))?;
+        self.write_inline_comments(f, anchor)?;
+        writeln!(f)
+    }
+}
+
+/// Returns the anchor that comments on a statement are attached to, if it has one.
+fn stmt_anchor(stmt: &Statement) -> Option<CommentAnchor> {
+    let span = stmt.meta()?.span?;
+    Some(CommentAnchor::Stmt(span.start))
+}
+
+/// Inserts indentation at the start of every non-empty line that is written through it.
+struct Indented<'a> {
+    inner: &'a mut dyn Write,
+    indent: &'static str,
+    needs_indent: bool,
+}
+
+impl Write for Indented<'_> {
+    fn write_str(&mut self, s: &str) -> Result {
+        for (i, line) in s.split('\n').enumerate() {
+            if i > 0 {
+                self.inner.write_char('\n')?;
+                self.needs_indent = true;
+            }
+
+            if line.is_empty() {
+                continue;
+            }
+
+            if self.needs_indent {
+                self.inner.write_str(self.indent)?;
+                self.needs_indent = false;
+            }
+
+            self.inner.write_str(line)?;
+        }
+
+        Ok(())
+    }
+}
+
+fn alias_suffix(t: ScalarType) -> char {
+    match t {
+        ScalarType::I32 => 'i',
+        ScalarType::U32 => 'u',
+        ScalarType::F32 => 'f',
+        ScalarType::F16 => 'h',
+        ScalarType::Bool => unreachable!("bool vectors have no alias"),
+    }
 }
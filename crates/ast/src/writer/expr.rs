@@ -0,0 +1,296 @@
+use std::fmt::{Display, LowerExp, Result, Write};
+
+use super::{LiteralStyle, NumeralStyle, ParenStyle, Writer};
+use crate::{BinOp, BinOpExpr, Expr, ExprNode, Lit, Postfix, PostfixExpr, UnOp, UnOpExpr};
+
+impl Writer<'_> {
+    pub fn write_expr(&self, f: &mut dyn Write, node: &ExprNode) -> Result {
+        match &node.expr {
+            Expr::Lit(lit) => self.write_lit(f, lit),
+            Expr::TypeCons(expr) => {
+                self.write_type(f, &expr.data_type)?;
+                self.write_args(f, &expr.args)
+            }
+            Expr::Var(expr) => f.write_str(&expr.ident),
+            Expr::Postfix(expr) => self.write_postfix_expr(f, expr),
+            Expr::UnOp(expr) => self.write_unop_expr(f, expr),
+            Expr::BinOp(expr) => self.write_binop_expr(f, expr),
+            Expr::FnCall(expr) => {
+                f.write_str(&expr.ident)?;
+                self.write_args(f, &expr.args)
+            }
+        }
+    }
+
+    pub fn write_lit(&self, f: &mut dyn Write, lit: &Lit) -> Result {
+        match (self.options.literals, lit) {
+            (_, Lit::Bool(v)) => write!(f, "{v}"),
+            (_, Lit::AbstractInt(v)) => f.write_str(&self.int_numeral(*v)),
+            (_, Lit::AbstractFloat(v)) => write!(f, "{}", AbstractFloat(self.float_numeral(v, *v))),
+            // The negation of `2147483648i` would be out of range, so i32::MIN can only be written
+            // as a conversion of an abstract int
+            (_, Lit::I32(i32::MIN)) => write!(f, "i32({})", self.int_numeral(i32::MIN.into())),
+            (LiteralStyle::Suffix, Lit::I32(v)) => write!(f, "{}i", self.int_numeral((*v).into())),
+            (LiteralStyle::Suffix, Lit::U32(v)) => write!(f, "{}u", self.int_numeral((*v).into())),
+            (LiteralStyle::Suffix, Lit::F32(v)) => {
+                write!(f, "{}f", self.float_numeral(v, (*v).into()))
+            }
+            (LiteralStyle::Suffix, Lit::F16(v)) => {
+                write!(f, "{}h", self.float_numeral(v, v.to_f64()))
+            }
+            (LiteralStyle::Constructor, Lit::I32(v)) => {
+                write!(f, "i32({})", self.int_numeral((*v).into()))
+            }
+            (LiteralStyle::Constructor, Lit::U32(v)) => {
+                write!(f, "u32({})", self.int_numeral((*v).into()))
+            }
+            // The argument must be written as an abstract float rather than an abstract int,
+            // since large float values don't fit into an abstract int
+            (LiteralStyle::Constructor, Lit::F32(v)) => {
+                let numeral = self.float_numeral(v, (*v).into());
+                write!(f, "f32({})", AbstractFloat(numeral))
+            }
+            (LiteralStyle::Constructor, Lit::F16(v)) => {
+                let numeral = self.float_numeral(v, v.to_f64());
+                write!(f, "f16({})", AbstractFloat(numeral))
+            }
+        }
+    }
+
+    /// Spells an integer in the configured numeral style, without a suffix.
+    fn int_numeral(&self, v: i64) -> String {
+        let sign = if v < 0 { "-" } else { "" };
+        match self.options.numerals {
+            NumeralStyle::Hex => format!("{sign}0x{:x}", v.unsigned_abs()),
+            NumeralStyle::Decimal | NumeralStyle::Exponent => v.to_string(),
+        }
+    }
+
+    /// Spells a float in the configured numeral style, without a suffix.
+    ///
+    /// `exact` is the same value as an `f64`, which every float type converts to exactly.
+    fn float_numeral(&self, v: &(impl Display + LowerExp), exact: f64) -> String {
+        match self.options.numerals {
+            NumeralStyle::Decimal => v.to_string(),
+            NumeralStyle::Hex => hex_float(exact),
+            NumeralStyle::Exponent => format!("{v:e}"),
+        }
+    }
+
+    pub(super) fn write_args(&self, f: &mut dyn Write, args: &[ExprNode]) -> Result {
+        write!(f, "(")?;
+
+        for (i, arg) in args.iter().enumerate() {
+            self.write_expr(f, arg)?;
+            if i != args.len() - 1 {
+                f.write_str(", ")?;
+            }
+        }
+
+        write!(f, ")")
+    }
+
+    pub(super) fn write_postfix(&self, f: &mut dyn Write, postfix: &Postfix) -> Result {
+        match postfix {
+            Postfix::Index(index) => {
+                write!(f, "[")?;
+                self.write_expr(f, index)?;
+                write!(f, "]")
+            }
+            Postfix::Member(member) => write!(f, ".{member}"),
+        }
+    }
+
+    fn write_postfix_expr(&self, f: &mut dyn Write, expr: &PostfixExpr) -> Result {
+        let PostfixExpr { inner, postfix } = expr;
+
+        // Postfix operators bind tighter than any prefix or infix operator
+        let parens = matches!(inner.expr, Expr::UnOp(_) | Expr::BinOp(_));
+
+        self.write_operand(f, inner, parens)?;
+        self.write_postfix(f, postfix)
+    }
+
+    fn write_unop_expr(&self, f: &mut dyn Write, expr: &UnOpExpr) -> Result {
+        let UnOpExpr { op, inner } = expr;
+
+        if matches!(op, UnOp::Neg) && matches!(inner.expr, Expr::Lit(Lit::I32(0))) {
+            return self.write_expr(f, inner);
+        }
+
+        let parens = match self.options.parens {
+            ParenStyle::Conservative => {
+                matches!(inner.expr, Expr::UnOp(_) | Expr::BinOp(_)) || is_negative_lit(inner)
+            }
+            // Two consecutive minus signs would be lexed as a decrement operator
+            ParenStyle::Minimal => {
+                matches!(inner.expr, Expr::BinOp(_))
+                    || (matches!(op, UnOp::Neg) && self.starts_with_minus(inner))
+            }
+            ParenStyle::Redundant => true,
+        };
+
+        write!(f, "{op}")?;
+        self.write_operand(f, inner, parens)
+    }
+
+    fn write_binop_expr(&self, f: &mut dyn Write, expr: &BinOpExpr) -> Result {
+        let BinOpExpr { op, left, right } = expr;
+
+        // We specifically wrap '<' in parentheses to avoid
+        // the tint parser confusing '<' and '>' with template lists.
+        // For example, the expression vec2<bool>(x < y, y > x) is not parsed correctly.
+        // Note that vec2<bool>(x <= y, y > x) is parsed correctly.
+        let needs_parentheses = matches!(op, BinOp::Less);
+
+        if needs_parentheses {
+            write!(f, "(")?;
+        }
+
+        self.write_operand(f, left, self.binop_operand_parens(*op, left, true))?;
+        write!(f, " {op} ")?;
+        self.write_operand(f, right, self.binop_operand_parens(*op, right, false))?;
+
+        if needs_parentheses {
+            write!(f, ")")?
+        }
+
+        Ok(())
+    }
+
+    fn write_operand(&self, f: &mut dyn Write, node: &ExprNode, parens: bool) -> Result {
+        if parens {
+            write!(f, "(")?;
+            self.write_expr(f, node)?;
+            write!(f, ")")
+        } else {
+            self.write_expr(f, node)
+        }
+    }
+
+    fn binop_operand_parens(&self, op: BinOp, operand: &ExprNode, is_left: bool) -> bool {
+        match self.options.parens {
+            ParenStyle::Conservative => matches!(operand.expr, Expr::BinOp(_)),
+            ParenStyle::Minimal => match &operand.expr {
+                Expr::BinOp(inner) => !is_valid_operand(op, inner.op, is_left),
+                _ => false,
+            },
+            ParenStyle::Redundant => true,
+        }
+    }
+
+    fn starts_with_minus(&self, node: &ExprNode) -> bool {
+        match &node.expr {
+            Expr::Lit(Lit::I32(i32::MIN)) => false,
+            Expr::Lit(Lit::AbstractInt(_) | Lit::AbstractFloat(_)) => is_negative_lit(node),
+            Expr::Lit(_) => self.options.literals == LiteralStyle::Suffix && is_negative_lit(node),
+            Expr::UnOp(expr) => matches!(expr.op, UnOp::Neg),
+            _ => false,
+        }
+    }
+}
+
+/// Determines whether a binary expression using `inner` can appear unparenthesized as an operand
+/// of `outer`, according to the WGSL expression grammar.
+///
+/// This also requires parentheses whenever leaving them out would change the shape of the tree
+/// (e.g. for the right operand of a left associative operator), so that the written program
+/// parses back to the same AST.
+fn is_valid_operand(outer: BinOp, inner: BinOp, is_left: bool) -> bool {
+    fn is_multiplicative(op: BinOp) -> bool {
+        matches!(op, BinOp::Times | BinOp::Divide | BinOp::Mod)
+    }
+
+    fn is_additive(op: BinOp) -> bool {
+        is_multiplicative(op) || matches!(op, BinOp::Plus | BinOp::Minus)
+    }
+
+    fn is_shift(op: BinOp) -> bool {
+        is_additive(op) || matches!(op, BinOp::LShift | BinOp::RShift)
+    }
+
+    fn is_relational(op: BinOp) -> bool {
+        is_shift(op)
+            || matches!(
+                op,
+                BinOp::Less
+                    | BinOp::LessEqual
+                    | BinOp::Greater
+                    | BinOp::GreaterEqual
+                    | BinOp::Equal
+                    | BinOp::NotEqual
+            )
+    }
+
+    match outer {
+        BinOp::Times | BinOp::Divide | BinOp::Mod => is_left && is_multiplicative(inner),
+        BinOp::Plus | BinOp::Minus if is_left => is_additive(inner),
+        BinOp::Plus | BinOp::Minus => is_multiplicative(inner),
+        // The operands of shift operators must be unary expressions
+        BinOp::LShift | BinOp::RShift => false,
+        // Relational operators are not associative
+        | BinOp::Less
+        | BinOp::LessEqual
+        | BinOp::Greater
+        | BinOp::GreaterEqual
+        | BinOp::Equal
+        | BinOp::NotEqual => is_shift(inner),
+        // Short circuiting operators can be chained but not mixed
+        BinOp::LogAnd | BinOp::LogOr => (is_left && inner == outer) || is_relational(inner),
+        // Bitwise operators can be chained but not mixed, and their other operands must be unary
+        // expressions
+        BinOp::BitAnd | BinOp::BitOr | BinOp::BitXOr => is_left && inner == outer,
+    }
+}
+
+fn is_negative_lit(node: &ExprNode) -> bool {
+    match node.expr {
+        Expr::Lit(Lit::I32(v)) => v < 0,
+        Expr::Lit(Lit::F32(v)) => v < 0.0,
+        Expr::Lit(Lit::F16(v)) => v < half::f16::ZERO,
+        Expr::Lit(Lit::AbstractInt(v)) => v < 0,
+        Expr::Lit(Lit::AbstractFloat(v)) => v < 0.0,
+        _ => false,
+    }
+}
+
+/// Makes sure that a formatted float is lexed as an abstract float rather than an abstract int.
+struct AbstractFloat(String);
+
+impl std::fmt::Display for AbstractFloat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> Result {
+        f.write_str(&self.0)?;
+        if !self.0.contains(['.', 'e', 'E', 'p']) {
+            f.write_str(".0")?;
+        }
+        Ok(())
+    }
+}
+
+/// Spells a float as a hexadecimal float, e.g. `0x1.8p1` for `3.0`.
+///
+/// The exponent is always included, so that the result can't be mistaken for a hex int and can be
+/// followed by a suffix.
+fn hex_float(v: f64) -> String {
+    let sign = if v.is_sign_negative() { "-" } else { "" };
+    let bits = v.abs().to_bits();
+
+    let exponent = (bits >> 52) as i64;
+    let mantissa = bits & ((1 << 52) - 1);
+
+    let (leading, exponent) = match exponent {
+        0 if mantissa == 0 => (0, 0),
+        // Subnormals have no implicit leading one
+        0 => (0, -1022),
+        _ => (1, exponent - 1023),
+    };
+
+    let fraction = format!("{mantissa:013x}");
+    let fraction = fraction.trim_end_matches('0');
+
+    if fraction.is_empty() {
+        format!("{sign}0x{leading}p{exponent}")
+    } else {
+        format!("{sign}0x{leading}.{fraction}p{exponent}")
+    }
+}
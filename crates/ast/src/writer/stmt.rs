@@ -0,0 +1,364 @@
+use std::fmt::{Result, Write};
+
+use super::{stmt_anchor, ParenStyle, Writer};
+use crate::{
+    AssignmentLhs, AssignmentStatement, CommentAnchor, CommentPosition, ContinuingBlock, Else,
+    FnCallStatement, ForLoopInit, ForLoopStatement, ForLoopUpdate, IfStatement, LetDeclStatement,
+    LhsExpr, LhsExprNode, LoopStatement, Statement, SwitchCase, SwitchStatement, VarDeclStatement,
+};
+
+impl Writer<'_> {
+    pub fn write_stmt(&self, f: &mut dyn Write, stmt: &Statement) -> Result {
+        let anchor = stmt_anchor(stmt);
+        let anchor = anchor.as_ref();
+
+        match stmt {
+            Statement::LetDecl(stmt) => {
+                self.write_let_decl(f, stmt)?;
+                write!(f, ";")
+            }
+            Statement::VarDecl(stmt) => {
+                self.write_var_decl(f, stmt)?;
+                write!(f, ";")
+            }
+            Statement::Assignment(stmt) => {
+                self.write_assignment(f, stmt)?;
+                write!(f, ";")
+            }
+            Statement::Increment(stmt) => {
+                self.write_assignment_lhs(f, &stmt.lhs)?;
+                write!(f, "++;")
+            }
+            Statement::Decrement(stmt) => {
+                self.write_assignment_lhs(f, &stmt.lhs)?;
+                write!(f, "--;")
+            }
+            Statement::Compound(stmt) => {
+                self.write_inline_attrs(f, &stmt.attrs)?;
+                writeln!(f, "{{")?;
+                self.write_body(f, &stmt.body, anchor, 0)?;
+                write!(f, "}}")
+            }
+            Statement::If(stmt) => {
+                self.write_inline_attrs(f, &stmt.attrs)?;
+                self.write_if(f, stmt, anchor, 0)
+            }
+            Statement::Return(stmt) => {
+                write!(f, "return")?;
+
+                if let Some(value) = &stmt.value {
+                    write!(f, " ")?;
+                    self.write_expr(f, value)?;
+                }
+
+                write!(f, ";")
+            }
+            Statement::Loop(stmt) => {
+                self.write_inline_attrs(f, &stmt.attrs)?;
+                self.write_loop(f, stmt, anchor)
+            }
+            Statement::While(stmt) => {
+                self.write_inline_attrs(f, &stmt.attrs)?;
+                write!(f, "while (")?;
+                self.write_expr(f, &stmt.condition)?;
+                writeln!(f, ") {{")?;
+                self.write_body(f, &stmt.body, anchor, 0)?;
+                write!(f, "}}")
+            }
+            Statement::Break => write!(f, "break;"),
+            Statement::Continue => write!(f, "continue;"),
+            Statement::Fallthrough => write!(f, "fallthrough;"),
+            Statement::Switch(stmt) => {
+                self.write_inline_attrs(f, &stmt.attrs)?;
+                self.write_switch(f, stmt, anchor)
+            }
+            Statement::ForLoop(stmt) => {
+                self.write_inline_attrs(f, &stmt.attrs)?;
+                self.write_for_loop(f, stmt, anchor)
+            }
+            Statement::FnCall(stmt) => {
+                self.write_fn_call(f, stmt)?;
+                write!(f, ";")
+            }
+        }
+    }
+
+    pub fn write_lhs_expr(&self, f: &mut dyn Write, node: &LhsExprNode) -> Result {
+        match &node.expr {
+            LhsExpr::Ident(ident) => f.write_str(ident),
+            LhsExpr::Postfix(inner, postfix) => {
+                let parens = match self.options.parens {
+                    ParenStyle::Conservative | ParenStyle::Redundant => true,
+                    ParenStyle::Minimal => {
+                        matches!(inner.expr, LhsExpr::Deref(_) | LhsExpr::AddressOf(_))
+                    }
+                };
+
+                self.write_lhs_operand(f, inner, parens)?;
+                self.write_postfix(f, postfix)
+            }
+            LhsExpr::Deref(inner) => {
+                write!(f, "*")?;
+                self.write_lhs_operand(f, inner, self.lhs_unop_parens(inner))
+            }
+            LhsExpr::AddressOf(inner) => {
+                write!(f, "&")?;
+                self.write_lhs_operand(f, inner, self.lhs_unop_parens(inner))
+            }
+        }
+    }
+
+    /// Writes the statements of a block, each on its own line and indented by one level.
+    ///
+    /// The block is the `index`th block of the node with the `owner` anchor, which is where the
+    /// comments that were written in the block when it was empty are attached.
+    pub(super) fn write_body(
+        &self,
+        f: &mut dyn Write,
+        stmts: &[Statement],
+        owner: Option<&CommentAnchor>,
+        index: usize,
+    ) -> Result {
+        let mut f = self.indented(f);
+
+        for stmt in stmts {
+            let Some(anchor) = stmt_anchor(stmt) else {
+                self.write_stmt(&mut f, stmt)?;
+                writeln!(f)?;
+                continue;
+            };
+
+            self.write_comments(&mut f, &anchor, CommentPosition::Before)?;
+            self.write_stmt(&mut f, stmt)?;
+            self.write_inline_comments(&mut f, &anchor)?;
+            writeln!(f)?;
+            self.write_comments(&mut f, &anchor, CommentPosition::After)?;
+        }
+
+        if let Some(owner) = owner {
+            self.write_comments(&mut f, owner, CommentPosition::Inside(index))?;
+        }
+
+        Ok(())
+    }
+
+    fn write_let_decl(&self, f: &mut dyn Write, stmt: &LetDeclStatement) -> Result {
+        write!(f, "let {}", stmt.ident)?;
+
+        if let Some(data_type) = &stmt.data_type {
+            write!(f, ": ")?;
+            self.write_type(f, data_type)?;
+        }
+
+        write!(f, " = ")?;
+        self.write_expr(f, &stmt.initializer)
+    }
+
+    fn write_var_decl(&self, f: &mut dyn Write, stmt: &VarDeclStatement) -> Result {
+        write!(f, "var {}", stmt.ident)?;
+
+        if let Some(data_type) = &stmt.data_type {
+            write!(f, ": ")?;
+            self.write_type(f, data_type)?;
+        }
+
+        if let Some(initializer) = &stmt.initializer {
+            write!(f, " = ")?;
+            self.write_expr(f, initializer)?;
+        }
+
+        Ok(())
+    }
+
+    fn write_assignment(&self, f: &mut dyn Write, stmt: &AssignmentStatement) -> Result {
+        self.write_assignment_lhs(f, &stmt.lhs)?;
+        write!(f, " {} ", stmt.op)?;
+        self.write_expr(f, &stmt.rhs)
+    }
+
+    fn write_assignment_lhs(&self, f: &mut dyn Write, lhs: &AssignmentLhs) -> Result {
+        match lhs {
+            AssignmentLhs::Phony => write!(f, "_"),
+            AssignmentLhs::Expr(node) => self.write_lhs_expr(f, node),
+        }
+    }
+
+    fn write_lhs_operand(&self, f: &mut dyn Write, node: &LhsExprNode, parens: bool) -> Result {
+        if parens {
+            write!(f, "(")?;
+            self.write_lhs_expr(f, node)?;
+            write!(f, ")")
+        } else {
+            self.write_lhs_expr(f, node)
+        }
+    }
+
+    fn lhs_unop_parens(&self, inner: &LhsExprNode) -> bool {
+        match self.options.parens {
+            ParenStyle::Conservative | ParenStyle::Redundant => true,
+            // Without parentheses, a postfix operator would be applied before the prefix operator
+            ParenStyle::Minimal => matches!(inner.expr, LhsExpr::Postfix(..)),
+        }
+    }
+
+    fn write_fn_call(&self, f: &mut dyn Write, stmt: &FnCallStatement) -> Result {
+        f.write_str(&stmt.ident)?;
+        self.write_args(f, &stmt.args)
+    }
+
+    /// Writes an if statement, whose body is the `index`th block of `owner`. The blocks of an
+    /// `else if` chain are all counted as blocks of the first if statement.
+    fn write_if(
+        &self,
+        f: &mut dyn Write,
+        stmt: &IfStatement,
+        owner: Option<&CommentAnchor>,
+        index: usize,
+    ) -> Result {
+        write!(f, "if (")?;
+        self.write_expr(f, &stmt.condition)?;
+        writeln!(f, ") {{")?;
+        self.write_body(f, &stmt.body, owner, index)?;
+        write!(f, "}}")?;
+
+        if let Some(else_) = &stmt.else_ {
+            write!(f, " else ")?;
+
+            match else_.as_ref() {
+                Else::If(stmt) => self.write_if(f, stmt, owner, index + 1)?,
+                Else::Else(stmts) => {
+                    writeln!(f, "{{")?;
+                    self.write_body(f, stmts, owner, index + 1)?;
+                    write!(f, "}}")?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn write_loop(
+        &self,
+        f: &mut dyn Write,
+        stmt: &LoopStatement,
+        owner: Option<&CommentAnchor>,
+    ) -> Result {
+        write!(f, "loop ")?;
+        self.write_inline_attrs(f, &stmt.body_attrs)?;
+        writeln!(f, "{{")?;
+        self.write_body(f, &stmt.body, owner, 0)?;
+
+        if let Some(continuing) = &stmt.continuing {
+            let mut f = self.indented(f);
+            self.write_continuing(&mut f, continuing, owner)?;
+            writeln!(f)?;
+        }
+
+        write!(f, "}}")
+    }
+
+    fn write_continuing(
+        &self,
+        f: &mut dyn Write,
+        block: &ContinuingBlock,
+        owner: Option<&CommentAnchor>,
+    ) -> Result {
+        writeln!(f, "continuing {{")?;
+        self.write_body(f, &block.stmts, owner, 1)?;
+
+        if let Some(break_if) = &block.break_if {
+            let mut f = self.indented(f);
+            write!(f, "break if ")?;
+            self.write_expr(&mut f, break_if)?;
+            writeln!(f, ";")?;
+        }
+
+        write!(f, "}}")
+    }
+
+    fn write_switch(
+        &self,
+        f: &mut dyn Write,
+        stmt: &SwitchStatement,
+        owner: Option<&CommentAnchor>,
+    ) -> Result {
+        write!(f, "switch (")?;
+        self.write_expr(f, &stmt.selector)?;
+        write!(f, ") ")?;
+        self.write_inline_attrs(f, &stmt.body_attrs)?;
+        writeln!(f, "{{")?;
+
+        {
+            let mut f = self.indented(f);
+
+            for (index, SwitchCase { selector, body }) in stmt.cases.iter().enumerate() {
+                write!(f, "case ")?;
+                self.write_expr(&mut f, selector)?;
+                writeln!(f, ": {{")?;
+                self.write_body(&mut f, body, owner, index)?;
+                writeln!(f, "}}")?;
+            }
+
+            writeln!(f, "default: {{")?;
+            self.write_body(&mut f, &stmt.default, owner, stmt.cases.len())?;
+            writeln!(f, "}}")?;
+        }
+
+        write!(f, "}}")
+    }
+
+    fn write_for_loop(
+        &self,
+        f: &mut dyn Write,
+        stmt: &ForLoopStatement,
+        owner: Option<&CommentAnchor>,
+    ) -> Result {
+        let ForLoopStatement { header, body, .. } = stmt;
+
+        write!(f, "for (")?;
+
+        if let Some(init) = &header.init {
+            match init {
+                ForLoopInit::VarDecl(stmt) => self.write_var_decl(f, stmt)?,
+                ForLoopInit::LetDecl(stmt) => self.write_let_decl(f, stmt)?,
+                ForLoopInit::Assignment(stmt) => self.write_assignment(f, stmt)?,
+                ForLoopInit::Increment(stmt) => {
+                    self.write_assignment_lhs(f, &stmt.lhs)?;
+                    write!(f, "++")?;
+                }
+                ForLoopInit::Decrement(stmt) => {
+                    self.write_assignment_lhs(f, &stmt.lhs)?;
+                    write!(f, "--")?;
+                }
+                ForLoopInit::Call(stmt) => self.write_fn_call(f, stmt)?,
+            }
+        }
+
+        write!(f, "; ")?;
+
+        if let Some(condition) = &header.condition {
+            self.write_expr(f, condition)?;
+        }
+
+        write!(f, "; ")?;
+
+        if let Some(update) = &header.update {
+            match update {
+                ForLoopUpdate::Assignment(stmt) => self.write_assignment(f, stmt)?,
+                ForLoopUpdate::Increment(stmt) => {
+                    self.write_assignment_lhs(f, &stmt.lhs)?;
+                    write!(f, "++")?;
+                }
+                ForLoopUpdate::Decrement(stmt) => {
+                    self.write_assignment_lhs(f, &stmt.lhs)?;
+                    write!(f, "--")?;
+                }
+                ForLoopUpdate::Call(stmt) => self.write_fn_call(f, stmt)?,
+            }
+        }
+
+        writeln!(f, ") {{")?;
+        self.write_body(f, body, owner, 0)?;
+        write!(f, "}}")
+    }
+}
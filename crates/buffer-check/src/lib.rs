@@ -1,5 +1,5 @@
 use common::Type;
-use reflection_types::{PipelineDescription, ResourceKind};
+use reflection_types::PipelineDescription;
 
 pub fn compare<'a>(
     mut buffers: impl Iterator<Item = &'a Vec<Vec<u8>>>,
@@ -12,7 +12,7 @@ pub fn compare<'a>(
                 .resources
                 .iter()
                 .enumerate()
-                .filter(|(_, it)| it.kind == ResourceKind::StorageBuffer)
+                .filter(|(_, it)| it.kind.is_output())
                 .enumerate()
             {
                 for (offset, size) in type_descs[j].ranges() {
@@ -59,6 +59,18 @@ pub enum Type {
     },
 }
 
+/// Returns the extent (width, height, depth) of a storage texture with the given dimension.
+///
+/// Shaders have no way of declaring the size of a storage texture, so the harness creates every
+/// texture with a fixed size per dimension and the generator produces init data to match.
+pub fn storage_texture_extent(dim: ast::types::TextureDimension) -> (u32, u32, u32) {
+    match dim {
+        ast::types::TextureDimension::D1 => (16, 1, 1),
+        ast::types::TextureDimension::D2 => (8, 8, 1),
+        ast::types::TextureDimension::D3 => (4, 4, 4),
+    }
+}
+
 fn aligned(size: u32, alignment: u32) -> u32 {
     size.div_ceil(alignment) * alignment
 }
@@ -115,6 +127,38 @@ impl Type {
         }
     }
 
+    /// Returns a type describing the texels of a storage texture, once they have been copied
+    /// into a tightly packed buffer.
+    pub fn texels(dim: ast::types::TextureDimension, format: ast::types::TexelFormat) -> Type {
+        use ast::types::TexelFormat;
+
+        let (width, height, depth) = storage_texture_extent(dim);
+        let scalar_type = (&format.channel_type()).try_into().unwrap();
+
+        let element_type = match format {
+            // 8-bit channels are packed into a single 32-bit word
+            TexelFormat::Rgba8unorm | TexelFormat::Rgba8uint | TexelFormat::Rgba8sint => {
+                Type::Scalar {
+                    scalar_type: ScalarType::U32,
+                }
+            }
+            TexelFormat::Rgba32uint | TexelFormat::Rgba32sint | TexelFormat::Rgba32float => {
+                Type::Vector {
+                    size: VectorSize::N4,
+                    scalar_type,
+                }
+            }
+            TexelFormat::R32uint | TexelFormat::R32sint | TexelFormat::R32float => {
+                Type::Scalar { scalar_type }
+            }
+        };
+
+        Type::Array {
+            size: width * height * depth,
+            element_type: Box::new(element_type),
+        }
+    }
+
     pub fn ranges(&self) -> Vec<(usize, usize)> {
         let mut ranges = vec![];
 
@@ -226,6 +270,10 @@ impl TryFrom<&ast::DataType> for Type {
             ast::DataType::AtomicCompareExchangeResult(_) => {
                 Err("atomic compare exchange result is not storable")
             }
+            ast::DataType::StorageTexture(..) | ast::DataType::Texture(_) => {
+                Err("textures are not storable")
+            }
+            ast::DataType::Sampler(_) => Err("samplers are not storable"),
         }
     }
 }
@@ -20,10 +20,13 @@ pub enum Builtin {
     ReverseBits,
     FirstLeadingBit,
     FirstTrailingBit,
+    Ldexp,
+    Log,
     Min,
     Max,
     Select,
     Sign,
+    Sqrt,
 }
 
 impl Builtin {
@@ -48,9 +51,29 @@ impl Builtin {
             "select" => Some(Builtin::Select),
             "sign" => Some(Builtin::Sign),
             "dot" => Some(Builtin::Dot),
+            "ldexp" => Some(Builtin::Ldexp),
+            "log" => Some(Builtin::Log),
+            "sqrt" => Some(Builtin::Sqrt),
             _ => None,
         }
     }
+
+    /// Returns `true` if the builtin has overloads for abstract numbers, which are evaluated
+    /// without converting the arguments to a concrete type.
+    pub fn accepts_abstract(&self) -> bool {
+        matches!(
+            self,
+            Builtin::Abs
+                | Builtin::Clamp
+                | Builtin::Dot
+                | Builtin::Exp
+                | Builtin::Exp2
+                | Builtin::Min
+                | Builtin::Max
+                | Builtin::Select
+                | Builtin::Sign
+        )
+    }
 }
 
 pub fn evaluate_builtin(ident: &Builtin, args: Vec<Option<Value>>) -> Option<Value> {
@@ -73,7 +96,7 @@ pub fn evaluate_builtin(ident: &Builtin, args: Vec<Option<Value>>) -> Option<Val
             evaluate_three_arg_builtin(ident, arg1, arg2, arg3)
         }
 
-        Builtin::Min | Builtin::Max => {
+        Builtin::Min | Builtin::Max | Builtin::Ldexp => {
             let arg1 = args[0].clone().unwrap();
             let arg2 = args[1].clone().unwrap();
 
@@ -119,6 +142,7 @@ fn evaluate_single_arg_builtin(ident: &Builtin, arg: Value) -> Option<Value> {
 
             Some(Value::Vector(result))
         }
+        _ => None,
     }
 }
 
@@ -171,6 +195,12 @@ fn evaluate_three_arg_builtin(
 
             Some(Value::Vector(result))
         }
+        // select(f, t, cond) with a scalar condition selects one of the vectors
+        (Value::Vector(val1), Value::Vector(val2), Value::Lit(Lit::Bool(cond)))
+            if matches!(ident, Builtin::Select) =>
+        {
+            Some(Value::Vector(if cond { val2 } else { val1 }))
+        }
         _ => None,
     }
 }
@@ -220,6 +250,8 @@ fn evaluate(ident: &Builtin, val: Lit) -> Option<Value> {
         Builtin::FirstLeadingBit => first_leading_bit(val),
         Builtin::FirstTrailingBit => first_trailing_bit(val),
         Builtin::Sign => sign(val),
+        Builtin::Sqrt => sqrt(val),
+        Builtin::Log => log(val),
         _ => todo!(),
     }
 }
@@ -228,6 +260,7 @@ fn evaluate_two_args(ident: &Builtin, val1: Lit, val2: Lit) -> Option<Value> {
     match ident {
         Builtin::Min => min(val1, val2),
         Builtin::Max => max(val1, val2),
+        Builtin::Ldexp => ldexp(val1, val2),
         _ => todo!(),
     }
 }
@@ -378,6 +411,18 @@ fn clamp(e: Lit, low: Lit, high: Lit) -> Option<Value> {
             }
             Some(e_val.clamp(low_val, high_val).into())
         }
+        (Lit::AbstractInt(e_val), Lit::AbstractInt(low_val), Lit::AbstractInt(high_val)) => {
+            if low_val > high_val {
+                return None;
+            }
+            Some(e_val.clamp(low_val, high_val).into())
+        }
+        (Lit::AbstractFloat(e_val), Lit::AbstractFloat(low_val), Lit::AbstractFloat(high_val)) => {
+            if low_val > high_val {
+                return None;
+            }
+            Some(e_val.clamp(low_val, high_val).into())
+        }
         _ => None,
     }
 }
@@ -429,10 +474,12 @@ fn insert_bits(e: Lit, newbits: Lit, offset: Lit, count: Lit) -> Option<Value> {
 
 fn abs(val: Lit) -> Option<Value> {
     match val {
-        Lit::I32(v) => Value::from_i32(Some(v.wrapping_abs())),
+        Lit::I32(v) => Value::from_i32(v.checked_abs()),
         Lit::F32(v) => Value::from_f32(Some(v.abs())),
         Lit::U32(v) => Value::from_u32(Some(v)),
         Lit::F16(v) => Value::from_f16(Some(half::f16::from_f32(v.to_f32().abs()))),
+        Lit::AbstractInt(v) => Value::from_abstract_int(v.checked_abs()),
+        Lit::AbstractFloat(v) => Some(v.abs().into()),
         _ => None,
     }
 }
@@ -458,6 +505,9 @@ fn exp(val: Lit) -> Option<Value> {
             let result = in_float16_range(half::f16::from_f32(v.to_f32().exp()));
             Value::from_f16(result)
         }
+        Lit::AbstractFloat(v) => {
+            Value::from_abstract_float(Some(v.exp()).filter(|v| v.is_finite()))
+        }
         _ => None,
     }
 }
@@ -492,16 +542,95 @@ fn exp2(val: Lit) -> Option<Value> {
             let result = in_float16_range(half::f16::from_f32(2.0_f32.powf(v.to_f32())));
             Value::from_f16(result)
         }
+        Lit::AbstractFloat(v) => {
+            Value::from_abstract_float(Some(v.exp2()).filter(|v| v.is_finite()))
+        }
+        _ => None,
+    }
+}
+
+fn sqrt(val: Lit) -> Option<Value> {
+    match val {
+        Lit::F32(v) => Value::from_f32(in_float_range(v.sqrt())),
+        Lit::F16(v) => Value::from_f16(in_float16_range(half::f16::from_f32(v.to_f32().sqrt()))),
+        _ => None,
+    }
+}
+
+fn log(val: Lit) -> Option<Value> {
+    match val {
+        Lit::F32(v) => Value::from_f32(in_float_range(v.ln())),
+        Lit::F16(v) => Value::from_f16(in_float16_range(half::f16::from_f32(v.to_f32().ln()))),
+        _ => None,
+    }
+}
+
+fn ldexp(val1: Lit, val2: Lit) -> Option<Value> {
+    match (val1, val2) {
+        (Lit::F32(v), Lit::I32(e)) => {
+            Value::from_f32(in_float_range((v as f64 * 2f64.powi(e)) as f32))
+        }
+        (Lit::F16(v), Lit::I32(e)) => Value::from_f16(in_float16_range(half::f16::from_f64(
+            v.to_f64() * 2f64.powi(e),
+        ))),
         _ => None,
     }
 }
 
+/// Returns `true` if a component of the result of a builtin that couldn't be evaluated is
+/// infinite or NaN, rather than just outside of the range that float results are kept in.
+pub fn has_non_finite_result(ident: &Builtin, args: &[Option<Value>]) -> bool {
+    let components = |arg: &Option<Value>| match arg {
+        Some(Value::Vector(v)) => v.clone(),
+        Some(arg) => vec![arg.clone()],
+        None => vec![],
+    };
+
+    let float = |lit: &Value| match lit {
+        Value::Lit(Lit::F32(v)) => Some(*v as f64),
+        Value::Lit(Lit::F16(v)) => Some(v.to_f64()),
+        Value::Lit(Lit::AbstractFloat(v)) => Some(*v),
+        _ => None,
+    };
+
+    let is_finite = |lit: &Value, result: f64| match lit {
+        Value::Lit(Lit::F32(_)) => (result as f32).is_finite(),
+        Value::Lit(Lit::F16(_)) => half::f16::from_f64(result).is_finite(),
+        _ => result.is_finite(),
+    };
+
+    let (xs, es) = match args {
+        [x] => (components(x), vec![]),
+        [x, e] => (components(x), components(e)),
+        _ => return false,
+    };
+
+    xs.iter().enumerate().any(|(i, x)| {
+        let Some(v) = float(x) else {
+            return false;
+        };
+
+        let result = match (ident, es.get(i)) {
+            (Builtin::Exp, _) => v.exp(),
+            (Builtin::Exp2, _) => v.exp2(),
+            (Builtin::Sqrt, _) => v.sqrt(),
+            (Builtin::Log, _) => v.ln(),
+            (Builtin::Ldexp, Some(Value::Lit(Lit::I32(e)))) => v * 2f64.powi(*e),
+            _ => return false,
+        };
+
+        !is_finite(x, result)
+    })
+}
+
 fn min(val1: Lit, val2: Lit) -> Option<Value> {
     match (val1, val2) {
         (Lit::I32(v1), Lit::I32(v2)) => Some(v1.min(v2).into()),
         (Lit::U32(v1), Lit::U32(v2)) => Some(v1.min(v2).into()),
         (Lit::F32(v1), Lit::F32(v2)) => Some(v1.min(v2).into()),
         (Lit::F16(v1), Lit::F16(v2)) => Some(v1.min(v2).into()),
+        (Lit::AbstractInt(v1), Lit::AbstractInt(v2)) => Some(v1.min(v2).into()),
+        (Lit::AbstractFloat(v1), Lit::AbstractFloat(v2)) => Some(v1.min(v2).into()),
         _ => None,
     }
 }
@@ -512,6 +641,8 @@ fn max(val1: Lit, val2: Lit) -> Option<Value> {
         (Lit::U32(v1), Lit::U32(v2)) => Some(v1.max(v2).into()),
         (Lit::F32(v1), Lit::F32(v2)) => Some(v1.max(v2).into()),
         (Lit::F16(v1), Lit::F16(v2)) => Some(v1.max(v2).into()),
+        (Lit::AbstractInt(v1), Lit::AbstractInt(v2)) => Some(v1.max(v2).into()),
+        (Lit::AbstractFloat(v1), Lit::AbstractFloat(v2)) => Some(v1.max(v2).into()),
         _ => None,
     }
 }
@@ -526,7 +657,9 @@ fn select(val1: Lit, val2: Lit, val3: Lit) -> Option<Value> {
         (Lit::U32(v1), Lit::U32(v2)) => Some(if cond { v2 } else { v1 }.into()),
         (Lit::F32(v1), Lit::F32(v2)) => Some(if cond { v2 } else { v1 }.into()),
         (Lit::F16(v1), Lit::F16(v2)) => Some(if cond { v2 } else { v1 }.into()),
-        // (Lit::Bool(v1), Lit::Bool(v2)) => Some(if cond { v2 } else { v1 }.into()),
+        (Lit::AbstractInt(v1), Lit::AbstractInt(v2)) => Some(if cond { v2 } else { v1 }.into()),
+        (Lit::AbstractFloat(v1), Lit::AbstractFloat(v2)) => Some(if cond { v2 } else { v1 }.into()),
+        (Lit::Bool(v1), Lit::Bool(v2)) => Some(if cond { v2 } else { v1 }.into()),
         _ => None,
     }
 }
@@ -616,11 +749,22 @@ fn sign(val: Lit) -> Option<Value> {
         } else {
             0.0
         }))),
+        Lit::AbstractInt(v) => Some(v.signum().into()),
+        Lit::AbstractFloat(v) => Some(
+            if v > 0.0 {
+                1.0
+            } else if v < 0.0 {
+                -1.0
+            } else {
+                0.0
+            }
+            .into(),
+        ),
         _ => None,
     }
 }
 
-fn evaluate_dot(arg1: Value, arg2: Value) -> Option<Value> {
+pub(crate) fn evaluate_dot(arg1: Value, arg2: Value) -> Option<Value> {
     match (arg1, arg2) {
         (Value::Vector(v1), Value::Vector(v2)) => {
             if v1.len() != v2.len() || v1.is_empty() {
@@ -632,8 +776,7 @@ fn evaluate_dot(arg1: Value, arg2: Value) -> Option<Value> {
                     let mut sum = 0i32;
                     for (x, y) in v1.iter().zip(v2.iter()) {
                         if let (Value::Lit(Lit::I32(xv)), Value::Lit(Lit::I32(yv))) = (x, y) {
-                            let product = xv.wrapping_mul(*yv);
-                            sum = sum.wrapping_add(product);
+                            sum = sum.checked_add(xv.checked_mul(*yv)?)?;
                         } else {
                             return None;
                         }
@@ -644,8 +787,7 @@ fn evaluate_dot(arg1: Value, arg2: Value) -> Option<Value> {
                     let mut sum = 0u32;
                     for (x, y) in v1.iter().zip(v2.iter()) {
                         if let (Value::Lit(Lit::U32(xv)), Value::Lit(Lit::U32(yv))) = (x, y) {
-                            let product = xv.wrapping_mul(*yv);
-                            sum = sum.wrapping_add(product);
+                            sum = sum.checked_add(xv.checked_mul(*yv)?)?;
                         } else {
                             return None;
                         }
@@ -680,6 +822,36 @@ fn evaluate_dot(arg1: Value, arg2: Value) -> Option<Value> {
                     }
                     Some(Value::Lit(Lit::F16(half::f16::from_f32(sum))))
                 }
+                (Value::Lit(Lit::AbstractInt(_)), Value::Lit(Lit::AbstractInt(_))) => {
+                    let mut sum = 0i64;
+                    for (x, y) in v1.iter().zip(v2.iter()) {
+                        if let (
+                            Value::Lit(Lit::AbstractInt(xv)),
+                            Value::Lit(Lit::AbstractInt(yv)),
+                        ) = (x, y)
+                        {
+                            sum = sum.checked_add(xv.checked_mul(*yv)?)?;
+                        } else {
+                            return None;
+                        }
+                    }
+                    Some(sum.into())
+                }
+                (Value::Lit(Lit::AbstractFloat(_)), Value::Lit(Lit::AbstractFloat(_))) => {
+                    let mut sum = 0.0f64;
+                    for (x, y) in v1.iter().zip(v2.iter()) {
+                        if let (
+                            Value::Lit(Lit::AbstractFloat(xv)),
+                            Value::Lit(Lit::AbstractFloat(yv)),
+                        ) = (x, y)
+                        {
+                            sum += xv * yv;
+                        } else {
+                            return None;
+                        }
+                    }
+                    Value::from_abstract_float(Some(sum).filter(|v| v.is_finite()))
+                }
                 _ => None,
             }
         }
@@ -0,0 +1,86 @@
+use std::fs::File;
+use std::io::Read;
+
+use clap::Parser;
+
+use crate::concretizer::Options as ConcretizerOptions;
+
+#[derive(Parser)]
+pub struct Options {
+    /// Path to a wgsl shader program (use '-' for stdin).
+    #[clap(action, default_value = "-")]
+    pub input: String,
+
+    /// Path at which to write output (use '-' for stdout).
+    #[clap(action, default_value = "-")]
+    pub output: String,
+
+    /// Maximum number of statements to execute when evaluating a function call.
+    #[clap(long, action, default_value = "10000")]
+    pub max_steps: u64,
+
+    #[clap(flatten)]
+    pub style: ast::writer::Options,
+}
+
+pub fn run(options: Options) -> eyre::Result<()> {
+    let input = read_shader_from_path(&options.input)?;
+    let ast = match parser::try_parse(&input) {
+        Ok(ast) => ast,
+        Err(diagnostics) => {
+            for diagnostic in &diagnostics {
+                eprintln!("error: {diagnostic}");
+            }
+
+            eprintln!("found {} errors in shader", diagnostics.len());
+            std::process::exit(1);
+        }
+    };
+
+    let (result, diagnostics) = crate::concretize_with_diagnostics(
+        ast,
+        ConcretizerOptions {
+            fold_budget: Some(options.max_steps),
+            ..ConcretizerOptions::default()
+        },
+    );
+
+    // Invalid expressions are left as they are, so the output can still be used to reproduce a
+    // bug that the shader triggers
+    for diagnostic in &diagnostics {
+        eprintln!("error: {diagnostic}");
+    }
+
+    struct Output(Box<dyn std::io::Write>);
+
+    impl std::fmt::Write for Output {
+        fn write_str(&mut self, s: &str) -> std::fmt::Result {
+            use std::io::Write;
+            self.0.write_all(s.as_bytes()).unwrap();
+            Ok(())
+        }
+    }
+
+    let output: Box<dyn std::io::Write> = match options.output.as_str() {
+        "-" => Box::new(std::io::stdout()),
+        path => Box::new(File::create(path)?),
+    };
+
+    ast::writer::Writer::new(options.style)
+        .write_module(&mut Output(output), &result)
+        .unwrap();
+
+    Ok(())
+}
+
+fn read_shader_from_path(path: &str) -> eyre::Result<String> {
+    let mut input: Box<dyn Read> = match path {
+        "-" => Box::new(std::io::stdin()),
+        path => Box::new(File::open(path)?),
+    };
+
+    let mut shader = String::new();
+    input.read_to_string(&mut shader)?;
+
+    Ok(shader)
+}
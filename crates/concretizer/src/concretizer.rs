@@ -1,6 +1,8 @@
 use crate::builtin::*;
+use crate::diagnostic::{Diagnostic, Rule};
 use crate::helper;
 use crate::value::*;
+use ast::visit::{fold, Fold};
 use ast::*;
 use std::cmp::PartialEq;
 
@@ -10,26 +12,29 @@ use std::cmp::PartialEq;
 // (i.e. the node is not a const expression, for example if
 // it contains a runtime variable).
 
+// Overflow of concrete integers is a shader creation error in constant expressions, as is
+// division by zero
 macro_rules! binop_int_arith {
     ($op:expr, $l:expr, $r:expr) => {
         match $op {
-            BinOp::Plus => Some(($l).wrapping_add($r)),
-            BinOp::Minus => Some(($l).wrapping_sub($r)),
-            BinOp::Times => Some(($l).wrapping_mul($r)),
-            BinOp::Divide => {
-                if $r == 0 {
-                    None
-                } else {
-                    Some(($l).wrapping_div($r))
-                }
-            }
-            BinOp::Mod => {
-                if $r == 0 {
-                    None
-                } else {
-                    Some(($l).wrapping_rem($r))
-                }
-            }
+            BinOp::Plus => ($l).checked_add($r),
+            BinOp::Minus => ($l).checked_sub($r),
+            BinOp::Times => ($l).checked_mul($r),
+            BinOp::Divide => ($l).checked_div($r),
+            BinOp::Mod => ($l).checked_rem($r),
+            _ => None,
+        }
+    };
+}
+
+macro_rules! binop_float_arith {
+    ($op:expr, $l:expr, $r:expr) => {
+        match $op {
+            BinOp::Plus => Some($l + $r),
+            BinOp::Minus => Some($l - $r),
+            BinOp::Times => Some($l * $r),
+            BinOp::Divide => Some($l / $r),
+            BinOp::Mod => Some($l % $r),
             _ => None,
         }
     };
@@ -83,14 +88,7 @@ fn binop_int_shift(op: &BinOp, l: Lit, r: Lit) -> Option<Value> {
 }
 
 fn binop_float(op: &BinOp, l: f32, r: f32) -> Option<f32> {
-    let result = match op {
-        BinOp::Plus => l + r,
-        BinOp::Minus => l - r,
-        BinOp::Times => l * r,
-        BinOp::Divide => l / r,
-        BinOp::Mod => l % r,
-        _ => todo!(),
-    };
+    let result = binop_float_arith!(op, l, r)?;
 
     in_float_range(result)
 
@@ -98,18 +96,150 @@ fn binop_float(op: &BinOp, l: f32, r: f32) -> Option<f32> {
 }
 
 fn binop_float16(op: &BinOp, l: half::f16, r: half::f16) -> Option<half::f16> {
-    let l_f = l.to_f32();
-    let r_f = r.to_f32();
+    let result = binop_float_arith!(op, l.to_f32(), r.to_f32())?;
+
+    in_float16_range(half::f16::from_f32(result))
+}
+
+// Abstract integers are evaluated as i64, and it is an error for the result to overflow
+fn binop_abstract_int(op: &BinOp, l: i64, r: i64) -> Option<i64> {
+    match op {
+        BinOp::Plus => l.checked_add(r),
+        BinOp::Minus => l.checked_sub(r),
+        BinOp::Times => l.checked_mul(r),
+        BinOp::Divide => l.checked_div(r),
+        BinOp::Mod => l.checked_rem(r),
+        _ => None,
+    }
+}
+
+// Abstract floats are evaluated as f64, and it is an error for the result to be infinite or NaN
+fn binop_abstract_float(op: &BinOp, l: f64, r: f64) -> Option<f64> {
+    let result = binop_float_arith!(op, l, r)?;
+
+    result.is_finite().then_some(result)
+}
+
+// Shifting an abstract integer is an error if any bits that are shifted out differ from the
+// resulting sign bit
+fn binop_abstract_shift(op: &BinOp, l: i64, r: Lit) -> Option<Value> {
+    let r = match r {
+        Lit::I32(r) => u32::try_from(r).ok()?,
+        Lit::U32(r) => r,
+        Lit::AbstractInt(r) => u32::try_from(r).ok()?,
+        _ => return None,
+    };
+
+    if r >= i64::BITS {
+        return None;
+    }
+
+    match op {
+        BinOp::LShift => Some(l << r).filter(|v| v >> r == l).map(Value::from),
+        BinOp::RShift => Some((l >> r).into()),
+        _ => None,
+    }
+}
+
+// `&&` and `||` are only defined for bools, and `&`, `|` and `^` for bools and integers
+fn binop_bitwise(op: &BinOp, l: Lit, r: Lit) -> Option<Value> {
+    macro_rules! bitwise {
+        ($l:expr, $r:expr) => {
+            match op {
+                BinOp::BitAnd => Some(($l & $r).into()),
+                BinOp::BitOr => Some(($l | $r).into()),
+                BinOp::BitXOr => Some(($l ^ $r).into()),
+                _ => None,
+            }
+        };
+    }
+
+    match (l, r) {
+        (Lit::Bool(l), Lit::Bool(r)) => match op {
+            BinOp::LogAnd => Some((l && r).into()),
+            BinOp::LogOr => Some((l || r).into()),
+            _ => bitwise!(l, r),
+        },
+        (Lit::I32(l), Lit::I32(r)) => bitwise!(l, r),
+        (Lit::U32(l), Lit::U32(r)) => bitwise!(l, r),
+        (Lit::AbstractInt(l), Lit::AbstractInt(r)) => bitwise!(l, r),
+        _ => None,
+    }
+}
+
+fn binop_compare(op: &BinOp, l: Lit, r: Lit) -> Option<Value> {
+    let ordering = match (l, r) {
+        (Lit::Bool(l), Lit::Bool(r)) => l.partial_cmp(&r),
+        (Lit::I32(l), Lit::I32(r)) => l.partial_cmp(&r),
+        (Lit::U32(l), Lit::U32(r)) => l.partial_cmp(&r),
+        (Lit::F32(l), Lit::F32(r)) => l.partial_cmp(&r),
+        (Lit::F16(l), Lit::F16(r)) => l.partial_cmp(&r),
+        (Lit::AbstractInt(l), Lit::AbstractInt(r)) => l.partial_cmp(&r),
+        (Lit::AbstractFloat(l), Lit::AbstractFloat(r)) => l.partial_cmp(&r),
+        _ => None,
+    }?;
+
     let result = match op {
-        BinOp::Plus => l_f + r_f,
-        BinOp::Minus => l_f - r_f,
-        BinOp::Times => l_f * r_f,
-        BinOp::Divide => l_f / r_f,
-        BinOp::Mod => l_f % r_f,
-        _ => todo!(),
+        BinOp::Equal => ordering.is_eq(),
+        BinOp::NotEqual => ordering.is_ne(),
+        BinOp::Less => ordering.is_lt(),
+        BinOp::LessEqual => ordering.is_le(),
+        BinOp::Greater => ordering.is_gt(),
+        BinOp::GreaterEqual => ordering.is_ge(),
+        _ => return None,
     };
 
-    in_float16_range(half::f16::from_f32(result))
+    Some(result.into())
+}
+
+/// Returns the rule that evaluating `l op r` breaks, given that it can't be evaluated.
+fn scalar_bin_op_rule(op: &BinOp, l: Lit, r: Lit) -> Rule {
+    let float_rule = |result: Option<bool>| match result {
+        Some(false) => Rule::FloatNotFinite,
+        _ => Rule::FloatOutOfRange,
+    };
+
+    match (op, l, r) {
+        (BinOp::LShift | BinOp::RShift, l, r) => {
+            let width = match l {
+                Lit::AbstractInt(_) => i64::BITS,
+                _ => u32::BITS,
+            };
+            let amount = match r {
+                Lit::I32(r) => r as i64,
+                Lit::U32(r) => r as i64,
+                Lit::AbstractInt(r) => r,
+                _ => 0,
+            };
+
+            if (0..width as i64).contains(&amount) {
+                Rule::ShiftOverflow
+            } else {
+                Rule::ShiftOutOfRange
+            }
+        }
+        (_, Lit::F32(l), Lit::F32(r)) => {
+            float_rule(binop_float_arith!(op, l, r).map(f32::is_finite))
+        }
+        (_, Lit::F16(l), Lit::F16(r)) => float_rule(
+            binop_float_arith!(op, l.to_f32(), r.to_f32())
+                .map(|result| half::f16::from_f32(result).is_finite()),
+        ),
+        (_, Lit::AbstractFloat(_), Lit::AbstractFloat(_)) => Rule::FloatNotFinite,
+        (BinOp::Divide | BinOp::Mod, _, Lit::I32(0) | Lit::U32(0) | Lit::AbstractInt(0)) => {
+            Rule::DivisionByZero
+        }
+        _ => Rule::IntegerOverflow,
+    }
+}
+
+/// Returns the rule that an expression of type `data_type` breaks when its value can't be
+/// computed, if nothing more is known about it.
+fn type_rule(data_type: &DataType) -> Rule {
+    match data_type.as_scalar() {
+        Some(ScalarType::F32 | ScalarType::F16) => Rule::FloatOutOfRange,
+        _ => Rule::IntegerOverflow,
+    }
 }
 
 pub(super) fn in_float_range(f: f32) -> Option<f32> {
@@ -149,6 +279,7 @@ impl From<ConcreteNode> for ExprNode {
         ExprNode {
             data_type: con.node.data_type,
             expr: con.node.expr,
+            meta: con.node.meta,
         }
     }
 }
@@ -156,6 +287,9 @@ impl From<ConcreteNode> for ExprNode {
 #[derive(Default)]
 pub struct Options {
     pub error_handling: ErrorHandling,
+    /// Replace constant expressions with their values, and evaluate calls to user-defined
+    /// functions with constant arguments, giving up on a call after this many steps.
+    pub fold_budget: Option<u64>,
 }
 
 #[derive(Default, PartialEq)]
@@ -165,315 +299,205 @@ pub enum ErrorHandling {
     Panic,
 }
 
+use std::cell::{Cell, RefCell};
 use std::collections::HashMap;
 
 #[derive(Default)]
 pub struct Concretizer {
     error_handling: ErrorHandling,
+    fold_budget: Option<u64>,
     // keep track of consts as we traverse the AST
     global_constants: HashMap<String, Value>,
     local_scopes: Vec<HashMap<String, Value>>,
+    // source location of the innermost expression being concretized, for error reporting
+    current_span: Option<Span>,
+    // user-defined functions other than the one being concretized, for evaluating calls
+    functions: HashMap<String, FnDecl>,
+    // return type of the function being concretized, which abstract return values are converted to
+    return_type: Option<DataType>,
+    steps_left: Cell<u64>,
+    // set when folding an expression would make it invalid
+    fold_invalid: Cell<bool>,
+    // set when the expression being concretized contains a call that was evaluated
+    folded_call: Cell<bool>,
+    diagnostics: RefCell<Vec<Diagnostic>>,
 }
 
 impl Concretizer {
     pub fn new(options: Options) -> Concretizer {
         Concretizer {
             error_handling: options.error_handling,
+            fold_budget: options.fold_budget,
             global_constants: HashMap::new(),
             local_scopes: Vec::new(),
+            current_span: None,
+            functions: HashMap::new(),
+            return_type: None,
+            steps_left: Cell::new(0),
+            fold_invalid: Cell::new(false),
+            folded_call: Cell::new(false),
+            diagnostics: RefCell::new(Vec::new()),
         }
     }
 
-    pub fn register_global_consts(&mut self, consts: &[GlobalConstDecl]) {
-        for decl in consts {
-            let con_node = self.concretize_expr(decl.initializer.clone());
-            if let Some(val) = con_node.value {
-                self.global_constants.insert(decl.name.clone(), val);
-            }
-        }
+    /// Returns the invalid expressions that were replaced, in the order they were found.
+    pub fn into_diagnostics(self) -> Vec<Diagnostic> {
+        self.diagnostics.into_inner()
     }
 
-    #[expect(dead_code)]
-    // This will be useful once we add consts to the AST
-    fn register_const(&mut self, name: String, val: Value) {
-        self.local_scopes.last_mut().unwrap().insert(name, val);
-    }
+    /// Concretizes the initializer of a module-scope constant, and registers its value for the
+    /// rest of the module.
+    pub(crate) fn concretize_global_const(&mut self, decl: GlobalConstDecl) -> GlobalConstDecl {
+        let con = self.fold_checked(decl.initializer, |this, node| {
+            let con = this.concretize_expr(node);
+            this.concretize_abstract(con, &decl.data_type)
+        });
 
-    fn with_scope<T>(&mut self, block: impl FnOnce(&mut Self) -> T) -> T {
-        self.local_scopes.push(HashMap::new());
-        let res = block(self);
-        self.local_scopes.pop();
-        res
-    }
+        if let Some(value) = &con.value {
+            self.global_constants
+                .insert(decl.name.clone(), value.clone());
+        }
 
-    #[expect(dead_code)]
-    // This will be useful once we add consts to the AST
-    fn insert_const(&mut self, name: String, val: Value) {
-        if let Some(scope) = self.local_scopes.last_mut() {
-            scope.insert(name, val);
+        GlobalConstDecl {
+            initializer: con.into(),
+            ..decl
         }
     }
 
-    fn lookup_const(&self, name: &str) -> Option<Value> {
-        for scope in self.local_scopes.iter().rev() {
-            if let Some(val) = scope.get(name) {
-                return Some(val.clone());
+    /// Concretizes the functions of a module. Each function can be called by the others while they
+    /// are concretized, so calls to it can be evaluated.
+    pub(crate) fn concretize_fns(&mut self, decls: Vec<FnDecl>) -> Vec<FnDecl> {
+        let names: Vec<String> = decls.iter().map(|decl| decl.name.clone()).collect();
+        self.functions = decls
+            .into_iter()
+            .map(|decl| (decl.name.clone(), decl))
+            .collect();
+
+        for name in &names {
+            if let Some(decl) = self.functions.remove(name) {
+                let decl = self.concretize_fn(decl);
+                self.functions.insert(name.clone(), decl);
             }
         }
-        self.global_constants.get(name).cloned()
-    }
 
-    pub(crate) fn concretize_fn(&mut self, mut decl: FnDecl) -> FnDecl {
-        self.local_scopes.clear();
-        decl.body = self.with_scope(|this| {
-            decl.body
-                .into_iter()
-                .map(|s| this.concretize_stmt(s))
-                .collect()
-        });
-        decl
+        names
+            .iter()
+            .filter_map(|name| self.functions.remove(name))
+            .collect()
     }
 
-    fn concretize_stmt(&mut self, stmt: Statement) -> Statement {
-        match stmt {
-            Statement::LetDecl(LetDeclStatement { ident, initializer }) => {
-                let con_init = self.concretize_expr(initializer);
-                LetDeclStatement::new(ident, con_init).into()
-            }
-            Statement::VarDecl(VarDeclStatement {
-                ident,
-                data_type,
-                initializer,
-            }) => VarDeclStatement::new(
-                ident,
-                data_type,
-                initializer.map(|e| self.concretize_expr(e).into()),
-            )
-            .into(),
-            Statement::Assignment(AssignmentStatement { lhs, op, rhs }) => {
-                AssignmentStatement::new(
-                    self.concretize_assignment_lhs(lhs),
-                    op,
-                    self.concretize_expr(rhs),
-                )
-                .into()
-            }
-            Statement::Compound(s) => {
-                let stmts = self
-                    .with_scope(|this| s.into_iter().map(|s| this.concretize_stmt(s)).collect());
-                Statement::Compound(stmts)
-            }
-            Statement::If(IfStatement {
-                condition,
-                body,
-                else_,
-            }) => {
-                let cond = self.concretize_expr(condition);
-                let new_body = self
-                    .with_scope(|this| body.into_iter().map(|s| this.concretize_stmt(s)).collect());
-
-                let new_else = else_
-                    .map(|els| match *els {
-                        Else::If(stmt) => Else::If(match self.concretize_stmt(stmt.into()) {
-                            Statement::If(s) => s,
-                            _ => unreachable!(),
-                        }),
-                        Else::Else(stmts) => {
-                            let new_stmts = self.with_scope(|this| {
-                                stmts.into_iter().map(|s| this.concretize_stmt(s)).collect()
-                            });
-                            Else::Else(new_stmts)
-                        }
-                    })
-                    .map(Box::new);
-
-                IfStatement::new(cond, new_body)
-                    .with_else(new_else.map(|e| *e))
-                    .into()
-            }
-            Statement::Return(ReturnStatement { value }) => ReturnStatement {
-                value: value.map(|e| self.concretize_expr(e).into()),
-            }
-            .into(),
-            Statement::Switch(SwitchStatement {
-                selector,
-                cases,
-                default,
-            }) => SwitchStatement::new(
-                self.concretize_expr(selector).into(),
-                cases
-                    .into_iter()
-                    .map(|c| self.concretize_switch_case(c))
-                    .collect(),
-                self.with_scope(|this| {
-                    default
-                        .into_iter()
-                        .map(|s| this.concretize_stmt(s))
-                        .collect()
-                }),
-            )
-            .into(),
-            Statement::FnCall(FnCallStatement { ident, args }) => FnCallStatement::new(
-                ident,
-                args.into_iter()
-                    .map(|e| self.concretize_expr(e).into())
-                    .collect(),
-            )
-            .into(),
-            Statement::Loop(LoopStatement { body, continuing }) => {
-                let new_body = body.into_iter().map(|s| self.concretize_stmt(s)).collect();
-                let new_continuing = continuing.map(|c| ContinuingBlock {
-                    stmts: c
-                        .stmts
-                        .into_iter()
-                        .map(|s| self.concretize_stmt(s))
-                        .collect(),
-                    break_if: c.break_if.map(|e| self.concretize_expr(e).into()),
-                });
-                LoopStatement::new(new_body, new_continuing).into()
-            }
-            Statement::While(WhileStatement { condition, body }) => {
-                let new_condition = self.concretize_expr(condition).into();
-                let new_body = body.into_iter().map(|s| self.concretize_stmt(s)).collect();
-                WhileStatement::new(new_condition, new_body).into()
-            }
-            Statement::ForLoop(ForLoopStatement { header, body }) => self.with_scope(|this| {
-                let new_header = ForLoopHeader {
-                    init: header.init.map(|init| this.concretize_for_init(init)),
-                    condition: header.condition.map(|e| this.concretize_expr(e).into()),
-                    update: header
-                        .update
-                        .map(|update| this.concretize_for_update(update)),
-                };
+    pub(crate) fn function(&self, name: &str) -> Option<&FnDecl> {
+        self.functions.get(name)
+    }
 
-                let new_body = body.into_iter().map(|s| this.concretize_stmt(s)).collect();
+    pub(crate) fn global_const(&self, name: &str) -> Option<&Value> {
+        self.global_constants.get(name)
+    }
 
-                ForLoopStatement::new(new_header, new_body).into()
-            }),
-            Statement::Increment(IncrementStatement { lhs }) => {
-                Statement::Increment(IncrementStatement::new(self.concretize_assignment_lhs(lhs)))
-            }
-            Statement::Decrement(DecrementStatement { lhs }) => {
-                Statement::Decrement(DecrementStatement::new(self.concretize_assignment_lhs(lhs)))
-            }
-            Statement::Break => Statement::Break,
-            Statement::Continue => Statement::Continue,
-            Statement::Fallthrough => Statement::Fallthrough,
-        }
+    /// Takes a step of the evaluation of a function call. Returns `None` once the budget for the
+    /// call is used up.
+    pub(crate) fn step(&self) -> Option<()> {
+        let steps_left = self.steps_left.get().checked_sub(1)?;
+        self.steps_left.set(steps_left);
+        Some(())
     }
 
-    fn concretize_for_init(&mut self, init: ForLoopInit) -> ForLoopInit {
-        match init {
-            ForLoopInit::VarDecl(VarDeclStatement {
-                ident,
-                data_type,
-                initializer,
-            }) => ForLoopInit::VarDecl(VarDeclStatement::new(
-                ident,
-                data_type,
-                initializer.map(|e| self.concretize_expr(e).into()),
-            )),
-            ForLoopInit::LetDecl(LetDeclStatement { ident, initializer }) => {
-                let con_init = self.concretize_expr(initializer);
-                ForLoopInit::LetDecl(LetDeclStatement::new(ident, con_init))
-            }
-            ForLoopInit::Assignment(AssignmentStatement { lhs, op, rhs }) => {
-                ForLoopInit::Assignment(AssignmentStatement::new(
-                    self.concretize_assignment_lhs(lhs),
-                    op,
-                    self.concretize_expr(rhs),
-                ))
-            }
-            ForLoopInit::Increment(IncrementStatement { lhs }) => {
-                ForLoopInit::Increment(IncrementStatement::new(self.concretize_assignment_lhs(lhs)))
-            }
-            ForLoopInit::Decrement(DecrementStatement { lhs }) => {
-                ForLoopInit::Decrement(DecrementStatement::new(self.concretize_assignment_lhs(lhs)))
-            }
-            ForLoopInit::Call(FnCallStatement { ident, args }) => {
-                ForLoopInit::Call(FnCallStatement::new(
-                    ident,
-                    args.into_iter()
-                        .map(|e| self.concretize_expr(e).into())
-                        .collect(),
-                ))
-            }
+    /// Concretizes an expression that isn't part of a larger expression. In fold mode the
+    /// expression is left unchanged if folding would make it invalid, for example by folding a call
+    /// into an out of bounds index, or if it is invalid already.
+    fn fold_checked(
+        &mut self,
+        node: ExprNode,
+        concretize: impl FnOnce(&mut Self, ExprNode) -> ConcreteNode,
+    ) -> ConcreteNode {
+        if self.fold_budget.is_none() {
+            return concretize(self, node);
         }
-    }
 
-    fn concretize_for_update(&mut self, update: ForLoopUpdate) -> ForLoopUpdate {
-        match update {
-            ForLoopUpdate::Assignment(AssignmentStatement { lhs, op, rhs }) => {
-                ForLoopUpdate::Assignment(AssignmentStatement::new(
-                    self.concretize_assignment_lhs(lhs),
-                    op,
-                    self.concretize_expr(rhs),
-                ))
-            }
-            ForLoopUpdate::Increment(IncrementStatement { lhs }) => ForLoopUpdate::Increment(
-                IncrementStatement::new(self.concretize_assignment_lhs(lhs)),
-            ),
-            ForLoopUpdate::Decrement(DecrementStatement { lhs }) => ForLoopUpdate::Decrement(
-                DecrementStatement::new(self.concretize_assignment_lhs(lhs)),
-            ),
-            ForLoopUpdate::Call(FnCallStatement { ident, args }) => {
-                ForLoopUpdate::Call(FnCallStatement::new(
-                    ident,
-                    args.into_iter()
-                        .map(|e| self.concretize_expr(e).into())
-                        .collect(),
-                ))
-            }
+        let original = node.clone();
+        self.folded_call.set(false);
+        let con = concretize(self, node);
+
+        if self.fold_invalid.replace(false) {
+            original.into()
+        } else {
+            con
         }
     }
 
-    fn concretize_assignment_lhs(&mut self, lhs: AssignmentLhs) -> AssignmentLhs {
-        match lhs {
-            AssignmentLhs::Phony => AssignmentLhs::Phony,
-            AssignmentLhs::Expr(expr) => AssignmentLhs::Expr(self.concretize_lhs_expr(expr)),
+    /// Concretizes an expression whose value is used as a value of `data_type`, if that is known.
+    /// An abstract value takes that type, as in `let x: u32 = 3000000000;`, or in
+    /// `return 3000000000;` in a function that returns a `u32`.
+    fn concretize_as(&mut self, node: ExprNode, data_type: Option<&DataType>) -> ExprNode {
+        match data_type {
+            Some(data_type) => self
+                .fold_checked(node, |this, node| {
+                    let con = this.concretize_expr(node);
+                    this.concretize_abstract(con, data_type)
+                })
+                .into(),
+            None => self.fold_expr(node),
         }
     }
 
-    fn concretize_lhs_expr(&mut self, node: LhsExprNode) -> LhsExprNode {
-        let expr = match node.expr {
-            LhsExpr::Ident(ident) => LhsExpr::Ident(ident),
-            LhsExpr::Postfix(expr, postfix) => LhsExpr::Postfix(
-                self.concretize_lhs_expr(*expr).into(),
-                match postfix {
-                    Postfix::Index(index) => {
-                        Postfix::Index(Box::new(self.concretize_expr(*index).into()))
-                    }
-                    Postfix::Member(string) => Postfix::Member(string),
-                },
-            ),
-            LhsExpr::Deref(_) => todo!(),
-            LhsExpr::AddressOf(_) => todo!(),
-        };
+    fn with_scope<T>(&mut self, block: impl FnOnce(&mut Self) -> T) -> T {
+        self.local_scopes.push(HashMap::new());
+        let res = block(self);
+        self.local_scopes.pop();
+        res
+    }
 
-        LhsExprNode {
-            data_type: node.data_type,
-            expr,
+    fn lookup_const(&self, name: &str) -> Option<Value> {
+        for scope in self.local_scopes.iter().rev() {
+            if let Some(val) = scope.get(name) {
+                return Some(val.clone());
+            }
         }
+        self.global_constants.get(name).cloned()
     }
 
-    fn concretize_switch_case(&mut self, case: SwitchCase) -> SwitchCase {
-        let concretized_selector = self.concretize_expr(case.selector);
+    fn concretize_fn(&mut self, decl: FnDecl) -> FnDecl {
+        self.local_scopes.clear();
+        self.return_type = decl.output.as_ref().map(|it| it.data_type.clone());
+        self.fold_fn(decl)
+    }
 
-        self.with_scope(|this| {
-            let concretized_body = case
-                .body
-                .into_iter()
-                .map(|s| this.concretize_stmt(s))
-                .collect();
+    /// Returns the parameter types of a user-defined function, which abstract arguments are
+    /// converted to.
+    fn param_types(&self, name: &str) -> Vec<DataType> {
+        self.function(name)
+            .map(|decl| decl.inputs.iter().map(|it| it.data_type.clone()).collect())
+            .unwrap_or_default()
+    }
 
-            SwitchCase {
-                selector: concretized_selector.into(),
-                body: concretized_body,
+    fn concretize_expr(&mut self, node: ExprNode) -> ConcreteNode {
+        let meta = node.meta;
+        let parent_span = self.current_span;
+        self.current_span = meta.span.or(parent_span);
+        let parent_folded_call = self.folded_call.replace(false);
+
+        let mut con = self.concretize_expr_inner(node);
+        con.node.meta = meta;
+
+        // Literals and constructors are left as they are, as their constant arguments are folded
+        if self.fold_budget.is_some() && !matches!(con.node.expr, Expr::Lit(_) | Expr::TypeCons(_))
+        {
+            if let Some(node) = con
+                .value
+                .as_ref()
+                .and_then(|v| value_node(v, &con.node.data_type))
+            {
+                con.node = ExprNode { meta, ..node };
             }
-        })
+        }
+
+        self.current_span = parent_span;
+        self.folded_call
+            .set(parent_folded_call || self.folded_call.get());
+        con
     }
 
-    fn concretize_expr(&mut self, node: ExprNode) -> ConcreteNode {
+    fn concretize_expr_inner(&mut self, node: ExprNode) -> ConcreteNode {
         //TODO: if expr contains var, return (since not concretizable)
 
         match node.expr {
@@ -501,37 +525,25 @@ impl Concretizer {
             }
             Expr::Postfix(expr) => {
                 let concrete_inner = self.concretize_expr(*expr.inner);
+                let inner_type = concrete_inner.node.data_type.dereference().clone();
 
-                let mut index_val = None;
-                let concrete_postfix = match expr.postfix {
+                let (concrete_postfix, value) = match expr.postfix {
                     Postfix::Index(index) => {
-                        let conc_index = self.concretize_expr(*index);
-                        index_val = conc_index.value.clone();
-                        Postfix::Index(Box::new(conc_index.into()))
-                    }
-                    Postfix::Member(string) => Postfix::Member(string),
-                };
-
-                let value = if let Some(inner_val) = &concrete_inner.value {
-                    match &concrete_postfix {
-                        Postfix::Member(member) => {
-                            if concrete_inner.node.data_type.dereference().is_vector() {
-                                self.eval_member_access(inner_val, member)
-                            } else {
-                                None
+                        let conc_index = self.concretize_index(&inner_type, *index);
+                        let value = match (&concrete_inner.value, &conc_index.value) {
+                            (Some(inner_val), Some(index_val)) => {
+                                self.eval_index_access(inner_val, index_val)
                             }
-                        }
-                        Postfix::Index(_) => {
-                            let ty = concrete_inner.node.data_type.dereference();
-                            if ty.is_vector() || matches!(ty, DataType::Array(_, _)) {
-                                self.eval_index_access(inner_val, &index_val)
-                            } else {
-                                None
-                            }
-                        }
+                            _ => None,
+                        };
+                        (Postfix::Index(Box::new(conc_index.into())), value)
+                    }
+                    Postfix::Member(member) => {
+                        let value = concrete_inner.value.as_ref().and_then(|inner_val| {
+                            self.eval_member_access(&inner_type, inner_val, &member)
+                        });
+                        (Postfix::Member(member), value)
                     }
-                } else {
-                    None
                 };
 
                 ConcreteNode {
@@ -543,8 +555,8 @@ impl Concretizer {
                 let value = self.lookup_const(&expr.ident);
                 ConcreteNode {
                     node: ExprNode {
-                        data_type: node.data_type,
                         expr: Expr::Var(expr),
+                        ..node
                     },
                     value,
                 }
@@ -558,45 +570,89 @@ impl Concretizer {
         ident: String,
         args: Vec<ConcreteNode>,
     ) -> ConcreteNode {
-        let (nodes, vals) = self.decompose_vec_con(args);
+        let function = Builtin::convert(ident.clone());
 
-        if self.contains_none(&vals) {
-            if helper::is_invalid_bits_call(&ident, &vals) {
-                return self.default_node(data_type);
-            }
+        // Builtins with abstract overloads are only evaluated as such if none of the numeric
+        // arguments are concrete
+        let is_abstract_call = function.as_ref().is_some_and(Builtin::accepts_abstract)
+            && args.iter().all(|arg| {
+                matches!(&arg.value, Some(v) if v.is_abstract() || matches!(v, Value::Lit(Lit::Bool(_))))
+            });
+
+        let (nodes, vals) = if is_abstract_call {
+            let (nodes, vals) = self.decompose_vec_con(args);
+            let vals = Value::unify_abstract(vals.into_iter().flatten().collect());
+            (nodes, vals.into_iter().map(Some).collect())
+        } else {
+            let params = self.param_types(&ident);
+            let args = args
+                .into_iter()
+                .enumerate()
+                .map(|(i, arg)| {
+                    let data_type = params
+                        .get(i)
+                        .cloned()
+                        .unwrap_or_else(|| arg.node.data_type.clone());
+                    self.concretize_abstract(arg, &data_type)
+                })
+                .collect();
+            self.decompose_vec_con(args)
+        };
 
-            if ident == "clamp" {
-                if let (Some(Some(low)), Some(Some(high))) = (vals.get(1), vals.get(2)) {
-                    if helper::is_invalid_clamp_bounds(low, high) {
-                        return self.default_node(data_type);
-                    }
+        // These are checked even if some arguments aren't constant
+        if helper::is_invalid_bits_call(&ident, &vals) {
+            return self.replace_invalid(data_type, Rule::BitsOutOfRange);
+        }
+
+        if ident == "clamp" {
+            if let (Some(Some(low)), Some(Some(high))) = (vals.get(1), vals.get(2)) {
+                if helper::is_invalid_clamp_bounds(low, high) {
+                    return self.replace_invalid(data_type, Rule::ClampBounds);
                 }
             }
+        }
 
+        if self.contains_none(&vals) {
             return ConcreteNode {
                 node: FnCallExpr { ident, args: nodes }.into_node(data_type),
                 value: None,
             };
         }
 
-        let function = Builtin::convert(ident.clone());
-
         match function {
             Some(f) => {
-                let evaluated_val = evaluate_builtin(&f, vals);
+                let evaluated_val = evaluate_builtin(&f, vals.clone());
 
                 match evaluated_val {
                     Some(_) => ConcreteNode {
                         node: FnCallExpr::new(ident, nodes).into_node(data_type),
                         value: evaluated_val,
                     },
-                    None => self.default_node(data_type),
+                    None => {
+                        let rule = if has_non_finite_result(&f, &vals) {
+                            Rule::FloatNotFinite
+                        } else {
+                            type_rule(&data_type)
+                        };
+                        self.replace_invalid(data_type, rule)
+                    }
+                }
+            }
+            None => {
+                let value = self.fold_budget.and_then(|budget| {
+                    self.steps_left.set(budget);
+                    self.eval_call(&ident, vals.into_iter().flatten().collect())
+                });
+
+                if value.is_some() {
+                    self.folded_call.set(true);
+                }
+
+                ConcreteNode {
+                    node: FnCallExpr::new(ident, nodes).into_node(data_type),
+                    value,
                 }
             }
-            None => ConcreteNode {
-                node: FnCallExpr::new(ident, nodes).into_node(data_type),
-                value: None,
-            },
         }
     }
 
@@ -608,7 +664,15 @@ impl Concretizer {
         let concrete_args: Vec<ConcreteNode> = expr
             .args
             .into_iter()
-            .map(|e| self.concretize_expr(e))
+            .enumerate()
+            .map(|(i, e)| {
+                let con = self.concretize_expr(e);
+
+                match constructor_arg_type(&data_type, i, &con.node.data_type) {
+                    Some(arg_type) => self.concretize_abstract(con, &arg_type),
+                    None => con,
+                }
+            })
             .collect();
 
         let (new_node, new_val) = self.decompose_vec_con(concrete_args);
@@ -616,16 +680,12 @@ impl Concretizer {
         let new_val = if self.contains_none(&new_val) {
             None
         } else {
-            let mut values: Vec<Value> = new_val.into_iter().map(|v| v.unwrap()).collect();
+            let values = new_val.into_iter().map(|v| v.unwrap()).collect();
 
-            // Handle vector splat constructor: vecN<T>(scalar)
-            if let DataType::Vector(size, _) = data_type {
-                if values.len() == 1 && size > 1 {
-                    values = vec![values[0].clone(); size as usize];
-                }
+            match self.eval_typecons(&data_type, values) {
+                Some(value) => Some(value),
+                None => return self.replace_invalid(data_type, Rule::ConversionOutOfRange),
             }
-
-            Some(Value::Vector(values))
         };
 
         ConcreteNode {
@@ -634,6 +694,108 @@ impl Concretizer {
         }
     }
 
+    /// Evaluates a value constructor. Returns `None` if one of the conversions it performs is out
+    /// of range.
+    pub(crate) fn eval_typecons(&self, data_type: &DataType, args: Vec<Value>) -> Option<Value> {
+        if args.is_empty() {
+            return Value::zero(data_type);
+        }
+
+        // Vector components and matrix columns can be given as a mix of scalars and vectors
+        let components = |args: Vec<Value>| -> Vec<Value> {
+            args.into_iter()
+                .flat_map(|arg| match arg {
+                    Value::Vector(v) => v,
+                    arg => vec![arg],
+                })
+                .collect()
+        };
+
+        let value = match data_type {
+            DataType::Scalar(_) => args.into_iter().next()?,
+            DataType::Vector(size, _) => {
+                let mut values = components(args);
+
+                // Handle vector splat constructor: vecN<T>(scalar)
+                if values.len() == 1 && *size > 1 {
+                    values = vec![values[0].clone(); *size as usize];
+                }
+
+                Value::Vector(values)
+            }
+            DataType::Matrix(_, r, _) => {
+                if let [Value::Matrix(_)] = args.as_slice() {
+                    args.into_iter().next()?
+                } else {
+                    let columns = components(args)
+                        .chunks(*r as usize)
+                        .map(|column| Value::Vector(column.to_vec()))
+                        .collect();
+                    Value::Matrix(columns)
+                }
+            }
+            DataType::Array(_, _) => Value::Array(args),
+            DataType::Struct(_) => Value::Struct(args),
+            _ => return None,
+        };
+
+        value.convert(data_type)
+    }
+
+    /// Concretizes the index of an access into a value of type `base`. A constant index that is
+    /// out of bounds is a shader creation error, so it is replaced with 0.
+    fn concretize_index(&mut self, base: &DataType, index: ExprNode) -> ConcreteNode {
+        let index = self.concretize_expr(index);
+
+        let in_bounds = match (&index.value, index_bound(base)) {
+            (Some(Value::Lit(Lit::I32(i))), Some(len)) => (0..len as i64).contains(&(*i as i64)),
+            (Some(Value::Lit(Lit::U32(u))), Some(len)) => *u < len,
+            (Some(Value::Lit(Lit::AbstractInt(i))), Some(len)) => (0..len as i64).contains(i),
+            _ => true,
+        };
+
+        if in_bounds {
+            let data_type = index.node.data_type.clone();
+            return self.concretize_abstract(index, &data_type);
+        }
+
+        self.report_invalid(Rule::IndexOutOfBounds);
+
+        let zero = match index.node.data_type {
+            DataType::Scalar(ScalarType::U32) => Lit::U32(0),
+            _ => Lit::I32(0),
+        };
+
+        ConcreteNode {
+            node: ExprNode {
+                meta: index.node.meta,
+                ..zero.into()
+            },
+            value: Some(Value::Lit(zero)),
+        }
+    }
+
+    /// Converts the value of an abstract expression to the concrete type it is used as. This is a
+    /// shader creation error if the value doesn't fit in that type, so the expression is replaced.
+    fn concretize_abstract(&self, con: ConcreteNode, data_type: &DataType) -> ConcreteNode {
+        match &con.value {
+            Some(value) if value.is_abstract() => match value.clone().concretize(data_type) {
+                Some(value) => ConcreteNode {
+                    node: con.node,
+                    value: Some(value),
+                },
+                None => {
+                    // The caller is done with the expression, so report it at its own span
+                    self.report_invalid_at(con.node.meta.span, Rule::ConversionOutOfRange);
+                    let mut default = self.default_node(data_type.clone());
+                    default.node.meta = con.node.meta;
+                    default
+                }
+            },
+            _ => con,
+        }
+    }
+
     fn decompose_vec_con(&self, vec: Vec<ConcreteNode>) -> (Vec<ExprNode>, Vec<Option<Value>>) {
         let mut new_node: Vec<ExprNode> = Vec::new();
         let mut new_val: Vec<Option<Value>> = Vec::new();
@@ -651,7 +813,9 @@ impl Concretizer {
             node: ExprNode {
                 data_type: lit.data_type(),
                 expr: Expr::Lit(lit),
+                meta: NodeMeta::default(),
             },
+            // Abstract literals are converted to a concrete type where they are used
             value: Some(Value::Lit(lit)),
         }
     }
@@ -663,6 +827,8 @@ impl Concretizer {
         left: ConcreteNode,
         right: ConcreteNode,
     ) -> ConcreteNode {
+        let (left, right) = self.concretize_operands(&op, left, right);
+
         // if either left or right is not a const-expression, then
         // this node is not a const-expression
         if left.value.is_none() || right.value.is_none() {
@@ -676,7 +842,7 @@ impl Concretizer {
                                 value: None,
                             }
                         }
-                        BinOp::Mod => return self.default_node(data_type),
+                        BinOp::Mod => return self.replace_invalid(data_type, Rule::DivisionByZero),
                         _ => {}
                     }
                 }
@@ -686,34 +852,137 @@ impl Concretizer {
                 node: ExprNode {
                     data_type,
                     expr: Expr::BinOp(BinOpExpr::new(op, left, right)),
+                    meta: NodeMeta::default(),
                 },
                 value: None,
             };
         }
 
-        let value: Option<Value> = self.evaluate_bin_op(
-            &op,
-            left.value.clone().unwrap(),
-            right.value.clone().unwrap(),
-        );
+        let (l_val, r_val) = (left.value.clone().unwrap(), right.value.clone().unwrap());
+        let value: Option<Value> = self.evaluate_bin_op(&op, l_val.clone(), r_val.clone());
 
         if value.is_none() {
-            self.default_node(data_type)
+            let rule = self.bin_op_rule(&op, &data_type, l_val, r_val);
+            self.replace_invalid(data_type, rule)
         } else {
             ConcreteNode {
                 node: ExprNode {
                     data_type,
                     expr: Expr::BinOp(BinOpExpr::new(op, left, right)),
+                    meta: NodeMeta::default(),
                 },
                 value,
             }
         }
     }
 
-    fn default_node(&self, data_type: DataType) -> ConcreteNode {
+    /// Converts abstract operands to the type of the other operand, unless they are both abstract.
+    fn concretize_operands(
+        &self,
+        op: &BinOp,
+        left: ConcreteNode,
+        right: ConcreteNode,
+    ) -> (ConcreteNode, ConcreteNode) {
+        let is_abstract = |con: &ConcreteNode| con.value.as_ref().is_some_and(Value::is_abstract);
+
+        if is_abstract(&left) && is_abstract(&right) {
+            let mut values = Value::unify_abstract(vec![left.value.unwrap(), right.value.unwrap()]);
+            let right_val = values.pop();
+            let left_val = values.pop();
+            return (
+                ConcreteNode {
+                    node: left.node,
+                    value: left_val,
+                },
+                ConcreteNode {
+                    node: right.node,
+                    value: right_val,
+                },
+            );
+        }
+
+        let left_type = operand_type(
+            op,
+            &left.node.data_type,
+            &right.node.data_type,
+            right.value.as_ref(),
+            true,
+        );
+        let right_type = operand_type(
+            op,
+            &right.node.data_type,
+            &left.node.data_type,
+            left.value.as_ref(),
+            false,
+        );
+
+        let concretize = |con: ConcreteNode, data_type: Option<DataType>| match data_type {
+            Some(data_type) if is_abstract(&con) => self.concretize_abstract(con, &data_type),
+            _ => con,
+        };
+
+        (concretize(left, left_type), concretize(right, right_type))
+    }
+
+    /// Reports an expression that is invalid at shader creation time because it breaks `rule`. This
+    /// panics if the concretizer was configured to do so, and otherwise the caller replaces the
+    /// expression. In fold mode folding the expression is undone instead, and it is only reported
+    /// if it doesn't contain an evaluated call, so that it was invalid before folding.
+    fn report_invalid(&self, rule: Rule) {
+        self.report_invalid_at(self.current_span, rule);
+    }
+
+    fn report_invalid_at(&self, span: Option<Span>, rule: Rule) {
+        if self.fold_budget.is_some() {
+            self.fold_invalid.set(true);
+
+            // Float results outside of the concretizer's range are still valid WGSL
+            if self.folded_call.get() || rule == Rule::FloatOutOfRange {
+                return;
+            }
+        }
+
         if self.error_handling == ErrorHandling::Panic {
-            panic!("Invalid expression")
+            match span {
+                Some(span) => panic!("Invalid expression at {span}: {rule}"),
+                None => panic!("Invalid expression: {rule}"),
+            }
         }
+
+        self.diagnostics
+            .borrow_mut()
+            .push(Diagnostic { span, rule });
+    }
+
+    fn replace_invalid(&self, data_type: DataType, rule: Rule) -> ConcreteNode {
+        self.report_invalid(rule);
+        self.default_node(data_type)
+    }
+
+    /// Returns the rule that evaluating `l op r` breaks, given that it can't be evaluated. This is
+    /// the rule that the first pair of scalar components that can't be evaluated breaks.
+    fn bin_op_rule(&self, op: &BinOp, data_type: &DataType, l: Value, r: Value) -> Rule {
+        let pairs = match (l, r) {
+            (Value::Vector(l), Value::Vector(r)) => l.into_iter().zip(r).collect(),
+            (Value::Vector(l), r @ Value::Lit(_)) => {
+                l.into_iter().zip(std::iter::repeat(r)).collect()
+            }
+            (l @ Value::Lit(_), Value::Vector(r)) => std::iter::repeat(l).zip(r).collect(),
+            (l, r) => vec![(l, r)],
+        };
+
+        pairs
+            .into_iter()
+            .find_map(|pair| match pair {
+                (Value::Lit(l), Value::Lit(r)) if self.eval_bin_op_scalar(op, l, r).is_none() => {
+                    Some(scalar_bin_op_rule(op, l, r))
+                }
+                _ => None,
+            })
+            .unwrap_or_else(|| type_rule(data_type))
+    }
+
+    fn default_node(&self, data_type: DataType) -> ConcreteNode {
         match data_type {
             DataType::Scalar(ty) => match ty {
                 ScalarType::U32 => ConcreteNode {
@@ -781,14 +1050,35 @@ impl Concretizer {
                 let col_node = self.default_node(vec_type);
                 ConcreteNode {
                     node: TypeConsExpr::new(data_type, vec![col_node.node; c as usize]).into(),
-                    value: Some(Value::Vector(vec![col_node.value.unwrap(); c as usize])),
+                    value: Some(Value::Matrix(vec![col_node.value.unwrap(); c as usize])),
+                }
+            }
+            DataType::Array(ref ty, Some(n)) => {
+                let elem_node = self.default_node((**ty).clone());
+                ConcreteNode {
+                    node: TypeConsExpr::new(data_type, vec![elem_node.node; n as usize]).into(),
+                    value: Some(Value::Array(vec![elem_node.value.unwrap(); n as usize])),
+                }
+            }
+            DataType::Struct(ref decl) => {
+                let members = decl
+                    .members
+                    .iter()
+                    .map(|member| self.default_node(member.data_type.clone()))
+                    .collect();
+                let (nodes, values) = self.decompose_vec_con(members);
+                ConcreteNode {
+                    node: TypeConsExpr::new(data_type, nodes).into(),
+                    value: Some(Value::Struct(
+                        values.into_iter().map(Option::unwrap).collect(),
+                    )),
                 }
             }
             _ => todo!("data type: {data_type}"),
         }
     }
 
-    fn evaluate_bin_op(&self, op: &BinOp, l: Value, r: Value) -> Option<Value> {
+    pub(crate) fn evaluate_bin_op(&self, op: &BinOp, l: Value, r: Value) -> Option<Value> {
         match (l.clone(), r.clone()) {
             (Value::Vector(lv), Value::Vector(rv)) => self.eval_bin_op_vector(op, lv, rv),
 
@@ -803,6 +1093,29 @@ impl Concretizer {
 
                 self.eval_bin_op_vector(op, lv.to_vec(), rv_vec)
             }
+            (Value::Matrix(lv), Value::Matrix(rv)) => match op {
+                BinOp::Plus | BinOp::Minus => into_matrix(self.eval_bin_op_vector(op, lv, rv)?),
+                BinOp::Times => rv
+                    .iter()
+                    .map(|column| mat_vec_mul(&lv, column))
+                    .collect::<Option<_>>()
+                    .map(Value::Matrix),
+                _ => None,
+            },
+            // matrix-scalar multiplication multiplies every component of the matrix
+            (Value::Matrix(lv), Value::Lit(_)) => {
+                into_matrix(self.eval_bin_op_vector(op, lv.clone(), vec![r; lv.len()])?)
+            }
+            (Value::Lit(_), Value::Matrix(rv)) => {
+                into_matrix(self.eval_bin_op_vector(op, vec![l; rv.len()], rv.clone())?)
+            }
+            (Value::Matrix(lv), Value::Vector(_)) if *op == BinOp::Times => mat_vec_mul(&lv, &r),
+            (Value::Vector(_), Value::Matrix(rv)) if *op == BinOp::Times => rv
+                .iter()
+                .map(|column| evaluate_dot(l.clone(), column.clone()))
+                .collect::<Option<_>>()
+                .map(Value::Vector),
+            _ => None,
         }
     }
 
@@ -815,12 +1128,6 @@ impl Concretizer {
             BinOp::Plus | BinOp::Minus | BinOp::Times | BinOp::Divide | BinOp::Mod => {
                 match (lv, rv) {
                     (Lit::I32(l_lit), Lit::I32(r_lit)) => {
-                        if l_lit == i32::MIN
-                            && r_lit == -1
-                            && (*op == BinOp::Divide || *op == BinOp::Mod)
-                        {
-                            return None;
-                        }
                         let result = binop_int_arith!(op, l_lit, r_lit);
                         Value::from_i32(result)
                     }
@@ -836,14 +1143,32 @@ impl Concretizer {
                         let result = binop_float16(op, l_lit, r_lit);
                         Value::from_f16(result)
                     }
+                    (Lit::AbstractInt(l_lit), Lit::AbstractInt(r_lit)) => {
+                        Value::from_abstract_int(binop_abstract_int(op, l_lit, r_lit))
+                    }
+                    (Lit::AbstractFloat(l_lit), Lit::AbstractFloat(r_lit)) => {
+                        Value::from_abstract_float(binop_abstract_float(op, l_lit, r_lit))
+                    }
                     _ => None,
                 }
             }
-            _ => None,
+            BinOp::LogAnd | BinOp::LogOr | BinOp::BitAnd | BinOp::BitOr | BinOp::BitXOr => {
+                binop_bitwise(op, lv, rv)
+            }
+            BinOp::Equal
+            | BinOp::NotEqual
+            | BinOp::Less
+            | BinOp::LessEqual
+            | BinOp::Greater
+            | BinOp::GreaterEqual => binop_compare(op, lv, rv),
         }
     }
 
     fn eval_bin_op_shift(&self, op: &BinOp, lv: Lit, rv: Lit) -> Option<Value> {
+        if let Lit::AbstractInt(l) = lv {
+            return binop_abstract_shift(op, l, rv);
+        }
+
         // check condition 1
         let result = binop_int_shift(op, lv, rv);
 
@@ -908,6 +1233,7 @@ impl Concretizer {
 
                     self.eval_bin_op_vector(op, lv_vec, rv.to_vec())
                 }
+                _ => None,
             };
 
             match elem {
@@ -929,21 +1255,23 @@ impl Concretizer {
             };
         }
 
-        let result = self.eval_unop(op, inner.clone());
+        let result = self.eval_unop(op, inner.value.clone().unwrap());
 
         match result {
             Some(r) => ConcreteNode {
                 node: UnOpExpr::new(op, inner.node).into(),
                 value: Some(r),
             },
-            None => self.default_node(data_type),
+            None => self.replace_invalid(data_type, Rule::IntegerOverflow),
         }
     }
 
-    fn eval_unop(&self, op: UnOp, inner: ConcreteNode) -> Option<Value> {
-        match inner.value.unwrap() {
+    pub(crate) fn eval_unop(&self, op: UnOp, inner: Value) -> Option<Value> {
+        match inner {
             Value::Vector(v) => self.eval_unop_vector(op, v),
+            Value::Matrix(v) => into_matrix(self.eval_unop_vector(op, v)?),
             Value::Lit(v) => self.eval_unop_scalar(op, v),
+            Value::Array(_) | Value::Struct(_) => None,
         }
     }
 
@@ -960,6 +1288,8 @@ impl Concretizer {
                     }
                     Lit::F32(f) => Value::from_f32(Some(-f)),
                     Lit::F16(f) => Value::from_f16(Some(-f)),
+                    Lit::AbstractInt(i) => Value::from_abstract_int(i.checked_neg()),
+                    Lit::AbstractFloat(f) => Some((-f).into()),
                     _ => {
                         panic!(); // can't negate other types
                     }
@@ -969,9 +1299,14 @@ impl Concretizer {
                 match inner {
                     Lit::I32(i) => Value::from_i32(Some(!i)),
                     Lit::U32(u) => Value::from_u32(Some(!u)),
+                    Lit::AbstractInt(i) => Some((!i).into()),
                     _ => panic!(), // can't bitnot other types
                 }
             }
+            UnOp::Not => match inner {
+                Lit::Bool(b) => Some((!b).into()),
+                _ => None,
+            },
             _ => None,
         }
     }
@@ -1001,17 +1336,30 @@ impl Concretizer {
                         }
                     }
                 }
+                _ => return None,
             }
         }
 
         Some(Value::Vector(result))
     }
 
-    fn eval_member_access(&self, val: &Value, member: &str) -> Option<Value> {
-        let Value::Vector(vec) = val else {
-            return None;
-        };
+    pub(crate) fn eval_member_access(
+        &self,
+        ty: &DataType,
+        val: &Value,
+        member: &str,
+    ) -> Option<Value> {
+        match (ty, val) {
+            (DataType::Vector(_, _), Value::Vector(vec)) => self.eval_swizzle(vec, member),
+            (DataType::Struct(decl), Value::Struct(members)) => {
+                let idx = decl.members.iter().position(|m| m.name == member)?;
+                members.get(idx).cloned()
+            }
+            _ => None,
+        }
+    }
 
+    fn eval_swizzle(&self, vec: &[Value], member: &str) -> Option<Value> {
         let char_to_idx = |c: char| match c {
             'x' | 'r' => Some(0),
             'y' | 'g' => Some(1),
@@ -1032,20 +1380,223 @@ impl Concretizer {
         }
     }
 
-    fn eval_index_access(&self, val: &Value, index: &Option<Value>) -> Option<Value> {
+    pub(crate) fn eval_index_access(&self, val: &Value, index: &Value) -> Option<Value> {
         let index_val = match index {
-            Some(Value::Lit(Lit::I32(i))) => {
-                if *i < 0 {
-                    return None;
+            Value::Lit(Lit::I32(i)) => usize::try_from(*i).ok()?,
+            Value::Lit(Lit::U32(u)) => *u as usize,
+            Value::Lit(Lit::AbstractInt(i)) => usize::try_from(*i).ok()?,
+            _ => return None,
+        };
+        val.elements()?.get(index_val).cloned()
+    }
+}
+
+/// Builds an expression that evaluates to `value`, which has type `data_type`.
+fn value_node(value: &Value, data_type: &DataType) -> Option<ExprNode> {
+    let args = |values: &[Value], ty: &DataType| -> Option<Vec<ExprNode>> {
+        values.iter().map(|v| value_node(v, ty)).collect()
+    };
+
+    let args = match (value, data_type) {
+        (Value::Lit(lit), DataType::Scalar(_)) => {
+            return Some(ExprNode {
+                data_type: data_type.clone(),
+                expr: Expr::Lit(*lit),
+                meta: NodeMeta::default(),
+            })
+        }
+        (Value::Vector(v), DataType::Vector(_, ty)) => args(v, &DataType::Scalar(*ty))?,
+        (Value::Matrix(v), DataType::Matrix(_, r, ty)) => args(v, &DataType::Vector(*r, *ty))?,
+        (Value::Array(v), DataType::Array(ty, Some(_))) => args(v, ty)?,
+        (Value::Struct(v), DataType::Struct(decl)) => v
+            .iter()
+            .zip(&decl.members)
+            .map(|(v, member)| value_node(v, &member.data_type))
+            .collect::<Option<_>>()?,
+        _ => return None,
+    };
+
+    Some(TypeConsExpr::new(data_type.clone(), args).into())
+}
+
+/// Returns the type that an abstract argument of a constructor of `data_type` is converted to,
+/// which is the component type of the constructed type.
+pub(crate) fn constructor_arg_type(
+    data_type: &DataType,
+    index: usize,
+    arg_type: &DataType,
+) -> Option<DataType> {
+    match data_type {
+        DataType::Scalar(ty) | DataType::Vector(_, ty) | DataType::Matrix(_, _, ty) => {
+            let arg_type = arg_type.dereference();
+            arg_type.as_scalar().map(|_| arg_type.map(*ty))
+        }
+        DataType::Array(ty, _) => Some((**ty).clone()),
+        DataType::Struct(decl) => decl.members.get(index).map(|m| m.data_type.clone()),
+        _ => None,
+    }
+}
+
+/// Returns the type that an abstract operand of `op` is converted to when the other operand is
+/// concrete, or `None` if it stays abstract. This is the operand's own type with the scalar type of
+/// the other operand, except that an abstract integer can be shifted by a constant amount, and the
+/// amount is always a `u32`.
+pub(crate) fn operand_type(
+    op: &BinOp,
+    own: &DataType,
+    other: &DataType,
+    other_value: Option<&Value>,
+    is_left: bool,
+) -> Option<DataType> {
+    let own = own.dereference();
+    let scalar = match op {
+        BinOp::LShift | BinOp::RShift if is_left => {
+            if other_value.is_some() {
+                return None;
+            }
+            own.as_scalar()
+        }
+        BinOp::LShift | BinOp::RShift => Some(ScalarType::U32),
+        _ => other.as_scalar(),
+    };
+
+    match (own.as_scalar(), scalar) {
+        (Some(_), Some(scalar)) => Some(own.map(scalar)),
+        _ => Some(own.clone()),
+    }
+}
+
+/// Returns the number of elements that can be indexed in a value of type `ty`, if it is known at
+/// shader creation time.
+fn index_bound(ty: &DataType) -> Option<u32> {
+    match ty {
+        DataType::Vector(n, _) | DataType::Matrix(n, _, _) => Some(*n as u32),
+        DataType::Array(_, n) => *n,
+        DataType::Ptr(view) => index_bound(&view.inner),
+        _ => None,
+    }
+}
+
+fn into_matrix(value: Value) -> Option<Value> {
+    match value {
+        Value::Vector(columns) => Some(Value::Matrix(columns)),
+        _ => None,
+    }
+}
+
+/// Multiplies a matrix by a column vector, by taking the dot product of each row of the matrix
+/// with the vector.
+fn mat_vec_mul(columns: &[Value], vec: &Value) -> Option<Value> {
+    let rows = columns.first()?.elements()?.len();
+
+    (0..rows)
+        .map(|i| {
+            let row = columns
+                .iter()
+                .map(|column| column.elements()?.get(i).cloned())
+                .collect::<Option<_>>()?;
+            evaluate_dot(Value::Vector(row), vec.clone())
+        })
+        .collect::<Option<_>>()
+        .map(Value::Vector)
+}
+
+impl Fold for Concretizer {
+    fn fold_block(&mut self, block: Vec<Statement>) -> Vec<Statement> {
+        self.with_scope(|this| fold::fold_block(this, block))
+    }
+
+    fn fold_for_loop(&mut self, stmt: ForLoopStatement) -> ForLoopStatement {
+        self.with_scope(|this| fold::fold_for_loop(this, stmt))
+    }
+
+    fn fold_let_decl(&mut self, stmt: LetDeclStatement) -> LetDeclStatement {
+        LetDeclStatement {
+            initializer: self.concretize_as(stmt.initializer, stmt.data_type.as_ref()),
+            ..stmt
+        }
+    }
+
+    fn fold_var_decl(&mut self, stmt: VarDeclStatement) -> VarDeclStatement {
+        VarDeclStatement {
+            initializer: stmt
+                .initializer
+                .map(|node| self.concretize_as(node, stmt.data_type.as_ref())),
+            ..stmt
+        }
+    }
+
+    fn fold_stmt(&mut self, stmt: Statement) -> Statement {
+        match stmt {
+            Statement::Return(ReturnStatement { value, meta }) => {
+                let return_type = self.return_type.clone();
+                ReturnStatement {
+                    value: value.map(|node| self.concretize_as(node, return_type.as_ref())),
+                    meta,
                 }
-                *i as usize
+                .into()
             }
-            Some(Value::Lit(Lit::U32(u))) => *u as usize,
-            _ => return None,
+            stmt => fold::fold_stmt(self, stmt),
+        }
+    }
+
+    fn fold_assignment(&mut self, stmt: AssignmentStatement) -> AssignmentStatement {
+        let lhs = self.fold_assignment_lhs(stmt.lhs);
+
+        // A compound assignment converts an abstract right-hand side to the scalar type of the
+        // left-hand side, as in `v *= 2;`
+        let data_type = match &lhs {
+            AssignmentLhs::Expr(lhs) => {
+                let store_type = lhs.data_type.dereference();
+                match stmt.op {
+                    AssignmentOp::Simple => Some(store_type.clone()),
+                    _ => store_type.as_scalar().map(DataType::Scalar),
+                }
+            }
+            AssignmentLhs::Phony => None,
         };
-        match val {
-            Value::Vector(vec) => vec.get(index_val).cloned(),
-            _ => None,
+
+        AssignmentStatement {
+            rhs: self.concretize_as(stmt.rhs, data_type.as_ref()),
+            lhs,
+            ..stmt
+        }
+    }
+
+    fn fold_fn_call_stmt(&mut self, stmt: FnCallStatement) -> FnCallStatement {
+        let params = self.param_types(&stmt.ident);
+        let args = stmt
+            .args
+            .into_iter()
+            .enumerate()
+            .map(|(i, node)| self.concretize_as(node, params.get(i)))
+            .collect();
+
+        FnCallStatement { args, ..stmt }
+    }
+
+    fn fold_lhs_expr(&mut self, node: LhsExprNode) -> LhsExprNode {
+        match node.expr {
+            LhsExpr::Postfix(inner, Postfix::Index(index)) => {
+                let inner = self.fold_lhs_expr(*inner);
+                let base = inner.data_type.dereference().clone();
+                let index =
+                    self.fold_checked(*index, |this, index| this.concretize_index(&base, index));
+                LhsExprNode {
+                    expr: LhsExpr::Postfix(Box::new(inner), Postfix::Index(Box::new(index.into()))),
+                    ..node
+                }
+            }
+            expr => fold::fold_lhs_expr(self, LhsExprNode { expr, ..node }),
         }
     }
+
+    fn fold_expr(&mut self, node: ExprNode) -> ExprNode {
+        self.fold_checked(node, |this, node| {
+            let con = this.concretize_expr(node);
+            let data_type = con.node.data_type.dereference().clone();
+            this.concretize_abstract(con, &data_type)
+        })
+        .into()
+    }
 }
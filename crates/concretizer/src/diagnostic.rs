@@ -0,0 +1,60 @@
+use std::fmt::Display;
+
+use ast::Span;
+
+/// A rule that a constant expression breaks, making it a shader creation error.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Rule {
+    DivisionByZero,
+    /// A shift by at least the bit width of the shifted value.
+    ShiftOutOfRange,
+    /// A left shift that shifts out bits that differ from the sign bit of the result.
+    ShiftOverflow,
+    IntegerOverflow,
+    FloatNotFinite,
+    /// Float results are kept in a range in which every implementation evaluates them exactly, so
+    /// this isn't a WGSL rule, but one of the concretizer.
+    FloatOutOfRange,
+    ConversionOutOfRange,
+    IndexOutOfBounds,
+    /// `extractBits` or `insertBits` with an offset and count that exceed the bit width.
+    BitsOutOfRange,
+    /// `clamp` with a low bound that is greater than its high bound.
+    ClampBounds,
+}
+
+impl Display for Rule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let description = match self {
+            Rule::DivisionByZero => "integer division by zero",
+            Rule::ShiftOutOfRange => "shift amount is not less than the bit width",
+            Rule::ShiftOverflow => "left shift overflows",
+            Rule::IntegerOverflow => "integer arithmetic overflows",
+            Rule::FloatNotFinite => "float result is infinite or NaN",
+            Rule::FloatOutOfRange => "float result is outside of the range the concretizer accepts",
+            Rule::ConversionOutOfRange => "value can't be represented in its type",
+            Rule::IndexOutOfBounds => "index is out of bounds",
+            Rule::BitsOutOfRange => "offset and count exceed the bit width",
+            Rule::ClampBounds => "low bound of clamp is greater than its high bound",
+        };
+
+        f.write_str(description)
+    }
+}
+
+/// A constant expression that the concretizer replaced, because it breaks `rule`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Diagnostic {
+    /// The location of the expression, if the module was parsed from source.
+    pub span: Option<Span>,
+    pub rule: Rule,
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.span {
+            Some(span) => write!(f, "{span}: {}", self.rule),
+            None => write!(f, "{}", self.rule),
+        }
+    }
+}
@@ -8,6 +8,8 @@ pub(crate) fn is_zero(val: &Value) -> bool {
         Value::Lit(Lit::U32(v)) => *v == 0,
         Value::Lit(Lit::F32(v)) => *v == 0.0,
         Value::Lit(Lit::F16(v)) => *v == half::f16::ZERO,
+        Value::Lit(Lit::AbstractInt(v)) => *v == 0,
+        Value::Lit(Lit::AbstractFloat(v)) => *v == 0.0,
         Value::Vector(vec) => vec.iter().any(is_zero),
         _ => false,
     }
@@ -40,6 +42,8 @@ pub fn is_invalid_clamp_bounds(low: &Value, high: &Value) -> bool {
             (Lit::U32(lv), Lit::U32(hv)) => lv > hv,
             (Lit::F32(lv), Lit::F32(hv)) => lv > hv,
             (Lit::F16(lv), Lit::F16(hv)) => lv > hv,
+            (Lit::AbstractInt(lv), Lit::AbstractInt(hv)) => lv > hv,
+            (Lit::AbstractFloat(lv), Lit::AbstractFloat(hv)) => lv > hv,
             _ => false,
         },
         (Value::Vector(l_vec), Value::Vector(h_vec)) => {
@@ -58,5 +62,6 @@ pub fn is_invalid_clamp_bounds(low: &Value, high: &Value) -> bool {
         (Value::Vector(l_vec), Value::Lit(_)) => {
             l_vec.iter().any(|l| is_invalid_clamp_bounds(l, high))
         }
+        _ => false,
     }
 }
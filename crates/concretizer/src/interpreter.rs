@@ -0,0 +1,547 @@
+//! Evaluation of calls to user-defined functions with constant arguments, so that their results
+//! can be folded.
+//!
+//! Only functions that don't touch memory outside of their own variables are evaluated, and only
+//! operations whose results are exact on the GPU, so folding a call doesn't change what the program
+//! computes. Anything else makes the evaluation give up by returning `None`.
+
+use std::collections::HashMap;
+
+use ast::*;
+
+use crate::builtin::{evaluate_builtin, Builtin};
+use crate::concretizer::{constructor_arg_type, operand_type, Concretizer};
+use crate::value::Value;
+
+enum Flow {
+    Next,
+    Break,
+    Continue,
+    Return(Option<Value>),
+}
+
+struct Frame {
+    scopes: Vec<HashMap<String, Value>>,
+    return_type: Option<DataType>,
+}
+
+impl Frame {
+    fn declare(&mut self, name: &str, value: Value) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name.to_owned(), value);
+        }
+    }
+
+    fn lookup(&self, name: &str) -> Option<&Value> {
+        self.scopes.iter().rev().find_map(|scope| scope.get(name))
+    }
+
+    fn lookup_mut(&mut self, name: &str) -> Option<&mut Value> {
+        self.scopes
+            .iter_mut()
+            .rev()
+            .find_map(|scope| scope.get_mut(name))
+    }
+}
+
+impl Concretizer {
+    /// Evaluates a call to the user-defined function `ident`.
+    pub(crate) fn eval_call(&self, ident: &str, args: Vec<Value>) -> Option<Value> {
+        self.call(ident, args)?
+    }
+
+    /// Runs the user-defined function `ident`, returning the value that it returns, if any.
+    fn call(&self, ident: &str, args: Vec<Value>) -> Option<Option<Value>> {
+        let decl = self.function(ident)?;
+
+        if decl.inputs.len() != args.len() {
+            return None;
+        }
+
+        let mut params = HashMap::new();
+        for (input, arg) in decl.inputs.iter().zip(args) {
+            if matches!(input.data_type, DataType::Ptr(_)) {
+                return None;
+            }
+            params.insert(input.name.clone(), arg.concretize(&input.data_type)?);
+        }
+
+        let mut frame = Frame {
+            scopes: vec![params],
+            return_type: decl.output.as_ref().map(|output| output.data_type.clone()),
+        };
+
+        match self.exec_block(&mut frame, &decl.body)? {
+            Flow::Return(value) => Some(value),
+            Flow::Next if frame.return_type.is_none() => Some(None),
+            _ => None,
+        }
+    }
+
+    fn exec_block(&self, frame: &mut Frame, block: &[Statement]) -> Option<Flow> {
+        frame.scopes.push(HashMap::new());
+        let flow = self.exec_stmts(frame, block);
+        frame.scopes.pop();
+        flow
+    }
+
+    fn exec_stmts(&self, frame: &mut Frame, stmts: &[Statement]) -> Option<Flow> {
+        for stmt in stmts {
+            match self.exec_stmt(frame, stmt)? {
+                Flow::Next => {}
+                flow => return Some(flow),
+            }
+        }
+
+        Some(Flow::Next)
+    }
+
+    fn exec_stmt(&self, frame: &mut Frame, stmt: &Statement) -> Option<Flow> {
+        self.step()?;
+
+        match stmt {
+            Statement::LetDecl(stmt) => self.exec_let(frame, stmt)?,
+            Statement::VarDecl(stmt) => self.exec_var(frame, stmt)?,
+            Statement::Assignment(stmt) => self.exec_assignment(frame, stmt)?,
+            Statement::Increment(stmt) => self.exec_update(frame, &stmt.lhs, BinOp::Plus)?,
+            Statement::Decrement(stmt) => self.exec_update(frame, &stmt.lhs, BinOp::Minus)?,
+            Statement::Compound(stmt) => return self.exec_block(frame, &stmt.body),
+            Statement::If(stmt) => return self.exec_if(frame, stmt),
+            Statement::Return(stmt) => {
+                let value = match (&stmt.value, &frame.return_type) {
+                    (Some(value), Some(return_type)) => {
+                        Some(self.eval_as(frame, value, return_type)?)
+                    }
+                    (None, None) => None,
+                    _ => return None,
+                };
+                return Some(Flow::Return(value));
+            }
+            Statement::Loop(stmt) => return self.exec_loop(frame, stmt),
+            Statement::While(stmt) => loop {
+                self.step()?;
+
+                if !self.eval_condition(frame, &stmt.condition)? {
+                    break;
+                }
+
+                match self.exec_block(frame, &stmt.body)? {
+                    Flow::Break => break,
+                    Flow::Return(value) => return Some(Flow::Return(value)),
+                    Flow::Next | Flow::Continue => {}
+                }
+            },
+            Statement::Break => return Some(Flow::Break),
+            Statement::Continue => return Some(Flow::Continue),
+            Statement::Switch(stmt) => return self.exec_switch(frame, stmt),
+            Statement::ForLoop(stmt) => {
+                frame.scopes.push(HashMap::new());
+                let flow = self.exec_for_loop(frame, stmt);
+                frame.scopes.pop();
+                return flow;
+            }
+            Statement::FnCall(stmt) => self.exec_call(frame, stmt)?,
+            Statement::Fallthrough => return None,
+        }
+
+        Some(Flow::Next)
+    }
+
+    fn exec_let(&self, frame: &mut Frame, stmt: &LetDeclStatement) -> Option<()> {
+        let data_type = match &stmt.data_type {
+            Some(data_type) => data_type,
+            None => stmt.initializer.data_type.dereference(),
+        };
+
+        let value = self.eval_as(frame, &stmt.initializer, data_type)?;
+        frame.declare(&stmt.ident, value);
+        Some(())
+    }
+
+    fn exec_var(&self, frame: &mut Frame, stmt: &VarDeclStatement) -> Option<()> {
+        let data_type = match (&stmt.data_type, &stmt.initializer) {
+            (Some(data_type), _) => data_type,
+            (None, Some(initializer)) => initializer.data_type.dereference(),
+            (None, None) => return None,
+        };
+
+        let value = match &stmt.initializer {
+            Some(initializer) => self.eval_as(frame, initializer, data_type)?,
+            None => Value::zero(data_type)?,
+        };
+
+        frame.declare(&stmt.ident, value);
+        Some(())
+    }
+
+    fn exec_assignment(&self, frame: &mut Frame, stmt: &AssignmentStatement) -> Option<()> {
+        let lhs = match &stmt.lhs {
+            AssignmentLhs::Phony => return self.eval(frame, &stmt.rhs).map(|_| ()),
+            AssignmentLhs::Expr(lhs) => lhs,
+        };
+
+        let op = match stmt.op {
+            AssignmentOp::Simple => None,
+            AssignmentOp::Plus => Some(BinOp::Plus),
+            AssignmentOp::Minus => Some(BinOp::Minus),
+            AssignmentOp::Times => Some(BinOp::Times),
+            AssignmentOp::Divide => Some(BinOp::Divide),
+            AssignmentOp::Mod => Some(BinOp::Mod),
+            AssignmentOp::And => Some(BinOp::BitAnd),
+            AssignmentOp::Or => Some(BinOp::BitOr),
+            AssignmentOp::Xor => Some(BinOp::BitXOr),
+        };
+
+        let value = match op {
+            None => self.eval_as(frame, &stmt.rhs, lhs.data_type.dereference())?,
+            Some(op) => {
+                let old = self.eval_lhs(frame, lhs)?;
+                let rhs = self.eval(frame, &stmt.rhs)?;
+                let rhs_type = operand_type(&op, &stmt.rhs.data_type, &lhs.data_type, None, false);
+                let rhs = concretize_operand(rhs, rhs_type)?;
+                self.eval_runtime_bin_op(&op, old, rhs)?
+            }
+        };
+
+        self.store(frame, lhs, value)
+    }
+
+    fn exec_update(&self, frame: &mut Frame, lhs: &AssignmentLhs, op: BinOp) -> Option<()> {
+        let AssignmentLhs::Expr(lhs) = lhs else {
+            return None;
+        };
+
+        let old = self.eval_lhs(frame, lhs)?;
+        let one = Value::from(1i64).convert(lhs.data_type.dereference())?;
+        let value = self.eval_runtime_bin_op(&op, old, one)?;
+        self.store(frame, lhs, value)
+    }
+
+    fn exec_if(&self, frame: &mut Frame, stmt: &IfStatement) -> Option<Flow> {
+        if self.eval_condition(frame, &stmt.condition)? {
+            return self.exec_block(frame, &stmt.body);
+        }
+
+        match stmt.else_.as_deref() {
+            Some(Else::If(stmt)) => self.exec_if(frame, stmt),
+            Some(Else::Else(block)) => self.exec_block(frame, block),
+            None => Some(Flow::Next),
+        }
+    }
+
+    fn exec_loop(&self, frame: &mut Frame, stmt: &LoopStatement) -> Option<Flow> {
+        loop {
+            self.step()?;
+
+            // The continuing block is in the scope of the loop body
+            frame.scopes.push(HashMap::new());
+            let flow = self.exec_loop_iteration(frame, stmt);
+            frame.scopes.pop();
+
+            match flow? {
+                Flow::Break => return Some(Flow::Next),
+                Flow::Return(value) => return Some(Flow::Return(value)),
+                Flow::Next | Flow::Continue => {}
+            }
+        }
+    }
+
+    fn exec_loop_iteration(&self, frame: &mut Frame, stmt: &LoopStatement) -> Option<Flow> {
+        match self.exec_stmts(frame, &stmt.body)? {
+            Flow::Next | Flow::Continue => {}
+            flow => return Some(flow),
+        }
+
+        let Some(continuing) = &stmt.continuing else {
+            return Some(Flow::Next);
+        };
+
+        match self.exec_block(frame, &continuing.stmts)? {
+            Flow::Next => {}
+            _ => return None,
+        }
+
+        match &continuing.break_if {
+            Some(condition) if self.eval_condition(frame, condition)? => Some(Flow::Break),
+            _ => Some(Flow::Next),
+        }
+    }
+
+    fn exec_switch(&self, frame: &mut Frame, stmt: &SwitchStatement) -> Option<Flow> {
+        let selector_type = stmt.selector.data_type.dereference();
+        let selector = self.eval_as(frame, &stmt.selector, selector_type)?;
+
+        let mut body = &stmt.default;
+        for case in &stmt.cases {
+            let value = self.eval_as(frame, &case.selector, selector_type)?;
+            let is_equal = self.evaluate_bin_op(&BinOp::Equal, selector.clone(), value);
+            if matches!(is_equal, Some(Value::Lit(Lit::Bool(true)))) {
+                body = &case.body;
+                break;
+            }
+        }
+
+        match self.exec_block(frame, body)? {
+            Flow::Break => Some(Flow::Next),
+            flow => Some(flow),
+        }
+    }
+
+    fn exec_for_loop(&self, frame: &mut Frame, stmt: &ForLoopStatement) -> Option<Flow> {
+        match &stmt.header.init {
+            Some(ForLoopInit::VarDecl(init)) => self.exec_var(frame, init)?,
+            Some(ForLoopInit::LetDecl(init)) => self.exec_let(frame, init)?,
+            Some(ForLoopInit::Assignment(init)) => self.exec_assignment(frame, init)?,
+            Some(ForLoopInit::Increment(init)) => {
+                self.exec_update(frame, &init.lhs, BinOp::Plus)?
+            }
+            Some(ForLoopInit::Decrement(init)) => {
+                self.exec_update(frame, &init.lhs, BinOp::Minus)?
+            }
+            Some(ForLoopInit::Call(init)) => self.exec_call(frame, init)?,
+            None => {}
+        }
+
+        loop {
+            self.step()?;
+
+            if let Some(condition) = &stmt.header.condition {
+                if !self.eval_condition(frame, condition)? {
+                    return Some(Flow::Next);
+                }
+            }
+
+            match self.exec_block(frame, &stmt.body)? {
+                Flow::Break => return Some(Flow::Next),
+                Flow::Return(value) => return Some(Flow::Return(value)),
+                Flow::Next | Flow::Continue => {}
+            }
+
+            match &stmt.header.update {
+                Some(ForLoopUpdate::Assignment(update)) => self.exec_assignment(frame, update)?,
+                Some(ForLoopUpdate::Increment(update)) => {
+                    self.exec_update(frame, &update.lhs, BinOp::Plus)?
+                }
+                Some(ForLoopUpdate::Decrement(update)) => {
+                    self.exec_update(frame, &update.lhs, BinOp::Minus)?
+                }
+                Some(ForLoopUpdate::Call(update)) => self.exec_call(frame, update)?,
+                None => {}
+            }
+        }
+    }
+
+    fn exec_call(&self, frame: &mut Frame, stmt: &FnCallStatement) -> Option<()> {
+        // Builtins that can be called as statements have side effects
+        self.function(&stmt.ident)?;
+        let args = self.eval_args(frame, &stmt.args)?;
+        self.call(&stmt.ident, args).map(|_| ())
+    }
+
+    /// Stores `value` in the variable, or part of a variable, that `lhs` refers to.
+    fn store(&self, frame: &mut Frame, lhs: &LhsExprNode, value: Value) -> Option<()> {
+        let (name, path) = self.eval_place(frame, lhs)?;
+
+        let mut target = frame.lookup_mut(&name)?;
+        for index in path {
+            target = target.component_mut(index)?;
+        }
+
+        *target = value;
+        Some(())
+    }
+
+    /// Returns the variable that `lhs` refers to, and the indices of the components of the variable
+    /// that it refers to.
+    fn eval_place(&self, frame: &Frame, lhs: &LhsExprNode) -> Option<(String, Vec<usize>)> {
+        match &lhs.expr {
+            LhsExpr::Ident(name) => Some((name.clone(), vec![])),
+            LhsExpr::Postfix(inner, postfix) => {
+                let (name, mut path) = self.eval_place(frame, inner)?;
+                let index = match postfix {
+                    Postfix::Index(index) => match self.eval(frame, index)? {
+                        Value::Lit(Lit::I32(i)) => usize::try_from(i).ok()?,
+                        Value::Lit(Lit::U32(u)) => u as usize,
+                        Value::Lit(Lit::AbstractInt(i)) => usize::try_from(i).ok()?,
+                        _ => return None,
+                    },
+                    Postfix::Member(member) => match inner.data_type.dereference() {
+                        DataType::Struct(decl) => {
+                            decl.members.iter().position(|m| m.name == *member)?
+                        }
+                        _ => match member.as_str() {
+                            "x" | "r" => 0,
+                            "y" | "g" => 1,
+                            "z" | "b" => 2,
+                            "w" | "a" => 3,
+                            _ => return None,
+                        },
+                    },
+                };
+                path.push(index);
+                Some((name, path))
+            }
+            LhsExpr::Deref(_) | LhsExpr::AddressOf(_) => None,
+        }
+    }
+
+    fn eval_lhs(&self, frame: &Frame, lhs: &LhsExprNode) -> Option<Value> {
+        let (name, path) = self.eval_place(frame, lhs)?;
+
+        let mut value = frame.lookup(&name)?;
+        for index in path {
+            value = match value {
+                Value::Struct(members) => members.get(index)?,
+                value => value.elements()?.get(index)?,
+            };
+        }
+
+        Some(value.clone())
+    }
+
+    fn eval_condition(&self, frame: &Frame, node: &ExprNode) -> Option<bool> {
+        match self.eval(frame, node)? {
+            Value::Lit(Lit::Bool(b)) => Some(b),
+            _ => None,
+        }
+    }
+
+    /// Evaluates `node`, converting it to `data_type` if its value is abstract.
+    fn eval_as(&self, frame: &Frame, node: &ExprNode, data_type: &DataType) -> Option<Value> {
+        self.eval(frame, node)?.concretize(data_type)
+    }
+
+    fn eval_args(&self, frame: &Frame, args: &[ExprNode]) -> Option<Vec<Value>> {
+        args.iter()
+            .map(|arg| self.eval_as(frame, arg, arg.data_type.dereference()))
+            .collect()
+    }
+
+    fn eval(&self, frame: &Frame, node: &ExprNode) -> Option<Value> {
+        match &node.expr {
+            Expr::Lit(lit) => Some(Value::Lit(*lit)),
+            Expr::TypeCons(expr) => {
+                let args = expr
+                    .args
+                    .iter()
+                    .enumerate()
+                    .map(|(i, arg)| {
+                        let value = self.eval(frame, arg)?;
+                        match constructor_arg_type(&node.data_type, i, &arg.data_type) {
+                            Some(data_type) => value.concretize(&data_type),
+                            None => Some(value),
+                        }
+                    })
+                    .collect::<Option<_>>()?;
+                self.eval_typecons(&node.data_type, args)
+            }
+            Expr::UnOp(expr) => match expr.op {
+                UnOp::AddressOf | UnOp::Deref => None,
+                op => self.eval_unop(op, self.eval(frame, &expr.inner)?),
+            },
+            Expr::BinOp(expr) => {
+                let left = self.eval(frame, &expr.left)?;
+
+                // The right operand of `&&` and `||` is only evaluated if it is needed
+                match (&expr.op, &left) {
+                    (BinOp::LogAnd, Value::Lit(Lit::Bool(false)))
+                    | (BinOp::LogOr, Value::Lit(Lit::Bool(true))) => return Some(left),
+                    _ => {}
+                }
+
+                let right = self.eval(frame, &expr.right)?;
+
+                // Operands that aren't constant expressions are converted to their concrete types
+                // even if they evaluate to abstract values, as they aren't evaluated at shader
+                // creation time
+                let (left, right) = if left.is_abstract() && right.is_abstract() {
+                    let mut values = Value::unify_abstract(vec![left, right]);
+                    let right = values.pop()?;
+                    (values.pop()?, right)
+                } else {
+                    let (l, r) = (&expr.left.data_type, &expr.right.data_type);
+                    (
+                        concretize_operand(left, operand_type(&expr.op, l, r, None, true))?,
+                        concretize_operand(right, operand_type(&expr.op, r, l, None, false))?,
+                    )
+                };
+
+                self.eval_runtime_bin_op(&expr.op, left, right)
+            }
+            Expr::FnCall(expr) => match Builtin::convert(expr.ident.clone()) {
+                // The GPU only approximates these
+                Some(Builtin::Exp | Builtin::Exp2) => None,
+                Some(builtin) => {
+                    let args = expr
+                        .args
+                        .iter()
+                        .map(|arg| self.eval(frame, arg))
+                        .collect::<Option<Vec<_>>>()?;
+
+                    let args = if builtin.accepts_abstract()
+                        && args
+                            .iter()
+                            .all(|arg| arg.is_abstract() || matches!(arg, Value::Lit(Lit::Bool(_))))
+                    {
+                        Value::unify_abstract(args)
+                    } else {
+                        args.into_iter()
+                            .zip(&expr.args)
+                            .map(|(value, arg)| value.concretize(arg.data_type.dereference()))
+                            .collect::<Option<_>>()?
+                    };
+
+                    evaluate_builtin(&builtin, args.into_iter().map(Some).collect())
+                }
+                None => {
+                    let args = self.eval_args(frame, &expr.args)?;
+                    self.eval_call(&expr.ident, args)
+                }
+            },
+            Expr::Postfix(expr) => {
+                let inner = self.eval(frame, &expr.inner)?;
+                match &expr.postfix {
+                    Postfix::Index(index) => {
+                        let index = self.eval(frame, index)?;
+                        self.eval_index_access(&inner, &index)
+                    }
+                    Postfix::Member(member) => {
+                        self.eval_member_access(expr.inner.data_type.dereference(), &inner, member)
+                    }
+                }
+            }
+            Expr::Var(expr) => frame
+                .lookup(&expr.ident)
+                .or_else(|| self.global_const(&expr.ident))
+                .cloned(),
+        }
+    }
+
+    /// Evaluates a binary operator at runtime. Integer overflow wraps at runtime, but evaluation
+    /// gives up on it like at shader creation time, and also on float division, which the GPU only
+    /// approximates.
+    fn eval_runtime_bin_op(&self, op: &BinOp, left: Value, right: Value) -> Option<Value> {
+        if matches!(op, BinOp::Divide | BinOp::Mod)
+            && matches!(first_lit(&left)?, Lit::F32(_) | Lit::F16(_))
+        {
+            return None;
+        }
+
+        self.evaluate_bin_op(op, left, right)
+    }
+}
+
+fn concretize_operand(value: Value, data_type: Option<DataType>) -> Option<Value> {
+    match data_type {
+        Some(data_type) => value.concretize(&data_type),
+        None => Some(value),
+    }
+}
+
+/// Returns the first scalar in a value.
+fn first_lit(value: &Value) -> Option<Lit> {
+    match value {
+        Value::Lit(lit) => Some(*lit),
+        value => first_lit(value.elements()?.first()?),
+    }
+}
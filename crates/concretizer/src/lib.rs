@@ -1,25 +1,482 @@
 mod builtin;
+pub mod cli;
 pub mod concretizer;
+pub mod diagnostic;
 mod helper;
+mod interpreter;
 pub mod value;
 
 use crate::concretizer::*;
+use crate::diagnostic::Diagnostic;
 use ast::*;
 
 pub fn concretize(ast: Module) -> Module {
     concretize_with(ast, Options::default())
 }
 
-pub fn concretize_with(mut ast: Module, options: Options) -> Module {
+pub fn concretize_with(ast: Module, options: Options) -> Module {
+    concretize_with_diagnostics(ast, options).0
+}
+
+/// Concretizes a module, also returning a diagnostic for every invalid expression that was
+/// replaced.
+pub fn concretize_with_diagnostics(mut ast: Module, options: Options) -> (Module, Vec<Diagnostic>) {
     let mut concretizer = Concretizer::new(options);
 
-    let functions = ast
-        .functions
+    // Constants are concretized first, so their values are known in every function
+    let mut fns = vec![];
+    let decls: Vec<Option<GlobalDecl>> = ast
+        .decls
+        .into_iter()
+        .map(|decl| match decl {
+            GlobalDecl::Const(c) => Some(GlobalDecl::Const(concretizer.concretize_global_const(c))),
+            GlobalDecl::Fn(f) => {
+                fns.push(f);
+                None
+            }
+            decl => Some(decl),
+        })
+        .collect();
+
+    let mut fns = concretizer.concretize_fns(fns).into_iter();
+
+    ast.decls = decls
         .into_iter()
-        .map(|f| concretizer.concretize_fn(f))
-        .collect::<Vec<_>>();
+        .filter_map(|decl| decl.or_else(|| fns.next().map(GlobalDecl::Fn)))
+        .collect();
+
+    (ast, concretizer.into_diagnostics())
+}
+
+#[cfg(test)]
+mod tests {
+    use ast::writer::Writer;
+
+    use super::*;
+    use crate::diagnostic::Rule;
+
+    fn write(module: &Module) -> String {
+        let mut out = String::new();
+        Writer::default().write_module(&mut out, module).unwrap();
+        out
+    }
+
+    fn assert_concretizes_to(source: &str, expected: &str) {
+        assert_concretizes_with(Options::default(), source, expected);
+    }
+
+    fn assert_folds_to(source: &str, expected: &str) {
+        let options = Options {
+            fold_budget: Some(1000),
+            ..Options::default()
+        };
+        assert_concretizes_with(options, source, expected);
+    }
+
+    fn assert_concretizes_with(options: Options, source: &str, expected: &str) {
+        let actual = write(&concretize_with(parser::parse(source), options));
+        assert_eq!(actual, write(&parser::parse(expected)));
+    }
+
+    #[test]
+    fn out_of_range_const_index() {
+        assert_concretizes_to(
+            r"
+fn f() {
+    var a = array<i32, 3>(1, 2, 3);
+    let v = vec2<f32>(1.0, 2.0);
+    let x = a[3] + i32(v[2u]);
+    a[-1] = a[2];
+}
+",
+            r"
+fn f() {
+    var a = array<i32, 3>(1, 2, 3);
+    let v = vec2<f32>(1.0, 2.0);
+    let x = a[0i] + i32(v[0u]);
+    a[0i] = a[2];
+}
+",
+        );
+    }
+
+    #[test]
+    fn composite_const_index() {
+        // The matrix product is vec2(4.0, 6.0), so the index is out of range
+        assert_concretizes_to(
+            r"
+struct S {
+    a: i32,
+    b: vec3<u32>,
+}
+
+fn f() {
+    var a = array<i32, 3>(1, 2, 3);
+    let x = a[i32((mat2x2<f32>(1.0, 2.0, 3.0, 4.0) * vec2<f32>(1.0, 1.0)).y)];
+    let y = a[S(1, vec3<u32>(0u, 1u, 2u)).b.z];
+    let z = a[S().a];
+}
+",
+            r"
+struct S {
+    a: i32,
+    b: vec3<u32>,
+}
+
+fn f() {
+    var a = array<i32, 3>(1, 2, 3);
+    let x = a[0i];
+    let y = a[S(1, vec3<u32>(0u, 1u, 2u)).b.z];
+    let z = a[S().a];
+}
+",
+        );
+    }
+
+    #[test]
+    fn division_by_composite_zero() {
+        assert_concretizes_to(
+            r"
+fn f() {
+    let x = 1 / array<i32, 2>(1, 0)[1];
+    let y = 1 / array<i32, 2>(1, 0)[0];
+}
+",
+            r"
+fn f() {
+    let x = 1i;
+    let y = 1 / array<i32, 2>(1, 0)[0];
+}
+",
+        );
+    }
+
+    #[test]
+    fn abstract_overflow() {
+        assert_concretizes_to(
+            r"
+fn f() {
+    var x = 1i;
+    let a = 2147483647 + 1;
+    let b = 2147483647 + 1 - 1;
+    let c = x + (2147483647 + 1);
+    let d = 4611686018427387904 * 2 - 1;
+    let e = 1 << 31;
+    let f = abs(-2147483648);
+    let g = 0.5 + 1;
+    let h = 1e38 * 10.0;
+    let i = 1e38 * 10.0 / 100.0;
+}
+",
+            r"
+fn f() {
+    var x = 1i;
+    let a = 1i;
+    let b = 2147483647 + 1 - 1;
+    let c = x + 1i;
+    let d = 1i - 1;
+    let e = 1i;
+    let f = 1i;
+    let g = 0.5 + 1;
+    let h = 1f;
+    let i = 1e38 * 10.0 / 100.0;
+}
+",
+        );
+    }
+
+    #[test]
+    fn typed_let_initializer() {
+        assert_concretizes_to(
+            r"
+fn f() {
+    let x: u32 = 3000000000;
+    let y: i32 = 3000000000;
+    var z: u32 = 4294967295;
+}
+",
+            r"
+fn f() {
+    let x: u32 = 3000000000;
+    let y: i32 = 1i;
+    var z: u32 = 4294967295;
+}
+",
+        );
+    }
+
+    #[test]
+    fn abstract_values_take_the_type_they_are_used_as() {
+        let source = r"
+fn g(a: u32, b: f32) {}
+
+fn h(a: u32) -> u32 {
+    return a;
+}
+
+fn f() -> u32 {
+    return 3000000000;
+}
+
+fn k() -> f32 {
+    return 2147483647 + 1;
+}
+
+fn main() {
+    var x: u32;
+    x = 3000000000;
+    x += 4000000000;
+    g(3000000000, 2147483647 + 1);
+    let y = h(3000000000);
+}
+";
+
+        let (module, diagnostics) =
+            concretize_with_diagnostics(parser::parse(source), Options::default());
+
+        assert_eq!(diagnostics, vec![]);
+        assert_eq!(write(&module), write(&parser::parse(source)));
 
-    ast.functions = functions;
+        if let Err(errors) = typecheck::check(&module) {
+            panic!("unexpected errors: {errors:?}");
+        }
 
-    ast
+        assert_concretizes_to(
+            r"
+fn g(a: u32) {}
+
+fn f() -> u32 {
+    return -1;
+}
+
+fn main() {
+    var x: u32;
+    x = -1;
+    g(-1);
+}
+",
+            r"
+fn g(a: u32) {}
+
+fn f() -> u32 {
+    return 1u;
+}
+
+fn main() {
+    var x: u32;
+    x = 1u;
+    g(1u);
+}
+",
+        );
+    }
+
+    #[test]
+    fn const_comparisons() {
+        let source = r"
+fn f() {
+    let a = 1 < 2;
+    let b = !(1i == 2i) && vec2<u32>(1u, 2u).y >= 2u;
+    let c = select(vec2<bool>(), vec2<bool>(true, false), 1.0 != 1.0);
+}
+";
+        assert_concretizes_to(source, source);
+    }
+
+    #[test]
+    fn fold_user_functions() {
+        assert_folds_to(
+            r"
+var<private> p: i32;
+
+fn add(a: i32, b: i32) -> i32 {
+    var sum = a;
+    for (var i = 0; i < b; i++) {
+        sum += 1;
+    }
+    return sum;
+}
+
+fn spin() -> i32 {
+    var i = 0;
+    loop {
+        i = i + 1;
+        if i < 0 {
+            break;
+        }
+    }
+    return i;
+}
+
+fn read_global() -> i32 {
+    return p;
+}
+
+fn five() -> i32 {
+    return 5;
+}
+
+fn f() {
+    var a = array<i32, 3>(1, 2, 3);
+    let v = a[five()];
+    let u = 1f - 1f;
+    let x = add(1, 2) * 2;
+    let y = vec2<i32>(add(2, 0), 1 + 1).x == 2;
+    let z = spin();
+    let w = read_global();
+}
+",
+            r"
+var<private> p: i32;
+
+fn add(a: i32, b: i32) -> i32 {
+    var sum = a;
+    for (var i = 0; i < b; i++) {
+        sum += 1;
+    }
+    return sum;
+}
+
+fn spin() -> i32 {
+    var i = 0;
+    loop {
+        i = i + 1;
+        if i < 0 {
+            break;
+        }
+    }
+    return i;
+}
+
+fn read_global() -> i32 {
+    return p;
+}
+
+fn five() -> i32 {
+    return 5;
+}
+
+fn f() {
+    var a = array<i32, 3>(1, 2, 3);
+    let v = a[five()];
+    let u = 1f - 1f;
+    let x = 6i;
+    let y = true;
+    let z = spin();
+    let w = read_global();
+}
+",
+        );
+    }
+
+    #[test]
+    fn fold_leaves_invalid_expressions_unchanged() {
+        let source = r"
+fn one() -> i32 {
+    return 1;
+}
+
+fn f() {
+    var a = array<i32, 3>(1, 2, 3);
+    let x = dot(vec2<i32>(2147483647i, 1i), vec2<i32>(1i, 1i));
+    let y = a[3] + one();
+    let z = a[one() + 2];
+    let w = 1f - 1f;
+}
+";
+        let options = Options {
+            fold_budget: Some(1000),
+            ..Options::default()
+        };
+
+        let (module, diagnostics) = concretize_with_diagnostics(parser::parse(source), options);
+        let rules = diagnostics
+            .iter()
+            .map(|diagnostic| (diagnostic.span.unwrap().line, diagnostic.rule))
+            .collect::<Vec<_>>();
+
+        assert_eq!(write(&module), write(&parser::parse(source)));
+        assert_eq!(
+            rules,
+            vec![(8, Rule::IntegerOverflow), (9, Rule::IndexOutOfBounds)]
+        );
+    }
+
+    #[test]
+    fn diagnostics() {
+        let module = parser::parse(
+            r"
+fn f() {
+    var x = 1i;
+    let a = 1i / 0i;
+    let b = x % 0i;
+    let c = 1u << 32u;
+    let d = 1i << 31u;
+    let e = vec2<i32>(2147483647i, 1i) + 1i;
+    let f = 3e38f * 10f;
+    let g = 1f - 1f;
+    let h = vec3<i32>()[3];
+    let i = u32(-1);
+    let j = extractBits(1u, 16u, 17u);
+    let k = clamp(1i, 2i, 0i);
+    let l: u32 = -1;
+    x = 3000000000;
+    let m = dot(vec2<i32>(2147483647i, 1i), vec2<i32>(1i, 1i));
+    let n = abs(i32(-2147483648));
+    let o = sqrt(-1f);
+    let p = log(0f);
+    let q = ldexp(1f, 200i);
+}
+",
+        );
+
+        let (_, diagnostics) = concretize_with_diagnostics(module, Options::default());
+        let rules = diagnostics
+            .iter()
+            .map(|diagnostic| (diagnostic.span.unwrap().line, diagnostic.rule))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            rules,
+            vec![
+                (4, Rule::DivisionByZero),
+                (5, Rule::DivisionByZero),
+                (6, Rule::ShiftOutOfRange),
+                (7, Rule::ShiftOverflow),
+                (8, Rule::IntegerOverflow),
+                (9, Rule::FloatNotFinite),
+                (10, Rule::FloatOutOfRange),
+                (11, Rule::IndexOutOfBounds),
+                (12, Rule::ConversionOutOfRange),
+                (13, Rule::BitsOutOfRange),
+                (14, Rule::ClampBounds),
+                (15, Rule::ConversionOutOfRange),
+                (16, Rule::ConversionOutOfRange),
+                (17, Rule::IntegerOverflow),
+                (18, Rule::IntegerOverflow),
+                (19, Rule::FloatNotFinite),
+                (20, Rule::FloatNotFinite),
+                (21, Rule::FloatNotFinite),
+            ]
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid expression")]
+    fn out_of_range_const_index_panics() {
+        let module = parser::parse(
+            r"
+fn f() {
+    let x = vec4<i32>()[4];
+}
+",
+        );
+        concretize_with(
+            module,
+            Options {
+                error_handling: ErrorHandling::Panic,
+                ..Options::default()
+            },
+        );
+    }
 }
@@ -4,6 +4,11 @@ use ast::*;
 pub enum Value {
     Lit(Lit),
     Vector(Vec<Value>),
+    /// The column vectors of a matrix.
+    Matrix(Vec<Value>),
+    Array(Vec<Value>),
+    /// The values of the members of a struct, in declaration order.
+    Struct(Vec<Value>),
 }
 impl From<i32> for Value {
     fn from(val: i32) -> Self {
@@ -33,6 +38,18 @@ impl From<bool> for Value {
     }
 }
 
+impl From<i64> for Value {
+    fn from(val: i64) -> Self {
+        Value::Lit(Lit::AbstractInt(val))
+    }
+}
+
+impl From<f64> for Value {
+    fn from(val: f64) -> Self {
+        Value::Lit(Lit::AbstractFloat(val))
+    }
+}
+
 impl Value {
     pub fn from_i32(val: Option<i32>) -> Option<Value> {
         val.map(|i| Value::Lit(Lit::I32(i)))
@@ -49,4 +66,197 @@ impl Value {
     pub fn from_bool(val: Option<bool>) -> Option<Value> {
         val.map(|i| Value::Lit(Lit::Bool(i)))
     }
+    pub fn from_abstract_int(val: Option<i64>) -> Option<Value> {
+        val.map(|i| Value::Lit(Lit::AbstractInt(i)))
+    }
+    pub fn from_abstract_float(val: Option<f64>) -> Option<Value> {
+        val.map(|i| Value::Lit(Lit::AbstractFloat(i)))
+    }
+
+    /// Returns `true` if the value is made of abstract numbers, which haven't been converted to a
+    /// concrete type yet.
+    pub fn is_abstract(&self) -> bool {
+        match self {
+            Value::Lit(lit) => matches!(lit, Lit::AbstractInt(_) | Lit::AbstractFloat(_)),
+            Value::Vector(v) | Value::Matrix(v) | Value::Array(v) | Value::Struct(v) => {
+                v.iter().any(Value::is_abstract)
+            }
+        }
+    }
+
+    fn has_abstract_float(&self) -> bool {
+        match self {
+            Value::Lit(lit) => matches!(lit, Lit::AbstractFloat(_)),
+            Value::Vector(v) | Value::Matrix(v) | Value::Array(v) | Value::Struct(v) => {
+                v.iter().any(Value::has_abstract_float)
+            }
+        }
+    }
+
+    fn into_abstract_float(self) -> Value {
+        match self {
+            Value::Lit(Lit::AbstractInt(v)) => Value::Lit(Lit::AbstractFloat(v as f64)),
+            Value::Lit(lit) => Value::Lit(lit),
+            Value::Vector(v) => {
+                Value::Vector(v.into_iter().map(Value::into_abstract_float).collect())
+            }
+            Value::Matrix(v) => {
+                Value::Matrix(v.into_iter().map(Value::into_abstract_float).collect())
+            }
+            Value::Array(v) => {
+                Value::Array(v.into_iter().map(Value::into_abstract_float).collect())
+            }
+            Value::Struct(v) => {
+                Value::Struct(v.into_iter().map(Value::into_abstract_float).collect())
+            }
+        }
+    }
+
+    /// Converts abstract integers to abstract floats if any of the values is an abstract float,
+    /// since that is the only abstract type they can all be converted to.
+    pub fn unify_abstract(values: Vec<Value>) -> Vec<Value> {
+        if values.iter().any(Value::has_abstract_float) {
+            values.into_iter().map(Value::into_abstract_float).collect()
+        } else {
+            values
+        }
+    }
+
+    /// Returns the value of the zero value constructor of `data_type`, or `None` if the type
+    /// isn't constructible.
+    pub fn zero(data_type: &DataType) -> Option<Value> {
+        match data_type {
+            DataType::Scalar(ty) => Some(Value::Lit(zero_lit(*ty))),
+            DataType::Vector(n, ty) => {
+                Some(Value::Vector(vec![Value::Lit(zero_lit(*ty)); *n as usize]))
+            }
+            DataType::Matrix(c, r, ty) => {
+                Some(Value::Matrix(vec![
+                    Value::zero(&DataType::Vector(*r, *ty))?;
+                    *c as usize
+                ]))
+            }
+            DataType::Array(ty, Some(n)) => Some(Value::Array(vec![Value::zero(ty)?; *n as usize])),
+            DataType::Struct(decl) => decl
+                .members
+                .iter()
+                .map(|member| Value::zero(&member.data_type))
+                .collect::<Option<_>>()
+                .map(Value::Struct),
+            _ => None,
+        }
+    }
+
+    /// Returns the components of a vector, the columns of a matrix or the elements of an array.
+    pub fn elements(&self) -> Option<&[Value]> {
+        match self {
+            Value::Vector(v) | Value::Matrix(v) | Value::Array(v) => Some(v),
+            Value::Lit(_) | Value::Struct(_) => None,
+        }
+    }
+
+    /// Returns a mutable reference to a component, column, element or member of the value.
+    pub fn component_mut(&mut self, index: usize) -> Option<&mut Value> {
+        match self {
+            Value::Vector(v) | Value::Matrix(v) | Value::Array(v) | Value::Struct(v) => {
+                v.get_mut(index)
+            }
+            Value::Lit(_) => None,
+        }
+    }
+
+    /// Converts an abstract value to the concrete type it is used as, leaving concrete values
+    /// unchanged.
+    pub fn concretize(self, data_type: &DataType) -> Option<Value> {
+        if self.is_abstract() {
+            self.convert(data_type)
+        } else {
+            Some(self)
+        }
+    }
+
+    /// Converts the value to `data_type`, following the rules for WGSL value constructors.
+    ///
+    /// Returns `None` if a scalar in the value can't be represented in the new type, which is a
+    /// shader creation error, or if the value doesn't have the shape of `data_type`.
+    pub fn convert(self, data_type: &DataType) -> Option<Value> {
+        let convert_all = |values: Vec<Value>, ty: &DataType| {
+            values
+                .into_iter()
+                .map(|v| v.convert(ty))
+                .collect::<Option<Vec<_>>>()
+        };
+
+        match (self, data_type) {
+            (Value::Lit(lit), DataType::Scalar(ty)) => convert_lit(lit, *ty).map(Value::Lit),
+            (Value::Vector(v), DataType::Vector(n, ty)) if v.len() == *n as usize => {
+                convert_all(v, &DataType::Scalar(*ty)).map(Value::Vector)
+            }
+            (Value::Matrix(v), DataType::Matrix(c, r, ty)) if v.len() == *c as usize => {
+                convert_all(v, &DataType::Vector(*r, *ty)).map(Value::Matrix)
+            }
+            (Value::Array(v), DataType::Array(ty, n))
+                if n.is_none_or(|n| v.len() == n as usize) =>
+            {
+                convert_all(v, ty).map(Value::Array)
+            }
+            (Value::Struct(v), DataType::Struct(decl)) if v.len() == decl.members.len() => v
+                .into_iter()
+                .zip(&decl.members)
+                .map(|(v, member)| v.convert(&member.data_type))
+                .collect::<Option<_>>()
+                .map(Value::Struct),
+            _ => None,
+        }
+    }
+}
+
+fn zero_lit(ty: ScalarType) -> Lit {
+    match ty {
+        ScalarType::Bool => Lit::Bool(false),
+        ScalarType::I32 => Lit::I32(0),
+        ScalarType::U32 => Lit::U32(0),
+        ScalarType::F32 => Lit::F32(0.0),
+        ScalarType::F16 => Lit::F16(half::f16::ZERO),
+    }
+}
+
+/// Converts a scalar to type `ty`. Conversions between concrete integers reinterpret the bits,
+/// and floats are truncated when they are converted to an integer. A value that doesn't fit in
+/// `ty` after that is an error.
+fn convert_lit(lit: Lit, ty: ScalarType) -> Option<Lit> {
+    let converted = match (lit, ty) {
+        (Lit::Bool(v), ScalarType::Bool) => Lit::Bool(v),
+        (Lit::Bool(v), _) => return convert_lit(Lit::U32(v.into()), ty),
+        (Lit::I32(v), ScalarType::Bool) => Lit::Bool(v != 0),
+        (Lit::U32(v), ScalarType::Bool) => Lit::Bool(v != 0),
+        (Lit::AbstractInt(v), ScalarType::Bool) => Lit::Bool(v != 0),
+        (Lit::F32(v), ScalarType::Bool) => Lit::Bool(v != 0.0),
+        (Lit::F16(v), ScalarType::Bool) => Lit::Bool(v != half::f16::ZERO),
+        (Lit::AbstractFloat(v), ScalarType::Bool) => Lit::Bool(v != 0.0),
+        (Lit::I32(v), ScalarType::I32) => Lit::I32(v),
+        (Lit::U32(v), ScalarType::I32) => Lit::I32(v as i32),
+        (Lit::I32(v), ScalarType::U32) => Lit::U32(v as u32),
+        (Lit::U32(v), ScalarType::U32) => Lit::U32(v),
+        (Lit::I32(v), ScalarType::F32 | ScalarType::F16) => {
+            return convert_lit(Lit::AbstractFloat(v.into()), ty)
+        }
+        (Lit::U32(v), ScalarType::F32 | ScalarType::F16) => {
+            return convert_lit(Lit::AbstractFloat(v.into()), ty)
+        }
+        (Lit::F32(v), ScalarType::F32) => Lit::F32(v),
+        (Lit::F32(v), _) => return convert_lit(Lit::AbstractFloat(v.into()), ty),
+        (Lit::F16(v), ScalarType::F16) => Lit::F16(v),
+        (Lit::F16(v), _) => return convert_lit(Lit::AbstractFloat(v.into()), ty),
+        (Lit::AbstractFloat(v), ScalarType::I32 | ScalarType::U32) => {
+            let v = v.trunc();
+            if !(i64::MIN as f64..i64::MAX as f64).contains(&v) {
+                return None;
+            }
+            return Lit::AbstractInt(v as i64).convert_abstract(ty);
+        }
+        (Lit::AbstractInt(_) | Lit::AbstractFloat(_), _) => return lit.convert_abstract(ty),
+    };
+
+    Some(converted)
 }
@@ -196,6 +196,32 @@ impl Device<'_> {
         })
     }
 
+    pub fn create_texture(
+        &self,
+        dimension: WGPUTextureDimension,
+        format: WGPUTextureFormat,
+        size: WGPUExtent3D,
+        usage: DeviceTextureUsage,
+    ) -> Texture {
+        ErrorScope::new(self, "texture creation failed").execute(|| unsafe {
+            Texture {
+                handle: wgpuDeviceCreateTexture(
+                    self.handle,
+                    &WGPUTextureDescriptor {
+                        dimension,
+                        format,
+                        size,
+                        usage: usage.bits as _,
+                        mipLevelCount: 1,
+                        sampleCount: 1,
+                        ..zeroed()
+                    },
+                )
+                .assert_not_null(),
+            }
+        })
+    }
+
     pub fn create_bind_group(
         &self,
         layout: &BindGroupLayout,
@@ -257,6 +283,27 @@ impl DeviceQueue {
     }
 }
 
+impl DeviceQueue {
+    pub fn write_texture(
+        &self,
+        texture: &Texture,
+        data: &[u8],
+        layout: WGPUTexelCopyBufferLayout,
+        size: WGPUExtent3D,
+    ) {
+        unsafe {
+            wgpuQueueWriteTexture(
+                self.handle,
+                &texture.as_copy_info(),
+                data.as_ptr().cast(),
+                data.len(),
+                &layout,
+                &size,
+            );
+        }
+    }
+}
+
 impl Drop for DeviceQueue {
     fn drop(&mut self) {
         unsafe {
@@ -379,6 +426,55 @@ impl Drop for DeviceBuffer {
     }
 }
 
+pub struct Texture {
+    handle: WGPUTexture,
+}
+
+bitflags::bitflags! {
+    pub struct DeviceTextureUsage: WGPUTextureUsage {
+        const STORAGE_BINDING = WGPUTextureUsage_StorageBinding;
+        const COPY_SRC = WGPUTextureUsage_CopySrc;
+        const COPY_DST = WGPUTextureUsage_CopyDst;
+    }
+}
+
+impl Texture {
+    pub fn create_view(&self) -> TextureView {
+        unsafe {
+            TextureView {
+                handle: wgpuTextureCreateView(self.handle, null()).assert_not_null(),
+            }
+        }
+    }
+
+    fn as_copy_info(&self) -> WGPUTexelCopyTextureInfo {
+        WGPUTexelCopyTextureInfo {
+            texture: self.handle,
+            ..unsafe { zeroed() }
+        }
+    }
+}
+
+impl Drop for Texture {
+    fn drop(&mut self) {
+        unsafe {
+            wgpuTextureRelease(self.handle);
+        }
+    }
+}
+
+pub struct TextureView {
+    handle: WGPUTextureView,
+}
+
+impl Drop for TextureView {
+    fn drop(&mut self) {
+        unsafe {
+            wgpuTextureViewRelease(self.handle);
+        }
+    }
+}
+
 pub struct BindGroupLayout {
     handle: WGPUBindGroupLayout,
 }
@@ -395,19 +491,31 @@ impl Drop for BindGroupLayout {
 
 pub struct BindGroupEntry<'a> {
     pub binding: u32,
-    pub buffer: &'a DeviceBuffer,
-    pub size: usize,
+    pub resource: BindingResource<'a>,
+}
+
+pub enum BindingResource<'a> {
+    Buffer {
+        buffer: &'a DeviceBuffer,
+        size: usize,
+    },
+    TextureView(&'a TextureView),
 }
 
 impl<'a> From<&BindGroupEntry<'a>> for WGPUBindGroupEntry {
     fn from(entry: &BindGroupEntry<'a>) -> Self {
+        let (buffer, size, texture_view) = match entry.resource {
+            BindingResource::Buffer { buffer, size } => (buffer.handle, size as _, null_mut()),
+            BindingResource::TextureView(view) => (null_mut(), 0, view.handle),
+        };
+
         WGPUBindGroupEntry {
             binding: entry.binding,
-            buffer: entry.buffer.handle,
+            buffer,
             offset: 0,
-            size: entry.size as _,
+            size,
             sampler: null_mut(),
-            textureView: null_mut(),
+            textureView: texture_view,
             nextInChain: null_mut(),
         }
     }
@@ -454,6 +562,26 @@ impl CommandEncoder {
         }
     }
 
+    pub fn copy_texture_to_buffer(
+        &self,
+        src: &Texture,
+        dst: &DeviceBuffer,
+        layout: WGPUTexelCopyBufferLayout,
+        size: WGPUExtent3D,
+    ) {
+        unsafe {
+            wgpuCommandEncoderCopyTextureToBuffer(
+                self.handle,
+                &src.as_copy_info(),
+                &WGPUTexelCopyBufferInfo {
+                    layout,
+                    buffer: dst.handle,
+                },
+                &size,
+            );
+        }
+    }
+
     pub fn finish(self) -> CommandBuffer {
         unsafe {
             CommandBuffer {
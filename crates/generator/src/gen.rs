@@ -4,17 +4,19 @@ mod fns;
 mod scope;
 mod stmt;
 mod structs;
+mod template;
 mod utils;
 
 pub mod builtins;
 
 use std::rc::Rc;
 
-use ast::types::{DataType, MemoryViewType};
+use ast::types::{DataType, MemoryViewType, TexelFormat, TextureDimension};
 use ast::{
-    AccessMode, AssignmentLhs, AssignmentOp, AssignmentStatement, BuiltinValue, FnAttr, FnDecl,
-    FnIOAttr, FnInput, GlobalVarAttr, GlobalVarDecl, LetDeclStatement, Module, Postfix,
-    PostfixExpr, ScalarType, ShaderStage, Statement, StorageClass, VarExpr, VarQualifier,
+    AccessMode, AssignmentLhs, AssignmentOp, AssignmentStatement, BuiltinValue, DiagnosticControl,
+    FnAttr, FnDecl, FnIOAttr, FnInput, GlobalDecl, GlobalVarAttr, GlobalVarDecl, LetDeclStatement,
+    Module, Postfix, PostfixExpr, ScalarType, Severity, ShaderStage, Statement, StorageClass,
+    StructDecl, StructMember, VarDeclStatement, VarExpr, VarQualifier,
 };
 use rand::prelude::{SliceRandom, StdRng};
 use rand::Rng;
@@ -23,9 +25,14 @@ use rand_distr::{Binomial, Distribution, StandardNormal};
 use crate::gen::scope::Scope;
 use crate::Options;
 
-use self::cx::Context;
+use self::cx::{Context, SelectionFilter};
 use self::structs::StructKind;
 
+/// Number of bind groups that resources can be spread across.
+///
+/// This is the minimum value of `maxBindGroups` guaranteed by WebGPU.
+const MAX_BIND_GROUPS: usize = 4;
+
 #[derive(Default)]
 struct FnState {
     is_loop: bool,
@@ -36,6 +43,31 @@ struct FnState {
     is_non_uniform: bool,
 }
 
+/// A variable whose initial value is copied to the output buffer by each entry point.
+enum InitCheck {
+    /// A global variable (with its reference type), which is captured at the start of the entry
+    /// point.
+    Global(String, DataType),
+    /// A function-scope variable declared without an initializer at the start of the entry point.
+    Uninit(String, DataType),
+}
+
+impl InitCheck {
+    fn member_name(&self) -> String {
+        match self {
+            InitCheck::Global(name, _) => format!("init_{name}"),
+            InitCheck::Uninit(name, _) => name.clone(),
+        }
+    }
+
+    fn data_type(&self) -> &DataType {
+        match self {
+            InitCheck::Global(_, ref_type) => ref_type.dereference(),
+            InitCheck::Uninit(_, data_type) => data_type,
+        }
+    }
+}
+
 pub struct Generator<'a> {
     rng: &'a mut StdRng,
     options: Rc<Options>,
@@ -45,6 +77,8 @@ pub struct Generator<'a> {
     global_scope: Scope,
     scope: Scope,
     current_block: Vec<Statement>,
+    storage_textures: Vec<(String, DataType)>,
+    init_checks: Vec<InitCheck>,
     f32_dist: StandardNormal,
     i32_dist: Binomial,
     u32_dist: Binomial,
@@ -61,6 +95,8 @@ impl<'a> Generator<'a> {
             global_scope: Scope::empty(),
             scope: Scope::empty(),
             current_block: vec![],
+            storage_textures: vec![],
+            init_checks: vec![],
             f32_dist: StandardNormal,
             i32_dist: Binomial::new(i32::MAX as u64 * 2, 0.5)
                 .expect("failed to create binomial distribution"),
@@ -83,7 +119,7 @@ impl<'a> Generator<'a> {
 
         let ub_type_decl =
             self.gen_struct_with("UniformBuffer".to_owned(), StructKind::UniformBuffer);
-        let sb_type_decl =
+        let mut sb_type_decl =
             self.gen_struct_with("StorageBuffer".to_owned(), StructKind::HostShareable);
 
         self.global_scope
@@ -118,12 +154,221 @@ impl<'a> Generator<'a> {
             global_vars.push(self.gen_global_var(name, &mut workgroup_size));
         }
 
+        global_vars.extend(self.gen_workgroup_vars());
+
+        // Bindings 0 and 1 of group 0 are taken by the input and output buffers
+        let mut next_bindings = [0; MAX_BIND_GROUPS];
+        next_bindings[0] = 2;
+
+        if self.options.enable_storage_textures {
+            for i in 0..self.rng.gen_range(1..=3) {
+                let name = format!("s_texture{i}");
+                let binding = self.gen_binding(&mut next_bindings, 0);
+                global_vars.push(self.gen_storage_texture(name, binding));
+            }
+        }
+
+        let mut buffer_structs = vec![];
+        let mut outputs = vec![(
+            "s_output".to_owned(),
+            DataType::Struct(sb_type_decl.clone()),
+        )];
+
+        for i in 1..=self.rng.gen_range(0..=self.options.max_uniform_buffers) {
+            let decl = self.gen_struct_with(format!("UniformBuffer{i}"), StructKind::UniformBuffer);
+            let name = format!("u_input{i}");
+            let data_type = DataType::Struct(decl.clone());
+
+            self.global_scope
+                .insert_readonly(name.clone(), data_type.clone());
+
+            global_vars.push(self.gen_buffer_var(
+                name,
+                data_type,
+                StorageClass::Uniform,
+                None,
+                &mut next_bindings,
+            ));
+
+            buffer_structs.push(decl);
+        }
+
+        let mut input_count = 0;
+        let mut output_count = 0;
+
+        for _ in 0..self.rng.gen_range(0..=self.options.max_storage_buffers) {
+            if self.rng.gen_bool(0.5) {
+                input_count += 1;
+
+                // Read-only storage buffers are filled with random data, so they are restricted to
+                // the same integer types as uniform buffers to avoid NaNs
+                let decl = self.gen_struct_with(
+                    format!("StorageInputBuffer{input_count}"),
+                    StructKind::UniformBuffer,
+                );
+                let name = format!("s_input{input_count}");
+                let data_type = DataType::Struct(decl.clone());
+
+                self.global_scope
+                    .insert_readonly(name.clone(), data_type.clone());
+
+                global_vars.push(self.gen_buffer_var(
+                    name,
+                    data_type,
+                    StorageClass::Storage,
+                    Some(AccessMode::Read),
+                    &mut next_bindings,
+                ));
+
+                buffer_structs.push(decl);
+            } else {
+                output_count += 1;
+
+                let decl = self.gen_struct_with(
+                    format!("StorageBuffer{output_count}"),
+                    StructKind::HostShareable,
+                );
+                let name = format!("s_output{output_count}");
+                let data_type = DataType::Struct(decl.clone());
+
+                global_vars.push(self.gen_buffer_var(
+                    name.clone(),
+                    data_type.clone(),
+                    StorageClass::Storage,
+                    Some(AccessMode::ReadWrite),
+                    &mut next_bindings,
+                ));
+
+                outputs.push((name, data_type));
+                buffer_structs.push(decl);
+            }
+        }
+
+        if self.options.uninit_vars {
+            for i in 0..self.rng.gen_range(1..=3) {
+                let data_type = self
+                    .cx
+                    .types
+                    .select_with_filter(self.rng, SelectionFilter::HostShareable);
+                self.init_checks
+                    .push(InitCheck::Uninit(format!("uninit{i}"), data_type));
+            }
+        }
+
+        if !self.init_checks.is_empty() {
+            // Extend the output struct with a member for each of the checked initial values
+            let mut members = sb_type_decl.members.clone();
+            for check in &self.init_checks {
+                members.push(StructMember::new(
+                    vec![],
+                    check.member_name(),
+                    check.data_type().clone(),
+                ));
+            }
+
+            sb_type_decl = StructDecl::new(sb_type_decl.name.clone(), members);
+            let data_type = DataType::Struct(sb_type_decl.clone());
+
+            global_vars
+                .iter_mut()
+                .find(|it| it.name == "s_output")
+                .unwrap()
+                .data_type = data_type.clone();
+
+            outputs
+                .iter_mut()
+                .find(|(name, _)| name == "s_output")
+                .unwrap()
+                .1 = data_type;
+        }
+
+        let entrypoint_count = self.rng.gen_range(1..=self.options.max_entry_points.max(1));
+        let mut entrypoints = vec![];
+
+        for i in 0..entrypoint_count {
+            let name = match i {
+                0 => "main".to_owned(),
+                i => format!("main_{i}"),
+            };
+
+            entrypoints.push(self.gen_entrypoint_function(
+                name,
+                DataType::Struct(ub_type_decl.clone()),
+                &outputs,
+            ));
+        }
+
+        let Context { types, fns } =
+            std::mem::replace(&mut self.cx, Context::new(self.options.clone()));
+
+        let mut functions = fns.into_fns();
+
+        functions.extend(entrypoints);
+
+        let mut extensions = vec![];
+        for ext in &self.options.extensions {
+            let enable = ast::Extension::from(*ext);
+            if !extensions.contains(&enable) {
+                extensions.push(enable);
+            }
+        }
+
+        let structs = types
+            .into_structs()
+            .into_iter()
+            .chain([ub_type_decl, sb_type_decl])
+            .chain(buffer_structs);
+
+        let decls = structs
+            .map(GlobalDecl::from)
+            .chain(global_vars.into_iter().map(GlobalDecl::from))
+            .chain(functions.into_iter().map(GlobalDecl::from))
+            .collect();
+
+        let diagnostics = self.gen_diagnostic_control().into_iter().collect();
+
+        Module {
+            extensions,
+            requires: vec![],
+            diagnostics,
+            decls,
+            comments: vec![],
+        }
+    }
+
+    /// Picks a random diagnostic filter for a `diagnostic` directive or attribute, if they are
+    /// enabled.
+    fn gen_diagnostic_control(&mut self) -> Option<DiagnosticControl> {
+        if !self.options.diagnostic_filters || self.rng.gen_bool(0.5) {
+            return None;
+        }
+
+        let mut rules = vec!["derivative_uniformity", "chromium.unreachable_code"];
+        if self.options.enable_subgroups() {
+            rules.push("subgroup_uniformity");
+        }
+
+        // Raising a rule to an error could reject shaders that are otherwise valid
+        let severity = [Severity::Warning, Severity::Info, Severity::Off]
+            .choose(self.rng)
+            .unwrap();
+
+        Some(DiagnosticControl::new(
+            *severity,
+            *rules.choose(self.rng).unwrap(),
+        ))
+    }
+
+    /// Generates the workgroup variables that the atomic and subgroup builtins operate on.
+    fn gen_workgroup_vars(&mut self) -> Vec<GlobalVarDecl> {
+        let mut vars = vec![];
+
         let atomic_vars = [
             ("wg_atomic_u32", DataType::Atomic(ScalarType::U32)),
             ("wg_atomic_i32", DataType::Atomic(ScalarType::I32)),
         ];
         for (name, ty) in atomic_vars {
-            global_vars.push(GlobalVarDecl {
+            vars.push(GlobalVarDecl {
                 attrs: vec![],
                 qualifier: Some(VarQualifier {
                     storage_class: StorageClass::WorkGroup,
@@ -150,7 +395,7 @@ impl<'a> Generator<'a> {
                 wg_vars.push(("wg_f16", DataType::Scalar(ScalarType::F16)));
             }
             for (name, ty) in wg_vars {
-                global_vars.push(GlobalVarDecl {
+                vars.push(GlobalVarDecl {
                     attrs: vec![],
                     qualifier: Some(VarQualifier {
                         storage_class: StorageClass::WorkGroup,
@@ -167,51 +412,33 @@ impl<'a> Generator<'a> {
             }
         }
 
-        let entrypoint = self.gen_entrypoint_function(
-            DataType::Struct(ub_type_decl.clone()),
-            DataType::Struct(sb_type_decl.clone()),
-        );
-
-        let Context { types, fns } =
-            std::mem::replace(&mut self.cx, Context::new(self.options.clone()));
-
-        let mut functions = fns.into_fns();
-
-        functions.push(entrypoint);
-
-        let mut extensions = vec![];
-        for ext in &self.options.extensions {
-            let enable = ast::Extension::from(*ext);
-            if !extensions.contains(&enable) {
-                extensions.push(enable);
-            }
-        }
-
-        Module {
-            extensions,
-            structs: {
-                let mut structs = types.into_structs();
-                structs.push(ub_type_decl);
-                structs.push(sb_type_decl);
-                structs
-            },
-            consts: vec![],
-            vars: global_vars,
-            functions,
-        }
+        vars
     }
 
     fn gen_global_var(&mut self, name: String, workgroup_size: &mut u32) -> GlobalVarDecl {
         let mut data_type;
         let mut storage_class;
 
-        data_type = self.cx.types.select(self.rng);
+        // Variables whose initial values are checked must be storable in the output buffer
+        data_type = if self.options.check_init_values() {
+            self.cx
+                .types
+                .select_with_filter(self.rng, SelectionFilter::HostShareable)
+        } else {
+            self.cx.types.select(self.rng)
+        };
 
         if self.rng.gen_bool(0.5) {
             data_type = DataType::Array(Rc::new(data_type), Some(self.rng.gen_range(1..=32)));
         }
 
-        storage_class = if self.rng.gen_bool(0.5) {
+        let workgroup_probability = if self.options.workgroup_zero_init {
+            0.8
+        } else {
+            0.5
+        };
+
+        storage_class = if self.rng.gen_bool(workgroup_probability) {
             StorageClass::WorkGroup
         } else {
             StorageClass::Private
@@ -234,13 +461,20 @@ impl<'a> Generator<'a> {
         let mem_view = MemoryViewType::new(data_type.clone(), storage_class);
         let ref_type = DataType::Ref(mem_view);
 
-        self.global_scope.insert_mutable(name.clone(), ref_type);
+        self.global_scope.insert_mutable(name.clone(), ref_type.clone());
 
-        let initializer = if storage_class == StorageClass::Private && self.rng.gen_bool(0.75) {
+        let initializer = if storage_class == StorageClass::Private
+            && (self.options.private_var_initializers || self.rng.gen_bool(0.75))
+        {
             Some(self.gen_const_expr(&data_type))
         } else {
             None
         };
+
+        if self.options.check_init_values() {
+            self.init_checks
+                .push(InitCheck::Global(name.clone(), ref_type));
+        }
         GlobalVarDecl {
             attrs: vec![],
             qualifier: Some(VarQualifier {
@@ -253,8 +487,92 @@ impl<'a> Generator<'a> {
         }
     }
 
+    /// Picks a binding index in the given group, occasionally leaving gaps between bindings.
+    fn gen_binding(&mut self, next_bindings: &mut [i32; MAX_BIND_GROUPS], group: usize) -> i32 {
+        let binding = next_bindings[group] + self.rng.gen_range(0..=2);
+        next_bindings[group] = binding + 1;
+        binding
+    }
+
+    fn gen_buffer_var(
+        &mut self,
+        name: String,
+        data_type: DataType,
+        storage_class: StorageClass,
+        access_mode: Option<AccessMode>,
+        next_bindings: &mut [i32; MAX_BIND_GROUPS],
+    ) -> GlobalVarDecl {
+        let group = self.rng.gen_range(0..MAX_BIND_GROUPS);
+        let binding = self.gen_binding(next_bindings, group);
+
+        GlobalVarDecl {
+            attrs: vec![
+                GlobalVarAttr::Group(group as i32),
+                GlobalVarAttr::Binding(binding),
+            ],
+            qualifier: Some(VarQualifier {
+                storage_class,
+                access_mode,
+            }),
+            name,
+            data_type,
+            initializer: None,
+        }
+    }
+
+    fn gen_storage_texture(&mut self, name: String, binding: i32) -> GlobalVarDecl {
+        const FORMATS: &[TexelFormat] = &[
+            TexelFormat::Rgba8unorm,
+            TexelFormat::Rgba8uint,
+            TexelFormat::Rgba8sint,
+            TexelFormat::Rgba32uint,
+            TexelFormat::Rgba32sint,
+            TexelFormat::Rgba32float,
+            TexelFormat::R32uint,
+            TexelFormat::R32sint,
+            TexelFormat::R32float,
+        ];
+
+        let dim = *[
+            TextureDimension::D1,
+            TextureDimension::D2,
+            TextureDimension::D3,
+        ]
+        .choose(self.rng)
+        .unwrap();
+
+        let format = *FORMATS.choose(self.rng).unwrap();
+
+        let access_mode = if format.is_readable() && self.rng.gen_bool(0.5) {
+            AccessMode::ReadWrite
+        } else {
+            AccessMode::Write
+        };
+
+        let data_type = DataType::StorageTexture(dim, format, access_mode);
+
+        self.global_scope
+            .insert_readonly(name.clone(), data_type.clone());
+        self.cx.fns.insert_texture(&data_type);
+        self.storage_textures
+            .push((name.clone(), data_type.clone()));
+
+        GlobalVarDecl {
+            attrs: vec![GlobalVarAttr::Group(0), GlobalVarAttr::Binding(binding)],
+            qualifier: None,
+            name,
+            data_type,
+            initializer: None,
+        }
+    }
+
     #[tracing::instrument(skip(self))]
-    fn gen_entrypoint_function(&mut self, in_buf_type: DataType, out_buf_type: DataType) -> FnDecl {
+    fn gen_entrypoint_function(
+        &mut self,
+        name: String,
+        in_buf_type: DataType,
+        outputs: &[(String, DataType)],
+    ) -> FnDecl {
         let prev_is_entrypoint = std::mem::replace(&mut self.fn_state.is_entrypoint, true);
         let mut function_scope = self.global_scope.clone();
         let mut inputs = vec![];
@@ -328,26 +646,77 @@ impl<'a> Generator<'a> {
                     .into(),
                 );
 
-                let out_lhs = AssignmentLhs::name("s_output", out_buf_type.clone());
-                let out_rhs = this.gen_expr(&out_buf_type);
-                this.current_block
-                    .push(AssignmentStatement::new(out_lhs, AssignmentOp::Simple, out_rhs).into());
+                // The primary output buffer is always written, while additional output buffers are
+                // only written by some of the entry points
+                for (i, (name, data_type)) in outputs.iter().enumerate() {
+                    if i > 0 && this.rng.gen_bool(0.5) {
+                        continue;
+                    }
+
+                    let out_lhs = AssignmentLhs::name(name.clone(), data_type.clone());
+                    let out_rhs = this.gen_expr(data_type);
+                    this.current_block.push(
+                        AssignmentStatement::new(out_lhs, AssignmentOp::Simple, out_rhs).into(),
+                    );
+                }
+
+                let (out_name, out_type) = &outputs[0];
+                for check in &this.init_checks {
+                    let rhs = match check {
+                        InitCheck::Global(name, _) => VarExpr::new(format!("init_{name}"))
+                            .into_node(check.data_type().clone()),
+                        InitCheck::Uninit(name, data_type) => {
+                            VarExpr::new(name).into_node(DataType::Ref(MemoryViewType::new(
+                                data_type.clone(),
+                                StorageClass::Function,
+                            )))
+                        }
+                    };
+
+                    let lhs = AssignmentLhs::member(
+                        out_name.clone(),
+                        out_type.clone(),
+                        check.member_name(),
+                    );
+                    this.current_block
+                        .push(AssignmentStatement::new(lhs, AssignmentOp::Simple, rhs).into());
+                }
             });
 
-            std::mem::replace(&mut this.current_block, prev_block)
+            let mut block = std::mem::replace(&mut this.current_block, prev_block);
+
+            // Capture initial values before any of the generated statements can modify them
+            let prologue = this.init_checks.iter().map(|check| match check {
+                InitCheck::Global(name, ref_type) => LetDeclStatement::new(
+                    format!("init_{name}"),
+                    VarExpr::new(name).into_node(ref_type.clone()),
+                )
+                .into(),
+                InitCheck::Uninit(name, data_type) => {
+                    VarDeclStatement::new(name, Some(data_type.clone()), None).into()
+                }
+            });
+
+            block.splice(0..0, prologue);
+            block
         });
 
         self.fn_state.is_entrypoint = prev_is_entrypoint;
 
+        let mut attrs = vec![
+            FnAttr::Stage(ShaderStage::Compute),
+            FnAttr::WorkgroupSize(vec![ast::ExprNode {
+                data_type: DataType::Scalar(ScalarType::U32),
+                expr: ast::Expr::Lit(ast::Lit::U32(1)),
+                meta: ast::NodeMeta::default(),
+            }]),
+        ];
+
+        attrs.extend(self.gen_diagnostic_control().map(FnAttr::Diagnostic));
+
         FnDecl {
-            attrs: vec![
-                FnAttr::Stage(ShaderStage::Compute),
-                FnAttr::WorkgroupSize(vec![ast::ExprNode {
-                    data_type: DataType::Scalar(ScalarType::U32),
-                    expr: ast::Expr::Lit(ast::Lit::U32(1)),
-                }]),
-            ],
-            name: "main".to_owned(),
+            attrs,
+            name,
             inputs,
             output: None,
             body: block,
@@ -417,3 +786,43 @@ impl<'a> Generator<'a> {
         half::f16::from_f32(clamped_f32)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+    use rand::SeedableRng;
+
+    use super::*;
+
+    #[test]
+    fn init_checks_extend_the_output_buffer() {
+        let options = Rc::new(Options::parse_from([
+            "generator",
+            "--uninit-vars",
+            "--workgroup-zero-init",
+        ]));
+
+        for seed in 0..5 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let module = Generator::new(&mut rng, options.clone()).gen_module();
+
+            // The output buffer and its struct declaration both need the members that the initial
+            // values are copied to
+            let output = module.vars().find(|it| it.name == "s_output").unwrap();
+            let DataType::Struct(output_type) = &output.data_type else {
+                panic!("`s_output` is not a struct");
+            };
+
+            let decl = module
+                .structs()
+                .find(|it| it.name == output_type.name)
+                .unwrap();
+
+            assert!(output_type.member_type("uninit0").is_some());
+            assert!(decl.member_type("uninit0").is_some());
+        }
+
+        let failures = crate::selftest::check_seeds(options, 0..2);
+        assert!(failures.is_empty(), "{} programs failed", failures.len());
+    }
+}
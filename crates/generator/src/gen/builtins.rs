@@ -1,7 +1,7 @@
 use std::collections::HashMap;
 use std::rc::Rc;
 
-use ast::{BuiltinFn, DataType, ScalarType};
+use ast::{AccessMode, BuiltinFn, DataType, ScalarType};
 
 use super::cx::Overload;
 use crate::gen::cx::Func;
@@ -261,6 +261,29 @@ pub fn gen_builtins(options: &Options) -> HashMap<DataType, Vec<Rc<Func>>> {
     map
 }
 
+/// Adds overloads for the texture builtins which operate on the given storage texture type.
+pub fn add_texture_builtins(map: &mut HashMap<DataType, Vec<Rc<Func>>>, texture: &DataType) {
+    use BuiltinFn::*;
+
+    let DataType::StorageTexture(_, format, access) = texture else {
+        unreachable!("expected storage texture type, got `{texture}`")
+    };
+
+    let dims_ty = TextureDimensions
+        .return_type(std::iter::once(texture))
+        .unwrap();
+
+    map.add(TextureDimensions, [texture.clone()], dims_ty.clone());
+
+    if *access != AccessMode::Write {
+        map.add(
+            TextureLoad,
+            [texture.clone(), dims_ty],
+            DataType::Vector(4, format.channel_type()),
+        );
+    }
+}
+
 trait HashMapExt {
     fn add(
         &mut self,
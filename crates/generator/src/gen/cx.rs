@@ -196,6 +196,10 @@ impl FnContext {
         }
     }
 
+    pub fn insert_texture(&mut self, texture: &DataType) {
+        builtins::add_texture_builtins(&mut self.map, texture);
+    }
+
     pub fn insert(&mut self, decl: FnDecl) -> Rc<Func> {
         let sig = FnSignature {
             ident: decl.name.clone(),
@@ -3,8 +3,8 @@ use rand::Rng;
 
 use ast::types::{DataType, MemoryViewType, ScalarType};
 use ast::{
-    BinOp, BinOpExpr, Expr, ExprNode, FnCallExpr, FnInput, Lit, Postfix, PostfixExpr, StructDecl,
-    TypeConsExpr, UnOp, UnOpExpr, VarDeclStatement, VarExpr,
+    BinOp, BinOpExpr, Expr, ExprNode, FnCallExpr, FnInput, Lit, NodeMeta, Postfix, PostfixExpr,
+    StructDecl, TypeConsExpr, UnOp, UnOpExpr, VarDeclStatement, VarExpr,
 };
 
 use super::cx::Func;
@@ -34,6 +34,10 @@ impl super::Generator<'_> {
             DataType::Atomic(_) | DataType::AtomicCompareExchangeResult(_) => {
                 panic!("explicit request to generate atomic expression: `{ty}`")
             }
+            // Textures and samplers can only be referenced through their global variables
+            DataType::StorageTexture(..) | DataType::Texture(_) | DataType::Sampler(_) => {
+                return self.gen_var_expr(ty)
+            }
         }
 
         if self.fn_state.expression_depth < 5 {
@@ -115,6 +119,7 @@ impl super::Generator<'_> {
         ExprNode {
             data_type: ty.clone(),
             expr: Expr::Lit(lit),
+            meta: NodeMeta::default(),
         }
     }
 
@@ -140,7 +145,10 @@ impl super::Generator<'_> {
             DataType::Ptr(_)
             | DataType::Ref(_)
             | DataType::Atomic(_)
-            | DataType::AtomicCompareExchangeResult(_) => {
+            | DataType::AtomicCompareExchangeResult(_)
+            | DataType::StorageTexture(..)
+            | DataType::Texture(_)
+            | DataType::Sampler(_) => {
                 unimplemented!("no type constructor for `{ty}`")
             }
         };
@@ -169,7 +177,10 @@ impl super::Generator<'_> {
             DataType::Ptr(_)
             | DataType::Ref(_)
             | DataType::Atomic(_)
-            | DataType::AtomicCompareExchangeResult(_) => {
+            | DataType::AtomicCompareExchangeResult(_)
+            | DataType::StorageTexture(..)
+            | DataType::Texture(_)
+            | DataType::Sampler(_) => {
                 unimplemented!("no type constructor for `{ty}`")
             }
         };
@@ -365,6 +376,10 @@ impl super::Generator<'_> {
             DataType::Ptr(_) => self.gen_pointer_deref(target, expr),
             DataType::Ref(_) => todo!(),
             DataType::Atomic(_) => unreachable!("atomic does not have accessors"),
+            DataType::StorageTexture(..) | DataType::Texture(_) => {
+                unreachable!("texture does not have accessors")
+            }
+            DataType::Sampler(_) => unreachable!("sampler does not have accessors"),
             DataType::AtomicCompareExchangeResult(_) => {
                 let member = if target == &DataType::Scalar(ScalarType::Bool) {
                     "exchanged"
@@ -426,11 +441,15 @@ impl super::Generator<'_> {
     fn gen_lit(&mut self, ty: &DataType) -> Lit {
         tracing::info!("generating lit with {:?}", ty);
 
+        let is_abstract = self.options.abstract_literals && self.rng.gen_bool(0.5);
+
         match ty {
             DataType::Scalar(t) => match t {
                 ScalarType::Bool => Lit::Bool(self.rng.gen()),
+                ScalarType::I32 if is_abstract => Lit::AbstractInt(self.gen_i32().into()),
                 ScalarType::I32 => Lit::I32(self.gen_i32()),
                 ScalarType::U32 => Lit::U32(self.gen_u32()),
+                ScalarType::F32 if is_abstract => Lit::AbstractFloat(self.gen_f32().into()),
                 ScalarType::F32 => Lit::F32(self.gen_f32()),
                 ScalarType::F16 => Lit::F16(self.gen_f16()),
             },
@@ -451,6 +470,9 @@ impl super::Generator<'_> {
             DataType::Ptr(_) => todo!(),
             DataType::Ref(_) => todo!(),
             DataType::Atomic(_) | DataType::AtomicCompareExchangeResult(_) => unreachable!(),
+            DataType::StorageTexture(..) | DataType::Texture(_) | DataType::Sampler(_) => {
+                unreachable!()
+            }
         };
 
         match scalar_ty {
@@ -475,6 +497,9 @@ impl super::Generator<'_> {
             DataType::Ptr(_) => todo!(),
             DataType::Ref(_) => todo!(),
             DataType::Atomic(_) | DataType::AtomicCompareExchangeResult(_) => unreachable!(),
+            DataType::StorageTexture(..) | DataType::Texture(_) | DataType::Sampler(_) => {
+                unreachable!()
+            }
         };
 
         let allowed: &[BinOp] = match scalar_ty {
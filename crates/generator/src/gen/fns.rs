@@ -1,7 +1,7 @@
 use std::mem;
 
 use ast::types::DataType;
-use ast::{FnDecl, FnInput, FnOutput};
+use ast::{FnAttr, FnDecl, FnInput, FnOutput};
 use rand::Rng;
 
 impl super::Generator<'_> {
@@ -24,8 +24,14 @@ impl super::Generator<'_> {
 
         self.fn_state = saved_state;
 
+        let attrs = self
+            .gen_diagnostic_control()
+            .map(FnAttr::Diagnostic)
+            .into_iter()
+            .collect();
+
         FnDecl {
-            attrs: vec![],
+            attrs,
             name,
             inputs: params,
             output: Some(FnOutput {
@@ -3,10 +3,11 @@ use std::mem;
 
 use ast::types::{DataType, MemoryViewType, ScalarType};
 use ast::{
-    AssignmentLhs, AssignmentOp, AssignmentStatement, BinOp, BinOpExpr, ContinuingBlock, Expr,
-    ExprNode, ForLoopHeader, ForLoopInit, ForLoopStatement, ForLoopUpdate, IfStatement,
-    LetDeclStatement, LhsExprNode, Lit, LoopStatement, ReturnStatement, Statement, StorageClass,
-    SwitchCase, SwitchStatement, UnOp, UnOpExpr, VarDeclStatement, VarExpr, WhileStatement,
+    AssignmentLhs, AssignmentOp, AssignmentStatement, BinOp, BinOpExpr, BuiltinFn, ContinuingBlock,
+    Expr, ExprNode, FnCallStatement, ForLoopHeader, ForLoopInit, ForLoopStatement, ForLoopUpdate,
+    IfStatement, LetDeclStatement, LhsExprNode, Lit, LoopStatement, NodeMeta, ReturnStatement,
+    Statement, StorageClass, SwitchCase, SwitchStatement, UnOp, UnOpExpr, VarDeclStatement,
+    VarExpr, WhileStatement,
 };
 use rand::prelude::SliceRandom;
 use rand::Rng;
@@ -28,6 +29,7 @@ enum StatementType {
     While,
     Break,
     Continue,
+    TextureStore,
 }
 
 impl super::Generator<'_> {
@@ -47,6 +49,10 @@ impl super::Generator<'_> {
             allowed.push(StatementType::Assignment);
         }
 
+        if !self.storage_textures.is_empty() {
+            allowed.push(StatementType::TextureStore);
+        }
+
         if self.fn_state.block_depth < self.options.max_block_depth {
             allowed.extend_from_slice(&[
                 // StatementType::Compound,
@@ -71,6 +77,7 @@ impl super::Generator<'_> {
             StatementType::While => 5,
             StatementType::Break => 5,
             StatementType::Continue => 5,
+            StatementType::TextureStore => 5,
         };
 
         match allowed.choose_weighted(self.rng, weights).unwrap() {
@@ -86,6 +93,7 @@ impl super::Generator<'_> {
             StatementType::While => self.gen_while_stmt(),
             StatementType::Break => Statement::Break,
             StatementType::Continue => Statement::Continue,
+            StatementType::TextureStore => self.gen_texture_store_stmt(),
         }
     }
 
@@ -103,6 +111,12 @@ impl super::Generator<'_> {
 
     fn gen_var_stmt(&mut self) -> Statement {
         let ty = self.cx.types.select(self.rng);
+
+        // Variables without an initializer are zero-initialized
+        if self.options.uninit_vars && self.rng.gen_bool(0.5) {
+            return VarDeclStatement::new(self.scope.next_name(), Some(ty), None).into();
+        }
+
         VarDeclStatement::new(self.scope.next_name(), None, Some(self.gen_expr(&ty))).into()
     }
 
@@ -141,6 +155,27 @@ impl super::Generator<'_> {
         AssignmentStatement::new(lhs.into(), AssignmentOp::Simple, rhs)
     }
 
+    fn gen_texture_store_stmt(&mut self) -> Statement {
+        let (name, data_type) = self.storage_textures.choose(self.rng).cloned().unwrap();
+
+        let DataType::StorageTexture(dim, format, _) = data_type else {
+            unreachable!()
+        };
+
+        let coords_type = match dim.coord_size() {
+            1 => DataType::Scalar(ScalarType::U32),
+            n => DataType::Vector(n, ScalarType::U32),
+        };
+
+        let args = vec![
+            VarExpr::new(name).into_node(data_type),
+            self.gen_expr(&coords_type),
+            self.gen_expr(&DataType::Vector(4, format.channel_type())),
+        ];
+
+        FnCallStatement::new(BuiltinFn::TextureStore.as_ref().to_owned(), args).into()
+    }
+
     // fn gen_compound_stmt(&mut self) -> Statement {
     //     let max_count = self
     //         .rng
@@ -268,6 +303,7 @@ impl super::Generator<'_> {
                     selector: ExprNode {
                         data_type: DataType::Scalar(ScalarType::I32),
                         expr: Expr::Lit(Lit::I32(value)),
+                        meta: NodeMeta::default(),
                     },
                     body,
                 }
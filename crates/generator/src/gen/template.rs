@@ -0,0 +1,444 @@
+use std::mem;
+
+use ast::types::{DataType, MemoryViewType};
+use ast::visit::{self, VisitorMut};
+use ast::{
+    AccessMode, AssignmentLhs, ContinuingBlock, Else, Expr, ExprNode, FnAttr, FnDecl, ForLoopInit,
+    ForLoopUpdate, GlobalDecl, GlobalVarDecl, Lit, Module, Statement, StorageClass, VarQualifier,
+};
+
+use super::cx::Context;
+use super::utils;
+
+impl super::Generator<'_> {
+    /// Fills the holes of a parsed template module with generated code.
+    ///
+    /// Expression holes (`/*@gen expr:T*/`) are replaced by a generated expression of type `T`,
+    /// and statement holes (`/*@gen stmts:N*/`) by a block of up to `N` generated statements.
+    /// Generated code may use any global or local variable that is in scope at the hole. Helper
+    /// functions produced while filling the holes are added to the resulting module.
+    pub fn fill_template(&mut self, mut module: Module) -> Module {
+        // The atomic and subgroup builtins expect the generator's own workgroup variables
+        for var in self.gen_workgroup_vars() {
+            if !module.vars().any(|it| it.name == var.name) {
+                module.insert_before_functions([var]);
+            }
+        }
+
+        for var in module.vars() {
+            self.insert_template_global(var);
+        }
+
+        for decl in module.consts() {
+            self.global_scope
+                .insert_readonly(decl.name.clone(), decl.data_type.clone());
+        }
+
+        module.decls = mem::take(&mut module.decls)
+            .into_iter()
+            .map(|decl| match decl {
+                GlobalDecl::Fn(decl) => GlobalDecl::Fn(self.fill_template_fn(decl)),
+                decl => decl,
+            })
+            .collect();
+
+        let Context { fns, .. } = mem::replace(&mut self.cx, Context::new(self.options.clone()));
+
+        // Helper functions go ahead of the template's functions, which may call them
+        module.insert_before_functions(fns.into_fns());
+        module
+    }
+
+    fn insert_template_global(&mut self, var: &GlobalVarDecl) {
+        let name = var.name.clone();
+        let data_type = var.data_type.clone();
+
+        match &var.qualifier {
+            Some(VarQualifier {
+                storage_class: StorageClass::Uniform,
+                ..
+            })
+            | Some(VarQualifier {
+                storage_class: StorageClass::Storage,
+                access_mode: None | Some(AccessMode::Read),
+            }) => self.global_scope.insert_readonly(name, data_type),
+            // Writable storage buffers hold the results of the template, so they are left for
+            // the template itself to write
+            Some(VarQualifier {
+                storage_class: StorageClass::Storage,
+                ..
+            }) => {}
+            Some(VarQualifier { storage_class, .. }) => {
+                let ref_type = DataType::Ref(MemoryViewType::new(data_type, *storage_class));
+                if let DataType::Atomic(_) = var.data_type {
+                    self.global_scope
+                        .insert_unassignable_reference(name, ref_type);
+                } else {
+                    self.global_scope.insert_mutable(name, ref_type);
+                }
+            }
+            None => {
+                if let DataType::StorageTexture(..) = data_type {
+                    self.global_scope
+                        .insert_readonly(name.clone(), data_type.clone());
+                    self.cx.fns.insert_texture(&data_type);
+                    self.storage_textures.push((name, data_type));
+                }
+            }
+        }
+    }
+
+    fn fill_template_fn(&mut self, mut decl: FnDecl) -> FnDecl {
+        let saved_state = mem::take(&mut self.fn_state);
+        let saved_return_type = mem::replace(
+            &mut self.return_type,
+            decl.output.as_ref().map(|output| output.data_type.clone()),
+        );
+
+        self.fn_state.is_entrypoint = decl
+            .attrs
+            .iter()
+            .any(|attr| matches!(attr, FnAttr::Stage(_)));
+
+        let mut function_scope = self.global_scope.clone();
+
+        for param in &decl.inputs {
+            function_scope.insert_readonly(param.name.clone(), param.data_type.clone());
+        }
+
+        let body = mem::take(&mut decl.body);
+        let (_, body) = self.with_scope(function_scope, |this| this.fill_template_stmts(body));
+
+        decl.body = body;
+
+        self.fn_state = saved_state;
+        self.return_type = saved_return_type;
+
+        decl
+    }
+
+    /// Fills a nested template block, whose declarations are not visible after the block.
+    fn fill_template_block(&mut self, block: Vec<Statement>) -> Vec<Statement> {
+        self.with_scope(self.scope.clone(), |this| this.fill_template_stmts(block))
+            .1
+    }
+
+    /// Fills a nested template block which is executed non-uniformly.
+    fn fill_template_branch(&mut self, block: Vec<Statement>) -> Vec<Statement> {
+        self.with_non_uniform(|this| this.fill_template_block(block))
+    }
+
+    /// Fills a template loop body, in which generated code may also break or continue.
+    fn fill_template_loop_body(&mut self, block: Vec<Statement>) -> Vec<Statement> {
+        let is_loop = mem::replace(&mut self.fn_state.is_loop, true);
+        let block = self.fill_template_branch(block);
+        self.fn_state.is_loop = is_loop;
+        block
+    }
+
+    /// Fills a sequence of template statements in the current scope.
+    fn fill_template_stmts(&mut self, block: Vec<Statement>) -> Vec<Statement> {
+        let mut filled = vec![];
+
+        let mut stmts = block.into_iter().peekable();
+
+        while let Some(stmt) = stmts.next() {
+            if let Statement::FnCall(call) = &stmt {
+                if call.ident == parser::STMTS_HOLE_FN {
+                    let count = match call.args[0].expr {
+                        Expr::Lit(Lit::U32(count)) => count,
+                        _ => unreachable!("statement hole must have a count"),
+                    };
+
+                    // Generated declarations stay in scope for the rest of the template block
+                    let (scope, mut block) = self.gen_stmt_block(count);
+                    self.scope = scope;
+
+                    // Don't let the generated code skip over the rest of the template
+                    if stmts.peek().is_some() && utils::is_terminal_stmt(block.last()) {
+                        block.pop();
+                    }
+
+                    filled.extend(block);
+                    continue;
+                }
+            }
+
+            let stmt = self.fill_template_stmt(stmt);
+
+            if let Statement::LetDecl(stmt) = &stmt {
+                self.scope
+                    .insert_readonly(stmt.ident.clone(), stmt.inferred_type().clone());
+            } else if let Statement::VarDecl(stmt) = &stmt {
+                let mem_view =
+                    MemoryViewType::new(stmt.inferred_type().clone(), StorageClass::Function);
+                self.scope
+                    .insert_mutable(stmt.ident.clone(), DataType::Ref(mem_view));
+            }
+
+            filled.push(stmt);
+        }
+
+        filled
+    }
+
+    fn fill_template_stmt(&mut self, stmt: Statement) -> Statement {
+        match stmt {
+            Statement::LetDecl(mut stmt) => {
+                self.fill_template_expr(&mut stmt.initializer);
+                stmt.into()
+            }
+            Statement::VarDecl(mut stmt) => {
+                if let Some(initializer) = &mut stmt.initializer {
+                    self.fill_template_expr(initializer);
+                }
+                stmt.into()
+            }
+            Statement::Assignment(mut stmt) => {
+                self.fill_template_lhs(&mut stmt.lhs);
+                self.fill_template_expr(&mut stmt.rhs);
+                stmt.into()
+            }
+            Statement::Increment(mut stmt) => {
+                self.fill_template_lhs(&mut stmt.lhs);
+                stmt.into()
+            }
+            Statement::Decrement(mut stmt) => {
+                self.fill_template_lhs(&mut stmt.lhs);
+                stmt.into()
+            }
+            Statement::Compound(mut stmt) => {
+                stmt.body = self.fill_template_block(stmt.body);
+                stmt.into()
+            }
+            Statement::If(mut stmt) => {
+                self.fill_template_expr(&mut stmt.condition);
+                stmt.body = self.fill_template_branch(stmt.body);
+                stmt.else_ = stmt
+                    .else_
+                    .map(|else_| Box::new(self.fill_template_else(*else_)));
+                stmt.into()
+            }
+            Statement::Return(mut stmt) => {
+                if let Some(value) = &mut stmt.value {
+                    self.fill_template_expr(value);
+                }
+                stmt.into()
+            }
+            Statement::Loop(mut stmt) => {
+                // Declarations in the loop body are visible in the continuing block
+                let (_, stmt) = self.with_scope(self.scope.clone(), |this| {
+                    let is_loop = mem::replace(&mut this.fn_state.is_loop, true);
+                    let body = mem::take(&mut stmt.body);
+                    stmt.body = this.with_non_uniform(|this| this.fill_template_stmts(body));
+                    this.fn_state.is_loop = is_loop;
+
+                    stmt.continuing = stmt.continuing.map(|continuing| {
+                        this.with_non_uniform(|this| this.fill_template_continuing(continuing))
+                    });
+
+                    stmt
+                });
+                stmt.into()
+            }
+            Statement::While(mut stmt) => {
+                self.fill_template_expr(&mut stmt.condition);
+                stmt.body = self.fill_template_loop_body(stmt.body);
+                stmt.into()
+            }
+            Statement::Switch(mut stmt) => {
+                self.fill_template_expr(&mut stmt.selector);
+                for case in &mut stmt.cases {
+                    case.body = self.fill_template_branch(mem::take(&mut case.body));
+                }
+                stmt.default = self.fill_template_branch(stmt.default);
+                stmt.into()
+            }
+            Statement::ForLoop(mut stmt) => {
+                // Variables declared in the loop header are only visible within the loop
+                let (_, stmt) = self.with_scope(self.scope.clone(), |this| {
+                    match &mut stmt.header.init {
+                        Some(ForLoopInit::VarDecl(init)) => {
+                            if let Some(initializer) = &mut init.initializer {
+                                this.fill_template_expr(initializer);
+                            }
+                            let mem_view = MemoryViewType::new(
+                                init.inferred_type().clone(),
+                                StorageClass::Function,
+                            );
+                            this.scope
+                                .insert_mutable(init.ident.clone(), DataType::Ref(mem_view));
+                        }
+                        Some(ForLoopInit::LetDecl(init)) => {
+                            this.fill_template_expr(&mut init.initializer);
+                            this.scope
+                                .insert_readonly(init.ident.clone(), init.inferred_type().clone());
+                        }
+                        Some(ForLoopInit::Assignment(init)) => {
+                            this.fill_template_lhs(&mut init.lhs);
+                            this.fill_template_expr(&mut init.rhs);
+                        }
+                        Some(ForLoopInit::Increment(init)) => this.fill_template_lhs(&mut init.lhs),
+                        Some(ForLoopInit::Decrement(init)) => this.fill_template_lhs(&mut init.lhs),
+                        Some(ForLoopInit::Call(init)) => {
+                            for arg in &mut init.args {
+                                this.fill_template_expr(arg);
+                            }
+                        }
+                        None => {}
+                    }
+
+                    if let Some(condition) = &mut stmt.header.condition {
+                        this.fill_template_expr(condition);
+                    }
+
+                    match &mut stmt.header.update {
+                        Some(ForLoopUpdate::Assignment(update)) => {
+                            this.fill_template_lhs(&mut update.lhs);
+                            this.fill_template_expr(&mut update.rhs);
+                        }
+                        Some(ForLoopUpdate::Increment(update)) => {
+                            this.fill_template_lhs(&mut update.lhs)
+                        }
+                        Some(ForLoopUpdate::Decrement(update)) => {
+                            this.fill_template_lhs(&mut update.lhs)
+                        }
+                        Some(ForLoopUpdate::Call(update)) => {
+                            for arg in &mut update.args {
+                                this.fill_template_expr(arg);
+                            }
+                        }
+                        None => {}
+                    }
+
+                    stmt.body = this.fill_template_loop_body(mem::take(&mut stmt.body));
+                    stmt
+                });
+                stmt.into()
+            }
+            Statement::FnCall(mut stmt) => {
+                for arg in &mut stmt.args {
+                    self.fill_template_expr(arg);
+                }
+                stmt.into()
+            }
+            stmt @ (Statement::Break | Statement::Continue | Statement::Fallthrough) => stmt,
+        }
+    }
+
+    fn fill_template_else(&mut self, else_: Else) -> Else {
+        match else_ {
+            Else::If(stmt) => match self.fill_template_stmt(stmt.into()) {
+                Statement::If(stmt) => Else::If(stmt),
+                _ => unreachable!(),
+            },
+            Else::Else(block) => Else::Else(self.fill_template_branch(block)),
+        }
+    }
+
+    fn fill_template_continuing(&mut self, mut continuing: ContinuingBlock) -> ContinuingBlock {
+        let is_continuing = mem::replace(&mut self.fn_state.is_continuing, true);
+        let is_loop = mem::replace(&mut self.fn_state.is_loop, false);
+
+        let (_, continuing) = self.with_scope(self.scope.clone(), |this| {
+            continuing.stmts = this.fill_template_stmts(continuing.stmts);
+            if let Some(break_if) = &mut continuing.break_if {
+                this.fill_template_expr(break_if);
+            }
+            continuing
+        });
+
+        self.fn_state.is_continuing = is_continuing;
+        self.fn_state.is_loop = is_loop;
+
+        continuing
+    }
+
+    fn fill_template_lhs(&mut self, lhs: &mut AssignmentLhs) {
+        HoleFiller(self).visit_assignment_lhs_mut(lhs);
+    }
+
+    fn fill_template_expr(&mut self, node: &mut ExprNode) {
+        HoleFiller(self).visit_expr_mut(node);
+    }
+}
+
+/// Replaces the expression holes within a single expression tree.
+struct HoleFiller<'g, 'a>(&'g mut super::Generator<'a>);
+
+impl VisitorMut for HoleFiller<'_, '_> {
+    fn visit_expr_mut(&mut self, node: &mut ExprNode) {
+        if matches!(&node.expr, Expr::FnCall(call) if call.ident == parser::EXPR_HOLE_FN) {
+            *node = self.0.gen_expr(&node.data_type.clone());
+        } else {
+            visit::walk_expr_mut(self, node);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    use clap::Parser;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use crate::gen::Generator;
+    use crate::Options;
+
+    const TEMPLATE: &str = r"
+struct S {
+    a: i32,
+    b: vec2<f32>,
+}
+
+@group(0) @binding(0)
+var<storage, read_write> output: S;
+
+var<private> p: u32;
+
+fn helper(s: S) -> f32 {
+    /*@gen stmts:2*/
+    return s.b.x + /*@gen expr:f32*/;
+}
+
+@compute @workgroup_size(1)
+fn main() {
+    let s = /*@gen expr:S*/;
+    var total = 0u;
+    for (var i = 0u; i < 4u; i++) {
+        total += /*@gen expr:u32*/;
+        /*@gen stmts:3*/
+    }
+    output = S(i32(total), vec2<f32>(helper(s)));
+}
+";
+
+    #[test]
+    fn filled_templates_are_valid() {
+        let options = Rc::new(Options::parse_from(["generator"]));
+
+        for seed in 0..5 {
+            let template = parser::try_parse(TEMPLATE).unwrap();
+            let module = Generator::new(&mut StdRng::seed_from_u64(seed), options.clone())
+                .fill_template(template);
+
+            let mut source = String::new();
+            ast::writer::Writer::default()
+                .write_module(&mut source, &module)
+                .unwrap();
+
+            assert!(!source.contains(parser::EXPR_HOLE_FN), "{source}");
+            assert!(!source.contains(parser::STMTS_HOLE_FN), "{source}");
+
+            if let Err(errors) = typecheck::check(&module) {
+                panic!("seed {seed} does not type check: {errors:?}\n{source}");
+            }
+
+            if let Err(message) = crate::selftest::round_trip(&module) {
+                panic!("seed {seed} does not round trip: {message}");
+            }
+        }
+    }
+}
@@ -1,4 +1,5 @@
 mod gen;
+pub mod selftest;
 
 use std::collections::HashMap;
 use std::fs::File;
@@ -7,7 +8,8 @@ use std::path::Path;
 use std::rc::Rc;
 use std::str::FromStr;
 
-use ast::{BuiltinFn, StorageClass, VarQualifier};
+use ast::types::{DataType, TexelFormat, TextureDimension};
+use ast::{AccessMode, BuiltinFn, Module, StorageClass, VarQualifier};
 use clap::Parser;
 use eyre::{bail, eyre};
 
@@ -15,6 +17,7 @@ pub use gen::{builtins, Generator};
 use rand::prelude::StdRng;
 use rand::rngs::OsRng;
 use rand::{Rng, SeedableRng};
+use serde::Deserialize;
 use tracing_subscriber::fmt::format::FmtSpan;
 use tracing_subscriber::EnvFilter;
 
@@ -33,6 +36,23 @@ impl From<GeneratorExtension> for ast::Extension {
     }
 }
 
+/// Format in which a shader is written out.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum Format {
+    /// WGSL source code.
+    Wgsl,
+    /// The serialized ast as JSON.
+    Json,
+}
+
+/// Reads a module from its serialized ast in the [`Format::Json`] format.
+pub fn parse_json(source: &str) -> serde_json::Result<Module> {
+    // Reconditioned expressions are nested deeper than serde_json allows by default
+    let mut deserializer = serde_json::Deserializer::from_str(source);
+    deserializer.disable_recursion_limit();
+    Module::deserialize(&mut deserializer)
+}
+
 #[derive(Clone, Copy, PartialEq, Eq)]
 pub enum Preset {
     /// Preset for crash-testing Tint.
@@ -56,10 +76,19 @@ pub struct Options {
     #[clap(action)]
     pub seed: Option<u64>,
 
+    /// Path to a WGSL template whose `/*@gen expr:T*/` and `/*@gen stmts:N*/` holes are filled in,
+    /// instead of generating a complete shader
+    #[clap(long, action)]
+    pub template: Option<String>,
+
     /// Print ast instead of WGSL code
     #[clap(short, long, action)]
     pub debug: bool,
 
+    /// Format in which to write the generated shader
+    #[clap(long, action, value_enum, default_value = "wgsl")]
+    pub format: Format,
+
     /// Enable built-in functions that are disabled by default
     #[clap(long = "enable-fn", action)]
     pub enabled_fns: Vec<BuiltinFn>,
@@ -68,6 +97,30 @@ pub struct Options {
     #[clap(long, action)]
     pub enable_pointers: bool,
 
+    /// Whether to enable generating storage textures.
+    #[clap(long, action)]
+    pub enable_storage_textures: bool,
+
+    /// Always generate const-expression initializers for private global variables.
+    #[clap(long, action)]
+    pub private_var_initializers: bool,
+
+    /// Prefer placing global variables in workgroup storage, where they are zero-initialized.
+    #[clap(long, action)]
+    pub workgroup_zero_init: bool,
+
+    /// Generate function-scope variable declarations without initializers.
+    #[clap(long, action)]
+    pub uninit_vars: bool,
+
+    /// Write some i32 and f32 literals without a suffix, as abstract literals.
+    #[clap(long, action)]
+    pub abstract_literals: bool,
+
+    /// Randomly add `@diagnostic` filters to functions and a `diagnostic` directive to the module.
+    #[clap(long, action)]
+    pub diagnostic_filters: bool,
+
     /// Skips the static pointer aliasing checks.
     ///
     /// This is only useful if reconditioning and pointer support is enabled.
@@ -106,6 +159,18 @@ pub struct Options {
     #[clap(long, action, default_value = "5")]
     pub max_fns: u32,
 
+    /// Maximum number of uniform buffers to generate in addition to the main input buffer
+    #[clap(long, action, default_value = "2")]
+    pub max_uniform_buffers: u32,
+
+    /// Maximum number of storage buffers to generate in addition to the main output buffer
+    #[clap(long, action, default_value = "3")]
+    pub max_storage_buffers: u32,
+
+    /// Maximum number of compute entry points to generate
+    #[clap(long, action, default_value = "3")]
+    pub max_entry_points: u32,
+
     /// Minimum number of structs to generate (excluding input and output)
     #[clap(long, action, default_value = "1")]
     pub min_structs: u32,
@@ -145,6 +210,9 @@ pub struct Options {
     /// Path to output file (use `-` for stdout)
     #[clap(short, long, action, default_value = "-")]
     pub output: String,
+
+    #[clap(flatten)]
+    pub style: ast::writer::Options,
 }
 
 impl Options {
@@ -154,6 +222,11 @@ impl Options {
     pub fn enable_subgroups(&self) -> bool {
         self.extensions.contains(&GeneratorExtension::Subgroups)
     }
+
+    /// Whether the initial values of variables should be copied to the output buffer.
+    pub fn check_init_values(&self) -> bool {
+        self.private_var_initializers || self.workgroup_zero_init || self.uninit_vars
+    }
 }
 
 pub fn run(mut options: Options) -> eyre::Result<()> {
@@ -196,7 +269,22 @@ pub fn run(mut options: Options) -> eyre::Result<()> {
     tracing::info!("generating shader from seed: {}", seed);
 
     let mut rng = StdRng::seed_from_u64(seed);
-    let mut shader = Generator::new(&mut rng, options.clone()).gen_module();
+    let mut shader = if let Some(template) = &options.template {
+        let source = std::fs::read_to_string(template)?;
+        let module = parser::try_parse(&source).map_err(|diagnostics| {
+            eyre!(
+                "invalid template {template}:\n{}",
+                parser::format_diagnostics(&diagnostics)
+            )
+        })?;
+        Generator::new(&mut rng, options.clone()).fill_template(module)
+    } else {
+        Generator::new(&mut rng, options.clone()).gen_module()
+    };
+
+    if cfg!(debug_assertions) {
+        typecheck_shader(&shader, "generation")?;
+    }
 
     if options.recondition {
         if options.enable_pointers
@@ -212,6 +300,10 @@ pub fn run(mut options: Options) -> eyre::Result<()> {
                 only_loops: options.preset == Some(Preset::Tint),
             },
         );
+
+        if cfg!(debug_assertions) {
+            typecheck_shader(&shader, "reconditioning")?;
+        }
     }
 
     let mut output: Box<dyn io::Write> = if options.output == "-" {
@@ -223,25 +315,36 @@ pub fn run(mut options: Options) -> eyre::Result<()> {
         Box::new(BufWriter::new(File::create(&options.output)?))
     };
 
-    if !options.debug {
+    if !options.debug && options.format == Format::Wgsl {
         let mut init_data = HashMap::new();
 
-        for var in &shader.vars {
-            if let Some(VarQualifier { storage_class, .. }) = &var.qualifier {
-                if *storage_class != StorageClass::Uniform {
-                    continue;
+        for var in shader.vars() {
+            let data: Vec<u8> = match (&var.qualifier, &var.data_type) {
+                (
+                    Some(VarQualifier {
+                        storage_class: StorageClass::Uniform,
+                        ..
+                    })
+                    | Some(VarQualifier {
+                        storage_class: StorageClass::Storage,
+                        access_mode: Some(AccessMode::Read),
+                    }),
+                    data_type,
+                ) => {
+                    let type_desc = common::Type::try_from(data_type).map_err(|e| eyre!(e))?;
+                    let size = type_desc.buffer_size();
+                    (0..size).map(|_| rng.gen()).collect()
                 }
+                (None, DataType::StorageTexture(dim, format, _)) => {
+                    gen_texel_data(&mut rng, *dim, *format)
+                }
+                _ => continue,
+            };
 
-                let type_desc = common::Type::try_from(&var.data_type).map_err(|e| eyre!(e))?;
-
-                let group = var.group_index().unwrap();
-                let binding = var.binding_index().unwrap();
-
-                let size = type_desc.buffer_size();
-                let data: Vec<u8> = (0..size).map(|_| rng.gen()).collect();
+            let group = var.group_index().unwrap();
+            let binding = var.binding_index().unwrap();
 
-                init_data.insert(format!("{group}:{binding}"), data);
-            }
+            init_data.insert(format!("{group}:{binding}"), data);
         }
 
         let init_data = serde_json::to_string(&init_data)?;
@@ -253,6 +356,9 @@ pub fn run(mut options: Options) -> eyre::Result<()> {
 
     if options.debug {
         writeln!(output, "{shader:#?}")?;
+    } else if options.format == Format::Json {
+        serde_json::to_writer_pretty(&mut output, &shader)?;
+        writeln!(output)?;
     } else {
         struct Output<'a>(&'a mut dyn std::io::Write);
 
@@ -263,8 +369,35 @@ pub fn run(mut options: Options) -> eyre::Result<()> {
             }
         }
 
-        ast::writer::Writer::default().write_module(&mut Output(&mut output), &shader)?;
+        ast::writer::Writer::new(options.style.clone())
+            .write_module(&mut Output(&mut output), &shader)?;
+    }
+
+    Ok(())
+}
+
+/// Checks that `shader` is well typed after the given stage, to catch bugs in the generator and
+/// reconditioner before the shader reaches a compiler.
+fn typecheck_shader(shader: &ast::Module, stage: &str) -> eyre::Result<()> {
+    if let Err(errors) = typecheck::check(shader) {
+        for error in &errors {
+            tracing::error!("{error}");
+        }
+
+        bail!("shader has {} type errors after {stage}", errors.len());
     }
 
     Ok(())
 }
+
+fn gen_texel_data(rng: &mut impl Rng, dim: TextureDimension, format: TexelFormat) -> Vec<u8> {
+    let size = common::Type::texels(dim, format).buffer_size();
+    match format {
+        // Random bit patterns could produce NaNs and infinities, which aren't guaranteed to be
+        // preserved by loads and stores
+        TexelFormat::Rgba32float | TexelFormat::R32float => (0..size / 4)
+            .flat_map(|_| rng.gen_range(-1000.0f32..1000.0).to_le_bytes())
+            .collect(),
+        _ => (0..size).map(|_| rng.gen()).collect(),
+    }
+}
@@ -0,0 +1,385 @@
+//! Round-trip checks of the generator against the writer and parser.
+//!
+//! Every generated program is written out and parsed back, and the parsed module must be
+//! structurally equal to the one that was written. The same check is repeated on the output of the
+//! concretizer and the reconditioner, so that programs they produce can always be printed too.
+
+use std::fmt::Display;
+use std::panic::{self, AssertUnwindSafe};
+use std::rc::Rc;
+
+use ast::visit::{Visitor, VisitorMut};
+use ast::writer::Writer;
+use ast::{Expr, ExprNode, LhsExprNode, Lit, Module, Statement, UnOp, UnOpExpr};
+use clap::Parser;
+use eyre::bail;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+use crate::Generator;
+
+#[derive(Parser)]
+pub struct Options {
+    /// Number of programs to check
+    #[clap(short = 'n', long, action, default_value = "100")]
+    pub count: u64,
+
+    /// Options for the generator. Programs are generated from consecutive seeds, starting from the
+    /// given seed or 0.
+    #[clap(flatten)]
+    pub generator: crate::Options,
+}
+
+/// The step of the round trip at which a program was first changed by printing and parsing it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Stage {
+    Generate,
+    Concretize,
+    Recondition,
+}
+
+impl Display for Stage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Stage::Generate => write!(f, "generation"),
+            Stage::Concretize => write!(f, "concretization"),
+            Stage::Recondition => write!(f, "reconditioning"),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Failure {
+    pub seed: u64,
+    pub stage: Stage,
+    pub message: String,
+    /// The minimized program, as it was generated.
+    pub source: String,
+}
+
+impl Display for Failure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "seed {} failed after {}: {}",
+            self.seed, self.stage, self.message
+        )?;
+        write!(f, "{}", self.source)
+    }
+}
+
+pub fn run(options: Options) -> eyre::Result<()> {
+    let first = options.generator.seed.unwrap_or(0);
+    let failures = check_seeds(Rc::new(options.generator), first..first + options.count);
+
+    for failure in &failures {
+        eprintln!("{failure}");
+    }
+
+    if !failures.is_empty() {
+        bail!("{} of {} programs failed", failures.len(), options.count);
+    }
+
+    eprintln!("{} programs passed", options.count);
+
+    Ok(())
+}
+
+/// Generates a program from each seed and checks that it round trips, returning the failures
+/// with their programs minimized.
+pub fn check_seeds(
+    options: Rc<crate::Options>,
+    seeds: impl IntoIterator<Item = u64>,
+) -> Vec<Failure> {
+    seeds
+        .into_iter()
+        .filter_map(|seed| {
+            let module =
+                Generator::new(&mut StdRng::seed_from_u64(seed), options.clone()).gen_module();
+
+            let (stage, _) = check_module(copy_module(&module).unwrap()).err()?;
+            let module = minimize(module, stage);
+            let (stage, message) = check_module(copy_module(&module).unwrap()).unwrap_err();
+
+            Some(Failure {
+                seed,
+                stage,
+                message,
+                source: write_module(&module),
+            })
+        })
+        .collect()
+}
+
+/// Checks that a module round trips, and that it still does after concretizing and
+/// reconditioning it.
+pub fn check_module(module: Module) -> Result<(), (Stage, String)> {
+    check_until(module, Stage::Recondition)
+}
+
+/// Checks the steps of the round trip up to and including `last`.
+fn check_until(module: Module, last: Stage) -> Result<(), (Stage, String)> {
+    round_trip(&module).map_err(|e| (Stage::Generate, e))?;
+    if last == Stage::Generate {
+        return Ok(());
+    }
+
+    let module =
+        catch_panic(|| concretizer::concretize(module)).map_err(|e| (Stage::Concretize, e))?;
+    round_trip(&module).map_err(|e| (Stage::Concretize, e))?;
+    if last == Stage::Concretize {
+        return Ok(());
+    }
+
+    let module =
+        catch_panic(|| reconditioner::recondition(module)).map_err(|e| (Stage::Recondition, e))?;
+    round_trip(&module).map_err(|e| (Stage::Recondition, e))
+}
+
+/// Checks that a module is parsed back into the same module after writing it out.
+pub(crate) fn round_trip(module: &Module) -> Result<(), String> {
+    let source = write_module(module);
+    let mut parsed = parser::try_parse(&source).map_err(|diagnostics| {
+        format!(
+            "output could not be parsed:\n{}",
+            parser::format_diagnostics(&diagnostics)
+        )
+    })?;
+
+    let mut expected = copy_module(module).unwrap();
+    Normalizer.visit_module_mut(&mut expected);
+    Normalizer.visit_module_mut(&mut parsed);
+
+    if parsed != expected {
+        let decl = expected
+            .decls
+            .iter()
+            .zip(&parsed.decls)
+            .find(|(a, b)| a != b)
+            .map_or("the module", |(decl, _)| decl.name());
+
+        return Err(format!("parsed `{decl}` differs from the one written"));
+    }
+
+    Ok(())
+}
+
+/// Rewrites the parts of a module that are printed the same way but represented differently by
+/// the generator and the parser.
+///
+/// The generator gives variables the type of their value, while the parser gives them a reference
+/// type, which is equivalent once the load rule is applied. Negated literals are printed as
+/// negative literals, which is how the parser reads them back.
+struct Normalizer;
+
+impl VisitorMut for Normalizer {
+    fn visit_lhs_expr_mut(&mut self, node: &mut LhsExprNode) {
+        ast::visit::walk_lhs_expr_mut(self, node);
+        node.data_type = node.data_type.dereference().clone();
+    }
+
+    fn visit_expr_mut(&mut self, node: &mut ExprNode) {
+        ast::visit::walk_expr_mut(self, node);
+
+        node.data_type = node.data_type.dereference().clone();
+
+        if let Expr::UnOp(UnOpExpr {
+            op: UnOp::Neg,
+            inner,
+        }) = &node.expr
+        {
+            let lit = match inner.expr {
+                Expr::Lit(Lit::I32(v)) if v != i32::MIN => Some(Lit::I32(-v)),
+                Expr::Lit(Lit::F32(v)) => Some(Lit::F32(-v)),
+                Expr::Lit(Lit::F16(v)) => Some(Lit::F16(-v)),
+                Expr::Lit(Lit::AbstractInt(v)) => v.checked_neg().map(Lit::AbstractInt),
+                Expr::Lit(Lit::AbstractFloat(v)) => Some(Lit::AbstractFloat(-v)),
+                _ => None,
+            };
+
+            if let Some(lit) = lit {
+                node.expr = Expr::Lit(lit);
+            }
+        }
+    }
+}
+
+fn catch_panic<T>(f: impl FnOnce() -> T) -> Result<T, String> {
+    panic::catch_unwind(AssertUnwindSafe(f)).map_err(|e| {
+        let message = e
+            .downcast_ref::<&str>()
+            .map(|it| it.to_string())
+            .or_else(|| e.downcast_ref::<String>().cloned())
+            .unwrap_or_default();
+        format!("panicked: {message}")
+    })
+}
+
+/// Removes declarations and statements from a failing module for as long as it stays well typed
+/// and keeps failing at the same stage.
+///
+/// Later declarations and statements are tried first, since they can only be used by the ones
+/// that come after them.
+fn minimize(mut module: Module, stage: Stage) -> Module {
+    let still_fails = |module: &Module| {
+        typecheck::check(module).is_ok()
+            && copy_module(module).is_some_and(
+                |module| matches!(check_until(module, stage), Err((it, _)) if it == stage),
+            )
+    };
+
+    loop {
+        let mut changed = false;
+
+        for i in (0..module.decls.len()).rev() {
+            let mut candidate = copy_module(&module).unwrap();
+            candidate.decls.remove(i);
+
+            if still_fails(&candidate) {
+                module = candidate;
+                changed = true;
+            }
+        }
+
+        for i in (0..count_stmts(&module)).rev() {
+            let mut candidate = copy_module(&module).unwrap();
+            StmtRemover(Some(i)).visit_module_mut(&mut candidate);
+
+            if still_fails(&candidate) {
+                module = candidate;
+                changed = true;
+            }
+        }
+
+        if !changed {
+            return module;
+        }
+    }
+}
+
+fn count_stmts(module: &Module) -> usize {
+    struct Counter(usize);
+
+    impl<'a> Visitor<'a> for Counter {
+        fn visit_stmt(&mut self, stmt: &'a Statement) {
+            self.0 += 1;
+            ast::visit::walk_stmt(self, stmt);
+        }
+    }
+
+    let mut counter = Counter(0);
+    counter.visit_module(module);
+    counter.0
+}
+
+/// Removes the statement at the given index, counting statements in the order they are visited.
+struct StmtRemover(Option<usize>);
+
+impl VisitorMut for StmtRemover {
+    fn visit_block_mut(&mut self, block: &mut Vec<Statement>) {
+        let mut i = 0;
+        while i < block.len() {
+            match self.0 {
+                None => return,
+                Some(0) => {
+                    block.remove(i);
+                    self.0 = None;
+                    return;
+                }
+                Some(n) => {
+                    self.0 = Some(n - 1);
+                    self.visit_stmt_mut(&mut block[i]);
+                }
+            }
+
+            i += 1;
+        }
+    }
+}
+
+fn write_module(module: &Module) -> String {
+    let mut source = String::new();
+    Writer::default()
+        .write_module(&mut source, module)
+        .expect("writing to a string cannot fail");
+    source
+}
+
+/// Copies a module by serializing it, since modules are not `Clone`.
+///
+/// This fails if the module refers to a struct that it doesn't declare.
+fn copy_module(module: &Module) -> Option<Module> {
+    let json = serde_json::to_string(module).ok()?;
+    crate::parse_json(&json).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::Path;
+    use std::rc::Rc;
+
+    use clap::Parser;
+
+    use super::*;
+
+    const SNAPSHOTS: &str = "../../parser/src/snapshots";
+
+    #[test]
+    fn generated_programs_round_trip() {
+        let options = Rc::new(crate::Options::parse_from(["generator"]));
+        // Each program takes a few seconds in a debug build, so only a few seeds are checked here.
+        // `wgslsmith selftest --count` checks more
+        let failures = check_seeds(options, 0..3);
+
+        // Minimized failures are saved next to the parser snapshots, where they are kept as
+        // regression tests once they are accepted
+        for failure in &failures {
+            insta::with_settings!({
+                snapshot_path => SNAPSHOTS,
+                prepend_module_to_snapshot => false,
+                description => format!("{}: {}", failure.stage, failure.message),
+            }, {
+                insta::assert_snapshot!(format!("selftest_seed_{}", failure.seed), failure.source);
+            });
+        }
+
+        assert!(failures.is_empty(), "{} programs failed", failures.len());
+    }
+
+    #[test]
+    fn reconditioned_module_json_round_trip() {
+        let options = Rc::new(crate::Options::parse_from(["generator"]));
+        let mut rng = StdRng::seed_from_u64(1);
+        let module = Generator::new(&mut rng, options).gen_module();
+        let module = reconditioner::recondition(module);
+
+        let json = serde_json::to_string_pretty(&module).unwrap();
+        let parsed = crate::parse_json(&json).unwrap();
+
+        assert_eq!(write_module(&parsed), write_module(&module));
+    }
+
+    #[test]
+    fn saved_failures_round_trip() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("src")
+            .join(SNAPSHOTS);
+
+        for entry in fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            let name = path.file_name().unwrap().to_string_lossy();
+            if !name.starts_with("selftest_seed_") || !name.ends_with(".snap") {
+                continue;
+            }
+
+            let snapshot = fs::read_to_string(&path).unwrap();
+            let (_, source) = snapshot.split_once("\n---\n").unwrap();
+            let module = parser::parse(source);
+
+            if let Err((stage, message)) = check_module(module) {
+                panic!("{name} failed after {stage}: {message}");
+            }
+        }
+    }
+}
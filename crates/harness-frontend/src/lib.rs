@@ -73,29 +73,52 @@ pub fn read_shader_from_path(path: &str) -> eyre::Result<String> {
     Ok(shader)
 }
 
+/// Reflects a pipeline description for each compute entry point in the shader.
+///
+/// Resources which are not accessed by an entry point are omitted from its description, since they
+/// won't be part of the bind group layout that gets created for the pipeline.
 pub fn reflect_shader(
     shader: &str,
-    mut input_data: HashMap<String, Vec<u8>>,
-) -> (PipelineDescription, Vec<common::Type>) {
-    let module = parser::parse(shader);
-
-    let (mut pipeline_desc, type_descs) = reflection::reflect(&module, |resource| {
-        input_data.remove(&format!("{}:{}", resource.group, resource.binding))
-    });
+    input_data: HashMap<String, Vec<u8>>,
+) -> eyre::Result<Vec<(PipelineDescription, Vec<common::Type>)>> {
+    let module = parser::try_parse(shader).map_err(|diagnostics| {
+        eyre!(
+            "invalid shader:\n{}",
+            parser::format_diagnostics(&diagnostics)
+        )
+    })?;
+
+    let pipelines = reflection::entry_points(&module)
+        .map(|entry_point| {
+            let (mut pipeline_desc, type_descs) =
+                reflection::reflect(&module, entry_point, |resource| {
+                    input_data
+                        .get(&format!("{}:{}", resource.group, resource.binding))
+                        .cloned()
+                });
+
+            let mut resource_vars = HashSet::new();
+
+            for resource in &pipeline_desc.resources {
+                resource_vars.insert(resource.name.clone());
+            }
 
-    let mut resource_vars = HashSet::new();
+            utils::remove_accessed_vars(&mut resource_vars, &module, entry_point);
 
-    for resource in &pipeline_desc.resources {
-        resource_vars.insert(resource.name.clone());
-    }
+            let (resources, type_descs) = pipeline_desc
+                .resources
+                .into_iter()
+                .zip(type_descs)
+                .filter(|(resource, _)| !resource_vars.contains(&resource.name))
+                .unzip();
 
-    utils::remove_accessed_vars(&mut resource_vars, &module);
+            pipeline_desc.resources = resources;
 
-    pipeline_desc
-        .resources
-        .retain(|resource| !resource_vars.contains(&resource.name));
+            (pipeline_desc, type_descs)
+        })
+        .collect();
 
-    (pipeline_desc, type_descs)
+    Ok(pipelines)
 }
 
 #[derive(Debug)]
@@ -195,21 +218,13 @@ pub mod cli {
     pub fn run(options: RunOptions, executor: &dyn Executor) -> eyre::Result<()> {
         let shader = super::read_shader_from_path(&options.shader)?;
         let input_data = super::read_input_data(&options.shader, options.input_data.as_deref())?;
-        let (pipeline_desc, type_descs) = super::reflect_shader(&shader, input_data);
+        let pipelines = super::reflect_shader(&shader, input_data)?;
 
-        let printer = super::Printer::new();
+        if pipelines.is_empty() {
+            return Err(eyre!("shader does not contain any compute entry points"));
+        }
 
-        let mut executions = vec![];
-        let mut is_fail = false;
-        let mut on_event = |event: ExecutionEvent| {
-            printer.print_execution_event(&event, &pipeline_desc)?;
-            if let ExecutionEvent::Success(buffers) = event {
-                executions.push(buffers);
-            } else if let ExecutionEvent::Failure(_) = event {
-                is_fail = true
-            }
-            Ok(())
-        };
+        let printer = super::Printer::new();
 
         let timeout = if options.timeout == 0 {
             None
@@ -217,31 +232,54 @@ pub mod cli {
             Some(Duration::from_secs(options.timeout))
         };
 
-        executor
-            .execute(
-                &shader,
-                &pipeline_desc,
-                &options.configs,
-                timeout,
-                &mut on_event,
-            )
-            .map_err(|e| match e {
-                crate::ExecutionError::NoDefaultConfigs => {
-                    eyre!("failed to find any suitable default configurations")
-                        .with_note(|| "use the `list` command to see all available configurations")
+        let mut is_mismatch = false;
+
+        for (pipeline_desc, type_descs) in &pipelines {
+            printer.print_entry_point(&pipeline_desc.entry_point)?;
+
+            let mut executions = vec![];
+            let mut is_fail = false;
+            let mut on_event = |event: ExecutionEvent| {
+                printer.print_execution_event(&event, pipeline_desc)?;
+                if let ExecutionEvent::Success(buffers) = event {
+                    executions.push(buffers);
+                } else if let ExecutionEvent::Failure(_) = event {
+                    is_fail = true
                 }
-                crate::ExecutionError::Other(e) => e,
-                e => eyre!(e),
-            })?;
+                Ok(())
+            };
+
+            executor
+                .execute(
+                    &shader,
+                    pipeline_desc,
+                    &options.configs,
+                    timeout,
+                    &mut on_event,
+                )
+                .map_err(|e| match e {
+                    crate::ExecutionError::NoDefaultConfigs => {
+                        eyre!("failed to find any suitable default configurations").with_note(
+                            || "use the `list` command to see all available configurations",
+                        )
+                    }
+                    crate::ExecutionError::Other(e) => e,
+                    e => eyre!(e),
+                })?;
 
-        if is_fail {
-            panic!("one or more executions failed");
+            if is_fail {
+                panic!("one or more executions failed");
+            }
+
+            if buffer_check::compare(executions.iter(), pipeline_desc, type_descs) {
+                printer.print_execution_result(ExecutionResult::Ok)?;
+            } else {
+                printer.print_execution_result(ExecutionResult::Mismatch)?;
+                is_mismatch = true;
+            }
         }
 
-        if buffer_check::compare(executions.iter(), &pipeline_desc, &type_descs) {
-            printer.print_execution_result(ExecutionResult::Ok)?;
-        } else {
-            printer.print_execution_result(ExecutionResult::Mismatch)?;
+        if is_mismatch {
             std::process::exit(1);
         }
 
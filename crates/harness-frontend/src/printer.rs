@@ -1,7 +1,7 @@
 use std::io::{self, Write};
 
 use bincode::{Decode, Encode};
-use reflection::{PipelineDescription, ResourceKind};
+use reflection::PipelineDescription;
 use termcolor::{Color, ColorChoice, ColorSpec, StandardStream, WriteColor};
 use types::{Config, ConfigId};
 
@@ -78,6 +78,18 @@ impl Printer {
         Ok(())
     }
 
+    pub fn print_entry_point(&self, entry_point: &str) -> io::Result<()> {
+        let mut stdout = StandardStream::stdout(ColorChoice::Auto);
+
+        write!(&mut stdout, "entry point: ")?;
+
+        stdout.set_color(&cyan())?;
+        writeln!(&mut stdout, "{entry_point}")?;
+        stdout.reset()?;
+
+        Ok(())
+    }
+
     fn print_default_configs(&self, configs: &[ConfigId]) -> io::Result<()> {
         let mut stdout = StandardStream::stdout(ColorChoice::Auto);
 
@@ -144,7 +156,7 @@ impl Printer {
         for (index, resource) in pipeline_desc
             .resources
             .iter()
-            .filter(|it| it.kind == ResourceKind::StorageBuffer)
+            .filter(|it| it.kind.is_output())
             .enumerate()
         {
             let group = resource.group;
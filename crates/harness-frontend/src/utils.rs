@@ -1,229 +1,60 @@
 use std::collections::HashSet;
 
-use ast::{
-    AssignmentLhs, Expr, ExprNode, ForLoopInit, ForLoopUpdate, LhsExpr, LhsExprNode, Module,
-    Postfix, Statement,
-};
+use ast::visit::{self, Visitor};
+use ast::{Expr, ExprNode, FnCallStatement, LhsExpr, LhsExprNode, Module};
 
-pub fn remove_accessed_vars(vars: &mut HashSet<String>, module: &Module) {
-    for decl in &module.functions {
-        for stmt in &decl.body {
-            visit_stmt(vars, stmt);
-        }
-    }
+struct Context<'a> {
+    vars: &'a mut HashSet<String>,
+    calls: Vec<String>,
 }
 
-fn visit_stmt(vars: &mut HashSet<String>, stmt: &Statement) {
-    match stmt {
-        Statement::LetDecl(decl) => visit_expr(vars, &decl.initializer),
-        Statement::VarDecl(decl) => {
-            if let Some(init) = &decl.initializer {
-                visit_expr(vars, init);
-            }
-        }
-        Statement::Assignment(stmt) => {
-            visit_assignment_lhs(vars, &stmt.lhs);
-            visit_expr(vars, &stmt.rhs);
-        }
-        Statement::Compound(stmts) => {
-            for stmt in stmts {
-                visit_stmt(vars, stmt);
-            }
-        }
-        Statement::If(stmt) => {
-            visit_expr(vars, &stmt.condition);
+/// Removes any variables from `vars` which are accessed by the given entry point, either directly
+/// or through any of the functions that it calls.
+pub fn remove_accessed_vars(vars: &mut HashSet<String>, module: &Module, entry_point: &str) {
+    let mut cx = Context {
+        vars,
+        calls: vec![entry_point.to_owned()],
+    };
 
-            for stmt in &stmt.body {
-                visit_stmt(vars, stmt);
-            }
-
-            let mut else_ = stmt.else_.as_deref();
-            while let Some(e) = else_ {
-                match e {
-                    ast::Else::If(stmt) => {
-                        visit_expr(vars, &stmt.condition);
-
-                        for stmt in &stmt.body {
-                            visit_stmt(vars, stmt);
-                        }
+    let mut visited = HashSet::new();
 
-                        else_ = stmt.else_.as_deref();
-                    }
-                    ast::Else::Else(body) => {
-                        for stmt in body {
-                            visit_stmt(vars, stmt);
-                        }
-
-                        else_ = None;
-                    }
-                }
-            }
+    while let Some(name) = cx.calls.pop() {
+        if !visited.insert(name.clone()) {
+            continue;
         }
-        Statement::Return(stmt) => {
-            if let Some(e) = &stmt.value {
-                visit_expr(vars, e);
-            }
-        }
-        Statement::Loop(stmt) => {
-            for stmt in &stmt.body {
-                visit_stmt(vars, stmt);
-            }
-            if let Some(c) = &stmt.continuing {
-                for stmt in &c.stmts {
-                    visit_stmt(vars, stmt);
-                }
-                if let Some(e) = &c.break_if {
-                    visit_expr(vars, e);
-                }
-            }
-        }
-        Statement::While(stmt) => {
-            visit_expr(vars, &stmt.condition);
-            for s in &stmt.body {
-                visit_stmt(vars, s);
-            }
-        }
-        Statement::Break => {}
-        Statement::Switch(stmt) => {
-            visit_expr(vars, &stmt.selector);
 
-            for case in &stmt.cases {
-                for stmt in &case.body {
-                    visit_stmt(vars, stmt);
-                }
-            }
-
-            for stmt in &stmt.default {
-                visit_stmt(vars, stmt);
-            }
+        // Calls to builtin functions won't have a matching declaration
+        if let Some(decl) = module.functions().find(|it| it.name == name) {
+            cx.visit_block(&decl.body);
         }
-        Statement::ForLoop(stmt) => {
-            if let Some(init) = &stmt.header.init {
-                match init {
-                    ForLoopInit::VarDecl(v) => {
-                        if let Some(i) = &v.initializer {
-                            visit_expr(vars, i);
-                        }
-                    }
-                    ForLoopInit::LetDecl(l) => {
-                        visit_expr(vars, &l.initializer);
-                    }
-                    ForLoopInit::Assignment(a) => {
-                        visit_assignment_lhs(vars, &a.lhs);
-                        visit_expr(vars, &a.rhs);
-                    }
-                    ForLoopInit::Increment(inc) => {
-                        visit_assignment_lhs(vars, &inc.lhs);
-                    }
-                    ForLoopInit::Decrement(dec) => {
-                        visit_assignment_lhs(vars, &dec.lhs);
-                    }
-                    ForLoopInit::Call(c) => {
-                        for arg in &c.args {
-                            visit_expr(vars, arg);
-                        }
-                    }
-                }
-            }
-
-            if let Some(condition) = &stmt.header.condition {
-                visit_expr(vars, condition);
-            }
-
-            if let Some(upd) = &stmt.header.update {
-                match upd {
-                    ForLoopUpdate::Assignment(a) => {
-                        visit_assignment_lhs(vars, &a.lhs);
-                        visit_expr(vars, &a.rhs);
-                    }
-                    ForLoopUpdate::Increment(inc) => {
-                        visit_assignment_lhs(vars, &inc.lhs);
-                    }
-                    ForLoopUpdate::Decrement(dec) => {
-                        visit_assignment_lhs(vars, &dec.lhs);
-                    }
-                    ForLoopUpdate::Call(c) => {
-                        for arg in &c.args {
-                            visit_expr(vars, arg);
-                        }
-                    }
-                }
-            }
-
-            for stmt in &stmt.body {
-                visit_stmt(vars, stmt);
-            }
-        }
-        Statement::FnCall(stmt) => {
-            for arg in &stmt.args {
-                visit_expr(vars, arg);
-            }
-        }
-        Statement::Increment(s) => {
-            visit_assignment_lhs(vars, &s.lhs);
-        }
-        Statement::Decrement(s) => {
-            visit_assignment_lhs(vars, &s.lhs);
-        }
-        Statement::Continue => {}
-        Statement::Fallthrough => {}
     }
 }
 
-fn visit_lhs_expr(vars: &mut HashSet<String>, node: &LhsExprNode) {
-    match &node.expr {
-        LhsExpr::Ident(ident) => {
-            vars.remove(ident);
-        }
-        LhsExpr::Postfix(expr, postfix) => {
-            visit_lhs_expr(vars, expr);
-            visit_postfix(vars, postfix);
-        }
-        LhsExpr::Deref(expr) => visit_lhs_expr(vars, expr),
-        LhsExpr::AddressOf(expr) => visit_lhs_expr(vars, expr),
+impl<'a> Visitor<'a> for Context<'_> {
+    fn visit_fn_call_stmt(&mut self, stmt: &'a FnCallStatement) {
+        self.calls.push(stmt.ident.clone());
+        visit::walk_fn_call_stmt(self, stmt);
     }
-}
 
-fn visit_expr(vars: &mut HashSet<String>, node: &ExprNode) {
-    match &node.expr {
-        Expr::Lit(_) => {}
-        Expr::TypeCons(expr) => {
-            for arg in &expr.args {
-                visit_expr(vars, arg);
-            }
-        }
-        Expr::Var(expr) => {
-            vars.remove(expr.ident.as_str());
-        }
-        Expr::Postfix(expr) => {
-            visit_expr(vars, &expr.inner);
-            visit_postfix(vars, &expr.postfix);
-        }
-        Expr::UnOp(expr) => visit_expr(vars, &expr.inner),
-        Expr::BinOp(expr) => {
-            visit_expr(vars, &expr.left);
-            visit_expr(vars, &expr.right);
-        }
-        Expr::FnCall(expr) => {
-            for arg in &expr.args {
-                visit_expr(vars, arg);
-            }
+    fn visit_lhs_expr(&mut self, node: &'a LhsExprNode) {
+        if let LhsExpr::Ident(ident) = &node.expr {
+            self.vars.remove(ident);
         }
+
+        visit::walk_lhs_expr(self, node);
     }
-}
 
-fn visit_assignment_lhs(vars: &mut HashSet<String>, lhs: &AssignmentLhs) {
-    match lhs {
-        AssignmentLhs::Phony => {}
-        AssignmentLhs::Expr(e) => {
-            visit_lhs_expr(vars, e);
+    fn visit_expr(&mut self, node: &'a ExprNode) {
+        match &node.expr {
+            Expr::Var(expr) => {
+                self.vars.remove(expr.ident.as_str());
+            }
+            Expr::FnCall(expr) => {
+                self.calls.push(expr.ident.clone());
+            }
+            _ => {}
         }
-    }
-}
 
-fn visit_postfix(vars: &mut HashSet<String>, postfix: &Postfix) {
-    match postfix {
-        Postfix::Index(index) => visit_expr(vars, index),
-        Postfix::Member(_) => {}
+        visit::walk_expr(self, node);
     }
 }
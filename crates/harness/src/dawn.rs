@@ -1,10 +1,9 @@
 use color_eyre::eyre::eyre;
-use dawn::webgpu::{
-    WGPUBackendType_WGPUBackendType_D3D12, WGPUBackendType_WGPUBackendType_Metal,
-    WGPUBackendType_WGPUBackendType_Vulkan, WGPUBool,
-};
+use dawn::webgpu::*;
 use dawn::*;
-use reflection::{PipelineDescription, ResourceKind};
+use reflection::{
+    PipelineDescription, ResourceKind, StorageTexture, TexelFormat, TextureDimension,
+};
 
 use crate::ConfigId;
 
@@ -20,6 +19,13 @@ enum BufferSet {
         size: usize,
         buffer: DeviceBuffer,
     },
+    Texture {
+        binding: u32,
+        desc: StorageTexture,
+        texture: Texture,
+        view: TextureView,
+        read: DeviceBuffer,
+    },
 }
 
 pub fn get_adapters() -> Vec<types::Adapter> {
@@ -61,6 +67,12 @@ pub async fn run(
             reflection::Extension::Subgroups => {
                 dawn::webgpu::WGPUFeatureName_WGPUFeatureName_Subgroups
             }
+            reflection::Extension::ClipDistances => {
+                dawn::webgpu::WGPUFeatureName_WGPUFeatureName_ClipDistances
+            }
+            reflection::Extension::DualSourceBlending => {
+                dawn::webgpu::WGPUFeatureName_WGPUFeatureName_DualSourceBlending
+            }
         })
         .collect();
 
@@ -72,7 +84,7 @@ pub async fn run(
 
     let queue = device.create_queue();
     let shader_module = device.create_shader_module(shader);
-    let pipeline = device.create_compute_pipeline(&shader_module, "main");
+    let pipeline = device.create_compute_pipeline(&shader_module, &meta.entry_point);
 
     // this will catch compilation errors
     instance.process_events();
@@ -85,12 +97,17 @@ pub async fn run(
         let size = resource.size as usize;
         match resource.kind {
             ResourceKind::StorageBuffer => {
-                let storage = device.create_buffer(
-                    mapped,
+                let mut storage = device.create_buffer(
+                    resource.init.is_some() as WGPUBool,
                     size,
                     DeviceBufferUsage::STORAGE | DeviceBufferUsage::COPY_SRC,
                 );
 
+                if let Some(init) = resource.init.as_deref() {
+                    storage.get_mapped_range(size).copy_from_slice(init);
+                    storage.unmap();
+                }
+
                 let read = device.create_buffer(
                     mapped,
                     size,
@@ -121,55 +138,142 @@ pub async fn run(
                     buffer,
                 })
             }
+            ResourceKind::StorageTexture(ref desc) => {
+                let texture = device.create_texture(
+                    match desc.dimension {
+                        TextureDimension::D1 => WGPUTextureDimension_WGPUTextureDimension_1D,
+                        TextureDimension::D2 => WGPUTextureDimension_WGPUTextureDimension_2D,
+                        TextureDimension::D3 => WGPUTextureDimension_WGPUTextureDimension_3D,
+                    },
+                    texture_format(desc.format),
+                    texture_extent(desc),
+                    DeviceTextureUsage::STORAGE_BINDING
+                        | DeviceTextureUsage::COPY_SRC
+                        | DeviceTextureUsage::COPY_DST,
+                );
+
+                if let Some(init) = resource.init.as_deref() {
+                    queue.write_texture(
+                        &texture,
+                        init,
+                        WGPUTexelCopyBufferLayout {
+                            offset: 0,
+                            bytesPerRow: desc.bytes_per_row(),
+                            rowsPerImage: desc.height,
+                        },
+                        texture_extent(desc),
+                    );
+                }
+
+                let view = texture.create_view();
+
+                let read = device.create_buffer(
+                    mapped,
+                    desc.padded_size() as usize,
+                    DeviceBufferUsage::COPY_DST | DeviceBufferUsage::MAP_READ,
+                );
+
+                buffer_sets.push(BufferSet::Texture {
+                    binding: resource.binding,
+                    desc: desc.clone(),
+                    texture,
+                    view,
+                    read,
+                });
+            }
         }
     }
 
-    let bind_group_entries = buffer_sets
+    let group_count = meta
+        .resources
         .iter()
-        .map(|buffers| match buffers {
-            BufferSet::Storage {
-                binding,
-                size,
-                storage,
-                ..
-            } => BindGroupEntry {
-                binding: *binding,
-                buffer: storage,
-                size: *size,
-            },
-            BufferSet::Uniform {
-                binding,
-                size,
-                buffer,
-            } => BindGroupEntry {
-                binding: *binding,
-                buffer,
-                size: *size,
-            },
+        .map(|resource| resource.group + 1)
+        .max()
+        .unwrap_or(0);
+
+    // Bind groups must be set for every group index up to the highest one used by the pipeline,
+    // even if some of them are empty
+    let bind_groups = (0..group_count)
+        .map(|group| {
+            let bind_group_entries = meta
+                .resources
+                .iter()
+                .zip(&buffer_sets)
+                .filter(|(resource, _)| resource.group == group)
+                .map(|(_, buffers)| match buffers {
+                    BufferSet::Storage {
+                        binding,
+                        size,
+                        storage,
+                        ..
+                    } => BindGroupEntry {
+                        binding: *binding,
+                        resource: BindingResource::Buffer {
+                            buffer: storage,
+                            size: *size,
+                        },
+                    },
+                    BufferSet::Uniform {
+                        binding,
+                        size,
+                        buffer,
+                    } => BindGroupEntry {
+                        binding: *binding,
+                        resource: BindingResource::Buffer {
+                            buffer,
+                            size: *size,
+                        },
+                    },
+                    BufferSet::Texture { binding, view, .. } => BindGroupEntry {
+                        binding: *binding,
+                        resource: BindingResource::TextureView(view),
+                    },
+                })
+                .collect::<Vec<_>>();
+
+            device.create_bind_group(&pipeline.get_bind_group_layout(group), &bind_group_entries)
         })
         .collect::<Vec<_>>();
 
-    let bind_group =
-        device.create_bind_group(&pipeline.get_bind_group_layout(0), &bind_group_entries);
-
     let encoder = device.create_command_encoder();
 
     {
         let compute_pass = encoder.begin_compute_pass();
         compute_pass.set_pipeline(&pipeline);
-        compute_pass.set_bind_group(0, &bind_group);
+        for (index, bind_group) in bind_groups.iter().enumerate() {
+            compute_pass.set_bind_group(index as u32, bind_group);
+        }
         compute_pass.dispatch(1, 1, 1);
     }
 
     for buffers in &buffer_sets {
-        if let BufferSet::Storage {
-            storage,
-            read,
-            size,
-            ..
-        } = buffers
-        {
-            encoder.copy_buffer_to_buffer(storage, read, *size);
+        match buffers {
+            BufferSet::Storage {
+                storage,
+                read,
+                size,
+                ..
+            } => {
+                encoder.copy_buffer_to_buffer(storage, read, *size);
+            }
+            BufferSet::Texture {
+                desc,
+                texture,
+                read,
+                ..
+            } => {
+                encoder.copy_texture_to_buffer(
+                    texture,
+                    read,
+                    WGPUTexelCopyBufferLayout {
+                        offset: 0,
+                        bytesPerRow: desc.padded_bytes_per_row(),
+                        rowsPerImage: desc.height,
+                    },
+                    texture_extent(desc),
+                );
+            }
+            BufferSet::Uniform { .. } => {}
         }
     }
 
@@ -179,19 +283,50 @@ pub async fn run(
 
     let mut results = vec![];
     for buffers in &buffer_sets {
-        if let BufferSet::Storage { read, size, .. } = buffers {
-            let mut rx = read.map_async(DeviceBufferMapMode::READ, *size);
-
-            while rx.try_recv().unwrap().is_none() {
-                instance.process_events();
-                std::thread::sleep(std::time::Duration::from_millis(16));
+        let (read, size, texture_desc) = match buffers {
+            BufferSet::Storage { read, size, .. } => (read, *size, None),
+            BufferSet::Texture { read, desc, .. } => {
+                (read, desc.padded_size() as usize, Some(desc))
             }
+            BufferSet::Uniform { .. } => continue,
+        };
 
-            let bytes = read.get_const_mapped_range(*size);
+        let mut rx = read.map_async(DeviceBufferMapMode::READ, size);
 
-            results.push(bytes.to_vec());
+        while rx.try_recv().unwrap().is_none() {
+            instance.process_events();
+            std::thread::sleep(std::time::Duration::from_millis(16));
         }
+
+        let bytes = read.get_const_mapped_range(size);
+
+        results.push(match texture_desc {
+            Some(desc) => desc.unpad(bytes),
+            None => bytes.to_vec(),
+        });
     }
 
     Ok(results)
 }
+
+fn texture_extent(desc: &StorageTexture) -> WGPUExtent3D {
+    WGPUExtent3D {
+        width: desc.width,
+        height: desc.height,
+        depthOrArrayLayers: desc.depth,
+    }
+}
+
+fn texture_format(format: TexelFormat) -> WGPUTextureFormat {
+    match format {
+        TexelFormat::Rgba8Unorm => WGPUTextureFormat_WGPUTextureFormat_RGBA8Unorm,
+        TexelFormat::Rgba8Uint => WGPUTextureFormat_WGPUTextureFormat_RGBA8Uint,
+        TexelFormat::Rgba8Sint => WGPUTextureFormat_WGPUTextureFormat_RGBA8Sint,
+        TexelFormat::Rgba32Uint => WGPUTextureFormat_WGPUTextureFormat_RGBA32Uint,
+        TexelFormat::Rgba32Sint => WGPUTextureFormat_WGPUTextureFormat_RGBA32Sint,
+        TexelFormat::Rgba32Float => WGPUTextureFormat_WGPUTextureFormat_RGBA32Float,
+        TexelFormat::R32Uint => WGPUTextureFormat_WGPUTextureFormat_R32Uint,
+        TexelFormat::R32Sint => WGPUTextureFormat_WGPUTextureFormat_R32Sint,
+        TexelFormat::R32Float => WGPUTextureFormat_WGPUTextureFormat_R32Float,
+    }
+}
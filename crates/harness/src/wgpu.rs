@@ -3,12 +3,14 @@ use std::borrow::Cow;
 use crate::ConfigId;
 use color_eyre::eyre::eyre;
 use color_eyre::Result;
-use reflection::{PipelineDescription, ResourceKind};
+use reflection::{PipelineDescription, ResourceKind, StorageTexture, TexelFormat};
 use wgpu::wgt::PollType::Wait;
 use wgpu::{
-    Backends, BindGroupDescriptor, BindGroupEntry, Buffer, BufferDescriptor, BufferUsages,
-    CommandEncoderDescriptor, ComputePassDescriptor, ComputePipelineDescriptor, DeviceDescriptor,
-    Instance, Limits, MapMode, ShaderModuleDescriptor, ShaderSource,
+    Backends, BindGroupDescriptor, BindGroupEntry, BindingResource, Buffer, BufferDescriptor,
+    BufferUsages, CommandEncoderDescriptor, ComputePassDescriptor, ComputePipelineDescriptor,
+    DeviceDescriptor, Extent3d, Instance, Limits, MapMode, ShaderModuleDescriptor, ShaderSource,
+    TexelCopyBufferInfo, TexelCopyBufferLayout, Texture, TextureDescriptor, TextureDimension,
+    TextureFormat, TextureUsages, TextureView, TextureViewDescriptor,
 };
 
 pub fn get_adapters() -> Vec<types::Adapter> {
@@ -57,6 +59,8 @@ pub async fn run(
             acc | match enable {
                 reflection::Extension::F16 => wgpu::Features::SHADER_F16,
                 reflection::Extension::Subgroups => wgpu::Features::SUBGROUP,
+                reflection::Extension::ClipDistances => wgpu::Features::CLIP_DISTANCES,
+                reflection::Extension::DualSourceBlending => wgpu::Features::DUAL_SOURCE_BLENDING,
             }
         });
 
@@ -87,9 +91,7 @@ pub async fn run(
 
     let (device, queue) = adapter.request_device(&device_descriptor).await?;
 
-    let preprocessor_opts = preprocessor::Options {
-        module_scope_constants: false,
-    };
+    let preprocessor_opts = preprocessor::Options::default();
 
     let preprocessed = preprocessor::preprocess(preprocessor_opts, shader.to_owned());
     let shader_module = device.create_shader_module(ShaderModuleDescriptor {
@@ -98,7 +100,7 @@ pub async fn run(
     });
 
     let pipeline = device.create_compute_pipeline(&ComputePipelineDescriptor {
-        entry_point: Some("main"),
+        entry_point: Some(&meta.entry_point),
         label: None,
         module: &shader_module,
         layout: None,
@@ -119,6 +121,13 @@ pub async fn run(
             binding: u32,
             buffer: Buffer,
         },
+        Texture {
+            binding: u32,
+            desc: StorageTexture,
+            texture: Texture,
+            view: TextureView,
+            staging_buffer: Buffer,
+        },
     }
 
     for resource in &meta.resources {
@@ -129,9 +138,17 @@ pub async fn run(
                     label: Some("Storage GPU Buffer"),
                     usage: BufferUsages::STORAGE | BufferUsages::COPY_SRC,
                     size,
-                    mapped_at_creation: false,
+                    mapped_at_creation: resource.init.is_some(),
                 });
 
+                if let Some(init) = resource.init.as_deref() {
+                    gpu_buffer
+                        .slice(..)
+                        .get_mapped_range_mut()
+                        .copy_from_slice(init);
+                    gpu_buffer.unmap();
+                }
+
                 let staging_buffer = device.create_buffer(&BufferDescriptor {
                     label: Some("Storage Staging Buffer"),
                     usage: BufferUsages::COPY_DST | BufferUsages::MAP_READ,
@@ -168,53 +185,146 @@ pub async fn run(
                     buffer,
                 });
             }
+            ResourceKind::StorageTexture(ref desc) => {
+                let extent = texture_extent(desc);
+
+                let texture = device.create_texture(&TextureDescriptor {
+                    label: Some("Storage Texture"),
+                    size: extent,
+                    mip_level_count: 1,
+                    sample_count: 1,
+                    dimension: match desc.dimension {
+                        reflection::TextureDimension::D1 => TextureDimension::D1,
+                        reflection::TextureDimension::D2 => TextureDimension::D2,
+                        reflection::TextureDimension::D3 => TextureDimension::D3,
+                    },
+                    format: texture_format(desc.format),
+                    usage: TextureUsages::STORAGE_BINDING
+                        | TextureUsages::COPY_SRC
+                        | TextureUsages::COPY_DST,
+                    view_formats: &[],
+                });
+
+                if let Some(init) = resource.init.as_deref() {
+                    queue.write_texture(
+                        texture.as_image_copy(),
+                        init,
+                        TexelCopyBufferLayout {
+                            offset: 0,
+                            bytes_per_row: Some(desc.bytes_per_row()),
+                            rows_per_image: Some(desc.height),
+                        },
+                        extent,
+                    );
+                }
+
+                let view = texture.create_view(&TextureViewDescriptor::default());
+
+                let staging_buffer = device.create_buffer(&BufferDescriptor {
+                    label: Some("Storage Texture Staging Buffer"),
+                    usage: BufferUsages::COPY_DST | BufferUsages::MAP_READ,
+                    size: desc.padded_size() as u64,
+                    mapped_at_creation: false,
+                });
+
+                resource_buffers.push(ResourceBuffer::Texture {
+                    binding: resource.binding,
+                    desc: desc.clone(),
+                    texture,
+                    view,
+                    staging_buffer,
+                });
+            }
         }
     }
 
-    let bind_group_entries = resource_buffers
+    let group_count = meta
+        .resources
         .iter()
-        .map(|res| match res {
-            ResourceBuffer::Storage {
-                binding,
-                gpu_buffer,
-                ..
-            } => BindGroupEntry {
-                binding: *binding,
-                resource: gpu_buffer.as_entire_binding(),
-            },
-            ResourceBuffer::Uniform {
-                binding, buffer, ..
-            } => BindGroupEntry {
-                binding: *binding,
-                resource: buffer.as_entire_binding(),
-            },
+        .map(|resource| resource.group + 1)
+        .max()
+        .unwrap_or(0);
+
+    // Bind groups must be set for every group index up to the highest one used by the pipeline,
+    // even if some of them are empty
+    let bind_groups = (0..group_count)
+        .map(|group| {
+            let bind_group_entries = meta
+                .resources
+                .iter()
+                .zip(&resource_buffers)
+                .filter(|(resource, _)| resource.group == group)
+                .map(|(_, res)| match res {
+                    ResourceBuffer::Storage {
+                        binding,
+                        gpu_buffer,
+                        ..
+                    } => BindGroupEntry {
+                        binding: *binding,
+                        resource: gpu_buffer.as_entire_binding(),
+                    },
+                    ResourceBuffer::Uniform {
+                        binding, buffer, ..
+                    } => BindGroupEntry {
+                        binding: *binding,
+                        resource: buffer.as_entire_binding(),
+                    },
+                    ResourceBuffer::Texture { binding, view, .. } => BindGroupEntry {
+                        binding: *binding,
+                        resource: BindingResource::TextureView(view),
+                    },
+                })
+                .collect::<Vec<_>>();
+
+            device.create_bind_group(&BindGroupDescriptor {
+                layout: &pipeline.get_bind_group_layout(group),
+                label: None,
+                entries: &bind_group_entries,
+            })
         })
         .collect::<Vec<_>>();
 
-    let bind_group = device.create_bind_group(&BindGroupDescriptor {
-        layout: &pipeline.get_bind_group_layout(0),
-        label: None,
-        entries: &bind_group_entries,
-    });
-
     let commands = {
         let mut encoder = device.create_command_encoder(&CommandEncoderDescriptor::default());
         {
             let mut pass = encoder.begin_compute_pass(&ComputePassDescriptor::default());
             pass.set_pipeline(&pipeline);
-            pass.set_bind_group(0, &bind_group, &[]);
+            for (index, bind_group) in bind_groups.iter().enumerate() {
+                pass.set_bind_group(index as u32, bind_group, &[]);
+            }
             pass.dispatch_workgroups(1, 1, 1);
         }
 
         for res in &resource_buffers {
-            if let ResourceBuffer::Storage {
-                size,
-                gpu_buffer,
-                staging_buffer,
-                ..
-            } = res
-            {
-                encoder.copy_buffer_to_buffer(gpu_buffer, 0, staging_buffer, 0, *size);
+            match res {
+                ResourceBuffer::Storage {
+                    size,
+                    gpu_buffer,
+                    staging_buffer,
+                    ..
+                } => {
+                    encoder.copy_buffer_to_buffer(gpu_buffer, 0, staging_buffer, 0, *size);
+                }
+                ResourceBuffer::Texture {
+                    desc,
+                    texture,
+                    staging_buffer,
+                    ..
+                } => {
+                    encoder.copy_texture_to_buffer(
+                        texture.as_image_copy(),
+                        TexelCopyBufferInfo {
+                            buffer: staging_buffer,
+                            layout: TexelCopyBufferLayout {
+                                offset: 0,
+                                bytes_per_row: Some(desc.padded_bytes_per_row()),
+                                rows_per_image: Some(desc.height),
+                            },
+                        },
+                        texture_extent(desc),
+                    );
+                }
+                ResourceBuffer::Uniform { .. } => {}
             }
         }
 
@@ -226,17 +336,25 @@ pub async fn run(
     let mut pending_mappings = vec![];
 
     for res in &resource_buffers {
-        if let ResourceBuffer::Storage { staging_buffer, .. } = res {
-            let slice = staging_buffer.slice(..);
-            let (tx, rx) = futures::channel::oneshot::channel();
+        let (staging_buffer, texture_desc) = match res {
+            ResourceBuffer::Storage { staging_buffer, .. } => (staging_buffer, None),
+            ResourceBuffer::Texture {
+                staging_buffer,
+                desc,
+                ..
+            } => (staging_buffer, Some(desc)),
+            ResourceBuffer::Uniform { .. } => continue,
+        };
 
-            slice.map_async(MapMode::Read, move |res| {
-                // ignore send errors if receiver dropped
-                let _ = tx.send(res);
-            });
+        let slice = staging_buffer.slice(..);
+        let (tx, rx) = futures::channel::oneshot::channel();
 
-            pending_mappings.push((rx, slice, staging_buffer));
-        }
+        slice.map_async(MapMode::Read, move |res| {
+            // ignore send errors if receiver dropped
+            let _ = tx.send(res);
+        });
+
+        pending_mappings.push((rx, slice, staging_buffer, texture_desc));
     }
 
     device.poll(Wait {
@@ -246,12 +364,15 @@ pub async fn run(
 
     let mut results = vec![];
 
-    for (rx, slice, raw_buffer) in pending_mappings {
+    for (rx, slice, raw_buffer, texture_desc) in pending_mappings {
         let map_result = rx.await?;
         map_result?;
 
         let bytes = slice.get_mapped_range();
-        results.push(bytes.to_vec());
+        results.push(match texture_desc {
+            Some(desc) => desc.unpad(&bytes),
+            None => bytes.to_vec(),
+        });
 
         drop(bytes);
         raw_buffer.unmap();
@@ -259,3 +380,25 @@ pub async fn run(
 
     Ok(results)
 }
+
+fn texture_extent(desc: &StorageTexture) -> Extent3d {
+    Extent3d {
+        width: desc.width,
+        height: desc.height,
+        depth_or_array_layers: desc.depth,
+    }
+}
+
+fn texture_format(format: TexelFormat) -> TextureFormat {
+    match format {
+        TexelFormat::Rgba8Unorm => TextureFormat::Rgba8Unorm,
+        TexelFormat::Rgba8Uint => TextureFormat::Rgba8Uint,
+        TexelFormat::Rgba8Sint => TextureFormat::Rgba8Sint,
+        TexelFormat::Rgba32Uint => TextureFormat::Rgba32Uint,
+        TexelFormat::Rgba32Sint => TextureFormat::Rgba32Sint,
+        TexelFormat::Rgba32Float => TextureFormat::Rgba32Float,
+        TexelFormat::R32Uint => TextureFormat::R32Uint,
+        TexelFormat::R32Sint => TextureFormat::R32Sint,
+        TexelFormat::R32Float => TextureFormat::R32Float,
+    }
+}
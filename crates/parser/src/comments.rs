@@ -0,0 +1,407 @@
+use std::cmp::Reverse;
+use std::ops::Range;
+
+use ast::visit::{self, Visitor};
+use ast::{Comment, CommentAnchor, CommentPosition, Module, Statement};
+use pest::iterators::Pair;
+
+use crate::Rule;
+
+/// A node that comments can be attached to, and the source range that it was parsed from.
+struct Anchor {
+    start: usize,
+    end: usize,
+    anchor: CommentAnchor,
+}
+
+/// A block of statements, and the node that it is the `index`th block of.
+struct Block {
+    start: usize,
+    end: usize,
+    owner: CommentAnchor,
+    index: usize,
+}
+
+/// Finds the comments in `source` and attaches each one to the declaration, struct member or
+/// statement that it was written next to.
+///
+/// A comment at the end of a node's last line is attached inline to that node. Any other comment
+/// is attached to the next node in the same block, or to the last node in the block if it comes
+/// after all of them, or to the block itself if it is empty. Comments before the first directive
+/// are kept at the top of the module.
+pub(crate) fn attach_comments(source: &str, root: Pair<Rule>, module: &Module) -> Vec<Comment> {
+    let comments = scan_comments(source);
+    if comments.is_empty() {
+        return vec![];
+    }
+
+    let mut anchors = vec![];
+    let mut blocks = vec![];
+    let mut directives_start = None;
+
+    collect_blocks(root.clone(), &mut blocks);
+
+    for pair in root
+        .into_inner()
+        .filter(|it| it.as_rule() == Rule::global_decl)
+    {
+        let pair = pair.into_inner().next().unwrap();
+        let span = pair.as_span();
+
+        match pair.as_rule() {
+            Rule::enable_directive | Rule::requires_directive | Rule::diagnostic_directive => {
+                directives_start.get_or_insert(span.start());
+                continue;
+            }
+            Rule::struct_decl => {
+                let name = crate::decl_name(&pair);
+                for member in pair
+                    .clone()
+                    .into_inner()
+                    .filter(|it| it.as_rule() == Rule::struct_member)
+                {
+                    let anchor = CommentAnchor::Member(
+                        name.to_owned(),
+                        crate::decl_name(&member).to_owned(),
+                    );
+                    anchors.push(Anchor {
+                        start: member.as_span().start(),
+                        end: member.as_span().end(),
+                        anchor,
+                    });
+                }
+            }
+            _ => {}
+        }
+
+        anchors.push(Anchor {
+            start: span.start(),
+            end: span.end(),
+            anchor: CommentAnchor::Decl(crate::decl_name(&pair).to_owned()),
+        });
+    }
+
+    let mut stmts = StmtAnchors(vec![]);
+    stmts.visit_module(module);
+    anchors.extend(stmts.0);
+
+    // The span of a node can run over the comments after it, for example when an if statement
+    // looks ahead for an else
+    for anchor in &mut anchors {
+        anchor.end = trim_trivia(source, &comments, anchor.start, anchor.end);
+    }
+
+    comments
+        .into_iter()
+        .map(|range| {
+            let (anchor, position) = match directives_start {
+                Some(start) if range.end <= start => {
+                    (CommentAnchor::Module, CommentPosition::Before)
+                }
+                _ => attach(source, &range, &anchors, &blocks),
+            };
+
+            Comment {
+                text: comment_text(source, &range),
+                anchor,
+                position,
+            }
+        })
+        .collect()
+}
+
+fn attach(
+    source: &str,
+    comment: &Range<usize>,
+    anchors: &[Anchor],
+    blocks: &[Block],
+) -> (CommentAnchor, CommentPosition) {
+    // Only semicolons and commas may come between a node and a comment at the end of its line
+    let inline = anchors
+        .iter()
+        .filter(|it| {
+            it.end <= comment.start
+                && source[it.end..comment.start]
+                    .chars()
+                    .all(|c| matches!(c, ' ' | '\t' | ';' | ','))
+        })
+        .max_by_key(|it| (it.end, Reverse(it.start)));
+
+    if let Some(anchor) = inline {
+        return (anchor.anchor.clone(), CommentPosition::Inline);
+    }
+
+    let container = anchors
+        .iter()
+        .filter(|it| it.start < comment.start && comment.end <= it.end)
+        .max_by_key(|it| it.start);
+
+    // A block that starts before the innermost node belongs to an outer node, in which case the
+    // comment is between the tokens of the innermost node rather than in one of its blocks
+    let block = blocks
+        .iter()
+        .filter(|it| it.start < comment.start && comment.end <= it.end)
+        .max_by_key(|it| it.start)
+        .filter(|block| container.is_none_or(|container| block.start >= container.start));
+
+    let is_inside = |it: &&Anchor| match (block, container) {
+        (Some(block), _) => it.start > block.start && it.end <= block.end,
+        (None, Some(container)) => it.start > container.start && it.end <= container.end,
+        (None, None) => true,
+    };
+
+    let next = anchors
+        .iter()
+        .filter(is_inside)
+        .filter(|it| it.start >= comment.end)
+        .min_by_key(|it| (it.start, Reverse(it.end)));
+
+    if let Some(anchor) = next {
+        return (anchor.anchor.clone(), CommentPosition::Before);
+    }
+
+    let prev = anchors
+        .iter()
+        .filter(is_inside)
+        .filter(|it| it.end <= comment.start)
+        .max_by_key(|it| (it.end, Reverse(it.start)));
+
+    if let Some(anchor) = prev {
+        return (anchor.anchor.clone(), CommentPosition::After);
+    }
+
+    if let Some(block) = block {
+        return (block.owner.clone(), CommentPosition::Inside(block.index));
+    }
+
+    // The comment is between the tokens of a node, so it is moved before the node
+    match container {
+        Some(container) => (container.anchor.clone(), CommentPosition::Before),
+        None => (CommentAnchor::Module, CommentPosition::After),
+    }
+}
+
+/// Moves the end of a node back over the whitespace and comments at the end of its span, to the
+/// end of its last token.
+fn trim_trivia(source: &str, comments: &[Range<usize>], start: usize, mut end: usize) -> usize {
+    loop {
+        end = start + source[start..end].trim_end().len();
+
+        match comments.iter().find(|it| it.start < end && end <= it.end) {
+            Some(comment) => end = comment.start,
+            None => return end,
+        }
+    }
+}
+
+/// Collects the blocks of the functions and statements under `pair`, numbering the blocks of each
+/// node in the order that the writer emits them.
+fn collect_blocks(pair: Pair<Rule>, blocks: &mut Vec<Block>) {
+    for pair in pair.into_inner() {
+        match pair.as_rule() {
+            Rule::function_decl => {
+                let owner = CommentAnchor::Decl(crate::decl_name(&pair).to_owned());
+                let body = pair.clone().into_inner().last().unwrap();
+                push_block(blocks, &body, owner, 0);
+            }
+            Rule::statement => {
+                let stmt = pair
+                    .clone()
+                    .into_inner()
+                    .find(|it| it.as_rule() != Rule::attribute_list)
+                    .unwrap();
+                let owner = CommentAnchor::Stmt(stmt.as_span().start());
+
+                match stmt.as_rule() {
+                    Rule::compound_statement => push_block(blocks, &stmt, owner, 0),
+                    Rule::if_statement => {
+                        let mut index = 0;
+                        let mut stmt = Some(stmt);
+
+                        // The blocks of an else if chain all belong to the first if statement
+                        while let Some(pair) = stmt.take() {
+                            for pair in pair.into_inner() {
+                                match pair.as_rule() {
+                                    Rule::compound_statement => {
+                                        push_block(blocks, &pair, owner.clone(), index);
+                                        index += 1;
+                                    }
+                                    Rule::if_statement => stmt = Some(pair),
+                                    _ => {}
+                                }
+                            }
+                        }
+                    }
+                    Rule::loop_statement => {
+                        let span = stmt.as_span();
+                        let continuing = stmt
+                            .clone()
+                            .into_inner()
+                            .find(|it| it.as_rule() == Rule::continuing_statement);
+
+                        // The body of a loop isn't a compound statement, since it can end with
+                        // a continuing block
+                        let body_end = continuing
+                            .as_ref()
+                            .map_or(span.end(), |it| it.as_span().start());
+
+                        blocks.push(Block {
+                            start: span.start(),
+                            end: body_end,
+                            owner: owner.clone(),
+                            index: 0,
+                        });
+
+                        if let Some(continuing) = continuing {
+                            blocks.push(Block {
+                                start: continuing.as_span().start(),
+                                end: continuing.as_span().end(),
+                                owner,
+                                index: 1,
+                            });
+                        }
+                    }
+                    Rule::while_statement | Rule::for_statement => {
+                        let body = stmt.clone().into_inner().last().unwrap();
+                        push_block(blocks, &body, owner, 0);
+                    }
+                    Rule::switch_statement => {
+                        let cases: Vec<_> = stmt
+                            .clone()
+                            .into_inner()
+                            .filter(|it| it.as_rule() == Rule::switch_case)
+                            .collect();
+
+                        // The default case is written after the others
+                        let mut index = 0;
+                        let default_index = cases.len() - 1;
+
+                        for case in cases {
+                            let is_default = case.as_str().starts_with("default");
+                            let body = case.into_inner().last().unwrap();
+
+                            if is_default {
+                                push_block(blocks, &body, owner.clone(), default_index);
+                            } else {
+                                push_block(blocks, &body, owner.clone(), index);
+                                index += 1;
+                            }
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            _ => {}
+        }
+
+        collect_blocks(pair, blocks);
+    }
+}
+
+fn push_block(blocks: &mut Vec<Block>, pair: &Pair<Rule>, owner: CommentAnchor, index: usize) {
+    let span = pair.as_span();
+
+    // The statements of a body with attributes are wrapped in a compound statement, which owns
+    // the block instead
+    let (owner, index) = match pair.clone().into_inner().next() {
+        Some(attr) if attr.as_rule() == Rule::attribute_list => {
+            (CommentAnchor::Stmt(span.start()), 0)
+        }
+        _ => (owner, index),
+    };
+
+    blocks.push(Block {
+        start: span.start(),
+        end: span.end(),
+        owner,
+        index,
+    });
+}
+
+/// Returns the byte ranges of the comments in `source`, skipping over template holes.
+///
+/// WGSL has no string literals, so anything that starts with `//` or `/*` is a comment.
+fn scan_comments(source: &str) -> Vec<Range<usize>> {
+    let bytes = source.as_bytes();
+    let mut comments = vec![];
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let rest = &bytes[i..];
+        if rest.starts_with(b"//") {
+            let end = rest
+                .iter()
+                .position(|&b| b == b'\n')
+                .map_or(bytes.len(), |n| i + n);
+            comments.push(i..end);
+            i = end;
+        } else if rest.starts_with(b"/*@gen") {
+            i += rest
+                .windows(2)
+                .position(|w| w == b"*/")
+                .map_or(rest.len(), |n| n + 2);
+        } else if rest.starts_with(b"/*") {
+            // Block comments may be nested
+            let mut depth = 0;
+            let mut end = i;
+            while end < bytes.len() {
+                if bytes[end..].starts_with(b"/*") {
+                    depth += 1;
+                    end += 2;
+                } else if bytes[end..].starts_with(b"*/") {
+                    depth -= 1;
+                    end += 2;
+                    if depth == 0 {
+                        break;
+                    }
+                } else {
+                    end += 1;
+                }
+            }
+            comments.push(i..end);
+            i = end;
+        } else {
+            i += 1;
+        }
+    }
+
+    comments
+}
+
+/// Returns the text of a comment, with the indentation of its first line removed from the lines
+/// that follow.
+fn comment_text(source: &str, range: &Range<usize>) -> String {
+    let text = source[range.clone()].trim_end();
+    let line_start = source[..range.start].rfind('\n').map_or(0, |n| n + 1);
+    let indent = range.start - line_start;
+
+    let mut lines = text.split('\n');
+    let mut result = lines.next().unwrap().to_owned();
+
+    for line in lines {
+        let trimmed = line.trim_start_matches([' ', '\t']);
+        let removed = (line.len() - trimmed.len()).min(indent);
+        result.push('\n');
+        result.push_str(line[removed..].trim_end());
+    }
+
+    result
+}
+
+/// Collects the statements in blocks that carry a span, which are the ones that the writer can
+/// emit comments next to.
+struct StmtAnchors(Vec<Anchor>);
+
+impl<'a> Visitor<'a> for StmtAnchors {
+    fn visit_stmt(&mut self, stmt: &'a Statement) {
+        if let Some(span) = stmt.meta().and_then(|it| it.span) {
+            self.0.push(Anchor {
+                start: span.start,
+                end: span.end,
+                anchor: CommentAnchor::Stmt(span.start),
+            });
+        }
+
+        visit::walk_stmt(self, stmt);
+    }
+}
@@ -0,0 +1,289 @@
+use std::fmt::Display;
+use std::ops::Range;
+
+use ast::Span;
+use pest::error::{Error, ErrorVariant, InputLocation, LineColLocation};
+use pest::iterators::Pair;
+use pest::Parser;
+
+use crate::{Rule, WGSLParser};
+
+/// An error found while parsing a shader.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Diagnostic {
+    pub message: String,
+    pub span: Span,
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}:{}: {}",
+            self.span.line, self.span.column, self.message
+        )
+    }
+}
+
+/// Formats a list of diagnostics with one per line.
+pub fn format_diagnostics(diagnostics: &[Diagnostic]) -> String {
+    let lines: Vec<_> = diagnostics.iter().map(|it| it.to_string()).collect();
+    lines.join("\n")
+}
+
+/// Upper bound on the number of syntax errors that are reported, since each one requires the whole
+/// shader to be parsed again.
+const MAX_SYNTAX_ERRORS: usize = 32;
+
+/// Parses the syntax tree of a shader.
+///
+/// When the shader has a syntax error, the statement or declaration that contains it is blanked
+/// out and the shader is parsed again, so that the errors which follow it can be reported too.
+pub(crate) fn parse_syntax(input: &str) -> Result<Pair<'_, Rule>, Vec<Diagnostic>> {
+    let mut error = match WGSLParser::parse(Rule::translation_unit, input) {
+        Ok(mut pairs) => return Ok(pairs.next().unwrap()),
+        Err(error) => error,
+    };
+
+    let mut source = input.as_bytes().to_vec();
+    let mut diagnostics = vec![];
+
+    loop {
+        let diagnostic = syntax_diagnostic(&error, &source);
+        let range = recovery_range(&source, diagnostic.span.start);
+
+        diagnostics.push(diagnostic);
+
+        if diagnostics.len() == MAX_SYNTAX_ERRORS || !blank(&mut source, range) {
+            break;
+        }
+
+        // Blanked out ranges start and end on punctuation, so the source is still valid UTF-8
+        let source = std::str::from_utf8(&source).unwrap();
+        match WGSLParser::parse(Rule::translation_unit, source) {
+            Ok(_) => break,
+            Err(e) => error = e,
+        }
+    }
+
+    Err(diagnostics)
+}
+
+fn syntax_diagnostic(error: &Error<Rule>, source: &[u8]) -> Diagnostic {
+    let start = match error.location {
+        InputLocation::Pos(pos) => pos,
+        InputLocation::Span((start, _)) => start,
+    };
+
+    let (line, column) = match error.line_col {
+        LineColLocation::Pos(pos) => pos,
+        LineColLocation::Span(start, _) => start,
+    };
+
+    let found = found_token(source, start);
+
+    let message = match &error.variant {
+        ErrorVariant::ParsingError { positives, .. } if !positives.is_empty() => {
+            let mut expected: Vec<&str> = vec![];
+            for rule in positives {
+                let name = describe_rule(*rule);
+                if !expected.contains(&name) {
+                    expected.push(name);
+                }
+            }
+
+            let expected = match expected.split_last() {
+                Some((last, [])) => last.to_string(),
+                Some((last, rest)) => format!("{} or {last}", rest.join(", ")),
+                None => unreachable!(),
+            };
+
+            format!("expected {expected}, found {found}")
+        }
+        ErrorVariant::ParsingError { .. } => format!("unexpected {found}"),
+        ErrorVariant::CustomError { message } => message.clone(),
+    };
+
+    Diagnostic {
+        message,
+        span: Span {
+            start,
+            end: start,
+            line,
+            column,
+        },
+    }
+}
+
+/// Describes the token at `offset`, for use in error messages.
+fn found_token(source: &[u8], offset: usize) -> String {
+    let rest = &source[offset.min(source.len())..];
+
+    let len = match rest.first() {
+        None => return "end of input".to_owned(),
+        Some(c) if c.is_ascii_alphanumeric() || *c == b'_' => rest
+            .iter()
+            .take_while(|c| c.is_ascii_alphanumeric() || **c == b'_')
+            .count(),
+        // A character other than ASCII is followed by its continuation bytes
+        Some(_) => 1 + rest[1..].iter().take_while(|c| **c & 0xC0 == 0x80).count(),
+    };
+
+    format!("`{}`", String::from_utf8_lossy(&rest[..len]))
+}
+
+fn describe_rule(rule: Rule) -> &'static str {
+    match rule {
+        Rule::EOI => "end of input",
+        Rule::ident => "identifier",
+        Rule::global_decl => "declaration",
+        Rule::statement => "statement",
+        Rule::type_decl | Rule::built_in_type_decl => "type",
+        Rule::attribute_list | Rule::attribute => "attribute",
+        Rule::compound_statement => "block",
+        Rule::expression
+        | Rule::unary_expression
+        | Rule::singular_expression
+        | Rule::primary_expression => "expression",
+        Rule::postfix_expression => "`[` or `.`",
+        Rule::assignment_operator | Rule::compound_assignment_operator => "assignment",
+        Rule::literal_expression
+        | Rule::bool_literal
+        | Rule::int_literal
+        | Rule::uint_literal
+        | Rule::float_literal => "literal",
+        Rule::op_plus
+        | Rule::op_minus
+        | Rule::op_times
+        | Rule::op_divide
+        | Rule::op_mod
+        | Rule::op_log_and
+        | Rule::op_log_or
+        | Rule::op_bit_and
+        | Rule::op_bit_or
+        | Rule::op_bit_xor
+        | Rule::op_lshift
+        | Rule::op_rshift
+        | Rule::op_equal
+        | Rule::op_nequal
+        | Rule::op_less
+        | Rule::op_less_eq
+        | Rule::op_greater
+        | Rule::op_greater_eq => "operator",
+        Rule::op_increment | Rule::op_decrement => "`++` or `--`",
+        Rule::storage_class => "address space",
+        Rule::access_mode => "access mode",
+        Rule::texel_format => "texel format",
+        Rule::param => "parameter",
+        Rule::struct_member => "struct member",
+        Rule::diagnostic_rule => "diagnostic rule",
+        _ => "token",
+    }
+}
+
+/// Returns the range of the statement or module scope declaration that contains `offset`.
+///
+/// Boundaries are found from the nesting of braces and parentheses, which doesn't require the
+/// source to be syntactically valid.
+fn recovery_range(source: &[u8], offset: usize) -> Range<usize> {
+    let marks = punctuation(source);
+
+    let mut braces = 0;
+    let mut parens = 0;
+    let mut start = 0;
+
+    for &(i, c) in marks.iter().take_while(|(i, _)| *i < offset) {
+        match c {
+            b'(' => parens += 1,
+            b')' => parens -= 1,
+            b'{' => braces += 1,
+            b'}' => braces -= 1,
+            _ => {}
+        }
+
+        if parens <= 0 && matches!(c, b'{' | b'}' | b';') {
+            start = i + 1;
+        }
+    }
+
+    let mut depth = 0;
+    let mut end = source.len();
+
+    for &(i, c) in marks.iter().skip_while(|(i, _)| *i < offset) {
+        match c {
+            b'(' => parens += 1,
+            b')' => parens -= 1,
+            b'{' => depth += 1,
+            b'}' if depth == 0 => {
+                // This closes the block that contains the statement, unless we're at module scope
+                end = if braces > 0 { i } else { i + 1 };
+                break;
+            }
+            b'}' => {
+                depth -= 1;
+                if depth == 0 && parens <= 0 && !is_followed_by_else(source, i + 1) {
+                    end = i + 1;
+                    break;
+                }
+            }
+            b';' if depth == 0 && parens <= 0 => {
+                end = i + 1;
+                break;
+            }
+            _ => {}
+        }
+    }
+
+    start..end
+}
+
+/// Finds the positions of braces, parentheses and semicolons outside of comments.
+fn punctuation(source: &[u8]) -> Vec<(usize, u8)> {
+    let mut marks = vec![];
+    let mut i = 0;
+
+    while i < source.len() {
+        match &source[i..] {
+            [b'/', b'/', ..] => {
+                while i < source.len() && source[i] != b'\n' {
+                    i += 1;
+                }
+            }
+            [b'/', b'*', ..] => {
+                i += 2;
+                while i < source.len() && !source[i..].starts_with(b"*/") {
+                    i += 1;
+                }
+                i += 2;
+            }
+            [c @ (b'{' | b'}' | b'(' | b')' | b';'), ..] => {
+                marks.push((i, *c));
+                i += 1;
+            }
+            _ => i += 1,
+        }
+    }
+
+    marks
+}
+
+fn is_followed_by_else(source: &[u8], offset: usize) -> bool {
+    let rest = &source[offset.min(source.len())..];
+    let skipped = rest.iter().take_while(|c| c.is_ascii_whitespace()).count();
+    rest[skipped..].starts_with(b"else")
+}
+
+/// Replaces the characters in `range` with spaces, keeping line breaks so that the positions of
+/// later errors are unchanged. Returns `false` if there was nothing to blank out.
+fn blank(source: &mut [u8], range: Range<usize>) -> bool {
+    let mut changed = false;
+
+    for c in &mut source[range] {
+        if !c.is_ascii_whitespace() {
+            *c = b' ';
+            changed = true;
+        }
+    }
+
+    changed
+}
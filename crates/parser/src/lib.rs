@@ -1,7 +1,16 @@
+mod comments;
+mod diagnostic;
+
+use std::cell::RefCell;
+use std::collections::HashMap;
 use std::hash::Hash;
 use std::rc::Rc;
+use std::str::FromStr;
 
-use ast::types::{DataType, MemoryViewType, ScalarType};
+use ast::types::{
+    DataType, MemoryViewType, SamplerKind, ScalarType, TextureDimension, TextureType,
+    TextureViewDimension,
+};
 use ast::*;
 use peeking_take_while::PeekableExt;
 use pest::iterators::Pair;
@@ -10,6 +19,18 @@ use pest::Parser;
 use rpds::HashTrieMap;
 use strum::IntoEnumIterator;
 
+pub use diagnostic::{format_diagnostics, Diagnostic};
+
+/// Name of the placeholder call that a `/*@gen expr:T*/` template hole is parsed into.
+///
+/// The resulting expression node has type `T` and no arguments.
+pub const EXPR_HOLE_FN: &str = "_wgslsmith_gen_expr";
+
+/// Name of the placeholder call statement that a `/*@gen stmts:N*/` template hole is parsed into.
+///
+/// The call has a single `u32` literal argument holding the requested statement count.
+pub const STMTS_HOLE_FN: &str = "_wgslsmith_gen_stmts";
+
 #[derive(pest_derive::Parser)]
 #[grammar = "grammar.pest"]
 struct WGSLParser;
@@ -33,6 +54,44 @@ pub struct Environment {
     vars: HashTrieMap<String, DataType>,
     fns: HashTrieMap<String, Func>,
     types: HashTrieMap<String, Rc<StructDecl>>,
+    /// Errors found so far, which are shared by every scope.
+    diagnostics: Rc<RefCell<Vec<Diagnostic>>>,
+    /// Lines of the source being parsed, for finding the positions of spans.
+    lines: Rc<LineIndex>,
+}
+
+/// The offsets at which the lines of a source start.
+///
+/// Pest finds the line of a position by counting the lines before it, which is quadratic when
+/// done for every node of a large shader.
+#[derive(Default)]
+struct LineIndex {
+    source: String,
+    starts: Vec<usize>,
+}
+
+impl LineIndex {
+    fn new(source: &str) -> LineIndex {
+        let starts = std::iter::once(0)
+            .chain(source.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+
+        LineIndex {
+            source: source.to_owned(),
+            starts,
+        }
+    }
+
+    /// Returns the (1-based) line and column of a byte offset.
+    fn line_col(&self, offset: usize) -> (usize, usize) {
+        let line = self.starts.partition_point(|&start| start <= offset).max(1);
+        let start = self.starts.get(line - 1).copied().unwrap_or(0);
+        let column = self
+            .source
+            .get(start..offset)
+            .map_or(0, |it| it.chars().count());
+        (line, column + 1)
+    }
 }
 
 fn builtins() -> HashTrieMap<String, Func> {
@@ -47,9 +106,16 @@ impl Environment {
             vars: HashTrieMap::new(),
             fns: builtins(),
             types: HashTrieMap::new(),
+            diagnostics: Default::default(),
+            lines: Default::default(),
         }
     }
 
+    /// Sets the source that spans are positioned in.
+    pub fn set_source(&mut self, source: &str) {
+        self.lines = Rc::new(LineIndex::new(source));
+    }
+
     pub fn var(&self, name: &str) -> Option<&DataType> {
         self.vars.get(name)
     }
@@ -77,78 +143,313 @@ impl Environment {
     pub fn insert_func(&mut self, name: String, ret_ty: DataType) {
         self.fns.insert_mut(name, Func::User(ret_ty));
     }
+
+    fn span(&self, pair: &Pair<Rule>) -> Span {
+        let span = pair.as_span();
+        let (line, column) = self.lines.line_col(span.start());
+        Span {
+            start: span.start(),
+            end: span.end(),
+            line,
+            column,
+        }
+    }
+
+    fn error(&self, pair: &Pair<Rule>, message: impl Into<String>) {
+        self.error_at(self.span(pair), message);
+    }
+
+    fn error_at(&self, span: Span, message: impl Into<String>) {
+        self.diagnostics.borrow_mut().push(Diagnostic {
+            message: message.into(),
+            span,
+        });
+    }
+
+    /// Returns the type of an expression, or reports `result`'s error at `span` and returns a
+    /// placeholder.
+    ///
+    /// Operands with errors are given placeholder types, so the error is only reported if none
+    /// have been reported within `span` already.
+    fn type_or_error(&self, result: Result<DataType, String>, span: Span) -> DataType {
+        result.unwrap_or_else(|message| {
+            let has_errors = self
+                .diagnostics
+                .borrow()
+                .iter()
+                .any(|it| it.span.start >= span.start && it.span.end <= span.end);
+
+            if !has_errors {
+                self.error_at(span, message);
+            }
+
+            error_type()
+        })
+    }
 }
 
+/// Type given to nodes whose real type can't be determined because of an error.
+fn error_type() -> DataType {
+    DataType::Scalar(ScalarType::I32)
+}
+
+/// Parses a WGSL module, panicking if it is invalid.
+///
+/// Expressions and statements in the resulting module have their source spans attached, and are
+/// numbered with [`ast::assign_node_ids`].
 pub fn parse(input: &str) -> Module {
-    let pairs = WGSLParser::parse(Rule::translation_unit, input).unwrap();
-    let pair = pairs.into_iter().next().unwrap();
-    parse_translation_unit(pair, &mut Environment::new())
+    try_parse(input).unwrap_or_else(|diagnostics| {
+        panic!("invalid shader:\n{}", format_diagnostics(&diagnostics))
+    })
+}
+
+/// Parses a WGSL module, returning every error that was found if it is invalid.
+///
+/// Parsing continues after an error at the next statement or declaration, so that all of the
+/// errors in a shader can be reported at once. Errors like references to undeclared variables are
+/// only reported once the shader is syntactically valid.
+pub fn try_parse(input: &str) -> Result<Module, Vec<Diagnostic>> {
+    let pair = diagnostic::parse_syntax(input)?;
+
+    let mut env = Environment::new();
+    env.set_source(input);
+    let mut module = parse_translation_unit(pair.clone(), &mut env);
+
+    let mut diagnostics = env.diagnostics.take();
+    if !diagnostics.is_empty() {
+        // Function return types are parsed twice, so errors in them are reported twice
+        diagnostics.sort_by_key(|it| it.span.start);
+        diagnostics.dedup();
+        return Err(diagnostics);
+    }
+
+    module.comments = comments::attach_comments(input, pair, &module);
+
+    ast::assign_node_ids(&mut module);
+    Ok(module)
 }
 
 pub fn parse_fn(input: &str, env: &mut Environment) -> FnDecl {
     let pairs = WGSLParser::parse(Rule::function_decl, input).unwrap();
     let pair = pairs.into_iter().next().unwrap();
+    env.set_source(input);
     parse_function_decl(pair, env)
 }
 
 fn parse_translation_unit(pair: Pair<Rule>, env: &mut Environment) -> Module {
-    let decls = pair
+    let mut extensions = vec![];
+    let mut requires = vec![];
+    let mut diagnostics = vec![];
+    let mut items = vec![];
+
+    for pair in pair
         .into_inner()
         .take_while(|pair| pair.as_rule() != Rule::EOI)
-        .map(|pair| parse_global_decl(pair, env))
-        .collect::<Vec<_>>();
+    {
+        let pair = pair.into_inner().next().unwrap();
+        match pair.as_rule() {
+            Rule::enable_directive => extensions.extend(parse_enable_directive(pair, env)),
+            Rule::requires_directive => requires.extend(parse_requires_directive(pair, env)),
+            Rule::diagnostic_directive => {
+                let control = pair.into_inner().next().unwrap();
+                diagnostics.extend(parse_diagnostic_control(control, env));
+            }
+            _ => items.push(pair),
+        }
+    }
 
-    let mut extensions = vec![];
-    let mut functions = vec![];
-    let mut structs = vec![];
-    let mut consts = vec![];
-    let mut vars = vec![];
-
-    for decl in decls {
-        match decl {
-            GlobalDecl::Enable(decl) => extensions.push(decl),
-            GlobalDecl::Const(decl) => consts.push(decl),
-            GlobalDecl::Var(decl) => vars.push(decl),
-            GlobalDecl::Struct(decl) => structs.push(decl),
-            GlobalDecl::Fn(decl) => functions.push(decl),
+    // Module scope declarations can be used before they are declared, so everything apart from
+    // function bodies is parsed first, in an order where each declaration comes after the ones
+    // that it refers to
+    let mut decls: Vec<Option<GlobalDecl>> = items.iter().map(|_| None).collect();
+
+    for index in dependency_order(&items) {
+        let pair = items[index].clone();
+        decls[index] = match pair.as_rule() {
+            Rule::global_constant_decl => {
+                Some(GlobalDecl::Const(parse_global_const_decl(pair, env)))
+            }
+            Rule::global_variable_decl => {
+                Some(GlobalDecl::Var(parse_global_variable_decl(pair, env)))
+            }
+            Rule::struct_decl => Some(GlobalDecl::Struct(parse_struct_decl(pair, env))),
+            Rule::function_decl => {
+                declare_function(pair, env);
+                None
+            }
+            _ => unreachable!(),
+        };
+    }
+
+    for (decl, pair) in decls.iter_mut().zip(items) {
+        if pair.as_rule() == Rule::function_decl {
+            *decl = Some(GlobalDecl::Fn(parse_function_decl(pair, env)));
         }
     }
 
     Module {
         extensions,
-        functions,
-        structs,
-        consts,
-        vars,
+        requires,
+        diagnostics,
+        decls: decls.into_iter().map(Option::unwrap).collect(),
+        comments: vec![],
+    }
+}
+
+/// Returns the indices of module scope declarations in an order where each declaration comes
+/// after the declarations that its types and initializer refer to.
+///
+/// Function bodies are not considered, since they are parsed once every signature is known.
+/// Cyclic references are invalid WGSL, and are left for the parser to reject.
+fn dependency_order(items: &[Pair<Rule>]) -> Vec<usize> {
+    fn collect_refs<'i>(pair: Pair<'i, Rule>, refs: &mut Vec<&'i str>) {
+        let rule = pair.as_rule();
+        for pair in pair.into_inner() {
+            match pair.as_rule() {
+                Rule::compound_statement => {}
+                Rule::ident => {
+                    if let Rule::type_decl | Rule::call_expression | Rule::var_expression = rule {
+                        refs.push(pair.as_str());
+                    }
+                }
+                _ => collect_refs(pair, refs),
+            }
+        }
+    }
+
+    fn visit(index: usize, deps: &[Vec<usize>], visited: &mut [bool], order: &mut Vec<usize>) {
+        if std::mem::replace(&mut visited[index], true) {
+            return;
+        }
+
+        for dep in &deps[index] {
+            visit(*dep, deps, visited, order);
+        }
+
+        order.push(index);
+    }
+
+    let names: HashMap<&str, usize> = items
+        .iter()
+        .enumerate()
+        .map(|(index, pair)| (decl_name(pair), index))
+        .collect();
+
+    let deps: Vec<Vec<usize>> = items
+        .iter()
+        .map(|pair| {
+            let mut refs = vec![];
+            collect_refs(pair.clone(), &mut refs);
+            refs.iter()
+                .filter_map(|it| names.get(it).copied())
+                .collect()
+        })
+        .collect();
+
+    let mut visited = vec![false; items.len()];
+    let mut order = vec![];
+
+    for index in 0..items.len() {
+        visit(index, &deps, &mut visited, &mut order);
     }
+
+    order
 }
 
-enum GlobalDecl {
-    Enable(Extension),
-    Const(GlobalConstDecl),
-    Var(GlobalVarDecl),
-    Struct(Rc<StructDecl>),
-    Fn(FnDecl),
+/// Returns the name of a module scope declaration or struct member.
+fn decl_name<'i>(pair: &Pair<'i, Rule>) -> &'i str {
+    let ident = pair
+        .clone()
+        .into_inner()
+        .find(|it| it.as_rule() == Rule::ident);
+    ident.unwrap().as_str()
 }
 
-fn parse_global_decl(pair: Pair<Rule>, env: &mut Environment) -> GlobalDecl {
-    let pair = pair.into_inner().next().unwrap();
-    match pair.as_rule() {
-        Rule::enable_directive => GlobalDecl::Enable(parse_enable_directive(pair)),
-        Rule::global_constant_decl => GlobalDecl::Const(parse_global_const_decl(pair, env)),
-        Rule::global_variable_decl => GlobalDecl::Var(parse_global_variable_decl(pair, env)),
-        Rule::struct_decl => GlobalDecl::Struct(parse_struct_decl(pair, env)),
-        Rule::function_decl => GlobalDecl::Fn(parse_function_decl(pair, env)),
-        _ => unreachable!(),
+/// Adds a function's return type to the environment, so that it can be called before its
+/// declaration is parsed.
+fn declare_function(pair: Pair<Rule>, env: &mut Environment) {
+    let mut pairs = pair
+        .into_inner()
+        .skip_while(|pair| pair.as_rule() == Rule::attribute_list);
+
+    let name = pairs.next().unwrap().as_str().to_owned();
+
+    if let Some(pair) = pairs.find(|pair| pair.as_rule() == Rule::type_decl) {
+        let data_type = parse_type_decl(pair, env);
+        env.insert_func(name, data_type);
     }
 }
 
-fn parse_enable_directive(pair: Pair<Rule>) -> Extension {
-    match pair.into_inner().next().unwrap().as_str() {
-        "f16" => Extension::F16,
-        "subgroups" => Extension::Subgroups,
-        ext => panic!("unsupported extension: {}", ext),
+fn parse_enable_directive(pair: Pair<Rule>, env: &Environment) -> Vec<Extension> {
+    pair.into_inner()
+        .filter_map(|pair| {
+            let ext = pair.as_str().parse().ok();
+            if ext.is_none() {
+                env.error(&pair, format!("unsupported extension `{}`", pair.as_str()));
+            }
+            ext
+        })
+        .collect()
+}
+
+fn parse_requires_directive(pair: Pair<Rule>, env: &Environment) -> Vec<LanguageFeature> {
+    pair.into_inner()
+        .filter_map(|pair| {
+            let feature = pair.as_str().parse().ok();
+            if feature.is_none() {
+                env.error(
+                    &pair,
+                    format!("unsupported language feature `{}`", pair.as_str()),
+                );
+            }
+            feature
+        })
+        .collect()
+}
+
+/// Parses the severity and rule name of a `diagnostic` directive or attribute.
+///
+/// Any rule name is accepted, since implementations are expected to ignore rules that they don't
+/// recognise.
+fn parse_diagnostic_control(pair: Pair<Rule>, env: &Environment) -> Option<DiagnosticControl> {
+    let mut pairs = pair.into_inner();
+    let severity = pairs.next().unwrap();
+    let rule = pairs.next().unwrap();
+
+    let Ok(severity) = severity.as_str().parse() else {
+        env.error(
+            &severity,
+            format!("invalid diagnostic severity `{}`", severity.as_str()),
+        );
+        return None;
+    };
+
+    Some(DiagnosticControl::new(severity, rule.as_str()))
+}
+
+/// Parses the argument of an attribute, reporting an error if it is missing or invalid.
+fn parse_attr_arg<T: FromStr>(
+    attr: &Pair<Rule>,
+    arg: Option<Pair<Rule>>,
+    env: &Environment,
+) -> Option<T> {
+    let Some(arg) = arg else {
+        env.error(
+            attr,
+            format!("missing argument for attribute `{}`", attr.as_str()),
+        );
+        return None;
+    };
+
+    let value = arg.as_str().trim().parse().ok();
+    if value.is_none() {
+        env.error(
+            &arg,
+            format!("invalid attribute argument `{}`", arg.as_str()),
+        );
     }
+
+    value
 }
 
 fn parse_global_const_decl(pair: Pair<Rule>, env: &mut Environment) -> GlobalConstDecl {
@@ -182,17 +483,21 @@ fn parse_global_variable_decl(pair: Pair<Rule>, env: &mut Environment) -> Global
     let attrs = pairs
         .by_ref()
         .peeking_take_while(|pair| pair.as_rule() == Rule::attribute_list)
-        .flat_map(|pair| {
-            pair.into_inner().map(|pair| {
-                let mut pairs = pair.into_inner();
-                let name = pairs.next().unwrap().as_str();
-                let arg = pairs.next().unwrap().as_str();
-                match name {
-                    "binding" => GlobalVarAttr::Binding(arg.parse().unwrap()),
-                    "group" => GlobalVarAttr::Group(arg.parse().unwrap()),
-                    _ => panic!("invalid global variable attribute: {}", name),
+        .flat_map(|pair| pair.into_inner())
+        .filter_map(|attr| {
+            let mut pairs = attr.clone().into_inner();
+            let name = pairs.next().unwrap();
+            match name.as_str() {
+                "binding" => parse_attr_arg(&attr, pairs.next(), env).map(GlobalVarAttr::Binding),
+                "group" => parse_attr_arg(&attr, pairs.next(), env).map(GlobalVarAttr::Group),
+                _ => {
+                    env.error(
+                        &name,
+                        format!("invalid variable attribute `{}`", name.as_str()),
+                    );
+                    None
                 }
-            })
+            }
         })
         .collect();
 
@@ -205,12 +510,7 @@ fn parse_global_variable_decl(pair: Pair<Rule>, env: &mut Environment) -> Global
 
             let access_mode = if matches!(pairs.peek(), Some(access_mode) if access_mode.as_rule() == Rule::access_mode)
             {
-                Some(match pairs.next().unwrap().as_str() {
-                    "read" => AccessMode::Read,
-                    "write" => AccessMode::Write,
-                    "read_write" => AccessMode::ReadWrite,
-                    _ => unreachable!(),
-                })
+                Some(parse_access_mode(pairs.next().unwrap()))
             } else {
                 None
             };
@@ -222,8 +522,8 @@ fn parse_global_variable_decl(pair: Pair<Rule>, env: &mut Environment) -> Global
         }
     }
 
-    let qualifier = qualifier.expect("module scope var declaration must specify storage class");
-    let name = pairs.next().unwrap().as_str().to_owned();
+    let name_pair = pairs.next().unwrap();
+    let name = name_pair.as_str().to_owned();
     let mut data_type = None;
     let mut expr = None;
 
@@ -239,23 +539,40 @@ fn parse_global_variable_decl(pair: Pair<Rule>, env: &mut Environment) -> Global
         expr = Some(parse_expression(pair, env))
     }
 
-    let data_type = data_type.unwrap_or_else(|| {
-        expr.as_ref()
-            .expect("var declaration must have type or initializer")
-            .data_type
-            .clone()
-    });
+    let data_type = match (data_type, &expr) {
+        (Some(data_type), _) => data_type,
+        (None, Some(expr)) => expr.data_type.clone(),
+        (None, None) => {
+            env.error(
+                &name_pair,
+                format!("variable `{name}` must have a type or initializer"),
+            );
+            error_type()
+        }
+    };
 
-    let mut ref_view = MemoryViewType::new(data_type.clone(), qualifier.storage_class);
-    if let Some(access_mode) = qualifier.access_mode {
-        ref_view.access_mode = access_mode;
-    }
+    if let Some(qualifier) = &qualifier {
+        let mut ref_view = MemoryViewType::new(data_type.clone(), qualifier.storage_class);
+        if let Some(access_mode) = qualifier.access_mode {
+            ref_view.access_mode = access_mode;
+        }
 
-    env.insert_var(name.clone(), DataType::Ref(ref_view));
+        env.insert_var(name.clone(), DataType::Ref(ref_view));
+    } else if data_type.is_handle() {
+        // Textures and samplers are handle types which live in the handle address space, which
+        // cannot be written explicitly
+        env.insert_var(name.clone(), data_type.clone());
+    } else {
+        env.error(
+            &name_pair,
+            format!("variable `{name}` must have an address space"),
+        );
+        env.insert_var(name.clone(), data_type.clone());
+    }
 
     GlobalVarDecl {
         attrs,
-        qualifier: Some(qualifier),
+        qualifier,
         name,
         data_type,
         initializer: expr,
@@ -272,36 +589,8 @@ fn parse_struct_decl(pair: Pair<Rule>, env: &mut Environment) -> Rc<StructDecl>
             let attrs = pairs
                 .by_ref()
                 .peeking_take_while(|pair| pair.as_rule() == Rule::attribute_list)
-                .flat_map(|pair| {
-                    pair.into_inner().map(|pair| {
-                        let mut pairs = pair.into_inner();
-                        let name = pairs.next().unwrap().as_str();
-                        match name {
-                            "align" => StructMemberAttr::Align(
-                                pairs.next().unwrap().as_str().parse().unwrap(),
-                            ),
-                            "blend_src" => StructMemberAttr::BlendSrc(
-                                pairs.next().unwrap().as_str().parse().unwrap(),
-                            ),
-                            "size" => StructMemberAttr::Size(
-                                pairs.next().unwrap().as_str().parse().unwrap(),
-                            ),
-                            "builtin" => StructMemberAttr::Builtin(
-                                pairs.next().unwrap().as_str().parse().unwrap(),
-                            ),
-                            "interpolate" => {
-                                let ty = pairs.next().unwrap().as_str().parse().unwrap();
-                                let sample = pairs.next().map(|arg| arg.as_str().parse().unwrap());
-                                StructMemberAttr::Interpolate(ty, sample)
-                            }
-                            "invariant" => StructMemberAttr::Invariant,
-                            "location" => StructMemberAttr::Location(
-                                pairs.next().unwrap().as_str().parse().unwrap(),
-                            ),
-                            _ => panic!("invalid struct member attribute: {}", name),
-                        }
-                    })
-                })
+                .flat_map(|pair| pair.into_inner())
+                .filter_map(|attr| parse_struct_member_attr(attr, env))
                 .collect();
 
             let name = pairs.next().unwrap().as_str().to_owned();
@@ -318,13 +607,45 @@ fn parse_struct_decl(pair: Pair<Rule>, env: &mut Environment) -> Rc<StructDecl>
     decl
 }
 
+fn parse_struct_member_attr(attr: Pair<Rule>, env: &Environment) -> Option<StructMemberAttr> {
+    let mut pairs = attr.clone().into_inner();
+    let name = pairs.next().unwrap();
+
+    let attr = match name.as_str() {
+        "align" => StructMemberAttr::Align(parse_attr_arg(&attr, pairs.next(), env)?),
+        "blend_src" => StructMemberAttr::BlendSrc(parse_attr_arg(&attr, pairs.next(), env)?),
+        "size" => StructMemberAttr::Size(parse_attr_arg(&attr, pairs.next(), env)?),
+        "builtin" => StructMemberAttr::Builtin(parse_attr_arg(&attr, pairs.next(), env)?),
+        "interpolate" => {
+            let ty = parse_attr_arg(&attr, pairs.next(), env)?;
+            let sample = match pairs.next() {
+                Some(arg) => Some(parse_attr_arg(&attr, Some(arg), env)?),
+                None => None,
+            };
+            StructMemberAttr::Interpolate(ty, sample)
+        }
+        "invariant" => StructMemberAttr::Invariant,
+        "location" => StructMemberAttr::Location(parse_attr_arg(&attr, pairs.next(), env)?),
+        _ => {
+            env.error(
+                &name,
+                format!("invalid struct member attribute `{}`", name.as_str()),
+            );
+            return None;
+        }
+    };
+
+    Some(attr)
+}
+
 fn parse_function_decl(pair: Pair<Rule>, env: &mut Environment) -> FnDecl {
     let mut pairs = pair.into_inner().peekable();
 
     let attrs = pairs
         .by_ref()
         .peeking_take_while(|pair| pair.as_rule() == Rule::attribute_list)
-        .flat_map(|pair| pair.into_inner().map(|p| parse_fn_attr(p, env)))
+        .flat_map(|pair| pair.into_inner())
+        .filter_map(|attr| parse_fn_attr(attr, env))
         .collect::<Vec<_>>();
 
     let name = pairs.next().unwrap().as_str().to_owned();
@@ -337,7 +658,8 @@ fn parse_function_decl(pair: Pair<Rule>, env: &mut Environment) -> FnDecl {
             let attrs = pairs
                 .by_ref()
                 .peeking_take_while(|pair| pair.as_rule() == Rule::attribute_list)
-                .flat_map(|pair| pair.into_inner().map(parse_fn_io_attr))
+                .flat_map(|pair| pair.into_inner())
+                .filter_map(|attr| parse_fn_io_attr(attr, env))
                 .collect::<Vec<_>>();
             let name = pairs.next().unwrap().as_str().to_owned();
             let data_type = parse_type_decl(pairs.next().unwrap(), env);
@@ -352,7 +674,8 @@ fn parse_function_decl(pair: Pair<Rule>, env: &mut Environment) -> FnDecl {
     let output_attrs = pairs
         .by_ref()
         .peeking_take_while(|pair| pair.as_rule() == Rule::attribute_list)
-        .flat_map(|pair| pair.into_inner().map(parse_fn_io_attr))
+        .flat_map(|pair| pair.into_inner())
+        .filter_map(|attr| parse_fn_io_attr(attr, env))
         .collect::<Vec<_>>();
 
     let output = pairs
@@ -373,7 +696,7 @@ fn parse_function_decl(pair: Pair<Rule>, env: &mut Environment) -> FnDecl {
         env.insert_var(param.name.clone(), param.data_type.clone());
     }
 
-    let body = parse_compound_statement(pairs.next().unwrap(), &env).into_compound_statement();
+    let body = parse_block(pairs.next().unwrap(), &env);
 
     FnDecl {
         attrs,
@@ -385,17 +708,26 @@ fn parse_function_decl(pair: Pair<Rule>, env: &mut Environment) -> FnDecl {
 }
 
 fn parse_statement(pair: Pair<Rule>, env: &mut Environment) -> Statement {
-    let pair = if pair.as_rule() == Rule::statement {
-        pair.into_inner().next().unwrap()
+    let (attrs, pair) = if pair.as_rule() == Rule::statement {
+        let mut pairs = pair.into_inner().peekable();
+        let attrs = parse_stmt_attrs(
+            pairs
+                .peeking_take_while(|pair| pair.as_rule() == Rule::attribute_list)
+                .flat_map(|pair| pair.into_inner()),
+            env,
+        );
+        (attrs, pairs.next().unwrap())
     } else {
-        pair
+        (vec![], pair)
     };
 
-    match pair.as_rule() {
+    let span = env.span(&pair);
+    let mut stmt = match pair.as_rule() {
+        Rule::hole_statement => parse_hole_statement(pair),
         Rule::let_statement => parse_let_statement(pair, env),
         Rule::var_statement => parse_var_statement(pair, env),
         Rule::assignment_statement => parse_assignment_statement(pair, env),
-        Rule::compound_statement => parse_compound_statement(pair, env),
+        Rule::compound_statement => parse_compound_statement(pair, env).into(),
         Rule::if_statement => parse_if_statement(pair, env),
         Rule::return_statement => parse_return_statement(pair, env),
         Rule::loop_statement => parse_loop_statement(pair, env),
@@ -409,7 +741,22 @@ fn parse_statement(pair: Pair<Rule>, env: &mut Environment) -> Statement {
         Rule::increment_statement => parse_increment_statement(pair, env),
         Rule::decrement_statement => parse_decrement_statement(pair, env),
         _ => unreachable!(),
+    };
+
+    if let Some(meta) = stmt.meta_mut() {
+        meta.span = Some(span);
+    }
+
+    if !attrs.is_empty() {
+        *stmt.attrs_mut().unwrap() = attrs;
     }
+
+    stmt
+}
+
+fn parse_hole_statement(pair: Pair<Rule>) -> Statement {
+    let count = pair.into_inner().next().unwrap().as_str().parse().unwrap();
+    FnCallStatement::new(STMTS_HOLE_FN.to_owned(), vec![Lit::U32(count).into()]).into()
 }
 
 fn parse_increment_statement(pair: Pair<Rule>, env: &Environment) -> Statement {
@@ -427,15 +774,32 @@ fn parse_decrement_statement(pair: Pair<Rule>, env: &Environment) -> Statement {
 fn parse_let_statement(pair: Pair<Rule>, env: &mut Environment) -> Statement {
     let mut pairs = pair.into_inner();
     let ident = pairs.next().unwrap().as_str().to_owned();
-    let initializer = parse_expression(pairs.next().unwrap(), env);
-    let stmt = LetDeclStatement::new(ident.clone(), initializer);
+
+    let mut pair = pairs.next().unwrap();
+
+    let specified_type = if pair.as_rule() == Rule::type_decl {
+        let ty = parse_type_decl(pair, env);
+        pair = pairs.next().unwrap();
+        Some(ty)
+    } else {
+        None
+    };
+
+    let initializer = parse_expression(pair, env);
+
+    let stmt = match specified_type {
+        Some(ty) => LetDeclStatement::new(ident.clone(), initializer).with_type(ty),
+        None => LetDeclStatement::new(ident.clone(), initializer),
+    };
+
     env.insert_var(ident, stmt.inferred_type().clone());
     stmt.into()
 }
 
 fn parse_var_statement(pair: Pair<Rule>, env: &mut Environment) -> Statement {
     let mut pairs = pair.into_inner();
-    let ident = pairs.next().unwrap().as_str().to_owned();
+    let ident_pair = pairs.next().unwrap();
+    let ident = ident_pair.as_str().to_owned();
 
     let mut pair = pairs.next();
 
@@ -453,6 +817,16 @@ fn parse_var_statement(pair: Pair<Rule>, env: &mut Environment) -> Statement {
         None
     };
 
+    let specified_type = if specified_type.is_none() && initializer.is_none() {
+        env.error(
+            &ident_pair,
+            format!("variable `{ident}` must have a type or initializer"),
+        );
+        Some(error_type())
+    } else {
+        specified_type
+    };
+
     let stmt = VarDeclStatement::new(ident.clone(), specified_type, initializer);
 
     let ref_view = MemoryViewType::new(stmt.inferred_type().clone(), StorageClass::Function);
@@ -496,32 +870,55 @@ fn parse_assignment_lhs(pair: Pair<Rule>, env: &Environment) -> AssignmentLhs {
     }
 }
 
-fn parse_compound_statement(pair: Pair<Rule>, env: &Environment) -> Statement {
+fn parse_compound_statement(pair: Pair<Rule>, env: &Environment) -> CompoundStatement {
     let mut inner_env = env.clone();
-    Statement::Compound(
-        pair.into_inner()
-            .map(|pair| parse_statement(pair, &mut inner_env))
-            .collect(),
-    )
+    let mut pairs = pair.into_inner().peekable();
+
+    let attrs = parse_stmt_attrs(
+        pairs
+            .peeking_take_while(|pair| pair.as_rule() == Rule::attribute_list)
+            .flat_map(|pair| pair.into_inner()),
+        env,
+    );
+
+    let body = pairs
+        .map(|pair| parse_statement(pair, &mut inner_env))
+        .collect();
+
+    CompoundStatement {
+        attrs,
+        ..CompoundStatement::new(body)
+    }
+}
+
+/// Parses the body of a function or statement.
+///
+/// If the body has attributes, its statements are wrapped in a compound statement that carries
+/// them, which declares the same scope.
+fn parse_block(pair: Pair<Rule>, env: &Environment) -> Vec<Statement> {
+    let span = env.span(&pair);
+    let mut stmt = parse_compound_statement(pair, env);
+    if stmt.attrs.is_empty() {
+        stmt.body
+    } else {
+        stmt.meta.span = Some(span);
+        vec![stmt.into()]
+    }
 }
 
 fn parse_if_statement(pair: Pair<Rule>, env: &Environment) -> Statement {
     let mut pairs = pair.into_inner();
     let condition = parse_expression(pairs.next().unwrap(), env);
-    let block = parse_compound_statement(pairs.next().unwrap(), env).into_compound_statement();
+    let block = parse_block(pairs.next().unwrap(), env);
 
-    let els = pairs
-        .next()
-        .map(|pair| match pair.as_rule() {
-            Rule::compound_statement => parse_compound_statement(pair, env),
-            Rule::if_statement => parse_if_statement(pair, env),
-            _ => unreachable!(),
-        })
-        .map(|stmt| match stmt {
-            Statement::Compound(stmts) => Else::Else(stmts),
+    let els = pairs.next().map(|pair| match pair.as_rule() {
+        Rule::compound_statement => Else::Else(parse_block(pair, env)),
+        Rule::if_statement => match parse_if_statement(pair, env) {
             Statement::If(stmt) => Else::If(stmt),
             _ => unreachable!(),
-        });
+        },
+        _ => unreachable!(),
+    });
 
     IfStatement::new(condition, block).with_else(els).into()
 }
@@ -543,16 +940,26 @@ fn parse_loop_statement(pair: Pair<Rule>, env: &Environment) -> Statement {
     let mut inner_env = env.clone();
     let mut pairs = pair.into_inner().peekable();
 
-    let body_stmts = pairs
+    let body_attrs = parse_stmt_attrs(
+        pairs
+            .peeking_take_while(|pair| pair.as_rule() == Rule::attribute_list)
+            .flat_map(|pair| pair.into_inner()),
+        env,
+    );
+
+    let block = pairs
         .by_ref()
         .peeking_take_while(|pair| pair.as_rule() != Rule::continuing_statement)
         .map(|pair| parse_statement(pair, &mut inner_env))
         .collect();
 
-    let block = Statement::Compound(body_stmts).into_compound_statement();
     let continuing = parse_continuing_statement(pairs.next(), &inner_env);
 
-    LoopStatement::new(block, continuing).into()
+    LoopStatement {
+        body_attrs,
+        ..LoopStatement::new(block, continuing)
+    }
+    .into()
 }
 
 fn parse_continuing_statement(
@@ -582,15 +989,22 @@ fn parse_continuing_statement(
 fn parse_while_statement(pair: Pair<Rule>, env: &Environment) -> Statement {
     let mut pairs = pair.into_inner();
     let condition = parse_expression(pairs.next().unwrap(), env);
-    let block = parse_compound_statement(pairs.next().unwrap(), env).into_compound_statement();
+    let block = parse_block(pairs.next().unwrap(), env);
 
     WhileStatement::new(condition, block).into()
 }
 
 fn parse_switch_statement(pair: Pair<Rule>, env: &Environment) -> Statement {
-    let mut pairs = pair.into_inner();
+    let switch_pair = pair.clone();
+    let mut pairs = pair.into_inner().peekable();
 
     let expr = parse_expression(pairs.next().unwrap(), env);
+    let body_attrs = parse_stmt_attrs(
+        pairs
+            .peeking_take_while(|pair| pair.as_rule() == Rule::attribute_list)
+            .flat_map(|pair| pair.into_inner()),
+        env,
+    );
 
     let mut cases = vec![];
     let mut default = None;
@@ -601,17 +1015,23 @@ fn parse_switch_statement(pair: Pair<Rule>, env: &Environment) -> Statement {
 
         if pair.as_rule() == Rule::expression {
             let selector = parse_expression(pair, env);
-            let body =
-                parse_compound_statement(pairs.next().unwrap(), env).into_compound_statement();
+            let body = parse_block(pairs.next().unwrap(), env);
             cases.push(SwitchCase { selector, body });
         } else {
-            default = Some(parse_compound_statement(pair, env).into_compound_statement());
+            default = Some(parse_block(pair, env));
         }
     }
 
-    let default = default.expect("switch statement must have default case");
+    let default = default.unwrap_or_else(|| {
+        env.error(&switch_pair, "switch statement must have a default case");
+        vec![]
+    });
 
-    SwitchStatement::new(expr, cases, default).into()
+    SwitchStatement {
+        body_attrs,
+        ..SwitchStatement::new(expr, cases, default)
+    }
+    .into()
 }
 
 fn parse_for_statement(pair: Pair<Rule>, env: &mut Environment) -> Statement {
@@ -654,7 +1074,7 @@ fn parse_for_statement(pair: Pair<Rule>, env: &mut Environment) -> Statement {
         pair = pairs.next().unwrap();
     }
 
-    let body = parse_compound_statement(pair, &inner_env);
+    let body = parse_block(pair, &inner_env);
 
     let header = ForLoopHeader {
         init,
@@ -662,7 +1082,7 @@ fn parse_for_statement(pair: Pair<Rule>, env: &mut Environment) -> Statement {
         update,
     };
 
-    ForLoopStatement::new(header, body.into_compound_statement()).into()
+    ForLoopStatement::new(header, body).into()
 }
 
 fn parse_call_statement(pair: Pair<Rule>, env: &Environment) -> Statement {
@@ -684,25 +1104,31 @@ fn parse_lhs_expression(pair: Pair<Rule>, env: &Environment) -> LhsExprNode {
         .collect();
 
     fn parse_core(pair: Pair<Rule>, env: &Environment) -> LhsExprNode {
+        let span = env.span(&pair);
         let pair = pair.into_inner().next().unwrap();
         match pair.as_rule() {
             Rule::lhs_expression => parse_lhs_expression(pair, env),
             Rule::ident => {
                 let ident = pair.as_str().to_owned();
                 LhsExprNode {
-                    data_type: env
-                        .var(&ident)
-                        .expect("variable must be defined before use")
-                        .clone(),
+                    data_type: env.var(&ident).cloned().unwrap_or_else(|| {
+                        env.error(&pair, format!("undeclared variable `{ident}`"));
+                        error_type()
+                    }),
                     expr: LhsExpr::Ident(ident),
+                    meta: NodeMeta::default(),
                 }
             }
             _ => unreachable!(),
         }
+        .with_span(span)
     }
 
-    let node = parse_core(pairs.next().unwrap(), env);
+    let core = pairs.next().unwrap();
+    let core_span = env.span(&core);
+    let node = parse_core(core, env);
     let node = pairs.fold(node, |node, pair| {
+        let postfix_span = core_span.to(env.span(&pair));
         let pair = pair.into_inner().next().unwrap();
         let postfix = match pair.as_rule() {
             Rule::expression => Postfix::Index(Box::new(parse_expression(pair, env))),
@@ -711,25 +1137,39 @@ fn parse_lhs_expression(pair: Pair<Rule>, env: &Environment) -> LhsExprNode {
         };
 
         LhsExprNode {
-            data_type: postfix.type_eval(&node.data_type),
+            data_type: env.type_or_error(postfix.try_type_eval(&node.data_type), postfix_span),
             expr: LhsExpr::Postfix(Box::new(node), postfix),
+            meta: NodeMeta::default(),
         }
+        .with_span(postfix_span)
     });
 
     prefixes.iter().rev().fold(node, |node, pair| {
-        let (data_type, expr) = match pair.as_rule() {
-            Rule::op_address_of => (
-                UnOp::AddressOf.type_eval(&node.data_type),
-                LhsExpr::AddressOf(Box::new(node)),
-            ),
-            Rule::op_indirection => (
-                UnOp::Deref.type_eval(&node.data_type),
-                LhsExpr::Deref(Box::new(node)),
-            ),
+        let prefix_span = node.meta.span.map(|it| env.span(pair).to(it));
+        let op = match pair.as_rule() {
+            Rule::op_address_of => UnOp::AddressOf,
+            Rule::op_indirection => UnOp::Deref,
             _ => unreachable!(),
         };
 
-        LhsExprNode { data_type, expr }
+        let data_type = env.type_or_error(
+            op.try_type_eval(&node.data_type),
+            prefix_span.unwrap_or_else(|| env.span(pair)),
+        );
+
+        let expr = match op {
+            UnOp::AddressOf => LhsExpr::AddressOf(Box::new(node)),
+            _ => LhsExpr::Deref(Box::new(node)),
+        };
+
+        LhsExprNode {
+            data_type,
+            expr,
+            meta: NodeMeta {
+                id: None,
+                span: prefix_span,
+            },
+        }
     })
 }
 
@@ -766,13 +1206,18 @@ fn parse_expression(pair: Pair<Rule>, env: &Environment) -> ExprNode {
 
     let primary = |pair| parse_unary_expression(pair, env);
     let infix = |l: ExprNode, op: Pair<Rule>, r: ExprNode| -> ExprNode {
-        BinOpExpr::new(op.as_rule().into(), l, r).into()
+        let span = l.meta.span.zip(r.meta.span).map(|(l, r)| l.to(r));
+        ExprNode {
+            meta: NodeMeta { id: None, span },
+            ..BinOpExpr::new(op.as_rule().into(), l, r).into()
+        }
     };
 
     precedence_table().climb(pairs, primary, infix)
 }
 
 fn parse_unary_expression(pair: Pair<Rule>, env: &Environment) -> ExprNode {
+    let span = env.span(&pair);
     let mut pairs = pair.into_inner();
 
     let first_pair = pairs.next().unwrap();
@@ -791,15 +1236,24 @@ fn parse_unary_expression(pair: Pair<Rule>, env: &Environment) -> ExprNode {
     };
 
     let expr = parse_unary_expression(pairs.next().unwrap(), env);
+    let data_type = env.type_or_error(op.try_type_eval(&expr.data_type), span);
 
-    UnOpExpr::new(op, expr).into()
+    ExprNode {
+        data_type,
+        expr: UnOpExpr::new(op, expr).into(),
+        meta: NodeMeta::default(),
+    }
+    .with_span(span)
 }
 
 fn parse_singular_expression(pair: Pair<Rule>, env: &Environment) -> ExprNode {
     let mut pairs = pair.into_inner();
-    let mut expr = parse_primary_expression(pairs.next().unwrap(), env);
+    let primary = pairs.next().unwrap();
+    let primary_span = env.span(&primary);
+    let mut expr = parse_primary_expression(primary, env);
 
     for pf in pairs {
+        let postfix_span = primary_span.to(env.span(&pf));
         let pair = pf.into_inner().next().unwrap();
         let pf = match pair.as_rule() {
             Rule::expression => Postfix::Index(Box::new(parse_expression(pair, env))),
@@ -807,60 +1261,165 @@ fn parse_singular_expression(pair: Pair<Rule>, env: &Environment) -> ExprNode {
             _ => unreachable!(),
         };
 
-        expr = PostfixExpr::new(expr, pf).into();
+        let data_type = env.type_or_error(pf.try_type_eval(&expr.data_type), postfix_span);
+
+        expr = ExprNode {
+            data_type,
+            expr: PostfixExpr::new(expr, pf).into(),
+            meta: NodeMeta::default(),
+        }
+        .with_span(postfix_span);
     }
 
     expr
 }
 
 fn parse_primary_expression(pair: Pair<Rule>, env: &Environment) -> ExprNode {
+    let span = env.span(&pair);
     let pair = pair.into_inner().next().unwrap();
-    match pair.as_rule() {
-        Rule::literal_expression => parse_literal_expression(pair),
+    let node = match pair.as_rule() {
+        Rule::hole_expression => parse_hole_expression(pair, env),
+        Rule::literal_expression => parse_literal_expression(pair, env),
         Rule::type_cons_expression => parse_type_cons_expression(pair, env),
         Rule::call_expression => parse_call_expression(pair, env),
         Rule::var_expression => parse_var_expression(pair, env),
         Rule::paren_expression => parse_paren_expression(pair, env),
         Rule::unary_expression => parse_unary_expression(pair, env),
         _ => unreachable!(),
-    }
+    };
+
+    node.with_span(span)
+}
+
+fn parse_hole_expression(pair: Pair<Rule>, env: &Environment) -> ExprNode {
+    let t = parse_type_decl(pair.into_inner().next().unwrap(), env);
+    FnCallExpr::new(EXPR_HOLE_FN, vec![]).into_node(t)
 }
 
-fn parse_literal_expression(pair: Pair<Rule>) -> ExprNode {
+fn parse_literal_expression(pair: Pair<Rule>, env: &Environment) -> ExprNode {
     let pair = pair.into_inner().next().unwrap();
+    let s = pair.as_str();
+
+    // Unsuffixed literals are abstract, even though their node has the default concrete type
+    let mut abstract_type = None;
+
     let (t, lit) = match pair.as_rule() {
-        Rule::bool_literal => (ScalarType::Bool, Lit::Bool(pair.as_str().parse().unwrap())),
+        Rule::bool_literal => (ScalarType::Bool, Some(Lit::Bool(s.parse().unwrap()))),
         Rule::uint_literal => (
             ScalarType::U32,
-            Lit::U32(pair.as_str().trim_end_matches('u').parse().unwrap()),
-        ),
-        Rule::int_literal => (
-            ScalarType::I32,
-            Lit::I32(pair.as_str().trim_end_matches('i').parse().unwrap()),
+            parse_int(s.trim_end_matches('u'))
+                .and_then(|v| u32::try_from(v).ok())
+                .map(Lit::U32),
         ),
+        Rule::int_literal => match s.strip_suffix('i') {
+            Some(digits) => (
+                ScalarType::I32,
+                parse_int(digits)
+                    .and_then(|v| i32::try_from(v).ok())
+                    .map(Lit::I32),
+            ),
+            None => {
+                abstract_type = Some("AbstractInt");
+                (ScalarType::I32, parse_int(s).map(Lit::AbstractInt))
+            }
+        },
         Rule::float_literal => {
-            let s = pair.as_str();
-            if s.ends_with('h') {
-                (
+            let is_hex = s.contains(['x', 'X']);
+
+            // `f` is also a hex digit, so hex floats only have a suffix if they have an exponent
+            let suffix = s
+                .chars()
+                .last()
+                .filter(|c| matches!(c, 'f' | 'h') && (!is_hex || s.contains(['p', 'P'])));
+
+            let digits = match suffix {
+                Some(_) => &s[..s.len() - 1],
+                None => s,
+            };
+
+            let value = match digits.strip_prefix('-') {
+                Some(digits) => parse_float(digits).map(|v| -v),
+                None => parse_float(digits),
+            };
+
+            match suffix {
+                Some('h') => (
                     ScalarType::F16,
-                    Lit::F16(s.trim_end_matches('h').parse::<half::f16>().unwrap()),
-                )
-            } else {
-                (
+                    value.and_then(|v| Lit::AbstractFloat(v).convert_abstract(ScalarType::F16)),
+                ),
+                Some(_) => (
                     ScalarType::F32,
-                    Lit::F32(s.trim_end_matches('f').parse().unwrap()),
-                )
+                    value.and_then(|v| Lit::AbstractFloat(v).convert_abstract(ScalarType::F32)),
+                ),
+                None => {
+                    abstract_type = Some("AbstractFloat");
+                    (
+                        ScalarType::F32,
+                        value.filter(|v| v.is_finite()).map(Lit::AbstractFloat),
+                    )
+                }
             }
         }
         _ => unreachable!(),
     };
 
+    let lit = lit.unwrap_or_else(|| {
+        let type_name = abstract_type.map_or_else(|| t.to_string(), str::to_owned);
+        env.error(
+            &pair,
+            format!("literal `{s}` is out of range for `{type_name}`"),
+        );
+        Lit::AbstractInt(0)
+    });
+
     ExprNode {
         data_type: DataType::Scalar(t),
         expr: Expr::Lit(lit),
+        meta: NodeMeta::default(),
     }
 }
 
+/// Parses the digits of a decimal or hex int literal, with an optional sign but without a suffix.
+fn parse_int(s: &str) -> Option<i64> {
+    let (negative, s) = match s.strip_prefix('-') {
+        Some(s) => (true, s),
+        None => (false, s),
+    };
+
+    let magnitude = match s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        Some(digits) => u64::from_str_radix(digits, 16).ok()?,
+        None => s.parse().ok()?,
+    };
+
+    if negative {
+        0i64.checked_sub_unsigned(magnitude)
+    } else {
+        i64::try_from(magnitude).ok()
+    }
+}
+
+/// Parses the digits of a decimal or hex float literal, without a sign or suffix.
+fn parse_float(s: &str) -> Option<f64> {
+    let Some(s) = s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) else {
+        return s.parse().ok();
+    };
+
+    let (mantissa, exponent) = match s.split_once(['p', 'P']) {
+        Some((mantissa, exponent)) => (mantissa, exponent.parse::<i32>().ok()?),
+        None => (s, 0),
+    };
+
+    let (whole, fraction) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+
+    let mut value = 0.0;
+    for digit in whole.chars().chain(fraction.chars()) {
+        value = value * 16.0 + digit.to_digit(16)? as f64;
+    }
+
+    let exponent = exponent.checked_sub(4 * fraction.len() as i32)?;
+    Some(value * 2f64.powi(exponent))
+}
+
 fn parse_type_cons_expression(pair: Pair<Rule>, env: &Environment) -> ExprNode {
     let mut pairs = pair.into_inner();
     let t_decl = pairs.next().unwrap();
@@ -873,7 +1432,7 @@ fn parse_type_cons_expression(pair: Pair<Rule>, env: &Environment) -> ExprNode {
     // Otherwise, parsing and writing i32(-2147483648) would output i32(i32(-2147483648))
     if t == DataType::Scalar(ScalarType::I32)
         && args.len() == 1
-        && args[0].expr == Expr::Lit(Lit::I32(i32::MIN))
+        && matches!(args[0].expr, Expr::Lit(lit) if lit.concretize() == Some(Lit::I32(i32::MIN)))
     {
         return Lit::I32(i32::MIN).into();
     }
@@ -882,6 +1441,7 @@ fn parse_type_cons_expression(pair: Pair<Rule>, env: &Environment) -> ExprNode {
 }
 
 fn parse_call_expression(pair: Pair<Rule>, env: &Environment) -> ExprNode {
+    let span = env.span(&pair);
     let mut pairs = pair.into_inner();
 
     let ident = pairs.next().unwrap();
@@ -889,6 +1449,10 @@ fn parse_call_expression(pair: Pair<Rule>, env: &Environment) -> ExprNode {
         .map(|pair| parse_expression(pair, env))
         .collect::<Vec<_>>();
 
+    if let Some(decl) = env.ty(ident.as_str()) {
+        return TypeConsExpr::new(DataType::Struct(decl.clone()), args).into();
+    }
+
     struct FunSig<'a>(&'a str, &'a [ExprNode]);
 
     impl std::fmt::Display for FunSig<'_> {
@@ -906,9 +1470,11 @@ fn parse_call_expression(pair: Pair<Rule>, env: &Environment) -> ExprNode {
         }
     }
 
-    let return_type = env
-        .func(ident.as_str(), args.iter().map(|arg| &arg.data_type))
-        .unwrap_or_else(|| panic!("`{}` not found", FunSig(ident.as_str(), &args)));
+    let return_type = env.type_or_error(
+        env.func(ident.as_str(), args.iter().map(|arg| &arg.data_type))
+            .ok_or_else(|| format!("`{}` not found", FunSig(ident.as_str(), &args))),
+        span,
+    );
 
     FnCallExpr::new(ident.as_str().to_owned(), args).into_node(return_type)
 }
@@ -920,6 +1486,17 @@ fn parse_type_decl(pair: Pair<Rule>, env: &Environment) -> DataType {
         pair.into_inner().next().unwrap().as_rule().into()
     }
 
+    fn parse_sampled_type(pair: Pair<Rule>, env: &Environment) -> ScalarType {
+        let scalar_type = parse_t_scalar(pair.clone());
+        if !matches!(
+            scalar_type,
+            ScalarType::F32 | ScalarType::I32 | ScalarType::U32
+        ) {
+            env.error(&pair, format!("invalid sampled type `{}`", pair.as_str()));
+        }
+        scalar_type
+    }
+
     match pair.as_rule() {
         Rule::t_scalar => DataType::Scalar(parse_t_scalar(pair)),
         Rule::t_vector => {
@@ -939,7 +1516,10 @@ fn parse_type_decl(pair: Pair<Rule>, env: &Environment) -> DataType {
                     Some('u') => ScalarType::U32,
                     Some('f') => ScalarType::F32,
                     Some('h') => ScalarType::F16,
-                    _ => panic!("Explicit type required for vector"),
+                    _ => {
+                        env.error(&t_vector, "vector type must have an element type");
+                        ScalarType::I32
+                    }
                 }
             };
 
@@ -967,12 +1547,69 @@ fn parse_type_decl(pair: Pair<Rule>, env: &Environment) -> DataType {
             let inner = pair.into_inner().next().unwrap();
             DataType::Atomic(parse_t_scalar(inner))
         }
+        Rule::t_storage_texture => {
+            let mut pairs = pair.into_inner();
+
+            let dim = match pairs.next().unwrap().as_str() {
+                "texture_storage_1d" => TextureDimension::D1,
+                "texture_storage_2d" => TextureDimension::D2,
+                "texture_storage_3d" => TextureDimension::D3,
+                _ => unreachable!(),
+            };
+
+            let format = pairs.next().unwrap().as_str().parse().unwrap();
+            let access_mode = parse_access_mode(pairs.next().unwrap());
+
+            DataType::StorageTexture(dim, format, access_mode)
+        }
+        Rule::t_sampled_texture => {
+            let mut pairs = pair.into_inner();
+
+            let dim = match pairs.next().unwrap().as_str() {
+                "texture_1d" => TextureViewDimension::D1,
+                "texture_2d" => TextureViewDimension::D2,
+                "texture_2d_array" => TextureViewDimension::D2Array,
+                "texture_3d" => TextureViewDimension::D3,
+                "texture_cube" => TextureViewDimension::Cube,
+                "texture_cube_array" => TextureViewDimension::CubeArray,
+                _ => unreachable!(),
+            };
+
+            let sampled_type = parse_sampled_type(pairs.next().unwrap(), env);
+
+            DataType::Texture(TextureType::Sampled(dim, sampled_type))
+        }
+        Rule::t_multisampled_texture => {
+            let inner = pair.into_inner().next().unwrap();
+            DataType::Texture(TextureType::Multisampled(parse_sampled_type(inner, env)))
+        }
+        Rule::t_depth_texture => DataType::Texture(match pair.as_str() {
+            "texture_depth_2d" => TextureType::Depth(TextureViewDimension::D2),
+            "texture_depth_2d_array" => TextureType::Depth(TextureViewDimension::D2Array),
+            "texture_depth_cube" => TextureType::Depth(TextureViewDimension::Cube),
+            "texture_depth_cube_array" => TextureType::Depth(TextureViewDimension::CubeArray),
+            "texture_depth_multisampled_2d" => TextureType::DepthMultisampled,
+            _ => unreachable!(),
+        }),
+        Rule::t_external_texture => DataType::Texture(TextureType::External),
+        Rule::t_sampler => DataType::Sampler(match pair.as_str() {
+            "sampler" => SamplerKind::Sampler,
+            "sampler_comparison" => SamplerKind::Comparison,
+            _ => unreachable!(),
+        }),
         Rule::array_type_decl => {
             let mut pairs = pair.into_inner();
             let pair = pairs.next().unwrap();
             DataType::Array(
                 Rc::new(parse_type_decl(pair, env)),
-                pairs.next().map(|it| it.as_str().parse().unwrap()),
+                pairs.next().and_then(|it| {
+                    let size = parse_int(it.as_str().trim_end_matches(['i', 'u']))
+                        .and_then(|v| u32::try_from(v).ok());
+                    if size.is_none() {
+                        env.error(&it, format!("invalid array size `{}`", it.as_str()));
+                    }
+                    size
+                }),
             )
         }
         Rule::ptr_type_decl => {
@@ -981,21 +1618,24 @@ fn parse_type_decl(pair: Pair<Rule>, env: &Environment) -> DataType {
             let inner = parse_type_decl(pairs.next().unwrap(), env);
             DataType::Ptr(MemoryViewType::new(inner, storage_class))
         }
-        Rule::ident => DataType::Struct(
-            env.ty(pair.as_str())
-                .unwrap_or_else(|| panic!("type not found: {}", pair.as_str()))
-                .clone(),
-        ),
+        Rule::ident => match env.ty(pair.as_str()) {
+            Some(decl) => DataType::Struct(decl.clone()),
+            None => {
+                env.error(&pair, format!("undeclared type `{}`", pair.as_str()));
+                error_type()
+            }
+        },
         _ => panic!("{}", pair),
     }
 }
 
 fn parse_var_expression(pair: Pair<Rule>, env: &Environment) -> ExprNode {
-    VarExpr::new(pair.as_str()).into_node(
-        env.var(pair.as_str())
-            .unwrap_or_else(|| panic!("variable `{}` must be defined before use", pair.as_str()))
-            .clone(),
-    )
+    let data_type = env.var(pair.as_str()).cloned().unwrap_or_else(|| {
+        env.error(&pair, format!("undeclared variable `{}`", pair.as_str()));
+        error_type()
+    });
+
+    VarExpr::new(pair.as_str()).into_node(data_type)
 }
 
 fn parse_paren_expression(pair: Pair<Rule>, env: &Environment) -> ExprNode {
@@ -1014,48 +1654,116 @@ fn parse_storage_class(pair: Pair<Rule>) -> StorageClass {
     }
 }
 
-fn parse_fn_attr(pair: Pair<Rule>, env: &Environment) -> FnAttr {
-    let mut attr_pairs = pair.into_inner();
-    let name = attr_pairs.next().unwrap().as_str();
-    match name {
+fn parse_access_mode(pair: Pair<Rule>) -> AccessMode {
+    match pair.as_str() {
+        "read" => AccessMode::Read,
+        "write" => AccessMode::Write,
+        "read_write" => AccessMode::ReadWrite,
+        _ => unreachable!(),
+    }
+}
+
+fn parse_stmt_attrs<'a>(
+    attrs: impl Iterator<Item = Pair<'a, Rule>>,
+    env: &Environment,
+) -> Vec<StmtAttr> {
+    attrs
+        .filter_map(|attr| parse_stmt_attr(attr, env))
+        .collect()
+}
+
+fn parse_stmt_attr(attr: Pair<Rule>, env: &Environment) -> Option<StmtAttr> {
+    let mut pairs = attr.clone().into_inner();
+    let name = pairs.next().unwrap();
+
+    match name.as_str() {
+        "diagnostic" => match pairs.next() {
+            Some(control) if control.as_rule() == Rule::diagnostic_control => Some(
+                StmtAttr::Diagnostic(parse_diagnostic_control(control, env)?),
+            ),
+            _ => {
+                env.error(&attr, "invalid argument for attribute `diagnostic`");
+                None
+            }
+        },
+        _ => {
+            env.error(
+                &name,
+                format!("invalid statement attribute `{}`", name.as_str()),
+            );
+            None
+        }
+    }
+}
+
+fn parse_fn_attr(attr: Pair<Rule>, env: &Environment) -> Option<FnAttr> {
+    let mut pairs = attr.clone().into_inner();
+    let name = pairs.next().unwrap();
+
+    let attr = match name.as_str() {
         "compute" => FnAttr::Stage(ShaderStage::Compute),
         "vertex" => FnAttr::Stage(ShaderStage::Vertex),
         "fragment" => FnAttr::Stage(ShaderStage::Fragment),
-        "stage" => FnAttr::Stage(match attr_pairs.next().unwrap().as_str().trim() {
-            "compute" => ShaderStage::Compute,
-            "vertex" => ShaderStage::Vertex,
-            "fragment" => ShaderStage::Fragment,
-            _ => panic!("invalid argument for stage attr"),
+        "stage" => FnAttr::Stage(match pairs.next().map(|it| it.as_str().trim()) {
+            Some("compute") => ShaderStage::Compute,
+            Some("vertex") => ShaderStage::Vertex,
+            Some("fragment") => ShaderStage::Fragment,
+            _ => {
+                env.error(&attr, "invalid argument for attribute `stage`");
+                return None;
+            }
         }),
         "workgroup_size" => {
-            FnAttr::WorkgroupSize(attr_pairs.map(|p| parse_expression(p, env)).collect())
+            FnAttr::WorkgroupSize(pairs.map(|p| parse_expression(p, env)).collect())
         }
         "must_use" => FnAttr::MustUse,
-        _ => panic!("invalid function attribute: {}", name),
-    }
+        "diagnostic" => match pairs.next() {
+            Some(control) if control.as_rule() == Rule::diagnostic_control => {
+                FnAttr::Diagnostic(parse_diagnostic_control(control, env)?)
+            }
+            _ => {
+                env.error(&attr, "invalid argument for attribute `diagnostic`");
+                return None;
+            }
+        },
+        _ => {
+            env.error(
+                &name,
+                format!("invalid function attribute `{}`", name.as_str()),
+            );
+            return None;
+        }
+    };
+
+    Some(attr)
 }
 
-fn parse_fn_io_attr(pair: Pair<Rule>) -> FnIOAttr {
-    let mut pairs = pair.into_inner();
-    let name = pairs.next().unwrap().as_str();
+fn parse_fn_io_attr(attr: Pair<Rule>, env: &Environment) -> Option<FnIOAttr> {
+    let mut pairs = attr.clone().into_inner();
+    let name = pairs.next().unwrap();
 
-    match name {
-        "builtin" => {
-            let arg = pairs.next().unwrap().as_str();
-            FnIOAttr::Builtin(arg.parse().unwrap())
-        }
+    let attr = match name.as_str() {
+        "builtin" => FnIOAttr::Builtin(parse_attr_arg(&attr, pairs.next(), env)?),
         "invariant" => FnIOAttr::Invariant,
-        "location" => {
-            let arg = pairs.next().unwrap().as_str();
-            FnIOAttr::Location(arg.parse().unwrap())
-        }
+        "location" => FnIOAttr::Location(parse_attr_arg(&attr, pairs.next(), env)?),
         "interpolate" => {
-            let ty = pairs.next().unwrap().as_str().parse().unwrap();
-            let sample = pairs.next().map(|arg| arg.as_str().parse().unwrap());
+            let ty = parse_attr_arg(&attr, pairs.next(), env)?;
+            let sample = match pairs.next() {
+                Some(arg) => Some(parse_attr_arg(&attr, Some(arg), env)?),
+                None => None,
+            };
             FnIOAttr::Interpolate(ty, sample)
         }
-        _ => panic!("invalid param attribute: {}", name),
-    }
+        _ => {
+            env.error(
+                &name,
+                format!("invalid parameter attribute `{}`", name.as_str()),
+            );
+            return None;
+        }
+    };
+
+    Some(attr)
 }
 
 impl From<Rule> for BinOp {
@@ -1109,23 +1817,550 @@ mod tests {
             #[test]
             fn $fn() {
                 const SRC: &str = include_str!(concat!("tests/", stringify!($name), ".wgsl"));
-                let pairs = WGSLParser::parse(Rule::translation_unit, SRC).unwrap();
-                let pair = pairs.into_iter().next().unwrap();
-                let module = parse_translation_unit(pair, &mut Environment::new());
-                insta::assert_debug_snapshot!(module);
+                insta::assert_debug_snapshot!(parse(SRC));
             }
         };
     }
 
     test_case!(calls);
+    test_case!(comments);
+    test_case!(directives);
     test_case!(floats);
+    test_case!(forward_refs);
+    test_case!(holes);
+    test_case!(lets);
+    test_case!(literals);
     test_case!(loops);
     test_case!(ptrs);
+    test_case!(sampling);
     test_case!(structs);
+    test_case!(textures);
 
     test_case!(test_1);
     test_case!(test_2);
     test_case!(test_3);
     test_case!(test_4);
     test_case!(test_5);
+
+    /// Writes `module` in its canonical form, which is the same for every writer style.
+    fn canonical(module: Module) -> String {
+        let mut out = String::new();
+        writer::Writer::default()
+            .write_module(&mut out, &ast::canon::canonicalize(module))
+            .unwrap();
+        out
+    }
+
+    #[test]
+    fn writer_styles_round_trip() {
+        use writer::{DeclOrder, LiteralStyle, NumeralStyle, Options, ParenStyle};
+
+        const SRC: &str = include_str!("tests/styles.wgsl");
+        let expected = canonical(parse(SRC));
+
+        for parens in [
+            ParenStyle::Conservative,
+            ParenStyle::Minimal,
+            ParenStyle::Redundant,
+        ] {
+            for literals in [LiteralStyle::Suffix, LiteralStyle::Constructor] {
+                for numerals in [
+                    NumeralStyle::Decimal,
+                    NumeralStyle::Hex,
+                    NumeralStyle::Exponent,
+                ] {
+                    for decl_order in [DeclOrder::Original, DeclOrder::Grouped, DeclOrder::Reversed]
+                    {
+                        let options = Options {
+                            parens,
+                            literals,
+                            numerals,
+                            decl_order,
+                            ..Options::default()
+                        };
+
+                        let mut written = String::new();
+                        writer::Writer::new(options.clone())
+                            .write_module(&mut written, &parse(SRC))
+                            .unwrap();
+
+                        let module = try_parse(&written).unwrap_or_else(|diagnostics| {
+                            panic!(
+                                "{options:?} wrote an invalid module:\n{written}\n{}",
+                                format_diagnostics(&diagnostics)
+                            )
+                        });
+
+                        assert_eq!(canonical(module), expected, "{options:?}");
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn canonical_forms_ignore_names_order_and_literals() {
+        let a = parse(
+            r#"
+            struct S { a: i32, b: f32 }
+            const K: i32 = 2i;
+            var<private> g: S;
+
+            fn helper(x: i32) -> i32 {
+                let y = x * K;
+                return y;
+            }
+
+            @compute @workgroup_size(1)
+            fn main() {
+                var t = helper(i32(-3));
+                g.a = t + 16i;
+                g.b = -1.5f;
+            }
+            "#,
+        );
+
+        let b = parse(
+            r#"
+            @compute @workgroup_size(1)
+            fn main() {
+                var result = helper(-3i);
+                g.a = result + 0x10i;
+                g.b = f32(-(1.5));
+            }
+
+            fn helper(value: i32) -> i32 {
+                let scaled = value * K;
+                return scaled;
+            }
+
+            var<private> g: S;
+            const K: i32 = 2i;
+            struct S { a: i32, b: f32 }
+            "#,
+        );
+
+        let c = parse(
+            r#"
+            struct S { a: i32, b: f32 }
+            const K: i32 = 2i;
+            var<private> g: S;
+
+            fn helper(x: i32) -> i32 {
+                let y = x * K;
+                return y;
+            }
+
+            @compute @workgroup_size(1)
+            fn main() {
+                var t = helper(i32(-3));
+                g.a = t + 17i;
+                g.b = -1.5f;
+            }
+            "#,
+        );
+
+        let hash = |module| ast::canon::structural_hash(&ast::canon::canonicalize(module));
+        let a = hash(a);
+        assert_eq!(a, hash(b));
+        assert_ne!(a, hash(c));
+    }
+
+    #[test]
+    fn canonical_names_keep_shadowing() {
+        let module = parse(
+            r#"
+            const var_0: i32 = 1i;
+
+            fn f(x: i32) -> i32 {
+                var y = x + var_0;
+                {
+                    let x = y * 2i;
+                    y = x;
+                }
+                for (var x = 0i; x < y; x++) {
+                    let var_0 = x;
+                    y -= var_0;
+                }
+                let z = x + y + var_0;
+                return z;
+            }
+            "#,
+        );
+
+        // Local names skip `var_0`, since that would shadow the constant
+        let expected = r#"let var_0: i32 = 1i;
+
+fn f(arg_0: i32) -> i32 {
+    var var_1 = arg_0 + var_0;
+    {
+        let var_2 = var_1 * 2i;
+        var_1 = var_2;
+    }
+    for (var var_3 = 0i; (var_3 < var_1); var_3++) {
+        let var_4 = var_3;
+        var_1 -= var_4;
+    }
+    let var_5 = (arg_0 + var_1) + var_0;
+    return var_5;
+}
+
+"#;
+
+        assert_eq!(canonical(module), expected);
+    }
+
+    #[test]
+    fn metrics() {
+        use std::collections::{BTreeMap, BTreeSet};
+
+        let module = parse(
+            r#"
+            struct S { a: i32 }
+            const K: i32 = 1i;
+            var<private> g: S;
+
+            fn leaf(p: ptr<function, i32>) -> i32 {
+                return *p + K;
+            }
+
+            fn middle(x: i32) -> i32 {
+                var y: i32 = x;
+                let p = &y;
+                *p = 2i;
+                return leaf(&y) + abs(x);
+            }
+
+            fn top() {
+                loop {
+                    for (var i = 0i; i < 2i; i++) {
+                        if (i == 1i) {
+                            g.a = middle(i);
+                        }
+                    }
+                    break;
+                }
+            }
+
+            fn recursive() {
+                recursive();
+                top();
+            }
+            "#,
+        );
+
+        let metrics = ast::metrics::compute(&module);
+
+        assert_eq!(
+            metrics.declarations,
+            BTreeMap::from([("const", 1), ("fn", 4), ("struct", 1), ("var", 1)])
+        );
+
+        assert_eq!(
+            metrics.statements,
+            BTreeMap::from([
+                ("assignment", 2),
+                ("break", 1),
+                ("call", 2),
+                ("for", 1),
+                ("if", 1),
+                ("let", 1),
+                ("loop", 1),
+                ("return", 2),
+                ("var", 1),
+            ])
+        );
+
+        assert_eq!(
+            metrics.expressions,
+            BTreeMap::from([
+                ("binop", 4),
+                ("call", 3),
+                ("ident", 9),
+                ("literal", 5),
+                ("unop", 3),
+            ])
+        );
+
+        // `leaf(&y) + abs(x)`
+        assert_eq!(metrics.max_expr_depth, 4);
+        // The body of the if statement in `top`
+        assert_eq!(metrics.max_block_depth, 4);
+        assert_eq!(metrics.max_loop_depth, 2);
+        // `recursive`, `top`, `middle` and `leaf`, counting `recursive` only once
+        assert_eq!(metrics.call_graph_depth, 4);
+
+        assert_eq!(metrics.builtins, BTreeSet::from(["abs".to_owned()]));
+        assert_eq!(
+            metrics.types,
+            [
+                "S",
+                "bool",
+                "i32",
+                "ptr<function, i32>",
+                "ref<function, i32>"
+            ]
+            .map(String::from)
+            .into()
+        );
+
+        assert_eq!(metrics.pointers.params, 1);
+        assert_eq!(metrics.pointers.address_of, 2);
+        assert_eq!(metrics.pointers.deref, 2);
+    }
+
+    /// Counts the nodes of each kind that a traversal visits.
+    #[derive(Default)]
+    struct VisitCounts(std::collections::BTreeMap<String, usize>);
+
+    impl VisitCounts {
+        fn record(&mut self, method: &str) {
+            let kind = method
+                .trim_start_matches("visit_")
+                .trim_start_matches("fold_")
+                .trim_end_matches("_mut");
+
+            *self.0.entry(kind.to_owned()).or_default() += 1;
+        }
+    }
+
+    macro_rules! count_visits {
+        ($($visit:ident => $walk:ident: $ty:ty;)*) => {
+            impl<'a> visit::Visitor<'a> for VisitCounts {
+                $(
+                    fn $visit(&mut self, node: &'a $ty) {
+                        self.record(stringify!($visit));
+                        visit::$walk(self, node);
+                    }
+                )*
+            }
+        };
+    }
+
+    macro_rules! count_visits_mut {
+        ($($visit:ident => $walk:ident: $ty:ty;)*) => {
+            impl visit::VisitorMut for VisitCounts {
+                $(
+                    fn $visit(&mut self, node: &mut $ty) {
+                        self.record(stringify!($visit));
+                        visit::$walk(self, node);
+                    }
+                )*
+            }
+        };
+    }
+
+    macro_rules! count_folds {
+        ($($fold:ident: $ty:ty;)*) => {
+            impl visit::Fold for VisitCounts {
+                $(
+                    fn $fold(&mut self, node: $ty) -> $ty {
+                        self.record(stringify!($fold));
+                        visit::fold::$fold(self, node)
+                    }
+                )*
+            }
+        };
+    }
+
+    count_visits! {
+        visit_module => walk_module: Module;
+        visit_global_const => walk_global_const: GlobalConstDecl;
+        visit_global_var => walk_global_var: GlobalVarDecl;
+        visit_fn => walk_fn: FnDecl;
+        visit_block => walk_block: [Statement];
+        visit_stmt => walk_stmt: Statement;
+        visit_let_decl => walk_let_decl: LetDeclStatement;
+        visit_var_decl => walk_var_decl: VarDeclStatement;
+        visit_assignment => walk_assignment: AssignmentStatement;
+        visit_increment => walk_increment: IncrementStatement;
+        visit_decrement => walk_decrement: DecrementStatement;
+        visit_if => walk_if: IfStatement;
+        visit_else => walk_else: Else;
+        visit_loop => walk_loop: LoopStatement;
+        visit_continuing => walk_continuing: ContinuingBlock;
+        visit_while => walk_while: WhileStatement;
+        visit_switch => walk_switch: SwitchStatement;
+        visit_switch_case => walk_switch_case: SwitchCase;
+        visit_for_loop => walk_for_loop: ForLoopStatement;
+        visit_for_header => walk_for_header: ForLoopHeader;
+        visit_for_init => walk_for_init: ForLoopInit;
+        visit_for_update => walk_for_update: ForLoopUpdate;
+        visit_fn_call_stmt => walk_fn_call_stmt: FnCallStatement;
+        visit_assignment_lhs => walk_assignment_lhs: AssignmentLhs;
+        visit_lhs_expr => walk_lhs_expr: LhsExprNode;
+        visit_expr => walk_expr: ExprNode;
+        visit_postfix => walk_postfix: Postfix;
+    }
+
+    count_visits_mut! {
+        visit_module_mut => walk_module_mut: Module;
+        visit_global_const_mut => walk_global_const_mut: GlobalConstDecl;
+        visit_global_var_mut => walk_global_var_mut: GlobalVarDecl;
+        visit_fn_mut => walk_fn_mut: FnDecl;
+        visit_block_mut => walk_block_mut: Vec<Statement>;
+        visit_stmt_mut => walk_stmt_mut: Statement;
+        visit_let_decl_mut => walk_let_decl_mut: LetDeclStatement;
+        visit_var_decl_mut => walk_var_decl_mut: VarDeclStatement;
+        visit_assignment_mut => walk_assignment_mut: AssignmentStatement;
+        visit_increment_mut => walk_increment_mut: IncrementStatement;
+        visit_decrement_mut => walk_decrement_mut: DecrementStatement;
+        visit_if_mut => walk_if_mut: IfStatement;
+        visit_else_mut => walk_else_mut: Else;
+        visit_loop_mut => walk_loop_mut: LoopStatement;
+        visit_continuing_mut => walk_continuing_mut: ContinuingBlock;
+        visit_while_mut => walk_while_mut: WhileStatement;
+        visit_switch_mut => walk_switch_mut: SwitchStatement;
+        visit_switch_case_mut => walk_switch_case_mut: SwitchCase;
+        visit_for_loop_mut => walk_for_loop_mut: ForLoopStatement;
+        visit_for_header_mut => walk_for_header_mut: ForLoopHeader;
+        visit_for_init_mut => walk_for_init_mut: ForLoopInit;
+        visit_for_update_mut => walk_for_update_mut: ForLoopUpdate;
+        visit_fn_call_stmt_mut => walk_fn_call_stmt_mut: FnCallStatement;
+        visit_assignment_lhs_mut => walk_assignment_lhs_mut: AssignmentLhs;
+        visit_lhs_expr_mut => walk_lhs_expr_mut: LhsExprNode;
+        visit_expr_mut => walk_expr_mut: ExprNode;
+        visit_postfix_mut => walk_postfix_mut: Postfix;
+    }
+
+    count_folds! {
+        fold_module: Module;
+        fold_global_const: GlobalConstDecl;
+        fold_global_var: GlobalVarDecl;
+        fold_fn: FnDecl;
+        fold_block: Vec<Statement>;
+        fold_stmt: Statement;
+        fold_let_decl: LetDeclStatement;
+        fold_var_decl: VarDeclStatement;
+        fold_assignment: AssignmentStatement;
+        fold_increment: IncrementStatement;
+        fold_decrement: DecrementStatement;
+        fold_if: IfStatement;
+        fold_else: Else;
+        fold_loop: LoopStatement;
+        fold_continuing: ContinuingBlock;
+        fold_while: WhileStatement;
+        fold_switch: SwitchStatement;
+        fold_switch_case: SwitchCase;
+        fold_for_loop: ForLoopStatement;
+        fold_for_header: ForLoopHeader;
+        fold_for_init: ForLoopInit;
+        fold_for_update: ForLoopUpdate;
+        fold_fn_call_stmt: FnCallStatement;
+        fold_assignment_lhs: AssignmentLhs;
+        fold_lhs_expr: LhsExprNode;
+        fold_expr: ExprNode;
+        fold_postfix: Postfix;
+    }
+
+    #[test]
+    fn traversals_visit_every_node_kind() {
+        use visit::{Fold, Visitor, VisitorMut};
+
+        let mut module = parse(
+            r#"
+            const C: i32 = 1i;
+            var<private> g: array<i32, 4>;
+
+            struct S { a: i32 }
+
+            fn f(x: i32) -> i32 {
+                return -x;
+            }
+
+            @compute @workgroup_size(1)
+            fn main() {
+                let s = S(C);
+                var y = f(s.a) + g[0];
+                y = y * 2i;
+                y++;
+                y--;
+                if (y > 0i) {
+                    y = 1i;
+                } else if (y < 0i) {
+                    y = 2i;
+                } else {
+                    y = 3i;
+                }
+                loop {
+                    continuing {
+                        break if y > 0i;
+                    }
+                }
+                while (y < 10i) {
+                    y += 1i;
+                }
+                switch (y) {
+                    case 1i: {
+                        y = 0i;
+                    }
+                    default: {
+                    }
+                }
+                for (var i = 0i; i < 2i; i++) {
+                    g[i] = i;
+                }
+                workgroupBarrier();
+            }
+            "#,
+        );
+
+        let mut visited = VisitCounts::default();
+        visited.visit_module(&module);
+
+        let mut visited_mut = VisitCounts::default();
+        visited_mut.visit_module_mut(&mut module);
+
+        let mut folded = VisitCounts::default();
+        folded.fold_module(module);
+
+        assert_eq!(
+            visited.0.keys().collect::<Vec<_>>(),
+            [
+                "assignment",
+                "assignment_lhs",
+                "block",
+                "continuing",
+                "decrement",
+                "else",
+                "expr",
+                "fn",
+                "fn_call_stmt",
+                "for_header",
+                "for_init",
+                "for_loop",
+                "for_update",
+                "global_const",
+                "global_var",
+                "if",
+                "increment",
+                "let_decl",
+                "lhs_expr",
+                "loop",
+                "module",
+                "postfix",
+                "stmt",
+                "switch",
+                "switch_case",
+                "var_decl",
+                "while"
+            ]
+        );
+
+        assert_eq!(visited.0, visited_mut.0);
+        assert_eq!(visited.0, folded.0);
+    }
+
+    macro_rules! error_test_case {
+        ($name:ident) => {
+            #[test]
+            fn $name() {
+                const SRC: &str = include_str!(concat!("tests/", stringify!($name), ".wgsl"));
+                insta::assert_debug_snapshot!(try_parse(SRC).unwrap_err());
+            }
+        };
+    }
+
+    error_test_case!(syntax_errors);
+    error_test_case!(semantic_errors);
 }
@@ -0,0 +1,54 @@
+// Leading comment for the module
+enable f16;
+
+/* The struct that is
+   passed to the entry point */
+struct S {
+    // The first member
+    a: i32, // inline on a member
+    b: f32,
+    // trailing member comment
+}
+
+const C: i32 = 1; // inline on a declaration
+
+fn f(x: i32) -> i32 {
+    // before a statement
+    var y = x;
+    if (y > 0) {
+        /* nested /* block */ comment */
+        y = y - 1; // inline on a nested statement
+    }
+    return y;
+    // after the last statement
+}
+
+@compute @workgroup_size(1)
+fn main() {
+    // in an otherwise empty body
+}
+
+fn g() {
+    var x = 1i;
+    if true { x = 2i; } // after an if block
+    if true {
+        // in an empty if block
+    } else {
+        x = 3i;
+    }
+    loop {
+        // in an empty loop body
+        continuing {
+            // in an empty continuing block
+            break if x > 0;
+        }
+    }
+    {
+        /* in an empty compound statement */
+    }
+    if true @diagnostic(off, derivative_uniformity) {
+        // in an empty body with attributes
+    }
+}
+
+// trailing comment at the end of the module
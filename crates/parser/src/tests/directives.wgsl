@@ -0,0 +1,45 @@
+enable f16, subgroups;
+enable clip_distances;
+requires readonly_and_readwrite_storage_textures, packed_4x8_integer_dot_product,;
+diagnostic(off, derivative_uniformity);
+diagnostic(warning, chromium.unreachable_code);
+
+@diagnostic(info, subgroup_uniformity)
+fn f(x: f16) -> f16 {
+    return x;
+}
+
+fn g(x: f32) -> f32 {
+    var y = x;
+    if (true) @diagnostic(off, derivative_uniformity) { }
+    @diagnostic(off, derivative_uniformity) if (x > 0.0) {
+        y = 1.0;
+    } else @diagnostic(info, derivative_uniformity) {
+        y = 2.0;
+    }
+    @diagnostic(off, derivative_uniformity) {
+        y = y + 1.0;
+    }
+    @diagnostic(off, derivative_uniformity) loop @diagnostic(info, subgroup_uniformity) {
+        break;
+    }
+    @diagnostic(off, derivative_uniformity) while (y > 0.0) {
+        y = y - 1.0;
+    }
+    @diagnostic(off, derivative_uniformity) switch (1i) @diagnostic(warning, derivative_uniformity) {
+        case 0i: @diagnostic(off, subgroup_uniformity) {
+            y = 3.0;
+        }
+        default: {
+        }
+    }
+    @diagnostic(off, derivative_uniformity) for (var i = 0i; i < 2i; i++) {
+        y = y * 2.0;
+    }
+    return y;
+}
+
+@compute @workgroup_size(1) @diagnostic(error, derivative_uniformity)
+fn main() {
+    let y = f(1.0h);
+}
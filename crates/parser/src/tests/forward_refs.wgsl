@@ -0,0 +1,21 @@
+var<private> state: Outer;
+
+const SCALE: i32 = OFFSET * 2i;
+
+struct Outer {
+    inner: Inner,
+}
+
+struct Inner {
+    value: i32,
+}
+
+fn main() {
+    state.inner.value = helper(SCALE);
+}
+
+fn helper(x: i32) -> i32 {
+    return x + OFFSET;
+}
+
+const OFFSET: i32 = 3i;
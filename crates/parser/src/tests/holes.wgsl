@@ -0,0 +1,14 @@
+var<private> x: i32;
+
+fn f(a: vec3<f32>) -> f32 {
+    /*@gen stmts:3*/
+    return a.x + /*@gen expr:f32*/;
+}
+
+@compute @workgroup_size(1)
+fn main() {
+    /* a regular comment */
+    let v = /*@gen expr:vec3<f32>*/;
+    x = i32(f(v)) * /*@gen expr:i32*/;
+    /*@gen stmts:5*/
+}
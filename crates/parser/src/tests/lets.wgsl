@@ -0,0 +1,12 @@
+struct S {
+    a: i32,
+}
+
+fn f() {
+    let x: i32 = 1;
+    let y: u32 = 3000000000;
+    let z: f32 = 1;
+    let v: vec2<f32> = vec2<f32>(1.0, 2.0);
+    let s: S = S(x);
+    let w = y;
+}
@@ -0,0 +1,15 @@
+fn main() {
+    let a = 0x1F;
+    let b = 0xffu;
+    let c = -0X80i;
+    let d = 1e3;
+    let e = 2.5E-2f;
+    let f = 1.;
+    let g = 0x1p4;
+    let h = 0x1.8p1f;
+    let i = 0x.8;
+    let j = 0xA.P-2;
+    let k = 1f;
+    let l = 4294967296;
+    var m: array<i32, 0x4u>;
+}
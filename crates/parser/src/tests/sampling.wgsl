@@ -0,0 +1,33 @@
+@group(0) @binding(0) var color: texture_2d<f32>;
+@group(0) @binding(1) var layers: texture_2d_array<u32>;
+@group(0) @binding(2) var volume: texture_3d<f32>;
+@group(0) @binding(3) var env: texture_cube<f32>;
+@group(0) @binding(4) var msaa: texture_multisampled_2d<i32>;
+@group(0) @binding(5) var shadow: texture_depth_2d;
+@group(0) @binding(6) var shadows: texture_depth_cube_array;
+@group(0) @binding(7) var video: texture_external;
+@group(0) @binding(8) var linear: sampler;
+@group(0) @binding(9) var shadow_cmp: sampler_comparison;
+
+fn sample_env(t: texture_cube<f32>, s: sampler, dir: vec3<f32>) -> vec4<f32> {
+    return textureSampleLevel(t, s, dir, 0.0f);
+}
+
+@fragment
+fn main(@builtin(position) pos: vec4<f32>) -> @location(0) vec4<f32> {
+    let uv = pos.xy / vec2<f32>(textureDimensions(color));
+    var c = textureSample(color, linear, uv);
+    c = c + textureSampleBias(color, linear, uv, 1.0f);
+    c = c + textureSampleGrad(volume, linear, vec3<f32>(uv, 0.5f), vec3<f32>(), vec3<f32>());
+    c = c + sample_env(env, linear, vec3<f32>(uv, 1.0f));
+    c = c + textureSampleBaseClampToEdge(video, linear, uv);
+    c = c + textureGather(0i, color, linear, uv) + textureGather(shadow, linear, uv);
+
+    let layer = textureLoad(layers, vec2<i32>(0i), 1i, 0i);
+    let texel = textureLoad(msaa, vec2<u32>(1u), 0i);
+    let depth = textureSampleCompare(shadow, shadow_cmp, uv, 0.5f);
+    let depths = textureGatherCompare(shadows, shadow_cmp, vec3<f32>(uv, 1.0f), 2i, 0.5f);
+    let counts = textureNumLayers(layers) + textureNumLevels(color) + textureNumSamples(msaa);
+
+    return c * depth + depths + vec4<f32>(vec4<u32>(layer.x, counts, 0u, 0u)) + vec4<f32>(texel);
+}
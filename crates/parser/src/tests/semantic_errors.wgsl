@@ -0,0 +1,29 @@
+enable foo;
+requires bar;
+diagnostic(loud, derivative_uniformity);
+
+struct S {
+    @align(x) a: i32,
+    b: T,
+}
+
+var<private> g;
+
+@group(0) @binding(0) var t0: texture_2d<bool>;
+@group(0) @binding(1) var t1: texture_multisampled_2d<f16>;
+
+@compute @workgroup_size(1)
+fn main() {
+    let x = y + 1;
+    let v = vec2<f32>(1.0, 2.0);
+    let w = v.x.y;
+    let z = undefined_fn(x).member;
+    let big = 3000000000i;
+    let huge = 0xFFFFFFFFFFFFFFFFF;
+    let inf = 1e400;
+    let t: Foo = 1i;
+    *x = 1;
+    @must_use loop {
+        break;
+    }
+}
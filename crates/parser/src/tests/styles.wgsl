@@ -0,0 +1,37 @@
+enable f16;
+
+struct Outer {
+    inner: Inner,
+    n: u32,
+}
+
+struct Inner {
+    x: f32,
+}
+
+const LIMIT: u32 = 4294967295u;
+const BASE: i32 = OFFSET * 2i;
+const OFFSET: i32 = -2147483647i - 1i;
+
+var<private> state: Outer;
+
+fn helper(a: i32, b: i32) -> i32 {
+    return a - (b - 1i) * -(-a) / (a % 3i) - (a - b) - -2i;
+}
+
+@compute @workgroup_size(1)
+fn main() {
+    let h = -1.5h;
+    let f = -0.125f;
+    let big = 3.4e38f;
+    let small = 1e-7f;
+    let af = 0.1 + 2.5e10;
+    let ai = 0x7fffffff - -5;
+    let min = i32(-2147483648);
+    var x = helper(BASE, 7i) << 2u;
+    x = (~x & (x | 1i)) ^ 3i;
+    let b = !(x > 0i) || (x < 10i && x != 5i);
+    let v = -vec2<f32>(1.0f, 2.0f).x;
+    state.n = LIMIT >> 1u;
+    state.inner.x = -v * big + f32(h);
+}
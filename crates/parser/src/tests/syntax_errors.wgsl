@@ -0,0 +1,23 @@
+struct S {
+    a: i32,
+    b: u32
+}
+
+fn f() -> i32 {
+    let x = 1 +;
+    let y: vec2<f32> = vec2<f32>(1.0, 2.0);
+    return x *;
+}
+
+@compute @workgroup_size(1)
+fn main() {
+    var a = 1;
+    a = ;
+    if (a > 0) {
+        a = a + 1
+    }
+}
+
+var<private> g: i32 = 0;
+
+fn é() {}
@@ -0,0 +1,12 @@
+@group(0) @binding(0) var tex1: texture_storage_1d<rgba8unorm, write>;
+@group(0) @binding(1) var tex2: texture_storage_2d<r32uint, read_write>;
+@group(0) @binding(2) var tex3: texture_storage_3d<rgba32sint, write>;
+
+@compute @workgroup_size(1)
+fn main() {
+    let dims = textureDimensions(tex2);
+    let value = textureLoad(tex2, vec2<u32>(1u, 2u) % dims);
+    textureStore(tex2, vec2<u32>(0u, 0u), value);
+    textureStore(tex1, 3u, vec4<f32>(0.5f, 0.25f, 1.0f, 0.0f));
+    textureStore(tex3, vec3<i32>(1i, 2i, 3i), vec4<i32>(-1i));
+}
@@ -1,6 +1,7 @@
 use std::collections::{HashMap, HashSet};
 use std::hash::Hash;
 
+use ast::visit::{self, Visitor};
 use ast::*;
 
 #[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
@@ -84,13 +85,13 @@ pub fn analyse(module: &Module) -> bool {
         idents: Default::default(),
     };
 
-    for var in &module.vars {
+    for var in module.vars() {
         global_scope
             .idents
             .insert(&var.name, RootIdentifier::Mem(analysis.next_mem_loc()));
     }
 
-    for func in module.functions.iter().rev() {
+    for func in module.functions().rev() {
         let accesses = visit_function(&mut analysis, global_scope.clone(), func);
         analysis.accesses.insert(&func.name, accesses);
     }
@@ -104,7 +105,7 @@ pub fn analyse(module: &Module) -> bool {
         ..
     } = analysis;
 
-    for func in module.functions.iter() {
+    for func in module.functions() {
         // Expand the calls to determine the full set of possible memory accesses
         for call in calls.get(func.name.as_str()).into_iter().flatten() {
             if let Some(call_accesses) = accesses
@@ -166,18 +167,11 @@ pub fn analyse(module: &Module) -> bool {
     true
 }
 
-// TODO: Use visitor pattern to avoid duplicating code with analysis in harness?
-
 fn visit_function<'a>(
     analysis: &mut Analysis<'a>,
     mut scope: Scope<'a>,
     func: &'a FnDecl,
 ) -> HashSet<(AccessType, RootIdentifier)> {
-    let mut cx = FnContext {
-        name: &func.name,
-        accesses: Default::default(),
-    };
-
     for (i, param) in func.inputs.iter().enumerate() {
         if let DataType::Ptr(_) = &param.data_type {
             scope
@@ -186,221 +180,165 @@ fn visit_function<'a>(
         }
     }
 
-    for stmt in &func.body {
-        visit_stmt(analysis, &mut scope, &mut cx, stmt);
-    }
+    let mut visitor = FnVisitor {
+        analysis,
+        scope,
+        cx: FnContext {
+            name: &func.name,
+            accesses: Default::default(),
+        },
+    };
 
-    cx.accesses
+    visitor.visit_block(&func.body);
+    visitor.cx.accesses
 }
 
-fn visit_stmt<'a>(
-    analysis: &mut Analysis<'a>,
-    scope: &mut Scope<'a>,
-    cx: &mut FnContext<'a>,
-    stmt: &'a Statement,
-) {
-    match stmt {
-        Statement::LetDecl(stmt) => visit_expr(analysis, scope, cx, &stmt.initializer),
-        Statement::VarDecl(stmt) => {
-            if let Some(initializer) = &stmt.initializer {
-                visit_expr(analysis, scope, cx, initializer);
-            }
-
-            scope
-                .idents
-                .insert(&stmt.ident, RootIdentifier::Mem(analysis.next_mem_loc()));
-        }
-        Statement::Assignment(stmt) => {
-            visit_lhs(analysis, scope, cx, &stmt.lhs);
-            visit_expr(analysis, scope, cx, &stmt.rhs);
-        }
-        Statement::Compound(block) => visit_stmt_block(analysis, scope, cx, block),
-        Statement::If(stmt) => visit_if_stmt(analysis, scope, cx, stmt),
-        Statement::Return(stmt) => {
-            if let Some(value) = &stmt.value {
-                visit_expr(analysis, scope, cx, value);
-            }
-        }
-        Statement::Loop(stmt) => {
-            visit_stmt_block(analysis, scope, cx, &stmt.body);
-            if let Some(continuing) = &stmt.continuing {
-                visit_stmt_block(analysis, scope, cx, &continuing.stmts);
-                if let Some(break_if) = &continuing.break_if {
-                    visit_expr(analysis, scope, cx, break_if);
-                }
-            }
-        }
-        Statement::While(stmt) => {
-            visit_expr(analysis, scope, cx, &stmt.condition);
-            visit_stmt_block(analysis, scope, cx, &stmt.body);
-        }
-        Statement::Break => {}
-        Statement::Switch(stmt) => {
-            visit_expr(analysis, scope, cx, &stmt.selector);
+struct FnVisitor<'a, 'b> {
+    analysis: &'b mut Analysis<'a>,
+    scope: Scope<'a>,
+    cx: FnContext<'a>,
+}
 
-            for case in &stmt.cases {
-                visit_stmt_block(analysis, scope, cx, &case.body);
-            }
+impl<'a> FnVisitor<'a, '_> {
+    fn with_scope(&mut self, f: impl FnOnce(&mut Self)) {
+        let scope = self.scope.clone();
+        f(self);
+        self.scope = scope;
+    }
 
-            visit_stmt_block(analysis, scope, cx, &stmt.default);
-        }
-        Statement::ForLoop(stmt) => {
-            let mut scope = scope.clone();
-
-            if let Some(init) = &stmt.header.init {
-                match init {
-                    ForLoopInit::VarDecl(stmt) => {
-                        if let Some(init) = &stmt.initializer {
-                            visit_expr(analysis, &mut scope, cx, init);
-                        }
-
-                        scope
-                            .idents
-                            .insert(&stmt.ident, RootIdentifier::Mem(analysis.next_mem_loc()));
-                    }
-                    ForLoopInit::LetDecl(stmt) => {
-                        visit_expr(analysis, &mut scope, cx, &stmt.initializer);
-                        if let DataType::Ptr(_) = stmt.initializer.data_type {
-                            let root = find_pointer_expr_root(&stmt.initializer);
-                            if let Some(&root_id) = scope.idents.get(root) {
-                                scope.idents.insert(stmt.ident.as_str(), root_id);
-                            } else {
-                                scope.idents.remove(stmt.ident.as_str());
-                            }
-                        } else {
-                            scope.idents.remove(stmt.ident.as_str());
-                        }
-                    }
-                    ForLoopInit::Assignment(stmt) => {
-                        visit_lhs(analysis, &mut scope, cx, &stmt.lhs);
-                        visit_expr(analysis, &mut scope, cx, &stmt.rhs);
-                    }
-                    ForLoopInit::Increment(stmt) => {
-                        handle_inc_dec(analysis, &mut scope, cx, &stmt.lhs);
-                    }
-                    ForLoopInit::Decrement(stmt) => {
-                        handle_inc_dec(analysis, &mut scope, cx, &stmt.lhs);
+    fn visit_function_call(&mut self, ident: &'a str, args: &'a [ExprNode]) {
+        let mut has_pointer_args = false;
+        let mut arg_ids = vec![];
+
+        for (i, arg) in args.iter().enumerate() {
+            if let DataType::Ptr(_) = arg.data_type {
+                has_pointer_args = true;
+                let root_ident = *self.scope.idents.get(find_pointer_expr_root(arg)).unwrap();
+                match root_ident {
+                    RootIdentifier::Mem(loc) => {
+                        self.analysis
+                            .points_to
+                            .entry(ident)
+                            .or_default()
+                            .entry(i as u32)
+                            .or_default()
+                            .insert(loc);
                     }
-                    ForLoopInit::Call(stmt) => {
-                        visit_function_call(analysis, &mut scope, cx, &stmt.ident, &stmt.args);
+                    RootIdentifier::Param(id) => {
+                        let locs = self
+                            .analysis
+                            .points_to
+                            .get(self.cx.name)
+                            .unwrap()
+                            .get(&id)
+                            .unwrap() as *const _;
+
+                        self.analysis
+                            .points_to
+                            .entry(ident)
+                            .or_default()
+                            .entry(i as u32)
+                            .or_default()
+                            // This should be safe as long as cx.name != ident (which shouldn't be
+                            // the case since recursion is forbidden in WGSL)
+                            .extend(unsafe { &*locs });
                     }
                 }
+                arg_ids.push(Some(root_ident));
+            } else {
+                self.visit_expr(arg);
+                arg_ids.push(None);
             }
+        }
 
-            if let Some(condition) = &stmt.header.condition {
-                visit_expr(analysis, &mut scope, cx, condition);
-            }
+        if has_pointer_args {
+            let id = self.analysis.next_id();
+            self.analysis
+                .calls
+                .entry(self.cx.name)
+                .or_default()
+                .insert(FnCall(id, ident, arg_ids));
+        }
+    }
 
-            if let Some(update) = &stmt.header.update {
-                match update {
-                    ForLoopUpdate::Assignment(stmt) => {
-                        visit_lhs(analysis, &mut scope, cx, &stmt.lhs);
-                        visit_expr(analysis, &mut scope, cx, &stmt.rhs);
-                    }
-                    ForLoopUpdate::Increment(stmt) => {
-                        handle_inc_dec(analysis, &mut scope, cx, &stmt.lhs);
-                    }
-                    ForLoopUpdate::Decrement(stmt) => {
-                        handle_inc_dec(analysis, &mut scope, cx, &stmt.lhs);
-                    }
-                    ForLoopUpdate::Call(stmt) => {
-                        visit_function_call(analysis, &mut scope, cx, &stmt.ident, &stmt.args);
-                    }
-                }
+    fn handle_inc_dec(&mut self, lhs: &'a AssignmentLhs) {
+        self.visit_assignment_lhs(lhs);
+        if let AssignmentLhs::Expr(lhs) = lhs {
+            let ident = find_lhs_ident(lhs);
+            if let Some(root_ident) = self.scope.idents.get(ident) {
+                self.cx.accesses.insert((AccessType::Read, *root_ident));
             }
-
-            visit_stmt_block(analysis, &mut scope, cx, &stmt.body);
-        }
-        Statement::FnCall(stmt) => {
-            visit_function_call(analysis, scope, cx, &stmt.ident, &stmt.args);
         }
-        Statement::Continue => {}
-        Statement::Fallthrough => {}
-        Statement::Increment(stmt) => handle_inc_dec(analysis, scope, cx, &stmt.lhs),
-        Statement::Decrement(stmt) => handle_inc_dec(analysis, scope, cx, &stmt.lhs),
     }
 }
 
-fn visit_stmt_block<'a>(
-    analysis: &mut Analysis<'a>,
-    scope: &mut Scope<'a>,
-    cx: &mut FnContext<'a>,
-    block: &'a [Statement],
-) {
-    let mut scope = scope.clone();
-    for stmt in block {
-        visit_stmt(analysis, &mut scope, cx, stmt);
+impl<'a> Visitor<'a> for FnVisitor<'a, '_> {
+    fn visit_block(&mut self, block: &'a [Statement]) {
+        self.with_scope(|this| visit::walk_block(this, block));
     }
-}
 
-fn visit_lhs<'a>(
-    _analysis: &mut Analysis<'a>,
-    scope: &mut Scope<'a>,
-    cx: &mut FnContext<'a>,
-    lhs: &'a AssignmentLhs,
-) {
-    if let AssignmentLhs::Expr(lhs) = &lhs {
-        let ident = find_lhs_ident(lhs);
-        let root_ident = scope.idents.get(ident);
-        if let Some(root_ident) = root_ident {
-            cx.accesses.insert((AccessType::Write, *root_ident));
+    fn visit_let_decl(&mut self, stmt: &'a LetDeclStatement) {
+        visit::walk_let_decl(self, stmt);
+
+        let root_id = match stmt.initializer.data_type {
+            DataType::Ptr(_) => self
+                .scope
+                .idents
+                .get(find_pointer_expr_root(&stmt.initializer))
+                .copied(),
+            _ => None,
+        };
+
+        if let Some(root_id) = root_id {
+            self.scope.idents.insert(stmt.ident.as_str(), root_id);
+        } else {
+            self.scope.idents.remove(stmt.ident.as_str());
         }
     }
-}
 
-fn visit_if_stmt<'a>(
-    analysis: &mut Analysis<'a>,
-    scope: &mut Scope<'a>,
-    cx: &mut FnContext<'a>,
-    stmt: &'a IfStatement,
-) {
-    visit_expr(analysis, scope, cx, &stmt.condition);
-    visit_stmt_block(analysis, scope, cx, &stmt.body);
-
-    if let Some(else_) = &stmt.else_ {
-        match else_.as_ref() {
-            Else::If(stmt) => visit_if_stmt(analysis, scope, cx, stmt),
-            Else::Else(body) => visit_stmt_block(analysis, scope, cx, body),
-        }
+    fn visit_var_decl(&mut self, stmt: &'a VarDeclStatement) {
+        visit::walk_var_decl(self, stmt);
+
+        let loc = self.analysis.next_mem_loc();
+        self.scope
+            .idents
+            .insert(&stmt.ident, RootIdentifier::Mem(loc));
     }
-}
 
-fn visit_expr<'a>(
-    analysis: &mut Analysis<'a>,
-    scope: &mut Scope<'a>,
-    cx: &mut FnContext<'a>,
-    node: &'a ExprNode,
-) {
-    match &node.expr {
-        Expr::Lit(_) => {}
-        Expr::TypeCons(expr) => {
-            for arg in &expr.args {
-                visit_expr(analysis, scope, cx, arg);
-            }
-        }
-        Expr::Var(expr) => {
-            let ident = scope.idents.get(expr.ident.as_str());
-            if let Some(root_ident) = ident {
-                cx.accesses.insert((AccessType::Read, *root_ident));
+    fn visit_increment(&mut self, stmt: &'a IncrementStatement) {
+        self.handle_inc_dec(&stmt.lhs);
+    }
+
+    fn visit_decrement(&mut self, stmt: &'a DecrementStatement) {
+        self.handle_inc_dec(&stmt.lhs);
+    }
+
+    fn visit_for_loop(&mut self, stmt: &'a ForLoopStatement) {
+        self.with_scope(|this| visit::walk_for_loop(this, stmt));
+    }
+
+    fn visit_fn_call_stmt(&mut self, stmt: &'a FnCallStatement) {
+        self.visit_function_call(&stmt.ident, &stmt.args);
+    }
+
+    fn visit_assignment_lhs(&mut self, lhs: &'a AssignmentLhs) {
+        if let AssignmentLhs::Expr(lhs) = &lhs {
+            let ident = find_lhs_ident(lhs);
+            if let Some(root_ident) = self.scope.idents.get(ident) {
+                self.cx.accesses.insert((AccessType::Write, *root_ident));
             }
         }
-        Expr::Postfix(expr) => {
-            visit_expr(analysis, scope, cx, &expr.inner);
+    }
 
-            match &expr.postfix {
-                Postfix::Index(index) => visit_expr(analysis, scope, cx, index),
-                Postfix::Member(_) => {}
+    fn visit_expr(&mut self, node: &'a ExprNode) {
+        match &node.expr {
+            Expr::Var(expr) => {
+                if let Some(root_ident) = self.scope.idents.get(expr.ident.as_str()) {
+                    self.cx.accesses.insert((AccessType::Read, *root_ident));
+                }
             }
-        }
-        Expr::UnOp(expr) => {
-            visit_expr(analysis, scope, cx, &expr.inner);
-        }
-        Expr::BinOp(expr) => {
-            visit_expr(analysis, scope, cx, &expr.left);
-            visit_expr(analysis, scope, cx, &expr.right);
-        }
-        Expr::FnCall(expr) => {
-            visit_function_call(analysis, scope, cx, &expr.ident, &expr.args);
+            Expr::FnCall(expr) => self.visit_function_call(&expr.ident, &expr.args),
+            _ => visit::walk_expr(self, node),
         }
     }
 }
@@ -414,62 +352,6 @@ fn find_lhs_ident(node: &LhsExprNode) -> &str {
     }
 }
 
-fn visit_function_call<'a>(
-    analysis: &mut Analysis<'a>,
-    scope: &mut Scope<'a>,
-    cx: &mut FnContext<'a>,
-    ident: &'a str,
-    args: &'a [ExprNode],
-) {
-    let mut has_pointer_args = false;
-    let mut arg_ids = vec![];
-
-    for (i, arg) in args.iter().enumerate() {
-        if let DataType::Ptr(_) = arg.data_type {
-            has_pointer_args = true;
-            let root_ident = scope.idents.get(find_pointer_expr_root(arg)).unwrap();
-            match root_ident {
-                RootIdentifier::Mem(loc) => {
-                    analysis
-                        .points_to
-                        .entry(ident)
-                        .or_default()
-                        .entry(i as u32)
-                        .or_default()
-                        .insert(*loc);
-                }
-                RootIdentifier::Param(id) => {
-                    let locs =
-                        analysis.points_to.get(cx.name).unwrap().get(id).unwrap() as *const _;
-
-                    analysis
-                        .points_to
-                        .entry(ident)
-                        .or_default()
-                        .entry(i as u32)
-                        .or_default()
-                        // This should be safe as long as cx.name != ident (which shouldn't be the
-                        // case since recursion is forbidden in WGSL)
-                        .extend(unsafe { &*locs });
-                }
-            }
-            arg_ids.push(Some(*root_ident));
-        } else {
-            visit_expr(analysis, scope, cx, arg);
-            arg_ids.push(None);
-        }
-    }
-
-    if has_pointer_args {
-        let id = analysis.next_id();
-        analysis
-            .calls
-            .entry(cx.name)
-            .or_default()
-            .insert(FnCall(id, ident, arg_ids));
-    }
-}
-
 fn find_pointer_expr_root(node: &ExprNode) -> &str {
     match &node.expr {
         Expr::Var(expr) => &expr.ident,
@@ -479,17 +361,46 @@ fn find_pointer_expr_root(node: &ExprNode) -> &str {
     }
 }
 
-fn handle_inc_dec<'a>(
-    analysis: &mut Analysis<'a>,
-    scope: &mut Scope<'a>,
-    cx: &mut FnContext<'a>,
-    lhs: &'a AssignmentLhs,
-) {
-    visit_lhs(analysis, scope, cx, lhs);
-    if let AssignmentLhs::Expr(lhs) = lhs {
-        let ident = find_lhs_ident(lhs);
-        if let Some(root_ident) = scope.idents.get(ident) {
-            cx.accesses.insert((AccessType::Read, *root_ident));
-        }
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pointer_lets_alias_their_target() {
+        let aliased = parser::parse(
+            r"
+fn f(a: ptr<function, i32>, b: ptr<function, i32>) {
+    *a = *b;
+}
+
+fn main() {
+    var x = 1i;
+    if (x > 0i) {
+        let p = &x;
+        f(p, &x);
+    }
+}
+",
+        );
+
+        let distinct = parser::parse(
+            r"
+fn f(a: ptr<function, i32>, b: ptr<function, i32>) {
+    *a = *b;
+}
+
+fn main() {
+    var x = 1i;
+    var y = 2i;
+    if (x > 0i) {
+        let p = &y;
+        f(p, &x);
+    }
+}
+",
+        );
+
+        assert!(!analyse(&aliased));
+        assert!(analyse(&distinct));
     }
 }
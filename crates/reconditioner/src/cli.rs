@@ -2,6 +2,7 @@ use std::fs::File;
 use std::io::Read;
 
 use clap::{Parser, ValueEnum};
+use eyre::bail;
 
 use crate::analysis;
 
@@ -23,6 +24,13 @@ pub struct Options {
         require_value_delimiter(true)
     )]
     pub enable: Vec<Feature>,
+
+    /// Explain why constant expressions were replaced, with the rule that each one breaks.
+    #[clap(long, action)]
+    pub explain: bool,
+
+    #[clap(flatten)]
+    pub style: ast::writer::Options,
 }
 
 #[derive(ValueEnum, Clone, Debug)]
@@ -32,7 +40,17 @@ pub enum Feature {
 
 pub fn run(options: Options) -> eyre::Result<()> {
     let input = read_shader_from_path(&options.input)?;
-    let ast = parser::parse(&input);
+    let ast = match parser::try_parse(&input) {
+        Ok(ast) => ast,
+        Err(diagnostics) => {
+            for diagnostic in &diagnostics {
+                eprintln!("error: {diagnostic}");
+            }
+
+            eprintln!("found {} errors in shader", diagnostics.len());
+            std::process::exit(1);
+        }
+    };
 
     let result = analysis::analyse(&ast);
     if !result {
@@ -47,7 +65,32 @@ pub fn run(options: Options) -> eyre::Result<()> {
         rec_opts.only_loops = true;
     }
 
-    let result = crate::recondition_with(ast, rec_opts);
+    let (result, diagnostics) = crate::recondition_with_diagnostics(ast, rec_opts);
+
+    if options.explain {
+        for diagnostic in &diagnostics {
+            match diagnostic
+                .span
+                .and_then(|span| input.get(span.start..span.end))
+            {
+                Some(source) => eprintln!("note: {diagnostic}: `{source}`"),
+                None => eprintln!("note: {diagnostic}"),
+            }
+        }
+
+        eprintln!("replaced {} invalid expressions", diagnostics.len());
+    }
+
+    // Catch reconditioner bugs here, since the fuzzer reconditions shaders through this command
+    if cfg!(debug_assertions) {
+        if let Err(errors) = typecheck::check(&result) {
+            for error in &errors {
+                eprintln!("error: {error}");
+            }
+
+            bail!("shader has {} type errors after reconditioning", errors.len());
+        }
+    }
 
     struct Output(Box<dyn std::io::Write>);
 
@@ -64,7 +107,7 @@ pub fn run(options: Options) -> eyre::Result<()> {
         path => Box::new(File::create(path)?),
     };
 
-    ast::writer::Writer::default()
+    ast::writer::Writer::new(options.style)
         .write_module(&mut Output(output), &result)
         .unwrap();
 
@@ -7,7 +7,9 @@ use std::collections::HashSet;
 use std::fmt::Display;
 
 use ast::types::{DataType, MemoryViewType, ScalarType};
+use ast::visit::{self, fold, Fold, VisitorMut};
 use ast::*;
+use concretizer::diagnostic::Diagnostic;
 
 pub struct ReconditionResult {
     pub ast: Module,
@@ -63,37 +65,50 @@ pub fn recondition(ast: Module) -> Module {
 }
 
 pub fn recondition_with(ast: Module, options: Options) -> Module {
+    recondition_with_diagnostics(ast, options).0
+}
+
+/// Reconditions a module, also returning the diagnostics for the invalid constant expressions that
+/// the concretizer replaced.
+pub fn recondition_with_diagnostics(ast: Module, options: Options) -> (Module, Vec<Diagnostic>) {
     let mut reconditioner = Reconditioner::new(options);
 
-    let mut ast = concretizer::concretize(ast);
+    let (mut ast, diagnostics) =
+        concretizer::concretize_with_diagnostics(ast, concretizer::concretizer::Options::default());
 
-    let functions = ast
-        .functions
+    ast.decls = ast
+        .decls
         .into_iter()
-        .map(|f| reconditioner.recondition_fn(f))
-        .collect::<Vec<_>>();
-
-    ast.functions = reconditioner
-        .wrappers
-        .iter()
-        .map(Wrapper::gen_fn_decl)
-        .chain(functions)
+        .map(|decl| match decl {
+            GlobalDecl::Fn(f) => GlobalDecl::Fn(reconditioner.fold_fn(f)),
+            decl => decl,
+        })
         .collect();
 
+    ast.insert_before_functions(reconditioner.wrappers.iter().map(Wrapper::gen_fn_decl));
+
     if reconditioner.loop_var > 0 {
-        ast.vars.push(GlobalVarDecl {
+        let counters_ty = DataType::array(ScalarType::U32, Some(reconditioner.loop_var));
+
+        // Uses of the counters array were created before its size was known
+        let mut sizer = LoopCountersSizer(counters_ty.clone());
+        for decl in ast.functions_mut() {
+            sizer.visit_fn_mut(decl);
+        }
+
+        ast.insert_before_functions([GlobalVarDecl {
             attrs: vec![],
-            data_type: DataType::array(ScalarType::U32, Some(reconditioner.loop_var)),
+            data_type: counters_ty,
             name: "LOOP_COUNTERS".into(),
             initializer: None,
             qualifier: Some(VarQualifier {
                 storage_class: StorageClass::Private,
                 access_mode: None,
             }),
-        });
+        }]);
     }
 
-    ast
+    (ast, diagnostics)
 }
 
 struct Reconditioner {
@@ -111,230 +126,7 @@ impl Reconditioner {
         }
     }
 
-    fn recondition_fn(&mut self, mut decl: FnDecl) -> FnDecl {
-        decl.body = decl
-            .body
-            .into_iter()
-            .map(|s| self.recondition_stmt(s))
-            .collect();
-        decl
-    }
-
-    fn recondition_else(&mut self, els: Else) -> Else {
-        match els {
-            Else::If(IfStatement {
-                condition,
-                body,
-                else_,
-            }) => Else::If(IfStatement {
-                condition: self.recondition_expr(condition),
-                body: body.into_iter().map(|s| self.recondition_stmt(s)).collect(),
-                else_: else_.map(|els| Box::new(self.recondition_else(*els))),
-            }),
-            Else::Else(stmts) => Else::Else(
-                stmts
-                    .into_iter()
-                    .map(|s| self.recondition_stmt(s))
-                    .collect(),
-            ),
-        }
-    }
-
-    fn recondition_stmt(&mut self, stmt: Statement) -> Statement {
-        match stmt {
-            Statement::LetDecl(LetDeclStatement { ident, initializer }) => {
-                LetDeclStatement::new(ident, self.recondition_expr(initializer)).into()
-            }
-            Statement::VarDecl(VarDeclStatement {
-                ident,
-                data_type,
-                initializer,
-            }) => VarDeclStatement::new(
-                ident,
-                data_type,
-                initializer.map(|e| self.recondition_expr(e)),
-            )
-            .into(),
-            Statement::Assignment(AssignmentStatement { lhs, op, rhs }) => {
-                AssignmentStatement::new(
-                    self.recondition_assignment_lhs(lhs),
-                    op,
-                    self.recondition_expr(rhs),
-                )
-                .into()
-            }
-            Statement::Compound(s) => {
-                Statement::Compound(s.into_iter().map(|s| self.recondition_stmt(s)).collect())
-            }
-            Statement::If(IfStatement {
-                condition,
-                body,
-                else_,
-            }) => IfStatement::new(
-                self.recondition_expr(condition),
-                body.into_iter().map(|s| self.recondition_stmt(s)).collect(),
-            )
-            .with_else(else_.map(|els| self.recondition_else(*els)))
-            .into(),
-            Statement::Return(ReturnStatement { value }) => ReturnStatement {
-                value: value.map(|e| self.recondition_expr(e)),
-            }
-            .into(),
-            Statement::Loop(LoopStatement {
-                mut body,
-                continuing,
-            }) => {
-                if continuing.is_some() {
-                    let last_decl_idx = body
-                        .iter()
-                        .rposition(|s| matches!(s, Statement::LetDecl(_) | Statement::VarDecl(_)));
-                    if let Some(idx) = last_decl_idx {
-                        for stmt in body.iter_mut().take(idx) {
-                            Self::replace_continue(stmt);
-                        }
-                    }
-                }
-
-                LoopStatement::new(
-                    self.recondition_loop_body(body),
-                    continuing.map(|ContinuingBlock { stmts, break_if }| {
-                        let new_stmts: Vec<Statement> = stmts
-                            .into_iter()
-                            .map(|s| self.recondition_stmt(s))
-                            .collect();
-
-                        ContinuingBlock {
-                            stmts: new_stmts,
-                            break_if: break_if.map(|e| self.recondition_expr(e)),
-                        }
-                    }),
-                )
-                .into()
-            }
-            Statement::While(stmt) => Statement::While(WhileStatement {
-                condition: self.recondition_expr(stmt.condition),
-                body: self.recondition_loop_body(stmt.body),
-            }),
-            Statement::Break => Statement::Break,
-            Statement::Switch(SwitchStatement {
-                selector,
-                cases,
-                default,
-            }) => SwitchStatement::new(
-                self.recondition_expr(selector),
-                cases
-                    .into_iter()
-                    .map(|SwitchCase { selector, body }| SwitchCase {
-                        selector: self.recondition_expr(selector),
-                        body: body
-                            .into_iter()
-                            .map(|it| self.recondition_stmt(it))
-                            .collect(),
-                    })
-                    .collect(),
-                default
-                    .into_iter()
-                    .map(|it| self.recondition_stmt(it))
-                    .collect(),
-            )
-            .into(),
-            Statement::ForLoop(ForLoopStatement { header, body }) => ForLoopStatement::new(
-                ForLoopHeader {
-                    init: header.init.map(|init| self.recondition_for_init(init)),
-                    condition: header.condition.map(|e| self.recondition_expr(e)),
-                    update: header
-                        .update
-                        .map(|update| self.recondition_for_update(update)),
-                },
-                self.recondition_loop_body(body),
-            )
-            .into(),
-            Statement::FnCall(FnCallStatement { ident, args }) => {
-                Statement::FnCall(FnCallStatement::new(
-                    ident,
-                    args.into_iter()
-                        .map(|it| self.recondition_expr(it))
-                        .collect(),
-                ))
-            }
-            Statement::Continue => Statement::Continue,
-            Statement::Fallthrough => Statement::Fallthrough,
-            Statement::Increment(IncrementStatement { lhs }) => {
-                IncrementStatement::new(self.recondition_assignment_lhs(lhs)).into()
-            }
-            Statement::Decrement(DecrementStatement { lhs }) => {
-                DecrementStatement::new(self.recondition_assignment_lhs(lhs)).into()
-            }
-        }
-    }
-
-    fn recondition_for_init(&mut self, init: ForLoopInit) -> ForLoopInit {
-        match init {
-            ForLoopInit::VarDecl(VarDeclStatement {
-                ident,
-                data_type,
-                initializer,
-            }) => ForLoopInit::VarDecl(VarDeclStatement::new(
-                ident,
-                data_type,
-                initializer.map(|e| self.recondition_expr(e)),
-            )),
-            ForLoopInit::LetDecl(LetDeclStatement { ident, initializer }) => ForLoopInit::LetDecl(
-                LetDeclStatement::new(ident, self.recondition_expr(initializer)),
-            ),
-            ForLoopInit::Assignment(AssignmentStatement { lhs, op, rhs }) => {
-                ForLoopInit::Assignment(AssignmentStatement::new(
-                    self.recondition_assignment_lhs(lhs),
-                    op,
-                    self.recondition_expr(rhs),
-                ))
-            }
-            ForLoopInit::Increment(IncrementStatement { lhs }) => ForLoopInit::Increment(
-                IncrementStatement::new(self.recondition_assignment_lhs(lhs)),
-            ),
-            ForLoopInit::Decrement(DecrementStatement { lhs }) => ForLoopInit::Decrement(
-                DecrementStatement::new(self.recondition_assignment_lhs(lhs)),
-            ),
-            ForLoopInit::Call(FnCallStatement { ident, args }) => {
-                ForLoopInit::Call(FnCallStatement::new(
-                    ident,
-                    args.into_iter()
-                        .map(|it| self.recondition_expr(it))
-                        .collect(),
-                ))
-            }
-        }
-    }
-
-    fn recondition_for_update(&mut self, update: ForLoopUpdate) -> ForLoopUpdate {
-        match update {
-            ForLoopUpdate::Assignment(AssignmentStatement { lhs, op, rhs }) => {
-                ForLoopUpdate::Assignment(AssignmentStatement::new(
-                    self.recondition_assignment_lhs(lhs),
-                    op,
-                    self.recondition_expr(rhs),
-                ))
-            }
-            ForLoopUpdate::Increment(IncrementStatement { lhs }) => ForLoopUpdate::Increment(
-                IncrementStatement::new(self.recondition_assignment_lhs(lhs)),
-            ),
-            ForLoopUpdate::Decrement(DecrementStatement { lhs }) => ForLoopUpdate::Decrement(
-                DecrementStatement::new(self.recondition_assignment_lhs(lhs)),
-            ),
-            ForLoopUpdate::Call(FnCallStatement { ident, args }) => {
-                ForLoopUpdate::Call(FnCallStatement::new(
-                    ident,
-                    args.into_iter()
-                        .map(|it| self.recondition_expr(it))
-                        .collect(),
-                ))
-            }
-        }
-    }
-
-    fn recondition_loop_body(&mut self, body: Vec<Statement>) -> Vec<Statement> {
-        let id = self.loop_var();
-
+    fn loop_counter_stmts(&mut self, id: u32) -> [Statement; 2] {
         let counters_ty = DataType::Ref(MemoryViewType::new(
             DataType::array(ScalarType::U32, None),
             StorageClass::Private,
@@ -365,147 +157,14 @@ impl Reconditioner {
             ),
         );
 
-        std::iter::once(break_check.into())
-            .chain(std::iter::once(counter_increment.into()))
-            .chain(body.into_iter().map(|s| self.recondition_stmt(s)))
-            .collect()
-    }
-
-    fn recondition_assignment_lhs(&mut self, lhs: AssignmentLhs) -> AssignmentLhs {
-        if self.only_loops {
-            return lhs;
-        }
-
-        match lhs {
-            AssignmentLhs::Phony => AssignmentLhs::Phony,
-            AssignmentLhs::Expr(expr) => AssignmentLhs::Expr(self.recondition_lhs_expr(expr)),
-        }
+        [break_check.into(), counter_increment.into()]
     }
 
-    fn recondition_lhs_expr(&mut self, node: LhsExprNode) -> LhsExprNode {
-        let expr = match node.expr {
-            LhsExpr::Ident(ident) => LhsExpr::Ident(ident),
-            LhsExpr::Postfix(expr, postfix) => {
-                let expr = Box::new(self.recondition_lhs_expr(*expr));
-                let postfix = match postfix {
-                    Postfix::Index(index) => {
-                        let index = self.recondition_expr(*index);
-                        Postfix::index(self.recondition_array_index(&expr.data_type, index))
-                    }
-                    Postfix::Member(ident) => Postfix::Member(ident),
-                };
-
-                LhsExpr::Postfix(expr, postfix)
-            }
-            LhsExpr::Deref(_) => todo!(),
-            LhsExpr::AddressOf(_) => todo!(),
-        };
-
-        LhsExprNode { expr, ..node }
-    }
-
-    fn recondition_expr(&mut self, node: ExprNode) -> ExprNode {
-        if self.only_loops {
-            return node;
-        }
-
-        let reconditioned = match node.expr {
-            Expr::TypeCons(expr) => Expr::TypeCons(TypeConsExpr::new(
-                expr.data_type,
-                expr.args
-                    .into_iter()
-                    .map(|e| self.recondition_expr(e))
-                    .collect(),
-            )),
-            Expr::UnOp(expr) => {
-                let inner = self.recondition_expr(*expr.inner);
-                let op = expr.op;
-                match op {
-                    UnOp::Neg => {
-                        let data_type = inner.data_type.dereference().clone();
-                        let mut expr = UnOpExpr::new(UnOp::Neg, inner).into();
-                        if !data_type.is_matrix()
-                            && matches!(
-                                data_type.as_scalar().unwrap(),
-                                ScalarType::F32 | ScalarType::F16
-                            )
-                        {
-                            expr = FnCallExpr::new(
-                                self.safe_wrapper(Wrapper::FloatOp(data_type.clone())),
-                                vec![ExprNode { data_type, expr }],
-                            )
-                            .into();
-                        }
-                        expr
-                    }
-                    _ => UnOpExpr::new(op, inner).into(),
-                }
-            }
-            Expr::BinOp(expr) => {
-                let left = self.recondition_expr(*expr.left);
-                let right = self.recondition_expr(*expr.right);
-                return self.recondition_bin_op_expr(node.data_type, expr.op, left, right);
-            }
-            Expr::FnCall(expr) => {
-                let mut args: Vec<ExprNode> = expr
-                    .args
-                    .into_iter()
-                    .map(|e| self.recondition_expr(e))
-                    .collect();
-
-                match expr.ident.as_str() {
-                    "subgroupBroadcast"
-                    | "subgroupShuffle"
-                    | "subgroupShuffleDown"
-                    | "subgroupShuffleUp"
-                    | "subgroupShuffleXor" => {
-                        let limit = 127;
-                        let limit_lit = match args[1].data_type.as_scalar().unwrap() {
-                            ScalarType::I32 => Lit::I32(limit),
-                            ScalarType::U32 => Lit::U32(limit as u32),
-                            _ => unreachable!(),
-                        };
-                        args[1] = BinOpExpr::new(BinOp::BitAnd, args[1].clone(), limit_lit).into();
-                    }
-                    _ => {}
-                }
-
-                let expr = FnCallExpr::new(expr.ident, args);
-
-                if !node.data_type.is_matrix()
-                    && matches!(
-                        node.data_type.as_scalar(),
-                        Some(ScalarType::F32 | ScalarType::F16)
-                    )
-                {
-                    FnCallExpr::new(
-                        self.safe_wrapper(Wrapper::FloatOp(node.data_type.clone())),
-                        vec![expr.into_node(node.data_type.clone())],
-                    )
-                    .into()
-                } else {
-                    expr.into()
-                }
-            }
-            Expr::Postfix(expr) => {
-                let e = self.recondition_expr(*expr.inner);
-                let postfix = match expr.postfix {
-                    Postfix::Index(index) => {
-                        let index = self.recondition_expr(*index);
-                        Postfix::Index(Box::new(self.recondition_array_index(&e.data_type, index)))
-                    }
-                    Postfix::Member(n) => Postfix::Member(n),
-                };
-
-                PostfixExpr::new(e, postfix).into()
-            }
-            e => e,
-        };
-
-        ExprNode {
-            data_type: node.data_type,
-            expr: reconditioned,
-        }
+    fn with_loop_counter(&mut self, id: u32, body: Vec<Statement>) -> Vec<Statement> {
+        self.loop_counter_stmts(id)
+            .into_iter()
+            .chain(body)
+            .collect()
     }
 
     fn recondition_array_index(&mut self, array_type: &DataType, index: ExprNode) -> ExprNode {
@@ -526,11 +185,37 @@ impl Reconditioner {
             _ => unreachable!("index expression must be an integer"),
         };
 
-        FnCallExpr::new(
+        // The wrapped index keeps the metadata of the original so it can be traced back
+        let meta = index.meta;
+        let wrapped = FnCallExpr::new(
             self.safe_wrapper(Wrapper::Index(index_type.clone())),
             vec![index, size_expr.into()],
         )
-        .into_node(index_type)
+        .into_node(index_type);
+
+        ExprNode { meta, ..wrapped }
+    }
+
+    /// Wraps the coordinates passed to a texture builtin so that they are always within the
+    /// bounds of the texture, since out of bounds accesses have implementation-defined results.
+    fn recondition_texture_coords(args: &mut [ExprNode]) {
+        if args.len() < 2 {
+            return;
+        }
+
+        let texture = args[0].clone();
+        let dims_type = BuiltinFn::TextureDimensions
+            .return_type(std::iter::once(&texture.data_type))
+            .unwrap();
+
+        let mut coords = args[1].clone();
+        if coords.data_type.dereference().as_scalar() == Some(ScalarType::I32) {
+            coords = TypeConsExpr::new(dims_type.clone(), vec![coords]).into();
+        }
+
+        let dims = FnCallExpr::new("textureDimensions", vec![texture]).into_node(dims_type);
+
+        args[1] = BinOpExpr::new(BinOp::Mod, coords, dims).into();
     }
 
     fn recondition_shift_expr(
@@ -639,54 +324,279 @@ impl Reconditioner {
         self.wrappers.insert(wrapper);
         ident
     }
+}
 
-    fn replace_continue(stmt: &mut Statement) {
-        match stmt {
-            Statement::Continue => {
-                *stmt = Statement::Break;
-            }
-            Statement::Compound(stmts) => {
-                for s in stmts {
-                    Self::replace_continue(s);
+impl Fold for Reconditioner {
+    fn fold_loop(&mut self, stmt: LoopStatement) -> LoopStatement {
+        let LoopStatement {
+            attrs,
+            body_attrs,
+            mut body,
+            continuing,
+            ..
+        } = stmt;
+
+        if continuing.is_some() {
+            let last_decl_idx = body
+                .iter()
+                .rposition(|s| matches!(s, Statement::LetDecl(_) | Statement::VarDecl(_)));
+            if let Some(idx) = last_decl_idx {
+                for stmt in body.iter_mut().take(idx) {
+                    ContinueReplacer.visit_stmt_mut(stmt);
                 }
             }
-            Statement::If(stmt) => {
-                for s in &mut stmt.body {
-                    Self::replace_continue(s);
-                }
-                if let Some(else_) = &mut stmt.else_ {
-                    Self::replace_continue_else(else_.as_mut());
+        }
+
+        let id = self.loop_var();
+        let stmt = fold::fold_loop(self, LoopStatement::new(body, continuing));
+
+        LoopStatement {
+            attrs,
+            body_attrs,
+            ..LoopStatement::new(self.with_loop_counter(id, stmt.body), stmt.continuing)
+        }
+    }
+
+    fn fold_while(&mut self, stmt: WhileStatement) -> WhileStatement {
+        let id = self.loop_var();
+        let stmt = fold::fold_while(self, stmt);
+
+        WhileStatement {
+            attrs: stmt.attrs,
+            ..WhileStatement::new(stmt.condition, self.with_loop_counter(id, stmt.body))
+        }
+    }
+
+    fn fold_for_loop(&mut self, stmt: ForLoopStatement) -> ForLoopStatement {
+        let id = self.loop_var();
+        let stmt = fold::fold_for_loop(self, stmt);
+
+        ForLoopStatement {
+            attrs: stmt.attrs,
+            ..ForLoopStatement::new(*stmt.header, self.with_loop_counter(id, stmt.body))
+        }
+    }
+
+    fn fold_fn_call_stmt(&mut self, stmt: FnCallStatement) -> FnCallStatement {
+        let mut stmt = fold::fold_fn_call_stmt(self, stmt);
+
+        if stmt.ident == "textureStore" {
+            Self::recondition_texture_coords(&mut stmt.args);
+        }
+
+        stmt
+    }
+
+    fn fold_assignment_lhs(&mut self, lhs: AssignmentLhs) -> AssignmentLhs {
+        if self.only_loops {
+            return lhs;
+        }
+
+        fold::fold_assignment_lhs(self, lhs)
+    }
+
+    fn fold_lhs_expr(&mut self, node: LhsExprNode) -> LhsExprNode {
+        let node = fold::fold_lhs_expr(self, node);
+
+        let expr = match node.expr {
+            LhsExpr::Postfix(expr, Postfix::Index(index)) => {
+                let index = self.recondition_array_index(&expr.data_type, *index);
+                LhsExpr::Postfix(expr, Postfix::index(index))
+            }
+            expr => expr,
+        };
+
+        LhsExprNode { expr, ..node }
+    }
+
+    fn fold_expr(&mut self, node: ExprNode) -> ExprNode {
+        if self.only_loops {
+            return node;
+        }
+
+        let node = fold::fold_expr(self, node);
+
+        let reconditioned = match node.expr {
+            Expr::UnOp(UnOpExpr {
+                op: UnOp::Neg,
+                inner,
+            }) => {
+                let data_type = inner.data_type.dereference().clone();
+                let mut expr = UnOpExpr::new(UnOp::Neg, *inner).into();
+                if !data_type.is_matrix()
+                    && matches!(
+                        data_type.as_scalar().unwrap(),
+                        ScalarType::F32 | ScalarType::F16
+                    )
+                {
+                    expr = FnCallExpr::new(
+                        self.safe_wrapper(Wrapper::FloatOp(data_type.clone())),
+                        vec![ExprNode {
+                            data_type,
+                            expr,
+                            meta: NodeMeta::default(),
+                        }],
+                    )
+                    .into();
                 }
+                expr
             }
-            Statement::Switch(stmt) => {
-                for case in &mut stmt.cases {
-                    for s in &mut case.body {
-                        Self::replace_continue(s);
+            Expr::BinOp(expr) => {
+                let reconditioned =
+                    self.recondition_bin_op_expr(node.data_type, expr.op, *expr.left, *expr.right);
+                return ExprNode {
+                    meta: node.meta,
+                    ..reconditioned
+                };
+            }
+            Expr::FnCall(expr) => {
+                let mut args = expr.args;
+
+                match expr.ident.as_str() {
+                    "subgroupBroadcast"
+                    | "subgroupShuffle"
+                    | "subgroupShuffleDown"
+                    | "subgroupShuffleUp"
+                    | "subgroupShuffleXor" => {
+                        let limit = 127;
+                        let limit_lit = match args[1].data_type.as_scalar().unwrap() {
+                            ScalarType::I32 => Lit::I32(limit),
+                            ScalarType::U32 => Lit::U32(limit as u32),
+                            _ => unreachable!(),
+                        };
+                        args[1] = BinOpExpr::new(BinOp::BitAnd, args[1].clone(), limit_lit).into();
                     }
+                    "textureLoad" => Self::recondition_texture_coords(&mut args),
+                    _ => {}
                 }
-                for s in &mut stmt.default {
-                    Self::replace_continue(s);
+
+                let expr = FnCallExpr::new(expr.ident, args);
+
+                if !node.data_type.is_matrix()
+                    && matches!(
+                        node.data_type.as_scalar(),
+                        Some(ScalarType::F32 | ScalarType::F16)
+                    )
+                {
+                    FnCallExpr::new(
+                        self.safe_wrapper(Wrapper::FloatOp(node.data_type.clone())),
+                        vec![expr.into_node(node.data_type.clone())],
+                    )
+                    .into()
+                } else {
+                    expr.into()
                 }
             }
-            _ => {}
+            Expr::Postfix(PostfixExpr {
+                inner,
+                postfix: Postfix::Index(index),
+            }) => {
+                let index = self.recondition_array_index(&inner.data_type, *index);
+                PostfixExpr::new(*inner, Postfix::index(index)).into()
+            }
+            e => e,
+        };
+
+        ExprNode {
+            data_type: node.data_type,
+            expr: reconditioned,
+            meta: node.meta,
         }
     }
+}
 
-    fn replace_continue_else(else_: &mut Else) {
-        match else_ {
-            Else::If(stmt) => {
-                for s in &mut stmt.body {
-                    Self::replace_continue(s);
-                }
-                if let Some(els) = &mut stmt.else_ {
-                    Self::replace_continue_else(els.as_mut());
-                }
-            }
-            Else::Else(stmts) => {
-                for s in stmts {
-                    Self::replace_continue(s);
-                }
+/// Replaces `continue` statements which would jump to the end of the current loop body, without
+/// descending into nested loops.
+struct ContinueReplacer;
+
+impl VisitorMut for ContinueReplacer {
+    fn visit_stmt_mut(&mut self, stmt: &mut Statement) {
+        match stmt {
+            Statement::Continue => *stmt = Statement::Break,
+            Statement::Compound(_) | Statement::If(_) | Statement::Switch(_) => {
+                visit::walk_stmt_mut(self, stmt)
             }
+            _ => {}
         }
     }
 }
+
+/// Replaces the type of every reference to `LOOP_COUNTERS` with a reference to the given type.
+struct LoopCountersSizer(DataType);
+
+impl LoopCountersSizer {
+    fn resize(&self, data_type: &mut DataType) {
+        if let DataType::Ref(view) = data_type {
+            *view = view.clone_with_type(self.0.clone());
+        }
+    }
+}
+
+impl VisitorMut for LoopCountersSizer {
+    fn visit_lhs_expr_mut(&mut self, node: &mut LhsExprNode) {
+        if matches!(&node.expr, LhsExpr::Ident(ident) if ident == "LOOP_COUNTERS") {
+            self.resize(&mut node.data_type);
+        }
+
+        visit::walk_lhs_expr_mut(self, node);
+    }
+
+    fn visit_expr_mut(&mut self, node: &mut ExprNode) {
+        if matches!(&node.expr, Expr::Var(var) if var.ident == "LOOP_COUNTERS") {
+            self.resize(&mut node.data_type);
+        }
+
+        visit::walk_expr_mut(self, node);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_well_typed(module: &Module) {
+        if let Err(errors) = typecheck::check(module) {
+            panic!("reconditioned module has type errors: {errors:?}");
+        }
+    }
+
+    #[test]
+    fn loop_counters_are_sized() {
+        // The counters array only gets its size once all loops have been reconditioned, so the
+        // references to it that are created before that must be updated
+        let module = recondition(parser::parse(
+            r"
+fn f() {
+    var x = 0i;
+    loop {
+        x = x + 1i;
+        if (x > 10i) {
+            break;
+        }
+    }
+    while (x > 0i) {
+        x = x - 1i;
+    }
+}
+",
+        ));
+
+        assert_well_typed(&module);
+    }
+
+    #[test]
+    fn vector_float_division() {
+        // The infinity check in the float division wrapper compares each component of the
+        // operands, so `abs` is applied to a scalar rather than to the vector
+        let module = recondition(parser::parse(
+            r"
+fn f() {
+    let a = vec2<f32>(1f, 2f);
+    let b = a / vec2<f32>(3f, 4f);
+}
+",
+        ));
+
+        assert_well_typed(&module);
+    }
+}
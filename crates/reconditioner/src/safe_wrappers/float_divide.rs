@@ -49,8 +49,8 @@ fn gen_condition(data_type: &DataType) -> ExprNode {
 
             // Case 2: Detect (+-Inf, +-Inf)
             // This is slightly below MAX but it's ok for now
-            let a_abs = FnCallExpr::new("abs", vec![a]).into_node(data_type.clone());
-            let b_abs = FnCallExpr::new("abs", vec![b]).into_node(data_type.clone());
+            let a_abs = FnCallExpr::new("abs", vec![a.clone()]).into_node(a.data_type);
+            let b_abs = FnCallExpr::new("abs", vec![b.clone()]).into_node(b.data_type);
 
             let a_is_inf = BinOpExpr::new(BinOp::Greater, a_abs, max_val);
             let b_is_inf = BinOpExpr::new(BinOp::Greater, b_abs, max_val);
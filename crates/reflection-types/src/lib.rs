@@ -8,6 +8,7 @@ pub struct ResourceData<'a> {
 
 #[derive(Clone, Debug, Decode, Encode)]
 pub struct PipelineDescription {
+    pub entry_point: String,
     pub resources: Vec<PipelineResource>,
     pub extensions: Vec<Extension>,
 }
@@ -16,6 +17,85 @@ pub struct PipelineDescription {
 pub enum ResourceKind {
     StorageBuffer,
     UniformBuffer,
+    StorageTexture(StorageTexture),
+}
+
+impl ResourceKind {
+    /// Returns `true` if the contents of the resource should be read back after execution.
+    pub fn is_output(&self) -> bool {
+        matches!(
+            self,
+            ResourceKind::StorageBuffer | ResourceKind::StorageTexture(_)
+        )
+    }
+}
+
+#[derive(Clone, Debug, Decode, Encode, PartialEq, Eq)]
+pub struct StorageTexture {
+    pub dimension: TextureDimension,
+    pub format: TexelFormat,
+    pub width: u32,
+    pub height: u32,
+    pub depth: u32,
+}
+
+impl StorageTexture {
+    /// Alignment required for rows of texels when copying between textures and buffers.
+    pub const COPY_BYTES_PER_ROW_ALIGNMENT: u32 = 256;
+
+    /// Number of bytes in a single row of texels.
+    pub fn bytes_per_row(&self) -> u32 {
+        self.width * self.format.texel_size()
+    }
+
+    /// Number of bytes in a single row of texels, once padded for a texture to buffer copy.
+    pub fn padded_bytes_per_row(&self) -> u32 {
+        self.bytes_per_row()
+            .next_multiple_of(Self::COPY_BYTES_PER_ROW_ALIGNMENT)
+    }
+
+    /// Size of the buffer needed to hold a copy of the texture with padded rows.
+    pub fn padded_size(&self) -> u32 {
+        self.padded_bytes_per_row() * self.height * self.depth
+    }
+
+    /// Strips the row padding from texture data that was copied into a buffer.
+    pub fn unpad(&self, data: &[u8]) -> Vec<u8> {
+        data.chunks(self.padded_bytes_per_row() as usize)
+            .flat_map(|row| &row[..self.bytes_per_row() as usize])
+            .copied()
+            .collect()
+    }
+}
+
+#[derive(Clone, Copy, Debug, Decode, Encode, PartialEq, Eq)]
+pub enum TextureDimension {
+    D1,
+    D2,
+    D3,
+}
+
+#[derive(Clone, Copy, Debug, Decode, Encode, PartialEq, Eq)]
+pub enum TexelFormat {
+    Rgba8Unorm,
+    Rgba8Uint,
+    Rgba8Sint,
+    Rgba32Uint,
+    Rgba32Sint,
+    Rgba32Float,
+    R32Uint,
+    R32Sint,
+    R32Float,
+}
+
+impl TexelFormat {
+    pub fn texel_size(&self) -> u32 {
+        match self {
+            TexelFormat::Rgba8Unorm | TexelFormat::Rgba8Uint | TexelFormat::Rgba8Sint => 4,
+            TexelFormat::Rgba32Uint | TexelFormat::Rgba32Sint | TexelFormat::Rgba32Float => 16,
+            TexelFormat::R32Uint | TexelFormat::R32Sint | TexelFormat::R32Float => 4,
+        }
+    }
 }
 
 #[derive(Clone, Debug, Decode, Encode)]
@@ -32,4 +112,6 @@ pub struct PipelineResource {
 pub enum Extension {
     F16,
     Subgroups,
+    ClipDistances,
+    DualSourceBlending,
 }
@@ -1,53 +1,75 @@
-use ast::{Module, StorageClass, VarQualifier};
-pub use types::{Extension, PipelineDescription, PipelineResource, ResourceData, ResourceKind};
+use ast::types::DataType;
+use ast::{FnAttr, Module, ShaderStage, StorageClass, VarQualifier};
+pub use types::{
+    Extension, PipelineDescription, PipelineResource, ResourceData, ResourceKind, StorageTexture,
+    TexelFormat, TextureDimension,
+};
+
+/// Returns the names of the compute entry points declared in the module.
+pub fn entry_points(module: &Module) -> impl Iterator<Item = &str> {
+    module
+        .functions()
+        .filter(|it| it.attrs.contains(&FnAttr::Stage(ShaderStage::Compute)))
+        .map(|it| it.name.as_str())
+}
 
 pub fn reflect(
     module: &Module,
+    entry_point: &str,
     mut init: impl FnMut(ResourceData<'_>) -> Option<Vec<u8>>,
 ) -> (PipelineDescription, Vec<common::Type>) {
     let mut resources = vec![];
     let mut types = vec![];
 
-    for var in &module.vars {
-        if let Some(VarQualifier { storage_class, .. }) = &var.qualifier {
-            let kind = match storage_class {
-                StorageClass::Uniform => ResourceKind::UniformBuffer,
-                StorageClass::Storage => ResourceKind::StorageBuffer,
-                _ => continue,
-            };
+    for var in module.vars() {
+        let (kind, type_desc) = match (&var.qualifier, &var.data_type) {
+            (Some(VarQualifier { storage_class, .. }), data_type) => {
+                let kind = match storage_class {
+                    StorageClass::Uniform => ResourceKind::UniformBuffer,
+                    StorageClass::Storage => ResourceKind::StorageBuffer,
+                    _ => continue,
+                };
+
+                let type_desc =
+                    common::Type::try_from(data_type).expect("invalid type for pipeline resource");
 
-            let type_desc =
-                common::Type::try_from(&var.data_type).expect("invalid type for pipeline resource");
+                (kind, type_desc)
+            }
+            (None, DataType::StorageTexture(dim, format, _)) => (
+                ResourceKind::StorageTexture(storage_texture(*dim, *format)),
+                common::Type::texels(*dim, *format),
+            ),
+            _ => continue,
+        };
 
-            let group = var
-                .group_index()
-                .expect("resource variable must have group attribute");
+        let group = var
+            .group_index()
+            .expect("resource variable must have group attribute");
 
-            let binding = var
-                .binding_index()
-                .expect("resource variable must have binding attribute");
+        let binding = var
+            .binding_index()
+            .expect("resource variable must have binding attribute");
 
-            let init = init(ResourceData {
-                name: &var.name,
-                group,
-                binding,
-            })
-            .map(|mut init| {
-                init.resize(type_desc.buffer_size() as usize, 0);
-                init
-            });
+        let init = init(ResourceData {
+            name: &var.name,
+            group,
+            binding,
+        })
+        .map(|mut init| {
+            init.resize(type_desc.buffer_size() as usize, 0);
+            init
+        });
 
-            resources.push(PipelineResource {
-                name: var.name.clone(),
-                kind,
-                group,
-                binding,
-                init,
-                size: type_desc.size().next_multiple_of(4),
-            });
+        resources.push(PipelineResource {
+            name: var.name.clone(),
+            kind,
+            group,
+            binding,
+            init,
+            size: type_desc.size().next_multiple_of(4),
+        });
 
-            types.push(type_desc);
-        }
+        types.push(type_desc);
     }
 
     let extensions = module
@@ -56,14 +78,48 @@ pub fn reflect(
         .map(|e| match e {
             ast::Extension::F16 => Extension::F16,
             ast::Extension::Subgroups => Extension::Subgroups,
+            ast::Extension::ClipDistances => Extension::ClipDistances,
+            ast::Extension::DualSourceBlending => Extension::DualSourceBlending,
         })
         .collect();
 
     (
         PipelineDescription {
+            entry_point: entry_point.to_owned(),
             resources,
             extensions,
         },
         types,
     )
 }
+
+fn storage_texture(
+    dim: ast::types::TextureDimension,
+    format: ast::types::TexelFormat,
+) -> StorageTexture {
+    use ast::types::{TexelFormat as AstTexelFormat, TextureDimension as AstTextureDimension};
+
+    let (width, height, depth) = common::storage_texture_extent(dim);
+
+    StorageTexture {
+        dimension: match dim {
+            AstTextureDimension::D1 => TextureDimension::D1,
+            AstTextureDimension::D2 => TextureDimension::D2,
+            AstTextureDimension::D3 => TextureDimension::D3,
+        },
+        format: match format {
+            AstTexelFormat::Rgba8unorm => TexelFormat::Rgba8Unorm,
+            AstTexelFormat::Rgba8uint => TexelFormat::Rgba8Uint,
+            AstTexelFormat::Rgba8sint => TexelFormat::Rgba8Sint,
+            AstTexelFormat::Rgba32uint => TexelFormat::Rgba32Uint,
+            AstTexelFormat::Rgba32sint => TexelFormat::Rgba32Sint,
+            AstTexelFormat::Rgba32float => TexelFormat::Rgba32Float,
+            AstTexelFormat::R32uint => TexelFormat::R32Uint,
+            AstTexelFormat::R32sint => TexelFormat::R32Sint,
+            AstTexelFormat::R32float => TexelFormat::R32Float,
+        },
+        width,
+        height,
+        depth,
+    }
+}
@@ -0,0 +1,49 @@
+use std::fs::File;
+use std::io::Read;
+
+use clap::Parser;
+
+#[derive(Parser)]
+pub struct Options {
+    /// Path to a wgsl shader program (use '-' for stdin).
+    #[clap(action, default_value = "-")]
+    pub input: String,
+}
+
+pub fn run(options: Options) -> eyre::Result<()> {
+    let input = read_shader_from_path(&options.input)?;
+    let ast = match parser::try_parse(&input) {
+        Ok(ast) => ast,
+        Err(diagnostics) => {
+            for diagnostic in &diagnostics {
+                eprintln!("error: {diagnostic}");
+            }
+
+            eprintln!("found {} errors in shader", diagnostics.len());
+            std::process::exit(1);
+        }
+    };
+
+    if let Err(errors) = crate::check(&ast) {
+        for error in &errors {
+            eprintln!("error: {error}");
+        }
+
+        eprintln!("found {} type errors", errors.len());
+        std::process::exit(1);
+    }
+
+    Ok(())
+}
+
+fn read_shader_from_path(path: &str) -> eyre::Result<String> {
+    let mut input: Box<dyn Read> = match path {
+        "-" => Box::new(std::io::stdin()),
+        path => Box::new(File::open(path)?),
+    };
+
+    let mut shader = String::new();
+    input.read_to_string(&mut shader)?;
+
+    Ok(shader)
+}
@@ -0,0 +1,184 @@
+use std::fmt::Display;
+
+use ast::types::{DataType, MemoryViewType};
+use ast::{BinOp, NodeMeta, UnOp};
+
+/// An error found while type checking a module.
+#[derive(Clone, Debug)]
+pub struct TypeError {
+    pub kind: TypeErrorKind,
+    /// Name of the function in which the error was found, or `None` for module scope declarations.
+    pub function: Option<String>,
+    /// Metadata of the closest expression or statement to the error.
+    pub meta: NodeMeta,
+}
+
+#[derive(Clone, Debug)]
+pub enum TypeErrorKind {
+    UndeclaredVar(String),
+    UndeclaredFn(String),
+    Redeclaration(String),
+    RecursiveCall(String),
+    /// The type stored in an expression node doesn't match the type derived from its operands.
+    AnnotationMismatch {
+        annotated: DataType,
+        derived: DataType,
+    },
+    Mismatch {
+        context: String,
+        expected: DataType,
+        found: DataType,
+    },
+    InvalidUnOp {
+        op: UnOp,
+        operand: DataType,
+    },
+    InvalidBinOp {
+        op: BinOp,
+        left: DataType,
+        right: DataType,
+    },
+    InvalidIndex {
+        base: DataType,
+        index: DataType,
+    },
+    InvalidMember {
+        base: DataType,
+        member: String,
+    },
+    InvalidConstructor {
+        data_type: DataType,
+        args: Vec<DataType>,
+    },
+    ArgCount {
+        function: String,
+        expected: usize,
+        found: usize,
+    },
+    NoMatchingOverload {
+        function: String,
+        args: Vec<DataType>,
+    },
+    NoReturnValue(String),
+    UnusedResult(String),
+    NotAssignable(DataType),
+    NotWritable(MemoryViewType),
+    InvalidIncrement(DataType),
+    NotStorable(DataType),
+    AddressOfVectorComponent,
+    MissingReturnValue(DataType),
+    UnexpectedReturnValue(DataType),
+    InvalidSwitchSelector(DataType),
+}
+
+impl Display for TypeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (self.meta.span, self.meta.id) {
+            (Some(span), _) => write!(f, "{span}: ")?,
+            (None, Some(id)) => write!(f, "{id}: ")?,
+            (None, None) => {}
+        }
+
+        if let Some(function) = &self.function {
+            write!(f, "in `{function}`: ")?;
+        }
+
+        write!(f, "{}", self.kind)
+    }
+}
+
+impl Display for TypeErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TypeErrorKind::UndeclaredVar(name) => write!(f, "use of undeclared variable `{name}`"),
+            TypeErrorKind::UndeclaredFn(name) => write!(f, "call to undeclared function `{name}`"),
+            TypeErrorKind::Redeclaration(name) => write!(f, "redeclaration of `{name}`"),
+            TypeErrorKind::RecursiveCall(name) => write!(f, "recursive call to `{name}`"),
+            TypeErrorKind::AnnotationMismatch { annotated, derived } => write!(
+                f,
+                "expression is annotated with type `{annotated}` but has type `{derived}`"
+            ),
+            TypeErrorKind::Mismatch {
+                context,
+                expected,
+                found,
+            } => write!(f, "expected `{expected}` for {context}, found `{found}`"),
+            TypeErrorKind::InvalidUnOp { op, operand } => {
+                write!(f, "operator `{op}` cannot be applied to `{operand}`")
+            }
+            TypeErrorKind::InvalidBinOp { op, left, right } => {
+                write!(
+                    f,
+                    "operator `{op}` cannot be applied to `{left}` and `{right}`"
+                )
+            }
+            TypeErrorKind::InvalidIndex { base, index } => {
+                write!(f, "cannot index into `{base}` with `{index}`")
+            }
+            TypeErrorKind::InvalidMember { base, member } => {
+                write!(f, "`{base}` has no member `{member}`")
+            }
+            TypeErrorKind::InvalidConstructor { data_type, args } => {
+                write!(f, "invalid constructor `{data_type}(")?;
+                write_types(f, args)?;
+                write!(f, ")`")
+            }
+            TypeErrorKind::ArgCount {
+                function,
+                expected,
+                found,
+            } => write!(
+                f,
+                "`{function}` expects {expected} arguments but {found} were given"
+            ),
+            TypeErrorKind::NoMatchingOverload { function, args } => {
+                write!(f, "no overload of `{function}` accepts `(")?;
+                write_types(f, args)?;
+                write!(f, ")`")
+            }
+            TypeErrorKind::NoReturnValue(name) => {
+                write!(f, "`{name}` does not return a value")
+            }
+            TypeErrorKind::UnusedResult(name) => {
+                write!(f, "result of `{name}` must be used")
+            }
+            TypeErrorKind::NotAssignable(ty) => {
+                write!(f, "cannot assign to expression of type `{ty}`")
+            }
+            TypeErrorKind::NotWritable(view) => {
+                write!(f, "cannot write through a reference to `{view}`")
+            }
+            TypeErrorKind::InvalidIncrement(ty) => {
+                write!(f, "cannot increment or decrement `{ty}`")
+            }
+            TypeErrorKind::NotStorable(ty) => {
+                write!(f, "variables cannot have type `{ty}`")
+            }
+            TypeErrorKind::AddressOfVectorComponent => {
+                write!(f, "cannot take the address of a vector component")
+            }
+            TypeErrorKind::MissingReturnValue(ty) => {
+                write!(f, "missing return value of type `{ty}`")
+            }
+            TypeErrorKind::UnexpectedReturnValue(ty) => {
+                write!(
+                    f,
+                    "function without return type returns a value of type `{ty}`"
+                )
+            }
+            TypeErrorKind::InvalidSwitchSelector(ty) => {
+                write!(f, "switch selector must be `i32` or `u32`, found `{ty}`")
+            }
+        }
+    }
+}
+
+fn write_types(f: &mut std::fmt::Formatter<'_>, types: &[DataType]) -> std::fmt::Result {
+    for (i, ty) in types.iter().enumerate() {
+        if i > 0 {
+            f.write_str(", ")?;
+        }
+        write!(f, "{ty}")?;
+    }
+    Ok(())
+}
@@ -0,0 +1,443 @@
+use ast::types::DataType;
+use ast::{
+    convert_abstract_type, BinOp, Expr, ExprNode, LhsExpr, LhsExprNode, NodeMeta, Postfix,
+    PostfixExpr, ScalarType, UnOp,
+};
+
+use crate::{Checker, TypeErrorKind};
+
+impl Checker<'_> {
+    /// Derives the type of an expression, checking that it agrees with the type the node is
+    /// annotated with.
+    ///
+    /// Returns `None` if the expression is ill-typed, in which case an error has been reported.
+    pub(crate) fn expr(&mut self, node: &ExprNode) -> Option<DataType> {
+        let parent_meta = self.enter(node.meta);
+        let ty = self.expr_inner(node);
+
+        if let Some(ty) = &ty {
+            self.check_annotation(&node.data_type, ty);
+        }
+
+        self.meta = parent_meta;
+        ty
+    }
+
+    /// Derives the type of the left-hand side of an assignment.
+    pub(crate) fn lhs_expr(&mut self, node: &LhsExprNode) -> Option<DataType> {
+        let parent_meta = self.enter(node.meta);
+
+        let ty = match &node.expr {
+            LhsExpr::Ident(ident) => self.var(ident),
+            LhsExpr::Postfix(inner, postfix) => {
+                let inner = self.lhs_expr(inner);
+                let index = match postfix {
+                    Postfix::Index(index) => self.expr(index),
+                    Postfix::Member(_) => None,
+                };
+                self.postfix(&inner?, postfix, index)
+            }
+            LhsExpr::Deref(inner) => {
+                let inner = self.lhs_expr(inner)?;
+                self.unop(UnOp::Deref, &inner)
+            }
+            LhsExpr::AddressOf(inner) => {
+                let inner = self.lhs_expr(inner)?;
+                self.unop(UnOp::AddressOf, &inner)
+            }
+        };
+
+        if let Some(ty) = &ty {
+            self.check_annotation(&node.data_type, ty);
+        }
+
+        self.meta = parent_meta;
+        ty
+    }
+
+    /// Makes `meta` the location for reported errors if it has any information, returning the
+    /// previous location.
+    fn enter(&mut self, meta: NodeMeta) -> NodeMeta {
+        let parent_meta = self.meta;
+        if meta.id.is_some() || meta.span.is_some() {
+            self.meta = meta;
+        }
+        parent_meta
+    }
+
+    fn check_annotation(&mut self, annotated: &DataType, derived: &DataType) {
+        // Whether a variable is annotated as a reference or as its store type is not consistent
+        // between the parser and the generator, so only the store types are compared
+        if annotated.dereference() != derived.dereference() {
+            self.error(TypeErrorKind::AnnotationMismatch {
+                annotated: annotated.clone(),
+                derived: derived.clone(),
+            });
+        }
+    }
+
+    fn expr_inner(&mut self, node: &ExprNode) -> Option<DataType> {
+        match &node.expr {
+            Expr::Lit(lit) => Some(lit.data_type()),
+            Expr::TypeCons(expr) => {
+                let args: Vec<_> = expr.args.iter().map(|arg| self.expr(arg)).collect();
+                let args = args.into_iter().collect::<Option<Vec<_>>>()?;
+                self.check_constructor(&expr.data_type, &expr.args, &args);
+                Some(expr.data_type.clone())
+            }
+            Expr::Var(expr) => self.var(&expr.ident),
+            Expr::Postfix(PostfixExpr { inner, postfix }) => {
+                let inner = self.expr(inner);
+                let index = match postfix {
+                    Postfix::Index(index) => self.expr(index),
+                    Postfix::Member(_) => None,
+                };
+                self.postfix(&inner?, postfix, index)
+            }
+            Expr::UnOp(expr) => {
+                let inner = self.expr(&expr.inner)?;
+
+                if expr.op == UnOp::AddressOf {
+                    if let Expr::Postfix(PostfixExpr { inner, .. }) = &expr.inner.expr {
+                        let base = match &inner.data_type {
+                            DataType::Ptr(view) => view.inner.as_ref(),
+                            ty => ty.dereference(),
+                        };
+
+                        if base.is_vector() {
+                            self.error(TypeErrorKind::AddressOfVectorComponent);
+                            return None;
+                        }
+                    }
+                }
+
+                self.unop(expr.op, &inner)
+            }
+            Expr::BinOp(expr) => {
+                let left = self.expr(&expr.left);
+                let right = self.expr(&expr.right);
+                let (left, right) = (left?, right?);
+                let (left, right) = expr
+                    .op
+                    .operand_types(&expr.left, &left, &expr.right, &right);
+
+                let ty = binop_type(expr.op, left.dereference(), right.dereference());
+                if ty.is_none() {
+                    self.error(TypeErrorKind::InvalidBinOp {
+                        op: expr.op,
+                        left: left.dereference().clone(),
+                        right: right.dereference().clone(),
+                    });
+                }
+
+                ty
+            }
+            Expr::FnCall(expr) => self.call(&expr.ident, &expr.args, true),
+        }
+    }
+
+    fn var(&mut self, ident: &str) -> Option<DataType> {
+        let ty = self.lookup(ident).cloned();
+        if ty.is_none() {
+            self.error(TypeErrorKind::UndeclaredVar(ident.to_owned()));
+        }
+        ty
+    }
+
+    fn postfix(
+        &mut self,
+        base: &DataType,
+        postfix: &Postfix,
+        index: Option<DataType>,
+    ) -> Option<DataType> {
+        let (view, inner) = match base {
+            DataType::Ref(view) | DataType::Ptr(view) => (Some(view), view.inner.as_ref()),
+            ty => (None, ty),
+        };
+
+        let (ty, is_reference) = match postfix {
+            Postfix::Index(_) => {
+                let index = index?;
+                let element_type = element_type(inner);
+
+                match (element_type, index.dereference()) {
+                    (Some(ty), DataType::Scalar(ScalarType::I32 | ScalarType::U32)) => (ty, true),
+                    (_, index) => {
+                        self.error(TypeErrorKind::InvalidIndex {
+                            base: base.clone(),
+                            index: index.clone(),
+                        });
+                        return None;
+                    }
+                }
+            }
+            Postfix::Member(member) => match member_type(inner, member) {
+                // A swizzle selecting multiple components produces a value rather than a reference
+                Some(ty) => (ty, !(inner.is_vector() && member.len() > 1)),
+                None => {
+                    self.error(TypeErrorKind::InvalidMember {
+                        base: base.clone(),
+                        member: member.clone(),
+                    });
+                    return None;
+                }
+            },
+        };
+
+        match view {
+            Some(view) if is_reference => Some(DataType::Ref(view.clone_with_type(ty))),
+            _ => Some(ty),
+        }
+    }
+
+    fn unop(&mut self, op: UnOp, operand: &DataType) -> Option<DataType> {
+        let ty = unop_type(op, operand);
+        if ty.is_none() {
+            self.error(TypeErrorKind::InvalidUnOp {
+                op,
+                operand: operand.clone(),
+            });
+        }
+        ty
+    }
+
+    /// Checks that a value of type `ty` can be constructed from `args`, which have the types
+    /// `arg_types`.
+    pub(crate) fn check_constructor(
+        &mut self,
+        ty: &DataType,
+        args: &[ExprNode],
+        arg_types: &[DataType],
+    ) {
+        let scalar = |ty: &DataType| match ty {
+            DataType::Scalar(t) => Some(*t),
+            _ => None,
+        };
+
+        // Abstract arguments are converted to the component type of the constructed type
+        let args: Vec<_> = args
+            .iter()
+            .zip(arg_types)
+            .enumerate()
+            .map(|(i, (arg, arg_type))| {
+                let component = match ty {
+                    DataType::Scalar(t) | DataType::Vector(_, t) | DataType::Matrix(_, _, t) => {
+                        Some(*t)
+                    }
+                    DataType::Array(element, _) => scalar(element),
+                    DataType::Struct(decl) => {
+                        decl.members.get(i).and_then(|it| scalar(&it.data_type))
+                    }
+                    _ => None,
+                };
+
+                component
+                    .filter(|_| arg.is_abstract())
+                    .and_then(|t| convert_abstract_type(arg_type, t))
+                    .unwrap_or_else(|| arg_type.dereference().clone())
+            })
+            .collect();
+
+        let valid = args.is_empty()
+            || match ty {
+                DataType::Scalar(_) => matches!(args.as_slice(), [DataType::Scalar(_)]),
+                DataType::Vector(n, t) => match args.as_slice() {
+                    // Splat or conversion
+                    [DataType::Scalar(s)] => s == t,
+                    [DataType::Vector(m, _)] => m == n,
+                    args => {
+                        let mut count = 0;
+                        args.iter().all(|arg| match arg {
+                            DataType::Scalar(s) if s == t => {
+                                count += 1;
+                                true
+                            }
+                            DataType::Vector(m, s) if s == t => {
+                                count += *m;
+                                true
+                            }
+                            _ => false,
+                        }) && count == *n
+                    }
+                },
+                DataType::Matrix(c, r, t) => match args.as_slice() {
+                    [DataType::Matrix(c2, r2, _)] => c == c2 && r == r2,
+                    args if args.len() == *c as usize => {
+                        args.iter().all(|arg| *arg == DataType::Vector(*r, *t))
+                    }
+                    args if args.len() == (*c * *r) as usize => {
+                        args.iter().all(|arg| *arg == DataType::Scalar(*t))
+                    }
+                    _ => false,
+                },
+                DataType::Array(element, Some(n)) => {
+                    args.len() == *n as usize && args.iter().all(|arg| arg == element.as_ref())
+                }
+                DataType::Struct(decl) => {
+                    args.len() == decl.members.len()
+                        && args
+                            .iter()
+                            .zip(&decl.members)
+                            .all(|(arg, member)| *arg == member.data_type)
+                }
+                _ => false,
+            };
+
+        if !valid {
+            self.error(TypeErrorKind::InvalidConstructor {
+                data_type: ty.clone(),
+                args,
+            });
+        }
+    }
+}
+
+fn element_type(ty: &DataType) -> Option<DataType> {
+    match ty {
+        DataType::Vector(_, t) => Some(DataType::Scalar(*t)),
+        DataType::Matrix(_, r, t) => Some(DataType::Vector(*r, *t)),
+        DataType::Array(t, _) => Some(t.as_ref().clone()),
+        _ => None,
+    }
+}
+
+fn member_type(ty: &DataType, member: &str) -> Option<DataType> {
+    match ty {
+        DataType::Struct(decl) => decl.member_type(member).cloned(),
+        DataType::Vector(n, t) => {
+            let is_swizzle = (1..=4).contains(&member.len())
+                && ["xyzw", "rgba"]
+                    .iter()
+                    .any(|set| member.chars().all(|c| set[..*n as usize].contains(c)));
+
+            if !is_swizzle {
+                None
+            } else if member.len() == 1 {
+                Some(DataType::Scalar(*t))
+            } else {
+                Some(DataType::Vector(member.len() as u8, *t))
+            }
+        }
+        DataType::AtomicCompareExchangeResult(t) => match member {
+            "old_value" => Some(DataType::Scalar(*t)),
+            "exchanged" => Some(DataType::Scalar(ScalarType::Bool)),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Returns the scalar type and component count (or `None` for scalars) of a scalar or vector type.
+fn components(ty: &DataType) -> Option<(ScalarType, Option<u8>)> {
+    match ty {
+        DataType::Scalar(t) => Some((*t, None)),
+        DataType::Vector(n, t) => Some((*t, Some(*n))),
+        _ => None,
+    }
+}
+
+fn is_numeric(t: ScalarType) -> bool {
+    !matches!(t, ScalarType::Bool)
+}
+
+fn is_integer(t: ScalarType) -> bool {
+    matches!(t, ScalarType::I32 | ScalarType::U32)
+}
+
+fn is_float(t: ScalarType) -> bool {
+    matches!(t, ScalarType::F32 | ScalarType::F16)
+}
+
+fn unop_type(op: UnOp, operand: &DataType) -> Option<DataType> {
+    match op {
+        UnOp::AddressOf => match operand {
+            DataType::Ref(view) => Some(DataType::Ptr(view.clone())),
+            _ => None,
+        },
+        UnOp::Deref => match operand {
+            DataType::Ptr(view) => Some(DataType::Ref(view.clone())),
+            _ => None,
+        },
+        op => {
+            let operand = operand.dereference();
+            let (t, _) = components(operand)?;
+            let valid = match op {
+                UnOp::Neg => matches!(t, ScalarType::I32) || is_float(t),
+                UnOp::Not => t == ScalarType::Bool,
+                UnOp::BitNot => is_integer(t),
+                UnOp::AddressOf | UnOp::Deref => unreachable!(),
+            };
+            valid.then(|| operand.clone())
+        }
+    }
+}
+
+/// Determines the result type of a binary operator, or `None` if the operand types are invalid.
+///
+/// The operands should already be dereferenced.
+pub(crate) fn binop_type(op: BinOp, left: &DataType, right: &DataType) -> Option<DataType> {
+    use DataType::{Matrix, Scalar, Vector};
+
+    match op {
+        BinOp::Plus | BinOp::Minus | BinOp::Times | BinOp::Divide | BinOp::Mod => {
+            match (left, right) {
+                (Matrix(..), Matrix(..)) if matches!(op, BinOp::Plus | BinOp::Minus) => {
+                    (left == right).then(|| left.clone())
+                }
+                (Matrix(c1, r1, t1), Matrix(c2, r2, t2)) if op == BinOp::Times => {
+                    (t1 == t2 && c1 == r2).then_some(Matrix(*c2, *r1, *t1))
+                }
+                (Matrix(c, r, t1), Vector(n, t2)) if op == BinOp::Times => {
+                    (t1 == t2 && c == n).then_some(Vector(*r, *t1))
+                }
+                (Vector(n, t1), Matrix(c, r, t2)) if op == BinOp::Times => {
+                    (t1 == t2 && r == n).then_some(Vector(*c, *t1))
+                }
+                (Matrix(_, _, t1), Scalar(t2)) | (Scalar(t2), Matrix(_, _, t1))
+                    if op == BinOp::Times =>
+                {
+                    let matrix = if left.is_matrix() { left } else { right };
+                    (t1 == t2).then(|| matrix.clone())
+                }
+                _ => {
+                    let (lt, ln) = components(left)?;
+                    let (rt, rn) = components(right)?;
+
+                    if lt != rt || !is_numeric(lt) {
+                        return None;
+                    }
+
+                    // A scalar operand may be combined with a vector operand
+                    match (ln, rn) {
+                        (ln, rn) if ln == rn => Some(left.clone()),
+                        (Some(_), None) => Some(left.clone()),
+                        (None, Some(_)) => Some(right.clone()),
+                        _ => None,
+                    }
+                }
+            }
+        }
+        BinOp::LogAnd | BinOp::LogOr => {
+            let bool = Scalar(ScalarType::Bool);
+            (*left == bool && *right == bool).then_some(bool)
+        }
+        BinOp::BitAnd | BinOp::BitOr | BinOp::BitXOr => {
+            let (t, _) = components(left)?;
+            let valid = is_integer(t) || (t == ScalarType::Bool && op != BinOp::BitXOr);
+            (valid && left == right).then(|| left.clone())
+        }
+        BinOp::LShift | BinOp::RShift => {
+            let (lt, ln) = components(left)?;
+            let (rt, rn) = components(right)?;
+            (is_integer(lt) && rt == ScalarType::U32 && ln == rn).then(|| left.clone())
+        }
+        BinOp::Equal | BinOp::NotEqual => {
+            components(left)?;
+            (left == right).then(|| left.map(ScalarType::Bool))
+        }
+        BinOp::Less | BinOp::LessEqual | BinOp::Greater | BinOp::GreaterEqual => {
+            let (t, _) = components(left)?;
+            (is_numeric(t) && left == right).then(|| left.map(ScalarType::Bool))
+        }
+    }
+}
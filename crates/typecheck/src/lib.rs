@@ -0,0 +1,858 @@
+//! An independent type checker for [`ast::Module`].
+//!
+//! The checker re-derives the type of every expression from its operands rather than trusting
+//! [`ExprNode::data_type`], so it can catch mistakes made by the generator or by transformation
+//! passes before a shader is handed to a compiler.
+
+pub mod cli;
+
+mod error;
+mod expr;
+
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use ast::types::{DataType, MemoryViewType};
+use ast::{
+    AccessMode, AssignmentLhs, AssignmentOp, AssignmentStatement, BinOp, BuiltinFn, Else, ExprNode,
+    FnDecl, ForLoopInit, ForLoopUpdate, LetDeclStatement, Module, NodeMeta, ScalarType, Statement,
+    StorageClass, StructDecl, VarDeclStatement,
+};
+
+pub use error::{TypeError, TypeErrorKind};
+
+/// Type checks a module, returning all errors that were found.
+pub fn check(module: &Module) -> Result<(), Vec<TypeError>> {
+    let mut checker = Checker::new(module);
+    checker.check_module();
+
+    if checker.errors.is_empty() {
+        Ok(())
+    } else {
+        Err(checker.errors)
+    }
+}
+
+struct Checker<'a> {
+    module: &'a Module,
+    fns: HashMap<&'a str, &'a FnDecl>,
+    structs: HashMap<&'a str, &'a Rc<StructDecl>>,
+    scopes: Vec<HashMap<&'a str, DataType>>,
+    function: Option<&'a FnDecl>,
+    meta: NodeMeta,
+    errors: Vec<TypeError>,
+}
+
+impl<'a> Checker<'a> {
+    fn new(module: &'a Module) -> Self {
+        Checker {
+            module,
+            fns: HashMap::new(),
+            structs: HashMap::new(),
+            scopes: vec![],
+            function: None,
+            meta: NodeMeta::default(),
+            errors: vec![],
+        }
+    }
+
+    fn error(&mut self, kind: TypeErrorKind) {
+        self.errors.push(TypeError {
+            kind,
+            function: self.function.map(|it| it.name.clone()),
+            meta: self.meta,
+        });
+    }
+
+    fn declare(&mut self, name: &'a str, ty: DataType) {
+        let scope = self.scopes.last_mut().unwrap();
+        if scope.insert(name, ty).is_some() {
+            self.error(TypeErrorKind::Redeclaration(name.to_owned()));
+        }
+    }
+
+    fn lookup(&self, name: &str) -> Option<&DataType> {
+        self.scopes.iter().rev().find_map(|scope| scope.get(name))
+    }
+
+    fn with_scope(&mut self, f: impl FnOnce(&mut Self)) {
+        self.scopes.push(HashMap::new());
+        f(self);
+        self.scopes.pop();
+    }
+
+    fn check_module(&mut self) {
+        let module = self.module;
+
+        // Declarations at module scope may be used before they are declared, so collect them all
+        // up front
+        self.scopes.push(HashMap::new());
+
+        for decl in module.structs() {
+            if self.structs.insert(&decl.name, decl).is_some() {
+                self.error(TypeErrorKind::Redeclaration(decl.name.clone()));
+            }
+        }
+
+        for decl in module.consts() {
+            self.declare(&decl.name, decl.data_type.clone());
+        }
+
+        for decl in module.vars() {
+            let ty = match &decl.qualifier {
+                Some(qualifier) => {
+                    let mut view =
+                        MemoryViewType::new(decl.data_type.clone(), qualifier.storage_class);
+                    if let Some(access_mode) = qualifier.access_mode {
+                        view.access_mode = access_mode;
+                    }
+                    DataType::Ref(view)
+                }
+                // Textures live in the handle address space and are used by value
+                None => decl.data_type.clone(),
+            };
+
+            self.declare(&decl.name, ty);
+        }
+
+        for decl in module.functions() {
+            if self.fns.insert(&decl.name, decl).is_some() {
+                self.error(TypeErrorKind::Redeclaration(decl.name.clone()));
+            }
+        }
+
+        for decl in module.consts() {
+            self.check_initializer(&decl.name, &decl.data_type, &decl.initializer);
+        }
+
+        for decl in module.vars() {
+            if matches!(decl.data_type, DataType::Ptr(_) | DataType::Ref(_)) {
+                self.error(TypeErrorKind::NotStorable(decl.data_type.clone()));
+            }
+
+            if let Some(initializer) = &decl.initializer {
+                self.check_initializer(&decl.name, &decl.data_type, initializer);
+            }
+        }
+
+        for decl in module.functions() {
+            self.check_fn(decl);
+        }
+    }
+
+    fn check_initializer(&mut self, name: &str, expected: &DataType, initializer: &ExprNode) {
+        if let Some(ty) = self.expr(initializer) {
+            self.expect_value(expected, initializer, &ty, || {
+                format!("initializer of `{name}`")
+            });
+        }
+    }
+
+    /// Reports an error if the (dereferenced) type `found` is not `expected`.
+    fn expect(&mut self, expected: &DataType, found: &DataType, context: impl FnOnce() -> String) {
+        let found = found.dereference();
+        if expected != found {
+            self.error(TypeErrorKind::Mismatch {
+                context: context(),
+                expected: expected.clone(),
+                found: found.clone(),
+            });
+        }
+    }
+
+    /// Like [`Checker::expect`], but an abstract expression `node` is also accepted if it converts
+    /// to `expected`.
+    fn expect_value(
+        &mut self,
+        expected: &DataType,
+        node: &ExprNode,
+        found: &DataType,
+        context: impl FnOnce() -> String,
+    ) {
+        let converted = match expected {
+            DataType::Scalar(t) if node.is_abstract() => ast::convert_abstract_type(found, *t),
+            _ => None,
+        };
+
+        if converted.as_ref() != Some(expected) {
+            self.expect(expected, found, context);
+        }
+    }
+
+    fn check_fn(&mut self, decl: &'a FnDecl) {
+        self.function = Some(decl);
+        self.meta = NodeMeta::default();
+
+        // Parameters share a scope with the function body
+        self.with_scope(|this| {
+            for param in &decl.inputs {
+                this.declare(&param.name, param.data_type.clone());
+            }
+
+            for stmt in &decl.body {
+                this.check_stmt(stmt);
+            }
+        });
+
+        self.function = None;
+    }
+
+    fn check_block(&mut self, block: &'a [Statement]) {
+        self.with_scope(|this| {
+            for stmt in block {
+                this.check_stmt(stmt);
+            }
+        });
+    }
+
+    fn check_stmt(&mut self, stmt: &'a Statement) {
+        if let Some(meta) = stmt.meta() {
+            self.meta = *meta;
+        }
+
+        match stmt {
+            Statement::LetDecl(stmt) => self.check_let(stmt),
+            Statement::VarDecl(stmt) => self.check_var(stmt),
+            Statement::Assignment(stmt) => self.check_assignment(stmt),
+            Statement::Increment(stmt) => self.check_inc_dec(&stmt.lhs),
+            Statement::Decrement(stmt) => self.check_inc_dec(&stmt.lhs),
+            Statement::Compound(stmt) => self.check_block(&stmt.body),
+            Statement::If(stmt) => {
+                let mut stmt = stmt;
+                loop {
+                    self.check_condition(&stmt.condition);
+                    self.check_block(&stmt.body);
+                    match stmt.else_.as_deref() {
+                        Some(Else::If(else_if)) => stmt = else_if,
+                        Some(Else::Else(block)) => break self.check_block(block),
+                        None => break,
+                    }
+                }
+            }
+            Statement::Return(stmt) => {
+                let value = stmt.value.as_ref().and_then(|it| self.expr(it));
+                let output = self
+                    .function
+                    .and_then(|it| it.output.as_ref())
+                    .map(|it| &it.data_type);
+
+                match (output, value) {
+                    (Some(output), Some(value)) => {
+                        let node = stmt.value.as_ref().unwrap();
+                        self.expect_value(output, node, &value, || "return value".to_owned())
+                    }
+                    (Some(output), None) if stmt.value.is_none() => {
+                        self.error(TypeErrorKind::MissingReturnValue(output.clone()))
+                    }
+                    (None, Some(value)) => self.error(TypeErrorKind::UnexpectedReturnValue(
+                        value.dereference().clone(),
+                    )),
+                    _ => {}
+                }
+            }
+            Statement::Loop(stmt) => self.with_scope(|this| {
+                for stmt in &stmt.body {
+                    this.check_stmt(stmt);
+                }
+
+                // The continuing block can see declarations from the loop body
+                if let Some(continuing) = &stmt.continuing {
+                    this.with_scope(|this| {
+                        for stmt in &continuing.stmts {
+                            this.check_stmt(stmt);
+                        }
+
+                        if let Some(break_if) = &continuing.break_if {
+                            this.check_condition(break_if);
+                        }
+                    });
+                }
+            }),
+            Statement::While(stmt) => {
+                self.check_condition(&stmt.condition);
+                self.check_block(&stmt.body);
+            }
+            Statement::Switch(stmt) => {
+                let selector = self.expr(&stmt.selector).map(|it| it.dereference().clone());
+
+                if let Some(selector) = &selector {
+                    if !matches!(
+                        selector,
+                        DataType::Scalar(ScalarType::I32 | ScalarType::U32)
+                    ) {
+                        self.error(TypeErrorKind::InvalidSwitchSelector(selector.clone()));
+                    }
+                }
+
+                for case in &stmt.cases {
+                    if let (Some(selector), Some(ty)) = (&selector, self.expr(&case.selector)) {
+                        self.expect_value(selector, &case.selector, &ty, || {
+                            "case selector".to_owned()
+                        });
+                    }
+
+                    self.check_block(&case.body);
+                }
+
+                self.check_block(&stmt.default);
+            }
+            Statement::ForLoop(stmt) => self.with_scope(|this| {
+                if let Some(init) = &stmt.header.init {
+                    match init {
+                        ForLoopInit::VarDecl(init) => this.check_var(init),
+                        ForLoopInit::LetDecl(init) => this.check_let(init),
+                        ForLoopInit::Assignment(init) => this.check_assignment(init),
+                        ForLoopInit::Increment(init) => this.check_inc_dec(&init.lhs),
+                        ForLoopInit::Decrement(init) => this.check_inc_dec(&init.lhs),
+                        ForLoopInit::Call(init) => {
+                            this.call(&init.ident, &init.args, false);
+                        }
+                    }
+                }
+
+                if let Some(condition) = &stmt.header.condition {
+                    this.check_condition(condition);
+                }
+
+                if let Some(update) = &stmt.header.update {
+                    match update {
+                        ForLoopUpdate::Assignment(update) => this.check_assignment(update),
+                        ForLoopUpdate::Increment(update) => this.check_inc_dec(&update.lhs),
+                        ForLoopUpdate::Decrement(update) => this.check_inc_dec(&update.lhs),
+                        ForLoopUpdate::Call(update) => {
+                            this.call(&update.ident, &update.args, false);
+                        }
+                    }
+                }
+
+                this.check_block(&stmt.body);
+            }),
+            Statement::FnCall(stmt) => {
+                self.call(&stmt.ident, &stmt.args, false);
+            }
+            Statement::Break | Statement::Continue | Statement::Fallthrough => {}
+        }
+    }
+
+    fn check_let(&mut self, stmt: &'a LetDeclStatement) {
+        self.meta = stmt.meta;
+
+        let init_ty = self.expr(&stmt.initializer);
+
+        let ty = match (&stmt.data_type, init_ty) {
+            (Some(ty), Some(init_ty)) => {
+                self.expect_value(ty, &stmt.initializer, &init_ty, || {
+                    format!("initializer of `{}`", stmt.ident)
+                });
+                ty.clone()
+            }
+            (Some(ty), None) => ty.clone(),
+            (None, Some(init_ty)) => init_ty.dereference().clone(),
+            (None, None) => return,
+        };
+
+        self.declare(&stmt.ident, ty);
+    }
+
+    fn check_var(&mut self, stmt: &'a VarDeclStatement) {
+        self.meta = stmt.meta;
+
+        let init_ty = stmt.initializer.as_ref().and_then(|it| self.expr(it));
+
+        let ty = match (&stmt.data_type, &stmt.initializer, init_ty) {
+            (Some(ty), Some(initializer), Some(init_ty)) => {
+                self.expect_value(ty, initializer, &init_ty, || {
+                    format!("initializer of `{}`", stmt.ident)
+                });
+                ty.clone()
+            }
+            (Some(ty), _, _) => ty.clone(),
+            (None, _, Some(init_ty)) => init_ty.dereference().clone(),
+            (None, _, None) => return,
+        };
+
+        if matches!(
+            ty,
+            DataType::Ptr(_)
+                | DataType::Ref(_)
+                | DataType::StorageTexture(..)
+                | DataType::Texture(_)
+                | DataType::Sampler(_)
+        ) {
+            self.error(TypeErrorKind::NotStorable(ty.clone()));
+        }
+
+        let view = MemoryViewType::new(ty, StorageClass::Function);
+        self.declare(&stmt.ident, DataType::Ref(view));
+    }
+
+    fn check_condition(&mut self, condition: &ExprNode) {
+        if let Some(ty) = self.expr(condition) {
+            self.expect(&DataType::Scalar(ScalarType::Bool), &ty, || {
+                "condition".to_owned()
+            });
+        }
+    }
+
+    fn check_assignment(&mut self, stmt: &AssignmentStatement) {
+        self.meta = stmt.meta;
+
+        let lhs = match &stmt.lhs {
+            AssignmentLhs::Phony => None,
+            AssignmentLhs::Expr(lhs) => self.lhs_expr(lhs),
+        };
+
+        let rhs = self.expr(&stmt.rhs);
+
+        let AssignmentLhs::Expr(_) = &stmt.lhs else {
+            return;
+        };
+
+        let (Some(lhs), Some(rhs)) = (lhs, rhs) else {
+            return;
+        };
+
+        let Some(store_type) = self.writable_store_type(&lhs) else {
+            return;
+        };
+
+        let op = match stmt.op {
+            AssignmentOp::Simple => {
+                return self.expect_value(&store_type, &stmt.rhs, &rhs, || {
+                    "right-hand side of assignment".to_owned()
+                })
+            }
+            AssignmentOp::Plus => BinOp::Plus,
+            AssignmentOp::Minus => BinOp::Minus,
+            AssignmentOp::Times => BinOp::Times,
+            AssignmentOp::Divide => BinOp::Divide,
+            AssignmentOp::Mod => BinOp::Mod,
+            AssignmentOp::And => BinOp::BitAnd,
+            AssignmentOp::Or => BinOp::BitOr,
+            AssignmentOp::Xor => BinOp::BitXOr,
+        };
+
+        // An abstract right-hand side is converted to the scalar type of the left-hand side
+        let rhs = store_type
+            .as_scalar()
+            .filter(|_| stmt.rhs.is_abstract())
+            .and_then(|t| ast::convert_abstract_type(&rhs, t))
+            .unwrap_or_else(|| rhs.dereference().clone());
+
+        if expr::binop_type(op, &store_type, &rhs).as_ref() != Some(&store_type) {
+            self.error(TypeErrorKind::InvalidBinOp {
+                op,
+                left: store_type,
+                right: rhs,
+            });
+        }
+    }
+
+    fn check_inc_dec(&mut self, lhs: &AssignmentLhs) {
+        let AssignmentLhs::Expr(lhs) = lhs else {
+            return;
+        };
+
+        let Some(lhs) = self.lhs_expr(lhs) else {
+            return;
+        };
+
+        if let Some(store_type) = self.writable_store_type(&lhs) {
+            if !matches!(
+                store_type,
+                DataType::Scalar(ScalarType::I32 | ScalarType::U32)
+            ) {
+                self.error(TypeErrorKind::InvalidIncrement(store_type));
+            }
+        }
+    }
+
+    /// Returns the store type of a reference that is the target of an assignment, or reports an
+    /// error if it cannot be written to.
+    fn writable_store_type(&mut self, lhs: &DataType) -> Option<DataType> {
+        let DataType::Ref(view) = lhs else {
+            self.error(TypeErrorKind::NotAssignable(lhs.clone()));
+            return None;
+        };
+
+        if view.access_mode == AccessMode::Read {
+            self.error(TypeErrorKind::NotWritable(view.clone()));
+            return None;
+        }
+
+        if let DataType::Atomic(_) = view.inner.as_ref() {
+            self.error(TypeErrorKind::NotAssignable(lhs.clone()));
+            return None;
+        }
+
+        Some(view.inner.as_ref().clone())
+    }
+
+    /// Checks a call to a user-defined or builtin function, returning its return type.
+    ///
+    /// `used` indicates whether the result of the call is used as a value.
+    fn call(&mut self, name: &str, args: &[ExprNode], used: bool) -> Option<DataType> {
+        let arg_types: Vec<_> = args.iter().map(|arg| self.expr(arg)).collect();
+        let arg_types = arg_types.into_iter().collect::<Option<Vec<_>>>()?;
+
+        if let Some(decl) = self.fns.get(name).copied() {
+            if self.function.map(|it| it.name.as_str()) == Some(name) {
+                self.error(TypeErrorKind::RecursiveCall(name.to_owned()));
+            }
+
+            if decl.inputs.len() != arg_types.len() {
+                self.error(TypeErrorKind::ArgCount {
+                    function: name.to_owned(),
+                    expected: decl.inputs.len(),
+                    found: arg_types.len(),
+                });
+            } else {
+                for (i, ((param, arg), ty)) in
+                    decl.inputs.iter().zip(args).zip(&arg_types).enumerate()
+                {
+                    self.expect_value(&param.data_type, arg, ty, || {
+                        format!("argument {} of `{name}`", i + 1)
+                    });
+                }
+            }
+
+            return match &decl.output {
+                Some(output) => Some(output.data_type.clone()),
+                None => {
+                    if used {
+                        self.error(TypeErrorKind::NoReturnValue(name.to_owned()));
+                    }
+                    None
+                }
+            };
+        }
+
+        // Structs may also be constructed with call syntax
+        if let Some(decl) = self.structs.get(name).copied() {
+            let ty = DataType::Struct(decl.clone());
+            self.check_constructor(&ty, args, &arg_types);
+            return Some(ty);
+        }
+
+        let Ok(builtin) = name.parse::<BuiltinFn>() else {
+            self.error(TypeErrorKind::UndeclaredFn(name.to_owned()));
+            return None;
+        };
+
+        let returns_value = !matches!(
+            builtin,
+            BuiltinFn::AtomicStore
+                | BuiltinFn::StorageBarrier
+                | BuiltinFn::TextureBarrier
+                | BuiltinFn::TextureStore
+                | BuiltinFn::WorkgroupBarrier
+        );
+
+        if !returns_value {
+            if used {
+                self.error(TypeErrorKind::NoReturnValue(name.to_owned()));
+            }
+            return None;
+        }
+
+        // If there is no overload for the types that abstract arguments have by default, they are
+        // converted to the scalar type of the concrete numeric arguments
+        let concrete = args.iter().zip(&arg_types).find_map(|(arg, ty)| {
+            ty.as_scalar()
+                .filter(|t| !arg.is_abstract() && *t != ScalarType::Bool)
+        });
+
+        let converted: Vec<_> = args
+            .iter()
+            .zip(&arg_types)
+            .map(|(arg, ty)| {
+                concrete
+                    .filter(|_| arg.is_abstract())
+                    .and_then(|t| ast::convert_abstract_type(ty, t))
+                    .unwrap_or_else(|| ty.clone())
+            })
+            .collect();
+
+        let return_type = builtin
+            .return_type(arg_types.iter())
+            .or_else(|| builtin.return_type(converted.iter()));
+
+        let Some(return_type) = return_type else {
+            self.error(TypeErrorKind::NoMatchingOverload {
+                function: name.to_owned(),
+                args: arg_types
+                    .iter()
+                    .map(|it| it.dereference().clone())
+                    .collect(),
+            });
+            return None;
+        };
+
+        if !used {
+            self.error(TypeErrorKind::UnusedResult(name.to_owned()));
+        }
+
+        Some(return_type)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ast::visit::{self, VisitorMut};
+    use ast::Expr;
+
+    use super::*;
+
+    macro_rules! assert_error {
+        ($module:expr, $pattern:pat) => {
+            let errors = check(&$module).unwrap_err();
+            assert!(
+                errors.iter().any(|it| matches!(it.kind, $pattern)),
+                "expected {}, found {errors:?}",
+                stringify!($pattern)
+            );
+        };
+    }
+
+    struct EditExprs<F>(F);
+
+    impl<F: FnMut(&mut ExprNode)> VisitorMut for EditExprs<F> {
+        fn visit_expr_mut(&mut self, node: &mut ExprNode) {
+            (self.0)(node);
+            visit::walk_expr_mut(self, node);
+        }
+    }
+
+    /// Parses `source` and applies `edit` to each of its expressions, to build modules with errors
+    /// that the parser would reject.
+    fn parse_and_edit(source: &str, edit: impl FnMut(&mut ExprNode)) -> Module {
+        let mut module = parser::parse(source);
+        EditExprs(edit).visit_module_mut(&mut module);
+        module
+    }
+
+    /// Parses `source` and renames the variables and functions called `from` to `to`.
+    fn parse_and_rename(source: &str, from: &str, to: &str) -> Module {
+        parse_and_edit(source, |node| match &mut node.expr {
+            Expr::Var(var) if var.ident == from => var.ident = to.to_owned(),
+            Expr::FnCall(call) if call.ident == from => call.ident = to.to_owned(),
+            _ => {}
+        })
+    }
+
+    #[test]
+    fn well_typed_module() {
+        let module = parser::parse(
+            r"
+struct S {
+    a: i32,
+    b: vec2<f32>,
+}
+
+var<private> g: S;
+const C: u32 = 3u;
+
+fn h(p: ptr<function, i32>) -> i32 {
+    *p = *p + 1;
+    return *p;
+}
+
+@compute @workgroup_size(1)
+fn main() {
+    var x = 1i;
+    let y = h(&x);
+    g.b = vec2<f32>(1.0, f32(y));
+    var a = array<u32, 3>(1u, 2u, C);
+    a[0] = a[1] + C;
+    x++;
+    switch (x) {
+        case 1: {
+            x = 2;
+        }
+        default: {}
+    }
+    for (var i = 0i; i < 4i; i++) {
+        if (x > i) {
+            break;
+        }
+    }
+    loop {
+        x = x - 1;
+        if (x < 0) {
+            break;
+        }
+    }
+    _ = g.a;
+}
+",
+        );
+
+        if let Err(errors) = check(&module) {
+            panic!("unexpected errors: {errors:?}");
+        }
+    }
+
+    #[test]
+    fn abstract_conversions() {
+        let module = parser::parse(include_str!("../../parser/src/tests/lets.wgsl"));
+        if let Err(errors) = check(&module) {
+            panic!("unexpected errors: {errors:?}");
+        }
+
+        let module = parser::parse(
+            r"
+struct S {
+    a: u32,
+    b: f32,
+}
+
+fn g(a: u32, b: f16) {}
+
+fn h() -> u32 {
+    return 3000000000;
+}
+
+fn k() -> f32 {
+    return 2147483647 + 1;
+}
+
+fn f() {
+    var x: u32 = 1;
+    x = 3000000000;
+    x += 1;
+    g(3000000000, 1.5);
+    let a = x + 1;
+    let b = 1 - x;
+    let c = 1.0f + 1;
+    let d = x << 1;
+    let e = max(x, 3000000000);
+    let s = S(1, 2);
+    let v = vec2<f32>(1, 2.0);
+    let w = array<u32, 2>(1, 2);
+    switch (x) {
+        case 3000000000: {}
+        default: {}
+    }
+}
+",
+        );
+
+        if let Err(errors) = check(&module) {
+            panic!("unexpected errors: {errors:?}");
+        }
+
+        let module = parser::parse("fn f() { let x: u32 = 1.0; }");
+        assert_error!(module, TypeErrorKind::Mismatch { .. });
+
+        let module = parser::parse("fn f() -> i32 { return 1.0 + 1; }");
+        assert_error!(module, TypeErrorKind::Mismatch { .. });
+
+        let module = parser::parse("fn f() { let x = 1u + 1.0; }");
+        assert_error!(module, TypeErrorKind::InvalidBinOp { .. });
+    }
+
+    #[test]
+    fn declaration_errors() {
+        let module = parse_and_rename("fn f() { let x = 1i; let y = x; }", "x", "z");
+        assert_error!(module, TypeErrorKind::UndeclaredVar(_));
+
+        let module = parse_and_rename(
+            "fn g() -> i32 { return 1i; } fn f() { let x = g(); }",
+            "g",
+            "h",
+        );
+        assert_error!(module, TypeErrorKind::UndeclaredFn(_));
+
+        let module = parser::parse("fn f() { let x = 1i; let x = 2i; }");
+        assert_error!(module, TypeErrorKind::Redeclaration(_));
+
+        let module = parser::parse("fn f() { var x = 1i; let p = &x; var q = p; }");
+        assert_error!(module, TypeErrorKind::NotStorable(DataType::Ptr(_)));
+    }
+
+    #[test]
+    fn expression_errors() {
+        let module = parse_and_edit("fn f() { let x = 1i; }", |node| {
+            node.data_type = DataType::Scalar(ScalarType::U32);
+        });
+        assert_error!(module, TypeErrorKind::AnnotationMismatch { .. });
+
+        let module = parser::parse("fn f() { let x = !1i; }");
+        assert_error!(module, TypeErrorKind::InvalidUnOp { .. });
+
+        let module = parser::parse("fn f() { let x = 1i + 1u; }");
+        assert_error!(module, TypeErrorKind::InvalidBinOp { .. });
+
+        let module = parse_and_rename(
+            "fn f() { let s = 1i; let v = vec2<i32>(); let y = v[0]; }",
+            "v",
+            "s",
+        );
+        assert_error!(module, TypeErrorKind::InvalidIndex { .. });
+
+        let module = parse_and_rename(
+            "fn f() { let s = 1i; let v = vec2<i32>(); let y = v.x; }",
+            "v",
+            "s",
+        );
+        assert_error!(module, TypeErrorKind::InvalidMember { .. });
+
+        let module = parser::parse("fn f() { let x = vec2<f32>(1i, true, 2u); }");
+        assert_error!(module, TypeErrorKind::InvalidConstructor { .. });
+
+        let module = parser::parse("fn f() { var v = vec2<f32>(); let p = &v.x; }");
+        assert_error!(module, TypeErrorKind::AddressOfVectorComponent);
+    }
+
+    #[test]
+    fn call_errors() {
+        let module = parser::parse("fn f() -> i32 { return f(); }");
+        assert_error!(module, TypeErrorKind::RecursiveCall(_));
+
+        let module = parser::parse("fn g(a: i32) {} fn f() { g(1i, 2i); }");
+        assert_error!(module, TypeErrorKind::ArgCount { .. });
+
+        let module = parse_and_rename(
+            "fn f() { let v = vec2<i32>(); let x = dot(v, v); }",
+            "dot",
+            "atomicLoad",
+        );
+        assert_error!(module, TypeErrorKind::NoMatchingOverload { .. });
+
+        let module = parse_and_rename(
+            "fn g() {} fn h() -> i32 { return 1i; } fn f() { let x = h(); }",
+            "h",
+            "g",
+        );
+        assert_error!(module, TypeErrorKind::NoReturnValue(_));
+
+        let module = parser::parse("fn f() { abs(1i); }");
+        assert_error!(module, TypeErrorKind::UnusedResult(_));
+    }
+
+    #[test]
+    fn statement_errors() {
+        let module = parser::parse("fn f() { var x: u32 = 1i; }");
+        assert_error!(module, TypeErrorKind::Mismatch { .. });
+
+        let module = parser::parse("fn f() { let x = 1i; x = 2i; }");
+        assert_error!(module, TypeErrorKind::NotAssignable(_));
+
+        let module = parser::parse(
+            "@group(0) @binding(0) var<uniform> u: vec4<f32>; fn f() { u = vec4<f32>(); }",
+        );
+        assert_error!(module, TypeErrorKind::NotWritable(_));
+
+        let module = parser::parse("fn f() { var x = true; x++; }");
+        assert_error!(module, TypeErrorKind::InvalidIncrement(_));
+
+        let module = parser::parse("fn f() -> i32 { return; }");
+        assert_error!(module, TypeErrorKind::MissingReturnValue(_));
+
+        let module = parser::parse("fn f() { return 1i; }");
+        assert_error!(module, TypeErrorKind::UnexpectedReturnValue(_));
+
+        let module = parser::parse("fn f() { switch (1.0) { default: {} } }");
+        assert_error!(module, TypeErrorKind::InvalidSwitchSelector(_));
+    }
+}
@@ -1,22 +1,78 @@
 use std::fs::File;
-use std::io::Read;
+use std::io::{Read, Write};
 
 use clap::Parser;
+use eyre::eyre;
+use generator::Format;
 
 #[derive(Parser)]
 pub struct Options {
-    /// Path to a wgsl shader program (use '-' for stdin).
+    /// Path to a wgsl shader program, or its json serialized ast (use '-' for stdin).
     #[clap(action, default_value = "-")]
     pub input: String,
 
     /// Path at which to write output (use '-' for stdout).
     #[clap(short, long, action, default_value = "-")]
     pub output: String,
+
+    /// Format of the input program.
+    #[clap(long, action, value_enum, default_value = "wgsl")]
+    pub from: Format,
+
+    /// Format in which to write the output.
+    #[clap(long, action, value_enum, default_value = "wgsl")]
+    pub to: Format,
+
+    /// Rewrite the program into its canonical form before writing it, so that programs which only
+    /// differ in naming or declaration order produce the same output.
+    #[clap(long, action)]
+    pub canonicalize: bool,
+
+    /// Check whether the input is already formatted instead of writing the output. Exits with an
+    /// error if formatting would change the input.
+    #[clap(long, action)]
+    pub check: bool,
+
+    #[clap(flatten)]
+    pub style: ast::writer::Options,
 }
 
 pub fn run(options: Options) -> eyre::Result<()> {
     let source = read_shader_from_path(&options.input)?;
-    let ast = parser::parse(&source);
+    let ast = match options.from {
+        Format::Wgsl => parser::try_parse(&source).map_err(|diagnostics| {
+            eyre!(
+                "invalid shader:\n{}",
+                parser::format_diagnostics(&diagnostics)
+            )
+        })?,
+        Format::Json => generator::parse_json(&source)?,
+    };
+
+    let ast = if options.canonicalize {
+        ast::canon::canonicalize(ast)
+    } else {
+        ast
+    };
+
+    if options.check {
+        let formatted = match options.to {
+            Format::Wgsl => {
+                let mut formatted = String::new();
+                ast::writer::Writer::new(options.style)
+                    .write_module(&mut formatted, &ast)
+                    .unwrap();
+                formatted
+            }
+            Format::Json => serde_json::to_string_pretty(&ast)? + "\n",
+        };
+
+        return if formatted == source {
+            Ok(())
+        } else {
+            Err(eyre!("{} is not formatted", options.input))
+        };
+    }
 
     struct Output(Box<dyn std::io::Write>);
 
@@ -28,14 +84,20 @@ pub fn run(options: Options) -> eyre::Result<()> {
         }
     }
 
-    let output: Box<dyn std::io::Write> = match options.output.as_str() {
+    let mut output: Box<dyn std::io::Write> = match options.output.as_str() {
         "-" => Box::new(std::io::stdout()),
         path => Box::new(File::create(path)?),
     };
 
-    ast::writer::Writer::default()
-        .write_module(&mut Output(output), &ast)
-        .unwrap();
+    match options.to {
+        Format::Wgsl => ast::writer::Writer::new(options.style)
+            .write_module(&mut Output(output), &ast)
+            .unwrap(),
+        Format::Json => {
+            serde_json::to_writer_pretty(&mut output, &ast)?;
+            writeln!(output)?;
+        }
+    }
 
     Ok(())
 }
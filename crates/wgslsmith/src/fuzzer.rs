@@ -1,9 +1,11 @@
+use std::collections::HashSet;
 use std::io::{self, BufWriter, Write as _};
 use std::path::{Path, PathBuf};
 use std::process::{Command, Stdio};
 use std::sync::{Arc, Mutex};
 use std::thread;
 
+use ast::writer::{DeclOrder, LiteralStyle, NumeralStyle, ParenStyle};
 use clap::{Parser, ValueEnum};
 use crossbeam_channel::select;
 use crossterm::event::KeyCode;
@@ -13,6 +15,8 @@ use crossterm::terminal::{
 };
 use eyre::eyre;
 use harness_types::ConfigId;
+use rand::seq::SliceRandom;
+use rand::Rng;
 use regex::Regex;
 use tap::Tap;
 use time::{format_description, OffsetDateTime, UtcOffset};
@@ -80,6 +84,19 @@ pub struct Options {
     /// Enable generating unstable float functions that are currently not reconditioned
     #[clap(long, action)]
     pub unstable_float: bool,
+
+    /// Write each reconditioned shader with a randomly chosen output style (indentation, type
+    /// spelling, parentheses, literals and numerals).
+    #[clap(long, action)]
+    pub randomize_style: bool,
+
+    /// Save test cases even if an equivalent shader has already been saved to the output
+    /// directory.
+    ///
+    /// By default, shaders that only differ from a saved one in naming, declaration order or
+    /// literal spelling are skipped.
+    #[clap(long, action)]
+    pub keep_duplicates: bool,
 }
 
 fn gen_shader(options: &Options) -> eyre::Result<String> {
@@ -113,9 +130,10 @@ fn gen_shader(options: &Options) -> eyre::Result<String> {
     Ok(String::from_utf8(output.stdout)?)
 }
 
-fn recondition_shader(shader: &str) -> eyre::Result<String> {
+fn recondition_shader(shader: &str, style: &[String]) -> eyre::Result<String> {
     let mut reconditioner = Command::new(std::env::current_exe().unwrap())
         .arg("recondition")
+        .args(style)
         .stdin(Stdio::piped())
         .stdout(Stdio::piped())
         .spawn()?;
@@ -135,6 +153,34 @@ fn recondition_shader(shader: &str) -> eyre::Result<String> {
     Ok(String::from_utf8(output.stdout)?)
 }
 
+/// Picks a random output style for the reconditioned shader, as arguments to `recondition`.
+fn random_style_args() -> Vec<String> {
+    fn choose<T: ValueEnum>(rng: &mut impl Rng) -> String {
+        let value = T::value_variants().choose(rng).unwrap();
+        value.to_possible_value().unwrap().get_name().to_owned()
+    }
+
+    let mut rng = rand::thread_rng();
+    let mut args = vec![
+        "--indent".to_owned(),
+        rng.gen_range(0..=8).to_string(),
+        "--parens".to_owned(),
+        choose::<ParenStyle>(&mut rng),
+        "--literals".to_owned(),
+        choose::<LiteralStyle>(&mut rng),
+        "--numerals".to_owned(),
+        choose::<NumeralStyle>(&mut rng),
+        "--decl-order".to_owned(),
+        choose::<DeclOrder>(&mut rng),
+    ];
+
+    if rng.gen() {
+        args.push("--type-aliases".to_owned());
+    }
+
+    args
+}
+
 impl ExecutionResult {
     fn should_save<'a>(
         &self,
@@ -184,6 +230,33 @@ fn save_shader(
     Ok(())
 }
 
+/// Computes the structural hash of the canonical form of a shader, or `None` if it can't be parsed.
+fn shader_hash(shader: &str) -> Option<u64> {
+    let module = parser::try_parse(shader).ok()?;
+    Some(ast::canon::structural_hash(&ast::canon::canonicalize(
+        module,
+    )))
+}
+
+/// Collects the hashes of the shaders that have already been saved to the output directory.
+fn load_saved_hashes(out: &Path) -> eyre::Result<HashSet<u64>> {
+    let mut hashes = HashSet::new();
+
+    if !out.exists() {
+        return Ok(hashes);
+    }
+
+    for entry in std::fs::read_dir(out)? {
+        let path = entry?.path().join("shader.wgsl");
+        if path.is_file() {
+            // Saved shaders that can't be parsed are skipped rather than aborting the fuzzer
+            hashes.extend(shader_hash(&std::fs::read_to_string(path)?));
+        }
+    }
+
+    Ok(hashes)
+}
+
 pub fn run(config: Config, options: Options) -> eyre::Result<()> {
     unsafe { UTC_OFFSET = Some(UtcOffset::current_local_offset()?) };
 
@@ -314,9 +387,15 @@ fn worker(
     harness: Harness,
     on_message: &mut dyn FnMut(WorkerMessage),
 ) -> eyre::Result<()> {
+    let mut saved_hashes = if options.keep_duplicates {
+        HashSet::new()
+    } else {
+        load_saved_hashes(&options.output)?
+    };
+
     loop {
         let mut logger = |line| on_message(WorkerMessage::Log(line));
-        let result = worker_iteration(&config, &options, &harness, &mut logger)?;
+        let result = worker_iteration(&config, &options, &harness, &mut saved_hashes, &mut logger)?;
         on_message(WorkerMessage::Result(result))
     }
 }
@@ -325,6 +404,7 @@ fn worker_iteration(
     config: &Config,
     options: &Options,
     harness: &Harness,
+    saved_hashes: &mut HashSet<u64>,
     logger: &mut dyn FnMut(String),
 ) -> eyre::Result<WorkerResult> {
     let shader = gen_shader(options)?;
@@ -333,7 +413,13 @@ fn worker_iteration(
         .ok_or_else(|| eyre!("expected first line of shader to be a JSON metadata comment"))?;
 
     let metadata = metadata.trim_start_matches("//").trim();
-    let reconditioned = match recondition_shader(shader) {
+    let style = if options.randomize_style {
+        random_style_args()
+    } else {
+        vec![]
+    };
+
+    let reconditioned = match recondition_shader(shader, &style) {
         Ok(reconditioned) => reconditioned,
         Err(_) => {
             eprintln!("reconditioner command failed, ignoring");
@@ -344,8 +430,13 @@ fn worker_iteration(
         }
     };
 
-    let exec_result =
-        harness_runner::exec_shader(harness, &options.configs, &reconditioned, metadata, logger);
+    let exec_result = harness_runner::exec_shader(
+        harness,
+        &options.configs,
+        &reconditioned,
+        metadata,
+        &mut *logger,
+    );
 
     let result = match exec_result {
         Ok(result) => result,
@@ -378,11 +469,19 @@ fn worker_iteration(
         output = Some(out.as_str());
     }
 
-    let should_save = result.should_save(
+    let mut should_save = result.should_save(
         &options.strategy,
         options.ignore.iter().chain(&config.fuzzer.ignore),
     );
 
+    if should_save
+        && !options.keep_duplicates
+        && matches!(shader_hash(shader), Some(hash) if !saved_hashes.insert(hash))
+    {
+        logger("skipping test case that duplicates a saved shader".to_owned());
+        should_save = false;
+    }
+
     if should_save {
         save_shader(&options.output, shader, &reconditioned, metadata, output)?;
     }
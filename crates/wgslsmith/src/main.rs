@@ -7,6 +7,7 @@ mod harness_runner;
 #[cfg(all(target_family = "unix", feature = "reducer"))]
 mod reducer;
 mod remote;
+mod stats;
 #[cfg(all(target_family = "unix", feature = "reducer"))]
 mod test;
 #[cfg(all(target_family = "unix", feature = "reducer"))]
@@ -39,9 +40,18 @@ enum Cmd {
     Gen(generator::Options),
     /// Recondition a shader to add safety checks.
     Recondition(reconditioner::cli::Options),
+    /// Fold constant expressions and calls to pure functions with constant arguments into their
+    /// values.
+    Fold(concretizer::cli::Options),
     /// Format a shader.
     Fmt(fmt::Options),
+    /// Type check a shader.
+    Typecheck(typecheck::cli::Options),
+    /// Print size and shape metrics of shaders.
+    Stats(stats::Options),
     Fuzz(fuzzer::Options),
+    /// Check that generated shaders are parsed back into the same shader after writing them.
+    Selftest(generator::selftest::Options),
     /// Reduce a shader.
     #[cfg(all(target_family = "unix", feature = "reducer"))]
     Reduce(reducer::Options),
@@ -99,8 +109,12 @@ fn main() -> eyre::Result<()> {
         }
         Cmd::Gen(options) => generator::run(options),
         Cmd::Recondition(options) => reconditioner::cli::run(options),
+        Cmd::Fold(options) => concretizer::cli::run(options),
         Cmd::Fmt(options) => fmt::run(options),
+        Cmd::Typecheck(options) => typecheck::cli::run(options),
+        Cmd::Stats(options) => stats::run(options),
         Cmd::Fuzz(options) => fuzzer::run(config, options),
+        Cmd::Selftest(options) => generator::selftest::run(options),
         #[cfg(all(target_family = "unix", feature = "reducer"))]
         Cmd::Reduce(options) => reducer::run(config, options),
         #[cfg(all(target_family = "unix", feature = "reducer"))]
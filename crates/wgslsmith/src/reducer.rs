@@ -340,6 +340,10 @@ fn thread_main(config: &Config, options: Options) -> eyre::Result<()> {
     crate::fmt::run(crate::fmt::Options {
         input: result_path.clone(),
         output: result_path,
+        from: generator::Format::Wgsl,
+        to: generator::Format::Wgsl,
+        canonicalize: false,
+        style: Default::default(),
     })?;
 
     // crate::reconditioner::run(crate::reconditioner::Options {
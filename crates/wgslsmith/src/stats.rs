@@ -0,0 +1,161 @@
+use std::fs::File;
+use std::io::Read;
+
+use ast::metrics::Metrics;
+use clap::{Parser, ValueEnum};
+use serde::Serialize;
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum StatsFormat {
+    Table,
+    Json,
+}
+
+#[derive(Parser)]
+pub struct Options {
+    /// Paths to wgsl shader programs (use '-' for stdin).
+    #[clap(action, default_value = "-")]
+    pub inputs: Vec<String>,
+
+    /// Format in which to write the statistics.
+    #[clap(long, action, value_enum, default_value = "table")]
+    pub format: StatsFormat,
+}
+
+#[derive(Serialize)]
+struct FileMetrics {
+    path: String,
+    metrics: Metrics,
+}
+
+pub fn run(options: Options) -> eyre::Result<()> {
+    let mut files = vec![];
+
+    for path in options.inputs {
+        let source = read_shader_from_path(&path)?;
+        let module = match parser::try_parse(&source) {
+            Ok(module) => module,
+            Err(diagnostics) => {
+                eprintln!(
+                    "skipping {path}:\n{}",
+                    parser::format_diagnostics(&diagnostics)
+                );
+                continue;
+            }
+        };
+
+        let metrics = ast::metrics::compute(&module);
+        files.push(FileMetrics { path, metrics });
+    }
+
+    match options.format {
+        StatsFormat::Table => print_table(&files),
+        StatsFormat::Json => println!("{}", serde_json::to_string_pretty(&files)?),
+    }
+
+    Ok(())
+}
+
+const COLUMNS: &[&str] = &[
+    "Stmts",
+    "Exprs",
+    "Expr Depth",
+    "Block Depth",
+    "Loop Depth",
+    "Call Depth",
+    "Builtins",
+    "Types",
+    "Pointers",
+];
+
+fn row(metrics: &Metrics) -> [usize; 9] {
+    [
+        metrics.total_statements(),
+        metrics.total_expressions(),
+        metrics.max_expr_depth,
+        metrics.max_block_depth,
+        metrics.max_loop_depth,
+        metrics.call_graph_depth,
+        metrics.builtins.len(),
+        metrics.types.len(),
+        metrics.pointers.params + metrics.pointers.address_of + metrics.pointers.deref,
+    ]
+}
+
+fn print_table(files: &[FileMetrics]) {
+    let mut rows: Vec<(String, Vec<String>)> = files
+        .iter()
+        .map(|file| {
+            let values = row(&file.metrics).iter().map(|it| it.to_string()).collect();
+            (file.path.clone(), values)
+        })
+        .collect();
+
+    // With more than one file, the distribution matters more than any individual row
+    if files.len() > 1 {
+        let mut totals = [0; 9];
+        for file in files {
+            for (total, value) in totals.iter_mut().zip(row(&file.metrics)) {
+                *total += value;
+            }
+        }
+
+        let means = totals
+            .iter()
+            .map(|total| format!("{:.1}", *total as f64 / files.len() as f64))
+            .collect();
+
+        rows.push(("(mean)".to_owned(), means));
+    }
+
+    let path_width = rows
+        .iter()
+        .map(|(path, _)| path.len())
+        .chain(["File".len()])
+        .max()
+        .unwrap();
+
+    let widths: Vec<usize> = COLUMNS
+        .iter()
+        .enumerate()
+        .map(|(i, name)| {
+            rows.iter()
+                .map(|(_, values)| values[i].len())
+                .chain([name.len()])
+                .max()
+                .unwrap()
+        })
+        .collect();
+
+    print!("{:<path_width$}", "File");
+    for (name, width) in COLUMNS.iter().zip(&widths) {
+        print!(" | {name:>width$}");
+    }
+    println!();
+
+    print!("{}", "-".repeat(path_width + 1));
+    for width in &widths {
+        print!("+{}", "-".repeat(width + 2));
+    }
+    println!();
+
+    for (path, values) in &rows {
+        print!("{path:<path_width$}");
+        for (value, width) in values.iter().zip(&widths) {
+            print!(" | {value:>width$}");
+        }
+        println!();
+    }
+}
+
+fn read_shader_from_path(path: &str) -> eyre::Result<String> {
+    let mut input: Box<dyn Read> = match path {
+        "-" => Box::new(std::io::stdin()),
+        path => Box::new(File::open(path)?),
+    };
+
+    let mut shader = String::new();
+    input.read_to_string(&mut shader)?;
+
+    Ok(shader)
+}
@@ -135,7 +135,7 @@ fn reduce_crash(
     let should_recondition = !options.no_recondition;
 
     let source = if should_recondition {
-        recondition(parser::parse(&source))
+        recondition(parse_shader(&source)?)
     } else {
         source
     };
@@ -179,7 +179,7 @@ fn reduce_mismatch(
     harness: &Harness,
     quiet: bool,
 ) -> eyre::Result<()> {
-    let module = parser::parse(&source);
+    let module = parse_shader(&source)?;
     let reconditioned = recondition(module);
 
     // The naga validator rejects valid WGSL, like `_ = 4294967295u + 1u;`.
@@ -201,6 +201,17 @@ fn reduce_mismatch(
     Ok(())
 }
 
+/// Parses a shader, failing instead of panicking if it's invalid since reducers produce lots of
+/// malformed candidates.
+fn parse_shader(source: &str) -> eyre::Result<Module> {
+    parser::try_parse(source).map_err(|diagnostics| {
+        eyre!(
+            "invalid shader:\n{}",
+            parser::format_diagnostics(&diagnostics)
+        )
+    })
+}
+
 fn recondition(module: Module) -> String {
     let reconditioned = reconditioner::recondition(module);
     let mut formatted = String::new();